@@ -0,0 +1,161 @@
+use crate::db::ChangeKind;
+use mysql::prelude::*;
+use mysql::{PooledConn, Transaction};
+use serde_json::Value;
+use std::env;
+
+/// 書き込み元（time_card_allowance_log.source）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncSource {
+    /// pdf モードの同期
+    CliPdf,
+    /// サーバーの "sync": true
+    ServerPdf,
+    /// verify / verify-dtako モードのINSERT
+    Verify,
+}
+
+impl SyncSource {
+    /// source 列に書く値
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CliPdf => "cli-pdf",
+            Self::ServerPdf => "server-pdf",
+            Self::Verify => "verify",
+        }
+    }
+}
+
+/// 監査ログを書くか（環境変数 AUDIT_LOG=1、デフォルトは書かない）
+/// 有効にする前に migrate で time_card_allowance_log を作成しておく
+pub fn enabled_from_env() -> bool {
+    env::var("AUDIT_LOG").is_ok_and(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "on"))
+}
+
+/// 監査ログの1行（ドライバー・月・テーブルごと）
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// time_card_allowance / time_card_kosoku
+    pub table: &'static str,
+    /// ドライバーID
+    pub driver_id: i32,
+    /// 対象月の初日（YYYY-MM-01）
+    pub month: String,
+    /// 追加・更新・削除
+    pub action: ChangeKind,
+    /// 変更前の値（追加なら None）
+    pub old_values: Option<Value>,
+    /// 変更後の値（削除なら None）
+    pub new_values: Option<Value>,
+    /// 書き込み元
+    pub source: SyncSource,
+}
+
+/// 監査ログをINSERT（データの書き込みと同じトランザクションで呼ぶ）
+pub fn write(tx: &mut Transaction, entries: &[AuditEntry]) -> mysql::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    tx.exec_batch(
+        "INSERT INTO time_card_allowance_log (table_name, driver_id, month, action, old_values, new_values, source)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        entries.iter().map(|e| {
+            (
+                e.table,
+                e.driver_id,
+                &e.month,
+                e.action.as_str(),
+                e.old_values.as_ref().map(Value::to_string),
+                e.new_values.as_ref().map(Value::to_string),
+                e.source.as_str(),
+            )
+        }),
+    )
+}
+
+/// 監査ログから読んだ1行（history モード用）
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    /// 記録日時（YYYY-MM-DD HH:MM:SS）
+    pub logged_at: String,
+    /// time_card_allowance / time_card_kosoku
+    pub table: String,
+    /// insert / update / delete
+    pub action: String,
+    /// 書き込み元（cli-pdf / server-pdf / verify）
+    pub source: String,
+    /// 変更前の値（追加なら None）
+    pub old_values: Option<Value>,
+    /// 変更後の値（削除なら None）
+    pub new_values: Option<Value>,
+}
+
+impl AuditRecord {
+    /// 値が変わったキーと変更前・変更後（キー順、ない値は None）
+    pub fn changes(&self) -> Vec<(String, Option<Value>, Option<Value>)> {
+        let object = |v: &Option<Value>| v.as_ref().and_then(Value::as_object).cloned().unwrap_or_default();
+        let (old, new) = (object(&self.old_values), object(&self.new_values));
+        let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        keys.into_iter()
+            .filter(|k| old.get(*k) != new.get(*k))
+            .map(|k| (k.clone(), old.get(k).cloned(), new.get(k).cloned()))
+            .collect()
+    }
+}
+
+/// time_card_allowance_log の1行（logged_at, table_name, action, source, old_values, new_values）
+type AuditRow = (String, String, String, String, Option<String>, Option<String>);
+
+/// ドライバー・月の監査ログ（古い順）
+pub fn fetch_history(conn: &mut PooledConn, driver_id: i32, year: i32, month: u32) -> mysql::Result<Vec<AuditRecord>> {
+    let rows: Vec<AuditRow> = conn.exec(
+        "SELECT DATE_FORMAT(logged_at, '%Y-%m-%d %H:%i:%s'), table_name, action, source, old_values, new_values
+         FROM time_card_allowance_log
+         WHERE driver_id = ? AND month = ?
+         ORDER BY logged_at, id",
+        (driver_id, format!("{}-{:02}-01", year, month)),
+    )?;
+    let json = |v: Option<String>| v.and_then(|s| serde_json::from_str(&s).ok());
+    Ok(rows
+        .into_iter()
+        .map(|(logged_at, table, action, source, old_values, new_values)| AuditRecord {
+            logged_at,
+            table,
+            action,
+            source,
+            old_values: json(old_values),
+            new_values: json(new_values),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_changes_lists_changed_keys_only() {
+        let record = AuditRecord {
+            logged_at: "2026-01-05 10:00:00".to_string(),
+            table: "time_card_allowance".to_string(),
+            action: "update".to_string(),
+            source: "cli-pdf".to_string(),
+            old_values: Some(json!({"overtime_count": 12.5, "shukkin_count": 21.0})),
+            new_values: Some(json!({"overtime_count": 14.0, "shukkin_count": 21.0, "tokukyu_count": 1})),
+        };
+        assert_eq!(
+            record.changes(),
+            vec![
+                ("overtime_count".to_string(), Some(json!(12.5)), Some(json!(14.0))),
+                ("tokukyu_count".to_string(), None, Some(json!(1))),
+            ]
+        );
+
+        // 追加は変更前なし
+        let inserted = AuditRecord { old_values: None, action: "insert".to_string(), ..record };
+        assert_eq!(inserted.changes().len(), 3);
+    }
+}
@@ -0,0 +1,668 @@
+use crate::coords_diff::{DiffOptions, DEFAULT_TOLERANCE_MM};
+use crate::db::{self, DriverQuery};
+use crate::month_range::{check_year_month, parse_month, MonthRange};
+use crate::server;
+use crate::verify::{self, DriverSelection};
+use chrono::{DateTime, Datelike, FixedOffset, Utc};
+use clap::error::ErrorKind;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use std::fmt::Display;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// DBを使うモード（対象年月を決めて実行する）
+pub const DB_MODES: &[&str] = &[
+    "db", "pdf", "pdf-shukei", "pdf-split", "verify", "verify-dtako", "report-zangyo-anomalies", "compare", "export-coords", "csv", "history",
+    "kosoku-debug",
+];
+
+/// Docker DB（書き込み用）も使うモード（同期・検証のINSERT・比較・監査ログ）
+pub const DOCKER_DB_MODES: &[&str] = &["pdf", "verify", "verify-dtako", "compare", "history"];
+
+/// Docker DBだけを使うモード（本番DBの設定は不要）
+pub const DOCKER_ONLY_MODES: &[&str] = &["history"];
+
+/// コマンドライン全体の定義（サブコマンドごとの引数と --help）
+/// 引数の誤りは使い方を表示して終了コード2（年月を既定値に読み替えて実行しない）
+pub fn command() -> Command {
+    Command::new("timecard-pdf-rs")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("タイムカードPDF生成（PHP版TCPDF互換）")
+        .args(global_args())
+        .subcommand(
+            Command::new("server")
+                .about("HTTPサーバーを起動")
+                .arg(
+                    Arg::new("bind")
+                        .value_name("PORT|HOST:PORT")
+                        .value_parser(server::parse_bind_addr)
+                        .help("待ち受けアドレス（省略時は BIND_ADDR、なければ 0.0.0.0:8080）"),
+                )
+                .arg(
+                    Arg::new("skip-db-check")
+                        .long("skip-db-check")
+                        .action(ArgAction::SetTrue)
+                        .help("起動時にDBの設定・接続を確認しない"),
+                ),
+        )
+        .subcommand(target_command("db", "本番DBのタイムカードを表示", true))
+        .subcommand(
+            target_command("pdf", "タイムカードPDFを生成（3人/ページ）", true)
+                .arg(Arg::new("no-sync").long("no-sync").action(ArgAction::SetTrue).help("Docker DBに同期しない"))
+                .arg(
+                    // 削除がデフォルトになったので何もしない（既存のスクリプト用に受け付ける）
+                    Arg::new("delete-stale").long("delete-stale").action(ArgAction::SetTrue).hide(true),
+                )
+                .arg(keep_stale_arg())
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("no-sync")
+                        .help("Docker DBに書き込まず、同期で変わる内容を表示する（PDFは生成しない）"),
+                )
+                .arg(output_arg("出力PDF（既定 timecard_YYYY_MM.pdf、範囲は timecard_YYYY-MM_YYYY-MM.pdf）"))
+                .arg(jobs_arg())
+                .args(month_range_args()),
+        )
+        .subcommand(
+            target_command("pdf-shukei", "集計レイアウトのPDFを生成（1人/ページ、日付横並び）", true)
+                .arg(output_arg("出力PDF（既定 timecard_shukei_YYYY_MM.pdf、範囲は timecard_shukei_YYYY-MM_YYYY-MM.pdf）"))
+                .arg(jobs_arg())
+                .args(month_range_args()),
+        )
+        .subcommand(
+            target_command("pdf-split", "1人1ファイルでPDFを生成", true)
+                .arg(Arg::new("outdir").long("outdir").value_name("DIR").default_value(".").help("出力先ディレクトリ")),
+        )
+        .subcommand(selection_args(target_command("verify", "拘束時間（TC_DC版）を計算してDocker DBにINSERT", false)).arg(keep_stale_arg()))
+        .subcommand(
+            selection_args(target_command("verify-dtako", "拘束時間（デジタコ版）を計算してDocker DBにINSERT", false)).arg(keep_stale_arg()).arg(
+                Arg::new("allow-partial")
+                    .long("allow-partial")
+                    .action(ArgAction::SetTrue)
+                    .help("一部のドライバーが失敗しても終了コード0"),
+            ),
+        )
+        .subcommand(
+            target_command("report-zangyo-anomalies", "残業異常レポートを出力", false).arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_parser(["csv", "json"])
+                    .default_value("csv")
+                    .help("出力形式"),
+            ),
+        )
+        .subcommand(
+            target_command("compare", "本番DB(PHP)とDocker DB(Rust)の拘束時間を日別に比較", true)
+                .arg(
+                    Arg::new("threshold")
+                        .long("threshold")
+                        .value_name("MINUTES")
+                        .value_parser(value_parser!(i32).range(0..))
+                        .default_value("0")
+                        .help("許容する差（分）"),
+                )
+                .arg(output_arg("比較結果のCSV")),
+        )
+        .subcommand(
+            target_command("export-coords", "描画内容を座標JSONに出力", true)
+                .arg(output_arg("出力JSON（既定 pdf_coordinates_rust_YYYY_MM.json）")),
+        )
+        .subcommand(
+            target_command("csv", "日別のタイムカードを分析用CSVに出力", true)
+                .arg(output_arg("出力CSV（既定 timecard_YYYY_MM.csv）"))
+                .arg(Arg::new("bom").long("bom").action(ArgAction::SetTrue).help("先頭にBOMを付ける（Excel用）")),
+        )
+        .subcommand(
+            target_command("kosoku-debug", "1人の拘束時間（TC_DC版・デジタコ版）の計算過程を表示（イベント・計上した区間・控除・日別の合計）", true)
+                .arg(
+                    Arg::new("day")
+                        .long("day")
+                        .value_name("DAY")
+                        .value_parser(value_parser!(u32).range(1..=31))
+                        .help("この日の分だけ表示"),
+                )
+                .arg(Arg::new("json").long("json").action(ArgAction::SetTrue).help("JSONで出力")),
+        )
+        .subcommand(target_command("history", "ドライバー・月の time_card_allowance / time_card_kosoku の変更履歴（監査ログ）を表示", true))
+        .subcommand(Command::new("migrate").about(
+            "Docker DBに time_card_kosoku / time_card_allowance / time_card_allowance_log を作成（既存なら列・一意キーを確認し、問題があれば終了コード1）",
+        ))
+        .subcommand(
+            Command::new("diff-coords")
+                .about("2つの座標JSONを要素ごとに比較（差分があれば終了コード1）")
+                .arg(Arg::new("a").value_name("A_JSON").required(true))
+                .arg(Arg::new("b").value_name("B_JSON").required(true))
+                .arg(
+                    Arg::new("tolerance")
+                        .long("tolerance")
+                        .value_name("MM")
+                        .value_parser(parse_tolerance)
+                        .help("位置ずれの許容差（mm、既定0.3）"),
+                )
+                .arg(
+                    Arg::new("ignore")
+                        .long("ignore")
+                        .value_name("TYPE")
+                        .value_delimiter(',')
+                        .action(ArgAction::Append)
+                        .help("比較しない要素の種類（カンマ区切り、複数回指定可）"),
+                )
+                .arg(Arg::new("json").long("json").action(ArgAction::SetTrue).help("JSONで出力")),
+        )
+        .subcommand(
+            Command::new("render-fixture")
+                .about("MonthlyTimecardの配列（JSON）からDBなしでPDF生成")
+                .arg(Arg::new("input").value_name("JSON").required(true))
+                .arg(Arg::new("output").value_name("OUTPUT").default_value("fixture.pdf")),
+        )
+        .subcommand(
+            Command::new("json")
+                .about("座標JSONからPDF生成（ディレクトリ指定で中の *.json をすべて）")
+                .arg(Arg::new("input").value_name("INPUT").required(true))
+                .arg(Arg::new("output").value_name("OUTPUT"))
+                .arg(
+                    Arg::new("font")
+                        .long("font")
+                        .value_name("FAMILY=PATH")
+                        .action(ArgAction::Append)
+                        .help("SetFontのfamily別フォント登録（複数指定可）"),
+                ),
+        )
+}
+
+/// 全サブコマンド共通のオプション（サブコマンドの前後どちらにも書ける）
+fn global_args() -> Vec<Arg> {
+    let flag = |id: &'static str, help: &'static str| Arg::new(id).long(id).action(ArgAction::SetTrue).global(true).help(help);
+    let option = |id: &'static str, value_name: &'static str, help: &'static str| {
+        Arg::new(id).long(id).value_name(value_name).global(true).help(help)
+    };
+    vec![
+        flag("ignore-maintenance", "メンテナンス中でも実行する"),
+        option("month", "YYYY-MM", "対象年月（位置引数の年月より優先）").value_parser(parse_month),
+        flag("yes", "現在月から離れた対象月でも確認しない"),
+        option("categories", "N,N", "対象ドライバーの給与区分（カンマ区切り）")
+            .value_delimiter(',')
+            .value_parser(value_parser!(i32)),
+        flag("include-officers", "役員を含める").visible_alias("include-yakuin"),
+        option("eigyosho", "N,N", "対象ドライバーの営業所コード（カンマ区切り、省略時は1のみ）")
+            .value_delimiter(',')
+            .value_parser(value_parser!(i32)),
+        option("kosoku-source", "SOURCE", "拘束列に表示する拘束時間の出典（auto / digitacho-rust / tcdc-rust / php-tcdc / php-digitacho、既定 auto）")
+            .value_parser(|s: &str| {
+                db::KosokuSource::parse(s)
+                    .ok_or_else(|| "auto / digitacho-rust / tcdc-rust / php-tcdc / php-digitacho のいずれかで指定してください".to_string())
+            }),
+        option("render", "KEY=VALUE", "レンダリングオプション（複数指定可、環境変数より優先）").action(ArgAction::Append),
+        option("per-page", "N", "1ページに並べる人数").value_parser(value_parser!(usize)),
+        option("watermark", "TEXT", "各ページの透かし"),
+        flag("deterministic", "同じ入力から同じバイト列のPDFを出力"),
+        option("password", "PASSWORD", "出力PDFをパスワードで暗号化"),
+        flag("quiet", "進捗表示・経過のログを出さない（警告・エラーのみ）"),
+    ]
+}
+
+/// 対象年月を指定するサブコマンド（[年 月] [ドライバーID]、--month 指定時は [ドライバーID] のみ）
+fn target_command(name: &'static str, about: &'static str, with_driver: bool) -> Command {
+    let (value_names, max): (&[&str], usize) = if with_driver { (&["YEAR", "MONTH", "DRIVER_ID"], 3) } else { (&["YEAR", "MONTH"], 2) };
+    let command = Command::new(name)
+        .about(about)
+        .arg(Arg::new("target").value_names(value_names).num_args(0..=max).help("対象年月（省略時は前月）"));
+    if with_driver {
+        command.arg(
+            Arg::new("driver-id")
+                .long("driver-id")
+                .value_name("ID")
+                .value_parser(value_parser!(i32).range(1..))
+                .help("対象ドライバー"),
+        )
+    } else {
+        command
+    }
+}
+
+/// 出力先（--output、従来の --out も可）
+fn output_arg(help: &'static str) -> Arg {
+    Arg::new("output").long("output").visible_alias("out").value_name("PATH").help(help)
+}
+
+/// --from YYYY-MM --to YYYY-MM: 複数月をまとめて出力（--split-months なら月ごとのファイル）
+fn month_range_args() -> Vec<Arg> {
+    vec![
+        Arg::new("from").long("from").value_name("YYYY-MM").value_parser(parse_month).requires("to").help("対象範囲の開始月"),
+        Arg::new("to").long("to").value_name("YYYY-MM").value_parser(parse_month).requires("from").help("対象範囲の終了月"),
+        Arg::new("split-months")
+            .long("split-months")
+            .action(ArgAction::SetTrue)
+            .requires("from")
+            .conflicts_with("output")
+            .help("範囲の各月を別のファイルに出力"),
+    ]
+}
+
+/// --jobs N: ドライバー別のデータ取得を並列に行うワーカー数（既定は PARALLEL_FETCH、上限 MAX_FETCH_JOBS）
+fn jobs_arg() -> Arg {
+    Arg::new("jobs")
+        .long("jobs")
+        .short('j')
+        .value_name("N")
+        .value_parser(value_parser!(u64).range(1..))
+        .help("データ取得の並列数（既定 4、上限 8、1で逐次）")
+}
+
+/// --jobs の値（指定なしなら PARALLEL_FETCH、どちらも上限 MAX_FETCH_JOBS に抑える）
+pub fn fetch_jobs(matches: &ArgMatches) -> usize {
+    match matches.try_get_one::<u64>("jobs").ok().flatten() {
+        Some(&jobs) => db::clamp_fetch_jobs(jobs as usize),
+        None => db::parallel_fetch_jobs_from_env(),
+    }
+}
+
+/// verifyの対象ドライバー指定（--sample N [--seed S] / --drivers-from-file path）
+fn selection_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("sample")
+                .long("sample")
+                .value_name("N")
+                .value_parser(value_parser!(u64).range(1..))
+                .conflicts_with("drivers-from-file")
+                .help("給与区分ごとに層別した N 人だけ実行"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("S")
+                .value_parser(value_parser!(u64))
+                .requires("sample")
+                .help("サンプルの乱数seed（既定0）"),
+        )
+        .arg(Arg::new("drivers-from-file").long("drivers-from-file").value_name("PATH").help("ファイルのドライバーIDのみ実行"))
+}
+
+/// --keep-stale: 計算結果にない拘束時間の行を削除しない（デフォルトは削除、RustのTC_DC/デジタコの行のみ）
+fn keep_stale_arg() -> Arg {
+    Arg::new("keep-stale")
+        .long("keep-stale")
+        .action(ArgAction::SetTrue)
+        .help("計算結果にない日の拘束時間の行（TC_DC/デジタコ）を削除せずに残す")
+}
+
+/// 引数の誤り: サブコマンドの使い方を表示して終了コード2で終了
+pub fn usage_error(subcommand: &str, message: impl Display) -> ! {
+    let mut root = command();
+    root.build();
+    match root.find_subcommand_mut(subcommand) {
+        Some(sub) => sub.error(ErrorKind::ValueValidation, message).exit(),
+        None => root.error(ErrorKind::ValueValidation, message).exit(),
+    }
+}
+
+/// ドライバー絞り込みオプション（--categories 2,3 / --include-officers / --eigyosho 1,2）
+#[derive(Debug, Clone, Default)]
+pub struct DriverFilterArgs {
+    pub categories: Option<Vec<i32>>,
+    pub include_officers: bool,
+    pub eigyosho: Option<Vec<i32>>,
+}
+
+impl DriverFilterArgs {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        Self {
+            categories: matches.get_many::<i32>("categories").map(|c| c.copied().collect()),
+            include_officers: matches.get_flag("include-officers"),
+            eigyosho: matches.get_many::<i32>("eigyosho").map(|c| c.copied().collect()),
+        }
+    }
+
+    /// 対象年月のドライバー取得条件
+    pub fn query(&self, year: i32, month: u32) -> DriverQuery {
+        DriverQuery::new(year, month)
+            .categories(self.categories.clone())
+            .include_officers(self.include_officers)
+            .eigyosho(self.eigyosho.clone())
+    }
+}
+
+/// verifyの対象ドライバー指定
+///   --sample N [--seed S]: 給与区分ごとに層別した N 人（seed省略時は0）
+///   --drivers-from-file path: ファイルのドライバーIDのみ
+pub fn driver_selection(matches: &ArgMatches) -> Result<DriverSelection, String> {
+    if let Some(&size) = matches.get_one::<u64>("sample") {
+        let size = size as usize;
+        let seed = matches.get_one::<u64>("seed").copied().unwrap_or(0);
+        return Ok(DriverSelection::Sample { size, seed });
+    }
+    match matches.get_one::<String>("drivers-from-file") {
+        Some(path) => Ok(DriverSelection::Ids(verify::read_driver_ids(path)?)),
+        None => Ok(DriverSelection::All),
+    }
+}
+
+/// JSONモードの（入力JSON, 出力PDF）の組
+///   - 入力がファイル: 出力は output（未指定なら拡張子を .pdf にしたパス）
+///   - 入力がディレクトリ: 中の *.json を名前順に、同じ名前の .pdf を output ディレクトリ（未指定なら入力と同じ）へ
+pub fn json_mode_jobs(input: &Path, output: Option<&Path>) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    if !input.is_dir() {
+        if !input.is_file() {
+            return Err(format!("座標JSONが見つかりません: {}", input.display()));
+        }
+        let output = output.map(Path::to_path_buf).unwrap_or_else(|| input.with_extension("pdf"));
+        return Ok(vec![(input.to_path_buf(), output)]);
+    }
+
+    let entries = std::fs::read_dir(input).map_err(|e| format!("ディレクトリを読み込めません: {}: {}", input.display(), e))?;
+    let mut inputs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")))
+        .collect();
+    if inputs.is_empty() {
+        return Err(format!("座標JSON（*.json）がありません: {}", input.display()));
+    }
+    inputs.sort();
+    let outdir = output.unwrap_or(input);
+    Ok(inputs
+        .into_iter()
+        .map(|path| {
+            let name = path.with_extension("pdf").file_name().map(PathBuf::from).unwrap_or_default();
+            (path, outdir.join(name))
+        })
+        .collect())
+}
+
+/// diff-coordsの比較条件
+///   --tolerance mm: 位置ずれの許容差（既定0.3mm）
+///   --ignore SetFont,Ln: 比較しない要素の種類（カンマ区切り、複数回指定可）
+pub fn diff_options(matches: &ArgMatches) -> DiffOptions {
+    DiffOptions {
+        tolerance_mm: matches.get_one::<f64>("tolerance").copied().unwrap_or(DEFAULT_TOLERANCE_MM),
+        ignore_types: matches
+            .get_many::<String>("ignore")
+            .into_iter()
+            .flatten()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
+    }
+}
+
+fn parse_tolerance(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|t| t.is_finite() && *t >= 0.0)
+        .ok_or_else(|| "0以上のmmを指定してください".to_string())
+}
+
+/// 確認なしで実行できる対象月の範囲（現在月から前後何ヶ月まで）
+const MAX_MONTHS_WITHOUT_CONFIRM: i32 = 2;
+
+/// 現在時刻（JST）での前月
+pub(crate) fn previous_month_jst(now: DateTime<Utc>) -> (i32, u32) {
+    let jst = FixedOffset::east_opt(9 * 3600).unwrap();
+    let today = now.with_timezone(&jst).date_naive();
+    if today.month() == 1 {
+        (today.year() - 1, 12)
+    } else {
+        (today.year(), today.month() - 1)
+    }
+}
+
+/// 現在月（JST）から対象月までの月数（過去はマイナス）
+fn months_from_now(year: i32, month: u32, now: DateTime<Utc>) -> i32 {
+    let jst = FixedOffset::east_opt(9 * 3600).unwrap();
+    let today = now.with_timezone(&jst).date_naive();
+    (year * 12 + month as i32) - (today.year() * 12 + today.month() as i32)
+}
+
+/// DBを使うモードの対象指定（位置引数の年月・--month・ドライバーID）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TargetArgs {
+    /// 位置引数の年月
+    pub positional: Option<(i32, u32)>,
+    /// --month YYYY-MM
+    pub month_option: Option<(i32, u32)>,
+    /// --from YYYY-MM --to YYYY-MM（pdf・pdf-shukei）
+    pub range: Option<MonthRange>,
+    pub driver_id: Option<i32>,
+    /// ドライバーIDを位置引数で指定したか（非推奨、--driver-id を使う）
+    pub positional_driver_id: bool,
+}
+
+impl TargetArgs {
+    /// 位置引数は [年 月] [ドライバーID]（--month 指定時は [ドライバーID] のみ）
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let values: Vec<&String> = matches.get_many::<String>("target").into_iter().flatten().collect();
+        let month_option = matches.get_one::<(i32, u32)>("month").copied();
+        let range = match matches.try_get_one::<(i32, u32)>("from").ok().flatten().zip(matches.try_get_one::<(i32, u32)>("to").ok().flatten()) {
+            Some(_) if month_option.is_some() => return Err("--month と --from/--to は同時に指定できません".to_string()),
+            Some((from, to)) => Some(MonthRange::new(*from, *to)?),
+            None => None,
+        };
+        let (positional, driver) = match (month_option.is_some() || range.is_some(), values.as_slice()) {
+            (true, []) | (false, []) => (None, None),
+            (true, [driver]) => (None, Some(*driver)),
+            (true, _) => return Err("--month・--from/--to 指定時の位置引数はドライバーIDのみです".to_string()),
+            (false, [value]) => return Err(format!("年と月の両方を指定してください: {}", value)),
+            (false, [year, month, rest @ ..]) => (Some(check_year_month(year, month)?), rest.first().copied()),
+        };
+        let positional_driver_id = driver.is_some();
+        let driver = driver
+            .map(|d| d.parse::<i32>().ok().filter(|id| *id > 0).ok_or_else(|| format!("ドライバーIDが不正です: {}", d)))
+            .transpose()?;
+        let option = matches.try_get_one::<i32>("driver-id").ok().flatten().copied();
+        let driver_id = match (driver, option) {
+            (Some(_), Some(_)) => return Err("ドライバーIDは位置引数か --driver-id のどちらかで指定してください".to_string()),
+            (d, o) => d.or(o),
+        };
+        Ok(Self { positional, month_option, range, driver_id, positional_driver_id })
+    }
+}
+
+/// 対象年月の解決結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetMonth {
+    pub year: i32,
+    pub month: u32,
+    /// 確認が必要な範囲外の月か
+    pub needs_confirmation: bool,
+}
+
+/// 対象年月を決定する（全モード共通）
+///   1. --month YYYY-MM・--from YYYY-MM（明示指定、確認不要。範囲は開始月）
+///   2. 位置引数 年 月
+///   3. 未指定なら前月（JST）
+///
+/// 現在月から MAX_MONTHS_WITHOUT_CONFIRM ヶ月より離れている場合は needs_confirmation
+pub fn resolve_target_month(target: &TargetArgs, now: DateTime<Utc>) -> TargetMonth {
+    if let Some((year, month)) = target.month_option.or(target.range.map(|r| r.from)) {
+        return TargetMonth { year, month, needs_confirmation: false };
+    }
+    let (year, month) = target.positional.unwrap_or_else(|| previous_month_jst(now));
+    let needs_confirmation = months_from_now(year, month, now).abs() > MAX_MONTHS_WITHOUT_CONFIRM;
+    TargetMonth { year, month, needs_confirmation }
+}
+
+/// 範囲外の対象月を実行してよいか確認（--yes 指定時、または端末で y と答えた場合のみ true）
+pub fn confirm_target_month(target: &TargetMonth, yes: bool) -> bool {
+    if !target.needs_confirmation || yes {
+        return true;
+    }
+    eprintln!(
+        "対象 {}年{}月 は現在月から{}ヶ月以上離れています。",
+        target.year, target.month, MAX_MONTHS_WITHOUT_CONFIRM + 1
+    );
+    if !io::stdin().is_terminal() {
+        eprintln!("実行する場合は --yes または --month YYYY-MM を指定してください");
+        return false;
+    }
+    eprint!("続行しますか? [y/N]: ");
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok();
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    /// サブコマンドの引数を解析（例: ["pdf", "2025", "12"]）
+    fn parse(list: &[&str]) -> ArgMatches {
+        let matches = command().try_get_matches_from(std::iter::once("bin").chain(list.iter().copied())).unwrap();
+        matches.subcommand().unwrap().1.clone()
+    }
+
+    fn target(list: &[&str]) -> Result<TargetArgs, String> {
+        TargetArgs::from_matches(&parse(list))
+    }
+
+    #[test]
+    fn test_command_definition() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn test_invalid_arguments_are_usage_errors() {
+        // 月の入れ忘れ（2026 1523）は前月に読み替えず、引数の誤り（終了コード2）
+        let err = target(&["pdf", "2026", "1523"]).unwrap_err();
+        assert!(err.contains("1523"));
+        assert!(target(&["pdf", "1999", "12"]).is_err());
+        assert!(target(&["pdf", "2026"]).is_err());
+        for list in [&["pdf", "--month", "2026-13"][..], &["pdf", "2025", "12", "1071", "9"], &["server", "http"], &["pfd"], &["pdf", "--kosoku-source", "php"], &["kosoku-debug", "--day", "32"]] {
+            let err = command().try_get_matches_from(std::iter::once("bin").chain(list.iter().copied())).unwrap_err();
+            assert_eq!(err.exit_code(), 2, "{:?}", list);
+        }
+    }
+
+    #[test]
+    fn test_driver_filter_args() {
+        let filter = DriverFilterArgs::from_matches(&parse(&["pdf", "2025", "12", "--eigyosho", "1,2", "--include-yakuin"]));
+        assert_eq!(filter.eigyosho, Some(vec![1, 2]));
+        assert!(filter.include_officers);
+
+        let filter = DriverFilterArgs::from_matches(&parse(&["pdf", "2025", "12"]));
+        assert_eq!((filter.eigyosho, filter.include_officers), (None, false));
+    }
+
+    #[test]
+    fn test_target_args() {
+        let t = target(&["pdf", "2025", "12", "1071"]).unwrap();
+        assert_eq!((t.positional, t.driver_id, t.positional_driver_id), (Some((2025, 12)), Some(1071), true));
+        let t = target(&["pdf", "--month", "2025-12", "1071"]).unwrap();
+        assert_eq!((t.month_option, t.positional, t.driver_id), (Some((2025, 12)), None, Some(1071)));
+        let t = target(&["pdf", "2025", "12", "--driver-id", "1071"]).unwrap();
+        assert_eq!((t.driver_id, t.positional_driver_id), (Some(1071), false));
+        assert!(target(&["pdf", "2025", "12", "1071", "--driver-id", "1072"]).is_err());
+        assert!(target(&["pdf", "--month", "2025-12", "2025", "12"]).is_err());
+        // 共通オプションはサブコマンドの前にも書ける
+        let matches = command().try_get_matches_from(["bin", "--month", "2025-11", "verify"]).unwrap();
+        let t = TargetArgs::from_matches(matches.subcommand_matches("verify").unwrap()).unwrap();
+        assert_eq!(t.month_option, Some((2025, 11)));
+
+        let matches = parse(&["kosoku-debug", "2025", "12", "--driver-id", "1523", "--day", "5", "--json", "--kosoku-source", "php_tcdc"]);
+        assert_eq!(TargetArgs::from_matches(&matches).unwrap().driver_id, Some(1523));
+        assert_eq!((matches.get_one::<u32>("day"), matches.get_flag("json")), (Some(&5), true));
+        assert_eq!(matches.get_one::<db::KosokuSource>("kosoku-source"), Some(&db::KosokuSource::PhpTcDc));
+    }
+
+    #[test]
+    fn test_month_range_args() {
+        let t = target(&["pdf", "--from", "2025-12", "--to", "2026-01", "--driver-id", "1071"]).unwrap();
+        assert_eq!(t.range.map(|r| r.months()), Some(vec![(2025, 12), (2026, 1)]));
+        assert!(!resolve_target_month(&t, Utc::now()).needs_confirmation);
+        assert!(target(&["pdf", "--from", "2026-02", "--to", "2026-01"]).is_err());
+        assert!(target(&["pdf", "--from", "2025-12", "--to", "2026-01", "2025", "12"]).is_err());
+        assert!(target(&["pdf", "--from", "2025-12", "--to", "2026-01", "--month", "2025-12"]).is_err());
+        // --from だけ・範囲なしの --split-months・--output との併用は引数の誤り
+        for list in [&["pdf", "--from", "2025-12"][..], &["pdf", "2025", "12", "--split-months"], &["pdf-shukei", "--from", "2025-12", "--to", "2026-01", "--split-months", "--output", "a.pdf"]] {
+            assert!(command().try_get_matches_from(std::iter::once("bin").chain(list.iter().copied())).is_err(), "{:?}", list);
+        }
+    }
+
+    #[test]
+    fn test_diff_options() {
+        let options = diff_options(&parse(&["diff-coords", "a.json", "b.json", "--tolerance", "0.5", "--ignore", "SetFont, Ln", "--ignore=Image"]));
+        assert_eq!(options.tolerance_mm, 0.5);
+        assert_eq!(options.ignore_types.len(), 3);
+        assert!(options.ignore_types.contains("Ln"));
+
+        assert_eq!(diff_options(&parse(&["diff-coords", "a", "b"])).tolerance_mm, DEFAULT_TOLERANCE_MM);
+        assert!(command().try_get_matches_from(["bin", "diff-coords", "a", "b", "--tolerance", "-1"]).is_err());
+    }
+
+    #[test]
+    fn test_json_mode_jobs() {
+        let dir = std::env::temp_dir().join(format!("timecard_json_jobs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.json", "a.JSON", "note.txt"] {
+            std::fs::write(dir.join(name), "{}").unwrap();
+        }
+
+        let jobs = json_mode_jobs(&dir, None).unwrap();
+        assert_eq!(jobs, [(dir.join("a.JSON"), dir.join("a.pdf")), (dir.join("b.json"), dir.join("b.pdf"))]);
+        let out = Path::new("out");
+        assert_eq!(json_mode_jobs(&dir, Some(out)).unwrap()[1].1, out.join("b.pdf"));
+        assert_eq!(json_mode_jobs(&dir.join("b.json"), None).unwrap(), [(dir.join("b.json"), dir.join("b.pdf"))]);
+        assert_eq!(json_mode_jobs(&dir.join("b.json"), Some(out)).unwrap()[0].1, out);
+        assert!(json_mode_jobs(&dir.join("missing.json"), None).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_previous_month_at_year_boundary() {
+        // 1月3日 → 前年12月
+        assert_eq!(previous_month_jst(utc(2026, 1, 3, 0, 0)), (2025, 12));
+        // UTC 12/31 15:30 = JST 1/1 00:30 → 12月
+        assert_eq!(previous_month_jst(utc(2025, 12, 31, 15, 30)), (2025, 12));
+        // UTC 12/31 14:00 = JST 12/31 23:00 → 11月
+        assert_eq!(previous_month_jst(utc(2025, 12, 31, 14, 0)), (2025, 11));
+        assert_eq!(previous_month_jst(utc(2026, 3, 15, 0, 0)), (2026, 2));
+    }
+
+    #[test]
+    fn test_resolve_default_is_previous_month() {
+        let t = resolve_target_month(&target(&["pdf"]).unwrap(), utc(2026, 1, 3, 0, 0));
+        assert_eq!((t.year, t.month), (2025, 12));
+        assert!(!t.needs_confirmation);
+    }
+
+    #[test]
+    fn test_resolve_old_positional_needs_confirmation() {
+        let now = utc(2026, 6, 10, 0, 0);
+        let t = resolve_target_month(&target(&["pdf", "2025", "12"]).unwrap(), now);
+        assert_eq!((t.year, t.month), (2025, 12));
+        assert!(t.needs_confirmation);
+        assert!(confirm_target_month(&t, true));
+
+        // 2ヶ月前までは確認不要
+        let t = resolve_target_month(&target(&["pdf", "2026", "4"]).unwrap(), now);
+        assert!(!t.needs_confirmation);
+        // 未来も同様
+        let t = resolve_target_month(&target(&["pdf", "2026", "12"]).unwrap(), now);
+        assert!(t.needs_confirmation);
+    }
+
+    #[test]
+    fn test_driver_selection() {
+        let selection = driver_selection(&parse(&["verify", "--sample", "20", "--seed=7"])).unwrap();
+        assert_eq!(selection, DriverSelection::Sample { size: 20, seed: 7 });
+        assert_eq!(driver_selection(&parse(&["verify"])).unwrap(), DriverSelection::All);
+        assert!(command().try_get_matches_from(["bin", "verify", "--sample", "0"]).is_err());
+        assert!(command().try_get_matches_from(["bin", "verify", "--sample", "2", "--drivers-from-file", "ids.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_month_option() {
+        let t = resolve_target_month(&target(&["pdf", "--month", "2024-02"]).unwrap(), utc(2026, 6, 10, 0, 0));
+        assert_eq!((t.year, t.month), (2024, 2));
+        assert!(!t.needs_confirmation);
+    }
+}
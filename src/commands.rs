@@ -0,0 +1,885 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+use crate::audit::SyncSource;
+use crate::coordinate_data::CoordinateData;
+use crate::db::{self, DbConfig, DriverFetchError, DriverQuery, FetchProgress, FetchResult, KosokuSource, TimecardDb};
+use crate::error::TimecardError;
+use crate::kosoku_trace::KosokuDebugReport;
+use crate::month_range::MonthRange;
+use crate::progress::{PhaseTimings, Progress};
+use crate::render_options::{RenderOptions, RenderOverrides};
+use crate::retry::RetryPolicy;
+use crate::tcpdf_compat::{SaveOptions, TcpdfCompat};
+use crate::tls::TlsFiles;
+use crate::{cli, coords_diff, docker_schema, export, kosoku_compare, pdf_split, server, shift_rules, timecard_data, verify, zangyo_report};
+
+/// PDF分割モード: DBからタイムカードを取得して1人1ファイルでPDF生成（DBへの同期はしない）
+pub fn run_pdf_split_mode((year, month): (i32, u32), target_driver_id: Option<i32>, save_options: SaveOptions, driver_filter: &cli::DriverFilterArgs, kosoku_source: KosokuSource, render_options: &RenderOptions, outdir: &str) -> Result<(), TimecardError> {
+    info!("タイムカードPDF生成（1人1ファイル）: {}年{}月 出力先: {}", year, month, outdir);
+
+    let config = DbConfig::production();
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?.with_kosoku_source(kosoku_source);
+    let mut timecards = db.get_all_monthly_timecards_with_kiso(&driver_filter.query(year, month))
+        .map_err(|e| TimecardError::Db(format!("タイムカード取得エラー: {}", e)))?;
+    if let Some(driver_id) = target_driver_id {
+        timecards.retain(|tc| tc.driver.id == driver_id);
+    }
+    info!("取得したタイムカード数: {}", timecards.len());
+
+    let files = pdf_split::render_per_driver(&timecards, render_options, &save_options)
+        .map_err(|e| TimecardError::Output(format!("PDF生成エラー: {}", e)))?;
+    fs::create_dir_all(outdir).map_err(|e| TimecardError::Output(format!("出力先を作成できません: {}: {}", outdir, e)))?;
+    for (name, bytes) in &files {
+        let path = std::path::Path::new(outdir).join(name);
+        fs::write(&path, bytes).map_err(|e| TimecardError::Output(format!("PDF保存エラー: {}: {}", path.display(), e)))?;
+    }
+    println!("{}ファイルを保存しました: {}", files.len(), outdir);
+    Ok(())
+}
+
+/// 座標JSON出力モード: DBからタイムカードを取得し、PDFの描画内容を座標JSONに書き出す（PDFは保存しない）
+/// PHPの pdf_coordinates_*.json と要素ごとに比較するためのもの（出力先の既定は pdf_coordinates_rust_YYYY_MM.json）
+pub fn run_export_coords_mode((year, month): (i32, u32), target_driver_id: Option<i32>, driver_filter: &cli::DriverFilterArgs, kosoku_source: KosokuSource, render_options: &RenderOptions, out: Option<&str>) -> Result<(), TimecardError> {
+    let default_out = format!("pdf_coordinates_rust_{}_{:02}.json", year, month);
+    let out = out.unwrap_or(&default_out);
+
+    let config = DbConfig::production();
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?.with_kosoku_source(kosoku_source);
+    let mut timecards = db.get_all_monthly_timecards_with_kiso(&driver_filter.query(year, month))
+        .map_err(|e| TimecardError::Db(format!("タイムカード取得エラー: {}", e)))?;
+    if let Some(driver_id) = target_driver_id {
+        timecards.retain(|tc| tc.driver.id == driver_id);
+    }
+    info!("取得したタイムカード数: {}", timecards.len());
+
+    let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+    pdf.record_coordinates();
+    pdf.render_timecards(&timecards, render_options).map_err(|e| TimecardError::Output(format!("PDF生成エラー: {}", e)))?;
+    let data = pdf.take_coordinates().expect("record_coordinates 済み");
+    let json = serde_json::to_string_pretty(&data)
+        .map_err(|e| TimecardError::Output(format!("座標JSONを作成できません: {}", e)))?;
+    fs::write(out, json).map_err(|e| TimecardError::Output(format!("座標JSONを保存できません: {}: {}", out, e)))?;
+    println!("{}ページ・{}要素を保存しました: {}", data.total_pages, data.elements.len(), out);
+    Ok(())
+}
+
+/// CSV出力モード: 日別のタイムカードを分析用CSV（export::EXPORT_COLUMNS）に出力
+pub fn run_csv_mode((year, month): (i32, u32), target_driver_id: Option<i32>, driver_filter: &cli::DriverFilterArgs, kosoku_source: KosokuSource, out: Option<&str>, bom: bool) -> Result<(), TimecardError> {
+    let default_out = format!("timecard_{}_{:02}.csv", year, month);
+    let out = out.unwrap_or(&default_out);
+
+    let config = DbConfig::production();
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?.with_kosoku_source(kosoku_source);
+    let mut timecards = db.get_all_monthly_timecards_with_kiso(&driver_filter.query(year, month))
+        .map_err(|e| TimecardError::Db(format!("タイムカード取得エラー: {}", e)))?;
+    if let Some(driver_id) = target_driver_id {
+        timecards.retain(|tc| tc.driver.id == driver_id);
+    }
+    info!("取得したタイムカード数: {}", timecards.len());
+
+    fs::write(out, export::to_csv(&timecards, bom)).map_err(|e| TimecardError::Output(format!("CSVを保存できません: {}: {}", out, e)))?;
+    println!("CSV saved to {}", out);
+    Ok(())
+}
+
+/// 座標JSON比較モード: diff-coords a.json b.json（差分があれば終了コード1）
+pub fn run_diff_coords_mode(path_a: &str, path_b: &str, options: &coords_diff::DiffOptions, json_output: bool) -> Result<(), TimecardError> {
+    let load = |path: &str| -> Result<CoordinateData, TimecardError> {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .map_err(|e| TimecardError::Input(format!("座標JSONを読み込めません: {}: {}", path, e)))
+    };
+    let report = coords_diff::diff(&load(path_a)?, &load(path_b)?, options);
+
+    if json_output {
+        let json = serde_json::to_string_pretty(&report).map_err(|e| TimecardError::Output(format!("JSONを作成できません: {}", e)))?;
+        println!("{}", json);
+    } else {
+        println!("A: {}", path_a);
+        println!("B: {}", path_b);
+        print!("{}", report.to_text());
+    }
+
+    if !report.diffs.is_empty() {
+        return Err(TimecardError::Differences(format!("座標の差分: {}件", report.diffs.len())));
+    }
+    Ok(())
+}
+
+/// フィクスチャモード: MonthlyTimecardの配列（JSON）を読み込んでPDF生成（レイアウト確認用、DB不要）
+/// `/api/timecards` の出力の timecards 部分もそのまま読み込める（集計は再計算しない）
+pub fn run_render_fixture_mode(json_path: &str, output: &str, save_options: SaveOptions, render_options: &RenderOptions) -> Result<(), TimecardError> {
+    let timecards: Vec<timecard_data::MonthlyTimecard> = fs::read_to_string(json_path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        .map_err(|e| TimecardError::Input(format!("フィクスチャを読み込めません: {}: {}", json_path, e)))?;
+    info!("{}人分のタイムカードを読み込みました: {}", timecards.len(), json_path);
+
+    let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+    pdf.set_save_options(save_options);
+    pdf.render_timecards(&timecards, render_options).map_err(|e| TimecardError::Output(format!("PDF生成エラー: {}", e)))?;
+    pdf.save(output).map_err(|e| TimecardError::Output(format!("PDF保存エラー: {}", e)))?;
+    println!("PDF saved to {}", output);
+    Ok(())
+}
+
+/// 残業異常レポート: 全ドライバーの残業元データから異常な日を抽出してファイル出力
+pub fn run_report_zangyo_mode((year, month): (i32, u32), format: &str, driver_filter: &cli::DriverFilterArgs) -> Result<(), TimecardError> {
+    let config = DbConfig::production();
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?;
+
+    let timecards = db.get_all_monthly_timecards_with_kiso(&driver_filter.query(year, month))
+        .map_err(|e| TimecardError::Db(format!("タイムカード取得エラー: {}", e)))?;
+
+    let report_config = zangyo_report::ZangyoReportConfig::from_env();
+    let anomalies = zangyo_report::find_anomalies(&timecards, &report_config);
+    println!("対象ドライバー: {}人, 異常: {}件", timecards.len(), anomalies.len());
+
+    let output_path = format!("zangyo_anomalies_{}_{:02}.{}", year, month, format);
+    let content = if format == "json" {
+        serde_json::to_string_pretty(&anomalies).map_err(|e| TimecardError::Output(format!("レポートを作成できません: {}", e)))?
+    } else {
+        zangyo_report::to_csv(&anomalies)
+    };
+    fs::write(&output_path, content).map_err(|e| TimecardError::Output(format!("レポートを保存できません: {}: {}", output_path, e)))?;
+    println!("Report saved to {}", output_path);
+    Ok(())
+}
+
+/// 比較モード: 本番DB(PHP)とDocker DB(Rust)の time_card_kosoku（TC_DC・デジタコ）を日別に比較
+/// 差が閾値を超える日があれば終了コード1（CIで使う）
+pub fn run_compare_mode((year, month): (i32, u32), target_driver_id: Option<i32>, threshold: i32, out: Option<&str>) -> Result<(), TimecardError> {
+    println!("=== 拘束時間比較: 本番DB(PHP) vs Docker DB(Rust) ===");
+    println!("対象: {}年{}月 / 許容差: {}分", year, month, threshold);
+    println!();
+
+    let config = DbConfig::production();
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?;
+    let (prod, docker) = db.fetch_kosoku_for_compare(year, month, target_driver_id)
+        .map_err(|e| TimecardError::Db(format!("拘束時間取得エラー: {}", e)))?;
+    let rows = kosoku_compare::build_rows(&prod, &docker);
+
+    let minutes = |m: Option<i32>| m.map(|m| m.to_string()).unwrap_or_else(|| "-".to_string());
+    let mut current_driver = None;
+    for row in &rows {
+        if current_driver != Some(row.driver_id) {
+            current_driver = Some(row.driver_id);
+            println!();
+            println!("--- Driver {} ---", row.driver_id);
+            println!("{:>10} {:>8} {:>8} {:>8} {:>8} {:>5}", "日付", "PHP TC", "Rust TC", "PHP デジ", "Rust デジ", "差");
+        }
+        let mark = if row.exceeds(threshold) { " !" } else { "" };
+        println!("{:>10} {:>8} {:>8} {:>8} {:>8} {:>5}{}",
+            row.date, minutes(row.php_tcdc), minutes(row.rust_tcdc),
+            minutes(row.php_digitacho), minutes(row.rust_digitacho), row.max_diff(), mark);
+    }
+
+    let exceeding: Vec<&kosoku_compare::KosokuCompareRow> = rows.iter().filter(|r| r.exceeds(threshold)).collect();
+    println!();
+    println!("比較した日数: {}件（本番 {}行, Docker {}行）", rows.len(), prod.len(), docker.len());
+    println!("許容差（{}分）を超えた日: {}件", threshold, exceeding.len());
+    for row in exceeding.iter().take(20) {
+        println!("  Driver {} / {}: 差 {}分", row.driver_id, row.date, row.max_diff());
+    }
+
+    if let Some(path) = out {
+        fs::write(path, kosoku_compare::to_csv(&rows, threshold))
+            .map_err(|e| TimecardError::Output(format!("{} を保存できません: {}", path, e)))?;
+        println!("CSV saved to {}", path);
+    }
+
+    if !exceeding.is_empty() {
+        return Err(TimecardError::Differences(format!("許容差（{}分）を超えた日: {}件", threshold, exceeding.len())));
+    }
+    Ok(())
+}
+
+/// HTTPサーバーモード（bind 省略時は BIND_ADDR、TLS_CERT / TLS_KEY があればHTTPS）
+/// 証明書・秘密鍵が読めない、DBの設定が不足している・接続できない（skip_db_check なら確認しない）時は待ち受けを始めずにエラー
+pub async fn run_server(bind: Option<SocketAddr>, skip_db_check: bool, render_base: RenderOverrides) -> Result<(), TimecardError> {
+    let bind = server::bind_addr(bind).map_err(TimecardError::Usage)?;
+    let tls = match TlsFiles::from_env().map_err(TimecardError::Usage)? {
+        Some(files) => Some(files.load().map_err(TimecardError::Input)?),
+        None => None,
+    };
+    let configs = [DbConfig::production(), DbConfig::docker()];
+    for config in &configs {
+        info!("{}: {}", config.label(), config);
+    }
+    if skip_db_check {
+        warn!("--skip-db-check: DBの設定・接続を確認せずに起動します");
+    } else {
+        server::check_databases(&configs).await.map_err(TimecardError::Db)?;
+    }
+    // AUTO_MIGRATE=1: Docker DBのテーブルがなければ作成（一意キーの問題は警告のみ）
+    if server::auto_migrate_from_env() {
+        let docker = db::LazyPool::new(DbConfig::docker());
+        tokio::task::spawn_blocking(move || docker.ensure_docker_schema())
+            .await
+            .map_err(|e| TimecardError::Db(format!("テーブル作成エラー: {}", e)))?
+            .map_err(|e| TimecardError::Db(format!("テーブル作成エラー: {}", e)))?;
+    }
+    server::run(bind, tls, render_base).await;
+    Ok(())
+}
+
+/// マイグレーションモード: Docker DBにテーブルを作成し、既存のテーブルは列・一意キーを確認する
+/// 既存のテーブルは変更しない（問題があれば終了コード1）
+pub fn run_migrate_mode() -> Result<(), TimecardError> {
+    let config = DbConfig::docker();
+    config.validate().map_err(TimecardError::Db)?;
+    info!("{}: {}", config.label(), config);
+    let pool = db::LazyPool::new(config);
+    let report = RetryPolicy::from_env()
+        .run("Docker DBのテーブル作成", || pool.ensure_docker_schema())
+        .map_err(|e| TimecardError::Db(format!("テーブル作成エラー: {}", e)))?;
+    for table in docker_schema::TABLES {
+        let status = if report.created.contains(&table.name) { "作成しました" } else { "既存" };
+        println!("{}: {}", table.name, status);
+    }
+    println!("スキーマバージョン: {}", docker_schema::SCHEMA_VERSION);
+    if !report.problems.is_empty() {
+        for problem in &report.problems {
+            println!("  ! {}", problem);
+        }
+        return Err(TimecardError::Db(format!("Docker DBのスキーマに問題があります（{}件）", report.problems.len())));
+    }
+    Ok(())
+}
+
+/// 履歴モード: ドライバー・月の time_card_allowance_log を古い順に表示
+pub fn run_history_mode((year, month): (i32, u32), driver_id: Option<i32>) -> Result<(), TimecardError> {
+    let driver_id = driver_id.ok_or_else(|| TimecardError::Usage("history にはドライバーID（--driver-id）が必要です".to_string()))?;
+    let config = DbConfig::docker();
+    info!("{}: {}", config.label(), config);
+    let pool = db::LazyPool::new(config);
+    let records = RetryPolicy::from_env()
+        .run("監査ログの取得", || pool.allowance_history(driver_id, year, month))
+        .map_err(|e| TimecardError::Db(format!("監査ログの取得エラー: {}", e)))?;
+
+    println!("=== ドライバーID {} {}年{}月 の変更履歴 ===", driver_id, year, month);
+    if records.is_empty() {
+        println!("記録がありません（AUDIT_LOG=1 で同期した変更のみ記録されます）");
+        return Ok(());
+    }
+    for record in &records {
+        println!("{}  {:<19}  {:<6}  {}", record.logged_at, record.table, record.action, record.source);
+        let value = |v: &Option<serde_json::Value>| v.as_ref().map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+        for (key, before, after) in record.changes() {
+            println!("    {:<24}  {:>8} → {}", key, value(&before), value(&after));
+        }
+    }
+    Ok(())
+}
+
+/// 拘束時間デバッグモード: 1人分の calculate_kosoku_from_punches・calculate_kosoku_digitacho を計算過程つきで実行
+pub fn run_kosoku_debug_mode((year, month): (i32, u32), driver_id: Option<i32>, day: Option<u32>, json_output: bool) -> Result<(), TimecardError> {
+    let driver_id = driver_id.ok_or_else(|| TimecardError::Usage("kosoku-debug にはドライバーID（--driver-id）が必要です".to_string()))?;
+    if let Some(day) = day.filter(|&d| chrono::NaiveDate::from_ymd_opt(year, month, d).is_none()) {
+        return Err(TimecardError::Usage(format!("{}年{}月に{}日はありません", year, month, day)));
+    }
+
+    let config = DbConfig::production();
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?;
+    let tcdc = db.calculate_kosoku_from_punches_with_trace(driver_id, year, month)
+        .map_err(|e| TimecardError::Db(format!("拘束時間（TC_DC版）の計算エラー: {}", e)))?;
+    let (digitacho, warnings, digitacho_trace) = db.calculate_kosoku_digitacho_with_trace(driver_id, year, month)
+        .map_err(|e| TimecardError::Db(format!("拘束時間（デジタコ版）の計算エラー: {}", e)))?;
+    let report = KosokuDebugReport::new(driver_id, year, month, day, tcdc, (digitacho.into_iter().collect(), digitacho_trace), warnings);
+
+    if json_output {
+        let json = serde_json::to_string_pretty(&report).map_err(|e| TimecardError::Output(format!("JSONを作成できません: {}", e)))?;
+        println!("{}", json);
+    } else {
+        print!("{}", report.to_text());
+    }
+    Ok(())
+}
+
+/// DBモード: 本番DBからタイムカードデータを取得
+pub fn run_db_mode((year, month): (i32, u32), target_driver_id: Option<i32>, driver_filter: &cli::DriverFilterArgs, kosoku_source: KosokuSource, render_options: &RenderOptions) -> Result<(), TimecardError> {
+    info!("タイムカードデータ取得: {}年{}月", year, month);
+    if let Some(id) = target_driver_id {
+        info!("ドライバーID: {}", id);
+    }
+
+    // 本番DBに接続
+    let config = DbConfig::production();
+    info!("接続先: {}:{}", config.host, config.port);
+
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?.with_kosoku_source(kosoku_source);
+    info!("接続成功");
+
+    // ドライバー一覧を取得
+    let drivers = db.get_active_drivers(&driver_filter.query(year, month))
+        .map_err(|e| TimecardError::Db(format!("ドライバー取得エラー: {}", e)))?;
+
+    info!("アクティブドライバー数: {}", drivers.len());
+
+    // 特定のドライバーIDが指定されていればそのドライバーを、なければ最初の3人を表示
+    let target_drivers: Vec<&timecard_data::Driver> = if let Some(id) = target_driver_id {
+        drivers.iter().filter(|d| d.id == id).collect()
+    } else {
+        drivers.iter().take(3).collect()
+    };
+
+    let punch_symbols = &render_options.punch_symbols;
+    let mismatch_threshold = timecard_data::kosoku_mismatch_minutes_from_env();
+    for driver in target_drivers {
+        let timecard = match db.get_monthly_timecard(driver, year, month) {
+            Ok(tc) => tc,
+            Err(e) => {
+                error!(driver_id = driver.id, "タイムカード取得エラー ({}): {}", driver.name, e);
+                continue;
+            }
+        };
+
+        println!("=== {} ({}) ===", timecard.driver.name, timecard.year_month_str());
+        println!("{:>2} {:>2} {:>7} {:>7} {:>7} {:>7} {:>5} {:>6} {:>6} {:>6} 備考",
+            "日", "曜", "出勤1", "退社1", "出勤2", "退社2", "残業", "拘束", "TC_DC", "デジタコ");
+        println!("{}", "-".repeat(94));
+
+        for day in &timecard.days {
+            // デジタコがある日は [08:00]、ない日は <08:00>
+            let [in1, out1, in2, out2] = punch_symbols.punches(day);
+            let zangyo = day.zangyo_with_tsuika_str();
+            let kosoku = day.kosoku_str();
+
+            let sunday_mark = if day.is_sunday { "*" } else { " " };
+            // 備考 = remarks + detail_st（優先順に「・」で連結）
+            let remarks = day.remarks_text("・");
+            // TC_DC版・デジタコ版の個別の値（差が閾値を超える日は!）
+            let minutes = |m: Option<i32>| m.map(|m| m.to_string()).unwrap_or_default();
+            let mismatch_mark = if day.kosoku_mismatch(mismatch_threshold) { "!" } else { "" };
+            println!("{}{:>2} {:>2} {:>7} {:>7} {:>7} {:>7} {:>5} {:>6} {:>6} {:>6}{} {}",
+                sunday_mark, day.day, day.weekday, in1, out1, in2, out2, zangyo, kosoku,
+                minutes(day.kosoku_tcdc), minutes(day.kosoku_digitacho), mismatch_mark, remarks);
+            // 3ペア目以降の打刻
+            for pair in timecard_data::PRINTED_PUNCH_PAIRS..day.clock_in.len().max(day.clock_out.len()) {
+                let get = |list: &Vec<String>| list.get(pair).map(|t| punch_symbols.wrap(day, t)).unwrap_or_default();
+                println!("      出勤{} {:>7} 退社{} {:>7}", pair + 1, get(&day.clock_in), pair + 1, get(&day.clock_out));
+            }
+            for warning in &day.warnings {
+                println!("      [WARN] {}", warning);
+            }
+        }
+
+        // 残業の内訳（どの行から来たか）
+        for day in timecard.days.iter().filter(|d| !d.zangyo_sources.is_empty()) {
+            let sources: Vec<String> = day.zangyo_sources.iter()
+                .map(|s| format!("{}#{} {}", s.table.as_str(), s.row_id, s.amount))
+                .collect();
+            println!("残業内訳 {:>2}日: {}", day.day, sources.join(", "));
+        }
+        for warning in &timecard.summary.warnings {
+            println!("[WARN] {}", warning);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// PDFモードのDocker DBへの同期
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncMode {
+    /// 差分を書き込む（デフォルト）
+    Write,
+    /// 同期しない（--no-sync）
+    Skip,
+    /// 変わる内容を表示するだけ（--dry-run）
+    DryRun,
+}
+
+/// 同期のドライランの結果を表で表示
+fn print_change_set(changes: &db::SyncChangeSet) {
+    println!("=== {}年{}月 Docker DBへの同期（dry-run、書き込みなし） ===", changes.year, changes.month);
+    println!("time_card_allowance: 変更 {}人, 変更なし {}人", changes.allowance.len(), changes.allowance_unchanged);
+    if !changes.allowance.is_empty() {
+        println!("{:>6}  {:<12}  {:<4}  {:<24}  {:>10}  {:>10}", "ID", "氏名", "種別", "列", "変更前", "変更後");
+        for change in &changes.allowance {
+            for field in &change.fields {
+                let before = field.before.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+                println!("{:>6}  {:<12}  {:<4}  {:<24}  {:>10}  {:>10}",
+                         change.driver_id, change.driver_name, change.kind.label(), field.field, before, field.after);
+            }
+        }
+    }
+    println!("time_card_kosoku: 変更 {}行, 変更なし {}行", changes.kosoku.len(), changes.kosoku_unchanged);
+    if !changes.kosoku.is_empty() {
+        println!("{:>6}  {:<10}  {:<8}  {:<4}  {:>8}  {:>8}", "ID", "日付", "種類", "種別", "変更前", "変更後");
+        let minutes = |m: Option<i32>| m.map(|m| m.to_string()).unwrap_or_else(|| "-".to_string());
+        for change in &changes.kosoku {
+            println!("{:>6}  {:<10}  {:<8}  {:<4}  {:>8}  {:>8}",
+                     change.driver_id, change.date, change.kosoku_type, change.kind.label(), minutes(change.before), minutes(change.after));
+        }
+    }
+    println!();
+}
+
+/// PDFモード: DBからタイムカードを取得してPDF生成
+/// 複数月（--from/--to）は月ごとに基礎日数・取得・同期を行い、1つのPDF（--split-months なら月ごとのファイル）にする
+#[allow(clippy::too_many_arguments)]
+pub fn run_pdf_mode(range: MonthRange, target_driver_id: Option<i32>, save_options: SaveOptions, driver_filter: &cli::DriverFilterArgs, kosoku_source: KosokuSource, render_options: &RenderOptions, sync: SyncMode, delete_stale: bool, output: Option<&str>, jobs: usize, split_months: bool) -> Result<(), TimecardError> {
+    info!("タイムカードPDF生成: {}", range);
+    if let Some(id) = target_driver_id {
+        info!("ドライバーID: {}", id);
+    }
+
+    // 本番DBに接続
+    let mut timings = PhaseTimings::start();
+    let config = DbConfig::production();
+    info!("接続先: {}:{}", config.host, config.port);
+
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?.with_kosoku_source(kosoku_source);
+    info!("接続成功");
+    timings.lap("DB接続");
+
+    // 同期に失敗してもPDFは出力し、最後に失敗として返す
+    let mut sync_errors = Vec::new();
+    let mut failed = Vec::new();
+    let mut months = Vec::new();
+    for (year, month) in range.months() {
+        if !range.is_single() {
+            info!("--- {}年{}月 ---", year, month);
+        }
+
+        // 基礎日数を取得
+        let kiso_date = db.get_kiso_date(year, month).map_err(|e| TimecardError::Db(format!("基礎日数取得エラー: {}", e)))?;
+        info!("基礎日数: {}", kiso_date);
+        timings.lap("基礎日数");
+
+        // タイムカードを取得
+        let fetched = fetch_timecards_with_progress(&db, &driver_filter.query(year, month), jobs, &mut timings)?;
+        let mut timecards = fetched.timecards;
+        failed.extend(fetched.failed);
+
+        // 特定ドライバーのみにフィルタリング
+        if let Some(driver_id) = target_driver_id {
+            timecards.retain(|tc| tc.driver.id == driver_id);
+        }
+
+        info!("取得したタイムカード数: {}", timecards.len());
+
+        if sync == SyncMode::DryRun {
+            let changes = db.sync_to_docker_dry_run(year, month, &timecards, delete_stale)
+                .map_err(|e| TimecardError::Db(format!("Docker DBとの比較に失敗しました: {}", e)))?;
+            print_change_set(&changes);
+            timings.lap("同期の比較（dry-run）");
+            months.push(((year, month), timecards));
+            continue;
+        }
+        if sync == SyncMode::Write {
+            // time_card_allowanceテーブルを差分更新（Docker DB）
+            match db.sync_all_timecard_allowances_to_docker(&timecards, SyncSource::CliPdf) {
+                Ok((inserted, updated, unchanged)) => {
+                    info!("time_card_allowance（Docker DB）を差分更新: 追加: {}, 更新: {}, 変更なし: {}",
+                          inserted, updated, unchanged);
+                }
+                Err(e) => {
+                    error!("time_card_allowance（Docker DB）の同期失敗: {}", e);
+                    sync_errors.push(format!("{}年{}月 time_card_allowance: {}", year, month, e));
+                }
+            }
+            timings.lap("同期（time_card_allowance）");
+
+            // time_card_kosokuテーブルを差分更新（Docker DB）- TC_DCとデジタコを別々に
+            match db.sync_kosoku_to_docker(&timecards, delete_stale, SyncSource::CliPdf) {
+                Ok((inserted, updated, unchanged, deleted)) => {
+                    info!("time_card_kosoku（Docker DB）を差分更新: 追加: {}, 更新: {}, 変更なし: {}, 削除: {}",
+                          inserted, updated, unchanged, deleted);
+                }
+                Err(e) => {
+                    error!("time_card_kosoku（Docker DB）の同期失敗: {}", e);
+                    sync_errors.push(format!("{}年{}月 time_card_kosoku: {}", year, month, e));
+                }
+            }
+            timings.lap("同期（time_card_kosoku）");
+        } else {
+            info!("Docker DBへの同期なし（--no-sync）");
+        }
+        months.push(((year, month), timecards));
+    }
+
+    if sync == SyncMode::DryRun {
+        info!("{}", timings.report().trim_end());
+        return fetch_failures(months.iter().map(|(_, timecards)| timecards.len()).sum(), &failed);
+    }
+
+    // PDF生成
+    let paths = save_month_pdfs(range, &months, split_months, &save_options, render_options, TcpdfCompat::render_timecards, &mut timings, |r| {
+        match (output, target_driver_id) {
+            (Some(path), _) => path.to_string(),
+            (None, Some(id)) => format!("timecard_{}_{}.pdf", r.file_label(), id),
+            (None, None) => format!("timecard_{}.pdf", r.file_label()),
+        }
+    })?;
+
+    for path in &paths {
+        println!("PDF saved to {}", path);
+    }
+    info!("{}", timings.report().trim_end());
+    if !sync_errors.is_empty() {
+        return Err(TimecardError::Db(format!("Docker DBへの同期に失敗しました（PDFは保存済み）: {}", sync_errors.join(" / "))));
+    }
+    fetch_failures(months.iter().map(|(_, timecards)| timecards.len()).sum(), &failed)
+}
+
+/// PDF集計モード: DBからタイムカードを取得してPDF生成（1人/ページ、日付横並び）
+#[allow(clippy::too_many_arguments)]
+pub fn run_pdf_shukei_mode(range: MonthRange, target_driver_id: Option<i32>, save_options: SaveOptions, driver_filter: &cli::DriverFilterArgs, kosoku_source: KosokuSource, render_options: &RenderOptions, output: Option<&str>, jobs: usize, split_months: bool) -> Result<(), TimecardError> {
+    info!("タイムカードPDF生成（集計モード、1人1ページ・日付横並び）: {}", range);
+    if let Some(id) = target_driver_id {
+        info!("ドライバーID: {}", id);
+    }
+
+    // 本番DBに接続
+    let mut timings = PhaseTimings::start();
+    let config = DbConfig::production();
+    info!("接続先: {}:{}", config.host, config.port);
+
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?.with_kosoku_source(kosoku_source);
+    info!("接続成功");
+    timings.lap("DB接続");
+
+    let mut failed = Vec::new();
+    let mut months = Vec::new();
+    for (year, month) in range.months() {
+        if !range.is_single() {
+            info!("--- {}年{}月 ---", year, month);
+        }
+
+        // 全ドライバーのタイムカードを取得（基礎日数付き）
+        let fetched = fetch_timecards_with_progress(&db, &driver_filter.query(year, month), jobs, &mut timings)?;
+        failed.extend(fetched.failed);
+
+        // 特定のドライバーIDが指定されていればフィルタ
+        let timecards: Vec<_> = if let Some(id) = target_driver_id {
+            fetched.timecards.into_iter().filter(|tc| tc.driver.id == id).collect()
+        } else {
+            fetched.timecards
+        };
+
+        info!("取得したタイムカード数: {}", timecards.len());
+        months.push(((year, month), timecards));
+    }
+
+    // PDF生成（集計モード）
+    let paths = save_month_pdfs(range, &months, split_months, &save_options, render_options, TcpdfCompat::render_timecards_shukei, &mut timings, |r| {
+        output.map(str::to_string).unwrap_or_else(|| format!("timecard_shukei_{}.pdf", r.file_label()))
+    })?;
+
+    for path in &paths {
+        println!("PDF saved to {}", path);
+    }
+    info!("{}", timings.report().trim_end());
+    fetch_failures(months.iter().map(|(_, timecards)| timecards.len()).sum(), &failed)
+}
+
+/// 月ごとのタイムカードをPDFに描画して保存し、保存したパスを返す
+///   - split_months: 月ごとに1ファイル
+///   - それ以外: range 全体で1ファイル（2か月以上なら月の区切りページを入れる）
+#[allow(clippy::too_many_arguments)]
+fn save_month_pdfs(
+    range: MonthRange,
+    months: &[((i32, u32), Vec<timecard_data::MonthlyTimecard>)],
+    split_months: bool,
+    save_options: &SaveOptions,
+    render_options: &RenderOptions,
+    render: fn(&mut TcpdfCompat, &[timecard_data::MonthlyTimecard], &RenderOptions) -> Result<(), String>,
+    timings: &mut PhaseTimings,
+    path: impl Fn(&MonthRange) -> String,
+) -> Result<Vec<String>, TimecardError> {
+    let files: Vec<(MonthRange, &[_])> = if split_months {
+        months.iter().map(|entry| (MonthRange::single(entry.0 .0, entry.0 .1), std::slice::from_ref(entry))).collect()
+    } else {
+        vec![(range, months)]
+    };
+
+    let mut paths = Vec::with_capacity(files.len());
+    for (file_range, file_months) in files {
+        // A4横向き: 297mm x 210mm
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.set_save_options(save_options.clone());
+        pdf.render_months(file_months, render_options, render).map_err(|e| TimecardError::Output(format!("PDF生成エラー: {}", e)))?;
+        timings.lap("PDF描画");
+
+        let output_path = path(&file_range);
+        pdf.save(&output_path).map_err(|e| TimecardError::Output(format!("PDF保存エラー: {}", e)))?;
+        timings.lap("保存");
+        paths.push(output_path);
+    }
+    Ok(paths)
+}
+
+/// 全ドライバーのタイムカードを jobs 並列・進捗表示つきで取得（基礎日数付き）
+/// ドライバー一覧とドライバー別のデータ取得の時間を timings に記録する
+/// 取得に失敗したドライバーは警告を出して除き、FetchResult.failed に残す
+fn fetch_timecards_with_progress(db: &TimecardDb, query: &DriverQuery, jobs: usize, timings: &mut PhaseTimings) -> Result<FetchResult, TimecardError> {
+    let mut progress = None;
+    let fetched = db
+        .get_all_monthly_timecards_with_kiso_parallel(query, jobs, |event| match event {
+            FetchProgress::Drivers { total } => {
+                timings.lap("ドライバー取得");
+                progress = Some(Progress::new(&format!("データ取得（{}並列）", jobs.min(db::MAX_FETCH_JOBS)), total));
+            }
+            FetchProgress::Driver { driver, .. } => {
+                if let Some(progress) = progress.as_mut() {
+                    progress.inc(&driver.name);
+                }
+            }
+        })
+        .map_err(|e| TimecardError::Db(format!("タイムカード取得エラー: {}", e)))?;
+    if let Some(progress) = &progress {
+        for failure in &fetched.failed {
+            progress.warn(&format!("タイムカード取得失敗 driver_id={} {}: {}", failure.driver_id, failure.name, failure.message));
+        }
+        progress.finish();
+    }
+    timings.lap("データ取得（ドライバー別）");
+    Ok(fetched)
+}
+
+/// 取得に失敗したドライバーがいれば一部失敗として返す（PDFは保存済み）
+fn fetch_failures(succeeded: usize, failed: &[DriverFetchError]) -> Result<(), TimecardError> {
+    if failed.is_empty() {
+        return Ok(());
+    }
+    Err(TimecardError::Partial { succeeded, failed_driver_ids: failed.iter().map(|f| f.driver_id).collect() })
+}
+
+/// 検証モード: 本番DBから計算してDocker DBにINSERT
+pub fn run_verify_mode((year, month): (i32, u32), driver_filter: &cli::DriverFilterArgs, selection: &verify::DriverSelection, delete_stale: bool) -> Result<(), TimecardError> {
+    info!("検証モード: 拘束時間計算 → Docker DB INSERT: {}年{}月", year, month);
+
+    // 本番DBに接続
+    let config = DbConfig::production();
+    info!("本番DB接続先: {}:{}", config.host, config.port);
+
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?;
+    info!("本番DB接続成功");
+    info!("計算ルール: {}", db.kosoku_rules());
+    info!("遅刻・早退: {}", shift_rules::ShiftRules::global());
+
+    // サンプル実行の場合は対象ドライバーを先に選ぶ
+    let mut query = driver_filter.query(year, month);
+    let mut driver_ids_arg = None;
+    if selection.is_partial() {
+        let drivers = db.get_active_drivers(&query).map_err(|e| TimecardError::Db(format!("ドライバー取得エラー: {}", e)))?;
+        let selected = verify::select_drivers(&drivers, selection);
+        driver_ids_arg = Some(report_verify_selection(selection, &selected, drivers.len(), year, month));
+        query = query.driver_ids(Some(selected.iter().map(|d| d.id).collect()));
+    }
+
+    // 対象ドライバーのタイムカードを取得（拘束時間計算含む）
+    let timecards = db.get_all_monthly_timecards(&query).map_err(|e| TimecardError::Db(format!("タイムカード取得エラー: {}", e)))?;
+
+    info!("取得したタイムカード数: {}", timecards.len());
+
+    // Docker DBにINSERT
+    let (count, deleted) = db.insert_kosoku_to_docker(&timecards, delete_stale).map_err(|e| TimecardError::Db(format!("INSERT失敗: {}", e)))?;
+    println!("Docker DBに拘束時間を{}件INSERTしました（計算結果にない行の削除: {}件）", count, deleted);
+
+    println!();
+    println!("検証コマンド:");
+    println!("  python3 .claude/tools/db_verify.py --compare --year {} --month {}{}", year, month, driver_ids_arg.unwrap_or_default());
+    Ok(())
+}
+
+/// サンプル実行の対象をレポートし、ドライバーIDをファイルに保存
+/// 戻り値は db_verify.py に渡す --driver-ids 引数
+fn report_verify_selection(selection: &verify::DriverSelection, selected: &[timecard_data::Driver], total: usize, year: i32, month: u32) -> String {
+    let ratio = if total > 0 { selected.len() as f64 * 100.0 / total as f64 } else { 0.0 };
+    println!("==============================");
+    match selection {
+        verify::DriverSelection::Sample { seed, .. } => {
+            println!("  サンプル実行: {}/{}人（{:.1}%、seed={}）", selected.len(), total, ratio, seed);
+        }
+        _ => println!("  指定ドライバーのみ実行: {}/{}人", selected.len(), total),
+    }
+    println!("  全体の結果ではありません");
+    println!("==============================");
+
+    let mut by_category: std::collections::BTreeMap<Option<i32>, usize> = std::collections::BTreeMap::new();
+    for d in selected {
+        *by_category.entry(d.category_c).or_default() += 1;
+    }
+    for (category, count) in &by_category {
+        match category {
+            Some(c) => println!("  給与区分 {}: {}人", c, count),
+            None => println!("  給与区分なし: {}人", count),
+        }
+    }
+    if let verify::DriverSelection::Ids(ids) = selection {
+        let missing: Vec<String> = ids.iter()
+            .filter(|id| !selected.iter().any(|d| d.id == **id))
+            .map(|id| id.to_string())
+            .collect();
+        if !missing.is_empty() {
+            warn!("対象月のアクティブドライバーにいないID: {}", missing.join(", "));
+        }
+    }
+
+    let ids: Vec<String> = selected.iter().map(|d| d.id.to_string()).collect();
+    let path = format!("verify_sample_{}_{:02}.txt", year, month);
+    match fs::write(&path, ids.join("\n") + "\n") {
+        Ok(()) => println!("  対象ドライバーIDを {} に保存（--drivers-from-file で再実行可能）", path),
+        Err(e) => warn!("{} を保存できません: {}", path, e),
+    }
+    println!();
+
+    format!(" --driver-ids {}", ids.join(","))
+}
+
+/// 検証モード（デジタコ版）: 本番DBから計算してDocker DBにINSERT
+/// 一部のドライバーが失敗したら終了コード1（allow_partial なら0、失敗したdriver_idは表示する）
+pub fn run_verify_digitacho_mode((year, month): (i32, u32), driver_filter: &cli::DriverFilterArgs, selection: &verify::DriverSelection, allow_partial: bool, delete_stale: bool) -> Result<(), TimecardError> {
+    info!("検証モード（デジタコ版）: DtakoEvents計算 → Docker DB INSERT: {}年{}月", year, month);
+
+    // 本番DBに接続
+    let mut timings = PhaseTimings::start();
+    let config = DbConfig::production();
+    info!("本番DB接続先: {}:{}", config.host, config.port);
+
+    let db = TimecardDb::connect(&config).map_err(|e| TimecardError::Db(format!("DB接続エラー: {}", e)))?;
+    info!("本番DB接続成功");
+    timings.lap("DB接続");
+
+    // アクティブドライバーを取得
+    let drivers = db.get_active_drivers(&driver_filter.query(year, month))
+        .map_err(|e| TimecardError::Db(format!("ドライバー取得エラー: {}", e)))?;
+    timings.lap("ドライバー取得");
+
+    info!("アクティブドライバー数: {}", drivers.len());
+
+    // サンプル実行の場合は対象ドライバーを絞り込む
+    let total_drivers = drivers.len();
+    let mut driver_ids_arg = None;
+    let drivers = if selection.is_partial() {
+        let selected = verify::select_drivers(&drivers, selection);
+        driver_ids_arg = Some(report_verify_selection(selection, &selected, total_drivers, year, month));
+        selected
+    } else {
+        drivers
+    };
+
+    // Docker DBにデジタコ版拘束時間をINSERT
+    info!("Docker DBにデジタコ版拘束時間をINSERT");
+    let mut total_inserted = 0;
+    let mut total_deleted = 0;
+    let mut failed_driver_ids = Vec::new();
+    let mut warning_count = 0;
+
+    let mut progress = Progress::new("INSERT", drivers.len());
+    for driver in &drivers {
+        match db.insert_digitacho_kosoku_to_docker(driver.id, year, month, delete_stale) {
+            Ok((count, deleted, warnings)) => {
+                total_inserted += count;
+                total_deleted += deleted;
+                for warning in &warnings {
+                    progress.warn(warning);
+                }
+                warning_count += warnings.len();
+            }
+            Err(e) => {
+                progress.error(&format!("driver_id={}: {}", driver.id, e));
+                failed_driver_ids.push(driver.id);
+            }
+        }
+        progress.inc(&driver.name);
+    }
+    progress.finish();
+    timings.lap("計算・INSERT（ドライバー別）");
+
+    let error_count = failed_driver_ids.len();
+    println!("[OK] {}件INSERT完了 (削除: {}件, エラー: {}件, 警告: {}件)", total_inserted, total_deleted, error_count, warning_count);
+    if selection.is_partial() && !drivers.is_empty() {
+        // サンプルのエラー率から全体を推定
+        let estimated = error_count as f64 * total_drivers as f64 / drivers.len() as f64;
+        println!("[SAMPLE] {}人中{}人でエラー → 全{}人では約{:.0}人と推定", drivers.len(), error_count, total_drivers, estimated);
+    }
+
+    println!();
+    println!("検証コマンド:");
+    println!("  python3 .claude/tools/db_verify.py --compare-dtako --year {} --month {}{}", year, month, driver_ids_arg.unwrap_or_default());
+    info!("{}", timings.report().trim_end());
+
+    // 最後に成功・失敗の件数をまとめる（例: 98 success / 2 failed）
+    let partial = TimecardError::Partial { succeeded: drivers.len() - error_count, failed_driver_ids };
+    println!();
+    if error_count == 0 {
+        println!("{} success / 0 failed", drivers.len());
+    } else if allow_partial {
+        println!("{}（--allow-partial）", partial);
+    } else {
+        return Err(partial);
+    }
+    Ok(())
+}
+
+/// 引数なしで起動した場合の座標JSONと出力PDF（従来の動作、非推奨）
+pub const LEGACY_JSON_INPUT: &str = "pdf_coordinates_20251230_172511.json";
+pub const LEGACY_JSON_OUTPUT: &str = "output_y05.pdf";
+
+/// JSONモード: PHPから出力された座標JSONからPDF生成
+///   json <input.json> [output.pdf]: 1ファイル（出力省略時は拡張子を .pdf に）
+///   json <ディレクトリ> [出力ディレクトリ]: 中の *.json をすべて同じ名前の .pdf に
+///
+/// --font family=path でフォントファミリーを追加登録（複数指定可）
+/// 1ファイルでも失敗したら終了コード1（他のファイルは続けて出力する）
+pub fn run_json_mode(input: PathBuf, output: Option<PathBuf>, save_options: SaveOptions, fonts: &[String]) -> Result<(), TimecardError> {
+    let jobs = cli::json_mode_jobs(&input, output.as_deref()).map_err(TimecardError::Input)?;
+    if let (true, Some(outdir)) = (input.is_dir(), &output) {
+        fs::create_dir_all(outdir).map_err(|e| TimecardError::Output(format!("出力先を作成できません: {}: {}", outdir.display(), e)))?;
+    }
+
+    let mut failed = 0;
+    for (input, output) in &jobs {
+        info!("座標JSON: {}", input.display());
+        match render_coordinate_file(input, output, &save_options, fonts) {
+            Ok(()) => println!("PDF saved to {}", output.display()),
+            Err(e) => {
+                error!("{}: {}", input.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(TimecardError::Output(format!("{}/{}ファイルでPDFを生成できませんでした", failed, jobs.len())));
+    }
+    Ok(())
+}
+
+/// 座標JSON1ファイルからPDFを生成して保存
+fn render_coordinate_file(input: &Path, output: &Path, save_options: &SaveOptions, fonts: &[String]) -> Result<(), String> {
+    let json_str = fs::read_to_string(input).map_err(|e| format!("座標JSONを読み込めません: {}", e))?;
+    let data: CoordinateData = serde_json::from_str(&json_str).map_err(|e| format!("座標JSONの形式が不正です: {}", e))?;
+
+    info!(
+        "Page size: {}mm x {}mm, Orientation: {}, Total pages: {}, Total elements: {}",
+        data.page_width_mm, data.page_height_mm, data.orientation, data.total_pages, data.elements.len()
+    );
+
+    // PDF生成
+    let mut pdf = TcpdfCompat::new(
+        data.page_width_mm,
+        data.page_height_mm,
+        &data.orientation,
+    );
+    pdf.set_save_options(save_options.clone());
+
+    for font in fonts {
+        match font.split_once('=') {
+            Some((family, path)) => {
+                info!("Font: {} = {}", family, path);
+                pdf.register_font(family, path);
+            }
+            None => warn!("--font は family=path 形式で指定してください: {}", font),
+        }
+    }
+
+    pdf.render_elements(&data.elements).map_err(|e| format!("PDF生成エラー: {}", e))?;
+    pdf.save(&output.to_string_lossy()).map_err(|e| format!("PDF保存エラー: {}", e))
+}
@@ -1,30 +1,52 @@
-use serde::{Deserialize, Serialize};
+// TCPDF座標JSONのスキーマ（描画で使わないフィールドも保持する）
+#![allow(dead_code)]
+
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
+/// 座標JSON（PHPのTCPDFが出力した描画内容）
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CoordinateData {
+    /// 用紙の幅（mm）
     pub page_width_mm: f64,
+    /// 用紙の高さ（mm）
     pub page_height_mm: f64,
+    /// 用紙の向き（"P": 縦、"L": 横）
     pub orientation: String,
+    /// 単位（"mm"）
     pub unit: String,
+    /// ページ数
     pub total_pages: u32,
+    /// 描画要素（seq 順）
     pub elements: Vec<Element>,
 }
 
+/// 描画要素1つ（Cell・Line・AddPage など）
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Element {
+    /// 1からの連番
     pub seq: u32,
+    /// TCPDFのメソッド名（Cell、MultiCell、setFillColor など）
     #[serde(rename = "type")]
     pub element_type: String,
+    /// 描画時点のページ番号
     pub page: u32,
+    /// メソッドの引数（種類ごとの *Params の形式）
     pub params: Value,
 }
 
-// MultiCell パラメータ
-#[derive(Debug, Deserialize)]
-pub struct MultiCellParams {
-    pub x: f64,
-    pub y: f64,
+/// x・y の省略値（null や "" はTCPDFと同じく現在のカーソル位置を使う）
+fn optional_coordinate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    Ok(Value::deserialize(deserializer)?.as_f64())
+}
+
+// MultiCell パラメータ（x・y がなければカーソル位置）
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct MultiCellParams {
+    #[serde(default, deserialize_with = "optional_coordinate")]
+    pub x: Option<f64>,
+    #[serde(default, deserialize_with = "optional_coordinate")]
+    pub y: Option<f64>,
     pub w: f64,
     pub h: f64,
     pub text: Value,  // String, Number, or null
@@ -34,11 +56,13 @@ pub struct MultiCellParams {
     pub ln: i32,
 }
 
-// Cell パラメータ
-#[derive(Debug, Deserialize)]
-pub struct CellParams {
-    pub x: f64,
-    pub y: f64,
+// Cell パラメータ（x・y がなければカーソル位置）
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct CellParams {
+    #[serde(default, deserialize_with = "optional_coordinate")]
+    pub x: Option<f64>,
+    #[serde(default, deserialize_with = "optional_coordinate")]
+    pub y: Option<f64>,
     pub w: f64,
     pub h: f64,
     pub text: Value,  // String, Number, or null
@@ -46,12 +70,13 @@ pub struct CellParams {
     pub align: String,
     pub fill: bool,
     pub ln: i32,
+    #[serde(default)]
     pub link: String,
 }
 
 // Line パラメータ
-#[derive(Debug, Deserialize)]
-pub struct LineParams {
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LineParams {
     pub x1: f64,
     pub y1: f64,
     pub x2: f64,
@@ -59,8 +84,8 @@ pub struct LineParams {
 }
 
 // Link パラメータ
-#[derive(Debug, Deserialize)]
-pub struct LinkParams {
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LinkParams {
     pub x: f64,
     pub y: f64,
     pub w: f64,
@@ -69,50 +94,173 @@ pub struct LinkParams {
 }
 
 // SetFont パラメータ
-#[derive(Debug, Deserialize)]
-pub struct SetFontParams {
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct SetFontParams {
     pub family: String,
+    #[serde(default)]
     pub style: String,
     pub size: Option<f64>,
 }
 
 // setFontSize パラメータ
-#[derive(Debug, Deserialize)]
-pub struct SetFontSizeParams {
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct SetFontSizeParams {
     pub size: f64,
 }
 
 // setFillColor パラメータ
-#[derive(Debug, Deserialize)]
-pub struct SetFillColorParams {
+#[derive(Debug, Deserialize, Serialize)]
+/// グレーの場合は col1 のみ（TCPDFと同じく省略した色は -1）
+pub(crate) struct SetFillColorParams {
     pub col1: i32,
+    #[serde(default = "unset_color")]
     pub col2: i32,
+    #[serde(default = "unset_color")]
     pub col3: i32,
+    #[serde(default = "unset_color")]
     pub col4: i32,
 }
 
+// SetTextColor パラメータ（setFillColorと同じ形式）
+pub(crate) type SetTextColorParams = SetFillColorParams;
+
+fn unset_color() -> i32 {
+    -1
+}
+
+// SetDrawColor パラメータ（setFillColorと同じ形式）
+pub(crate) type SetDrawColorParams = SetFillColorParams;
+
+// SetLineWidth パラメータ（mm）
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct SetLineWidthParams {
+    pub width: f64,
+}
+
+// Rect パラメータ（style: "D"/"" 枠線、"F" 塗りつぶし、"DF"/"FD" 両方）
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct RectParams {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+    #[serde(default)]
+    pub style: String,
+}
+
+// Text パラメータ（x, y はテキストの左上）
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct TextParams {
+    pub x: f64,
+    pub y: f64,
+    pub text: Value,  // String, Number, or null
+}
+
+// Image パラメータ（file: 画像ファイルのパス、data: Base64の画像データ のどちらか）
+// w・h の片方が0なら縦横比を保ち、両方0なら72dpiの大きさ
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ImageParams {
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub data: Option<String>,
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub w: f64,
+    #[serde(default)]
+    pub h: f64,
+}
+
 // AddPage パラメータ
-#[derive(Debug, Deserialize)]
-pub struct AddPageParams {
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct AddPageParams {
+    #[serde(default)]
     pub orientation: String,
+    #[serde(default)]
     pub format: Vec<f64>,
 }
 
 // setAbsX パラメータ
-#[derive(Debug, Deserialize)]
-pub struct SetAbsXParams {
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct SetAbsXParams {
     pub x: f64,
 }
 
 // setAbsY パラメータ
-#[derive(Debug, Deserialize)]
-pub struct SetAbsYParams {
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct SetAbsYParams {
     pub y: f64,
 }
 
-// Ln パラメータ
-#[derive(Debug, Deserialize)]
-pub struct LnParams {
+// Ln パラメータ（h が数値でなければ直前のセルの高さ）
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LnParams {
+    #[serde(default)]
     pub h: Value,
-    pub y_before: f64,
+    #[serde(default)]
+    pub y_before: Option<f64>,
+}
+
+/// 描画内容を座標JSON（PHPの出力と同じ形式）として記録する
+/// seq は1からの連番、page は記録時点のページ番号（AddPageで進む）
+/// SetFont・SetTextColor・setFillColor は値が変わった時だけ記録する
+#[derive(Debug, Default)]
+pub(crate) struct CoordinateRecorder {
+    elements: Vec<Element>,
+    page: u32,
+    font: Option<(String, f64)>,
+    text_color: Option<(i32, i32, i32)>,
+    fill_gray: Option<i32>,
+}
+
+impl CoordinateRecorder {
+    /// 要素を1つ追加
+    pub fn push(&mut self, element_type: &str, params: impl Serialize) {
+        self.elements.push(Element {
+            seq: self.elements.len() as u32 + 1,
+            element_type: element_type.to_string(),
+            page: self.page,
+            params: serde_json::to_value(params).unwrap_or(Value::Null),
+        });
+    }
+
+    pub fn add_page(&mut self, orientation: &str, width_mm: f64, height_mm: f64) {
+        self.page += 1;
+        self.push("AddPage", AddPageParams { orientation: orientation.to_string(), format: vec![width_mm, height_mm] });
+    }
+
+    pub fn set_font(&mut self, family: &str, style: &str, size: f64) {
+        let font = (style.to_string(), size);
+        if self.font.as_ref() != Some(&font) {
+            self.push("SetFont", SetFontParams { family: family.to_string(), style: font.0.clone(), size: Some(size) });
+            self.font = Some(font);
+        }
+    }
+
+    pub fn set_text_color(&mut self, (r, g, b): (i32, i32, i32)) {
+        if self.text_color != Some((r, g, b)) {
+            self.push("SetTextColor", SetTextColorParams { col1: r, col2: g, col3: b, col4: -1 });
+            self.text_color = Some((r, g, b));
+        }
+    }
+
+    pub fn set_fill_gray(&mut self, gray: i32) {
+        if self.fill_gray != Some(gray) {
+            self.push("setFillColor", SetFillColorParams { col1: gray, col2: -1, col3: -1, col4: -1 });
+            self.fill_gray = Some(gray);
+        }
+    }
+
+    /// 記録した要素を座標JSONにする
+    pub fn into_data(self, page_width_mm: f64, page_height_mm: f64, orientation: &str) -> CoordinateData {
+        CoordinateData {
+            page_width_mm,
+            page_height_mm,
+            orientation: orientation.to_string(),
+            unit: "mm".to_string(),
+            total_pages: self.page,
+            elements: self.elements,
+        }
+    }
 }
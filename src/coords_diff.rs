@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::coordinate_data::{CoordinateData, Element};
+
+/// 位置ずれの既定の許容差（mm）
+pub const DEFAULT_TOLERANCE_MM: f64 = 0.3;
+
+/// 丸めた座標で組にならなかった要素を、近い順に組にする範囲（mm）
+const MATCH_RADIUS_MM: f64 = 5.0;
+
+/// 位置の比較に使う座標・サイズ
+const GEOMETRY_KEYS: [&str; 8] = ["x", "y", "w", "h", "x1", "y1", "x2", "y2"];
+
+/// 座標JSONの比較条件
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// 位置ずれの許容差（mm、これを超えたら差分）
+    pub tolerance_mm: f64,
+    /// 比較しない要素の種類（例: SetFont）
+    pub ignore_types: HashSet<String>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self { tolerance_mm: DEFAULT_TOLERANCE_MM, ignore_types: HashSet::new() }
+    }
+}
+
+/// 差分の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    /// 文字（位置のない要素はパラメータ）が違う
+    TextMismatch,
+    /// A にしかない
+    OnlyInA,
+    /// B にしかない
+    OnlyInB,
+    /// 座標・サイズが許容差を超えてずれている
+    Drift,
+}
+
+impl DiffKind {
+    fn label(self) -> &'static str {
+        match self {
+            DiffKind::TextMismatch => "文字違い",
+            DiffKind::OnlyInA => "Aのみ",
+            DiffKind::OnlyInB => "Bのみ",
+            DiffKind::Drift => "位置ずれ",
+        }
+    }
+}
+
+/// 差分1件
+#[derive(Debug, Clone, Serialize)]
+pub struct CoordDiff {
+    pub page: u32,
+    pub kind: DiffKind,
+    pub element_type: String,
+    /// A・B の seq（片方にしかなければNone）
+    pub seq_a: Option<u32>,
+    pub seq_b: Option<u32>,
+    /// 位置（A優先、位置のない要素はNone）
+    pub position: Option<(f64, f64)>,
+    pub text_a: Option<String>,
+    pub text_b: Option<String>,
+    /// 座標・サイズの差の最大値（mm）
+    pub delta_mm: Option<f64>,
+}
+
+/// 比較結果
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport {
+    pub tolerance_mm: f64,
+    pub ignored_types: Vec<String>,
+    /// 組にして比較した要素の数
+    pub compared: usize,
+    pub diffs: Vec<CoordDiff>,
+}
+
+impl DiffReport {
+    /// ページごとの差分件数
+    pub fn counts_by_page(&self) -> BTreeMap<u32, BTreeMap<DiffKind, usize>> {
+        let mut counts: BTreeMap<u32, BTreeMap<DiffKind, usize>> = BTreeMap::new();
+        for diff in &self.diffs {
+            *counts.entry(diff.page).or_default().entry(diff.kind).or_default() += 1;
+        }
+        counts
+    }
+
+    /// 人が読む形式（ページごとに件数と各差分）
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let counts = self.counts_by_page();
+        for (page, kinds) in &counts {
+            let summary: Vec<String> = kinds.iter().map(|(kind, n)| format!("{} {}", kind.label(), n)).collect();
+            let total: usize = kinds.values().sum();
+            out.push_str(&format!("=== ページ {}: {}件（{}）===\n", page, total, summary.join(" / ")));
+            for diff in self.diffs.iter().filter(|d| d.page == *page) {
+                let position = diff.position.map(|(x, y)| format!(" ({:.1}, {:.1})", x, y)).unwrap_or_default();
+                let detail = match diff.kind {
+                    DiffKind::TextMismatch => format!(
+                        "{:?} ≠ {:?}",
+                        diff.text_a.as_deref().unwrap_or_default(),
+                        diff.text_b.as_deref().unwrap_or_default()
+                    ),
+                    DiffKind::OnlyInA => format!("{:?}", diff.text_a.as_deref().unwrap_or_default()),
+                    DiffKind::OnlyInB => format!("{:?}", diff.text_b.as_deref().unwrap_or_default()),
+                    DiffKind::Drift => format!("{:.2}mm", diff.delta_mm.unwrap_or_default()),
+                };
+                out.push_str(&format!("  {} {}{} {}\n", diff.kind.label(), diff.element_type, position, detail));
+            }
+        }
+        out.push_str(&format!(
+            "比較した要素: {}組 / 差分: {}件（許容差 {}mm）\n",
+            self.compared,
+            self.diffs.len(),
+            self.tolerance_mm
+        ));
+        out
+    }
+}
+
+/// 2つの座標JSONを比較する
+///   1. (ページ, 種類, 1mm単位に丸めたx/y) が同じ要素を組にする（同じ文字を優先）
+///   2. 残りは同じページ・種類の中で近い順に組にする（MATCH_RADIUS_MM以内）
+///   3. 位置のない要素（SetFontなど）はページ・種類ごとに出現順で組にする
+///
+/// 組にした要素は文字と座標・サイズを比べ、組にならなかった要素は片方のみとして報告する
+pub fn diff(a: &CoordinateData, b: &CoordinateData, options: &DiffOptions) -> DiffReport {
+    let mut diffs = Vec::new();
+    let mut compared = 0;
+
+    let groups_a = group(&a.elements, options);
+    let groups_b = group(&b.elements, options);
+    let keys: std::collections::BTreeSet<_> = groups_a.keys().chain(groups_b.keys()).collect();
+    for key in keys {
+        let empty = Vec::new();
+        let list_a = groups_a.get(key).unwrap_or(&empty);
+        let list_b = groups_b.get(key).unwrap_or(&empty);
+        let (pairs, rest_a, rest_b) = pair_elements(list_a, list_b);
+        compared += pairs.len();
+        for (ea, eb) in pairs {
+            diffs.extend(compare_pair(ea, eb, options.tolerance_mm));
+        }
+        diffs.extend(rest_a.into_iter().map(|e| only_in(e, DiffKind::OnlyInA)));
+        diffs.extend(rest_b.into_iter().map(|e| only_in(e, DiffKind::OnlyInB)));
+    }
+    diffs.sort_by(|x, y| {
+        (x.page, x.position.map(|p| (p.1, p.0)).unwrap_or_default(), &x.element_type, x.kind)
+            .partial_cmp(&(y.page, y.position.map(|p| (p.1, p.0)).unwrap_or_default(), &y.element_type, y.kind))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut ignored_types: Vec<String> = options.ignore_types.iter().cloned().collect();
+    ignored_types.sort();
+    DiffReport { tolerance_mm: options.tolerance_mm, ignored_types, compared, diffs }
+}
+
+/// (ページ, 種類) ごとに要素をまとめる（除外する種類は入れない）
+fn group<'a>(elements: &'a [Element], options: &DiffOptions) -> BTreeMap<(u32, String), Vec<&'a Element>> {
+    let mut groups: BTreeMap<(u32, String), Vec<&Element>> = BTreeMap::new();
+    for element in elements.iter().filter(|e| !options.ignore_types.contains(&e.element_type)) {
+        groups.entry((element.page, element.element_type.clone())).or_default().push(element);
+    }
+    groups
+}
+
+type Pairing<'a> = (Vec<(&'a Element, &'a Element)>, Vec<&'a Element>, Vec<&'a Element>);
+
+/// 同じページ・種類の要素を組にする（組, Aの残り, Bの残り）
+fn pair_elements<'a>(list_a: &[&'a Element], list_b: &[&'a Element]) -> Pairing<'a> {
+    let mut used_b = vec![false; list_b.len()];
+    let mut pairs = Vec::new();
+    let mut rest_a = Vec::new();
+
+    // 位置のない要素は出現順
+    let (positioned_a, plain_a): (Vec<&Element>, Vec<&Element>) = list_a.iter().partition(|e| anchor(e).is_some());
+    let plain_b: Vec<usize> = (0..list_b.len()).filter(|&i| anchor(list_b[i]).is_none()).collect();
+    for (ea, &ib) in plain_a.iter().zip(&plain_b) {
+        pairs.push((*ea, list_b[ib]));
+        used_b[ib] = true;
+    }
+    rest_a.extend(plain_a.iter().skip(plain_b.len()).copied());
+
+    // 1. 丸めた座標が同じもの（同じ文字を優先）
+    let mut unmatched_a = Vec::new();
+    for prefer_same_text in [true, false] {
+        let candidates = if prefer_same_text { positioned_a.clone() } else { std::mem::take(&mut unmatched_a) };
+        for ea in candidates {
+            let found = (0..list_b.len()).find(|&i| {
+                !used_b[i]
+                    && anchor(list_b[i]).is_some()
+                    && rounded(ea) == rounded(list_b[i])
+                    && (!prefer_same_text || text(ea) == text(list_b[i]))
+            });
+            match found {
+                Some(i) => {
+                    used_b[i] = true;
+                    pairs.push((ea, list_b[i]));
+                }
+                None => unmatched_a.push(ea),
+            }
+        }
+    }
+
+    // 2. 残りは近い順
+    let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+    for (ia, ea) in unmatched_a.iter().enumerate() {
+        for (ib, eb) in list_b.iter().enumerate().filter(|(i, _)| !used_b[*i]) {
+            if let (Some(pa), Some(pb)) = (anchor(ea), anchor(eb)) {
+                let distance = ((pa.0 - pb.0).powi(2) + (pa.1 - pb.1).powi(2)).sqrt();
+                if distance <= MATCH_RADIUS_MM {
+                    candidates.push((distance, ia, ib));
+                }
+            }
+        }
+    }
+    candidates.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    let mut used_a = vec![false; unmatched_a.len()];
+    for (_, ia, ib) in candidates {
+        if !used_a[ia] && !used_b[ib] {
+            used_a[ia] = true;
+            used_b[ib] = true;
+            pairs.push((unmatched_a[ia], list_b[ib]));
+        }
+    }
+    rest_a.extend(unmatched_a.iter().zip(&used_a).filter(|(_, used)| !**used).map(|(e, _)| *e));
+
+    let rest_b = list_b.iter().zip(&used_b).filter(|(_, used)| !**used).map(|(e, _)| *e).collect();
+    (pairs, rest_a, rest_b)
+}
+
+/// 組にした要素を比べる（文字違い・位置ずれ、両方あれば2件）
+fn compare_pair(a: &Element, b: &Element, tolerance_mm: f64) -> Vec<CoordDiff> {
+    let mut diffs = Vec::new();
+    let base = CoordDiff {
+        page: a.page,
+        kind: DiffKind::TextMismatch,
+        element_type: a.element_type.clone(),
+        seq_a: Some(a.seq),
+        seq_b: Some(b.seq),
+        position: anchor(a),
+        text_a: text(a),
+        text_b: text(b),
+        delta_mm: None,
+    };
+
+    let differs = match anchor(a) {
+        Some(_) => text(a) != text(b),
+        // 位置のない要素はパラメータ全体を比べる
+        None => a.params != b.params,
+    };
+    if differs {
+        let mut diff = base.clone();
+        if anchor(a).is_none() {
+            diff.text_a = Some(a.params.to_string());
+            diff.text_b = Some(b.params.to_string());
+        }
+        diffs.push(diff);
+    }
+
+    let delta = GEOMETRY_KEYS
+        .iter()
+        .filter_map(|key| Some((a.params.get(*key)?.as_f64()? - b.params.get(*key)?.as_f64()?).abs()))
+        .fold(0.0, f64::max);
+    if delta > tolerance_mm + 1e-9 {
+        diffs.push(CoordDiff { kind: DiffKind::Drift, delta_mm: Some(delta), ..base });
+    }
+    diffs
+}
+
+fn only_in(element: &Element, kind: DiffKind) -> CoordDiff {
+    let (text_a, text_b, seq_a, seq_b) = match kind {
+        DiffKind::OnlyInA => (text(element).or_else(|| Some(element.params.to_string())), None, Some(element.seq), None),
+        _ => (None, text(element).or_else(|| Some(element.params.to_string())), None, Some(element.seq)),
+    };
+    CoordDiff {
+        page: element.page,
+        kind,
+        element_type: element.element_type.clone(),
+        seq_a,
+        seq_b,
+        position: anchor(element),
+        text_a,
+        text_b,
+        delta_mm: None,
+    }
+}
+
+/// 要素の位置（Lineは始点、x/yのない要素はNone）
+fn anchor(element: &Element) -> Option<(f64, f64)> {
+    let params = &element.params;
+    let x = params.get("x").or_else(|| params.get("x1"))?.as_f64()?;
+    let y = params.get("y").or_else(|| params.get("y1"))?.as_f64()?;
+    Some((x, y))
+}
+
+/// 1mm単位に丸めた位置
+fn rounded(element: &Element) -> Option<(i64, i64)> {
+    anchor(element).map(|(x, y)| (x.round() as i64, y.round() as i64))
+}
+
+/// 要素の文字（数値も文字列にする、textのない要素はNone）
+fn text(element: &Element) -> Option<String> {
+    match element.params.get("text")? {
+        Value::Null => Some(String::new()),
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(elements: Value) -> CoordinateData {
+        let elements: Vec<Element> = serde_json::from_value(elements).unwrap();
+        CoordinateData {
+            page_width_mm: 297.0,
+            page_height_mm: 210.0,
+            orientation: "L".to_string(),
+            unit: "mm".to_string(),
+            total_pages: 1,
+            elements,
+        }
+    }
+
+    fn cell(seq: u32, page: u32, x: f64, y: f64, text: &str) -> Value {
+        serde_json::json!({"seq": seq, "type": "MultiCell", "page": page, "params": {"x": x, "y": y, "w": 10.0, "h": 5.0, "text": text}})
+    }
+
+    #[test]
+    fn test_diff_kinds_per_page() {
+        let a = data(serde_json::json!([
+            {"seq": 1, "type": "SetFont", "page": 1, "params": {"family": "msmincho", "size": 10.0}},
+            cell(2, 1, 10.0, 10.0, "日"),
+            cell(3, 1, 20.0, 10.0, "月"),
+            cell(4, 1, 30.0, 10.0, "火"),
+            cell(5, 2, 10.0, 10.0, "水"),
+            cell(6, 2, 50.0, 50.0, "Aだけ")
+        ]));
+        let b = data(serde_json::json!([
+            {"seq": 1, "type": "SetFont", "page": 1, "params": {"family": "msmincho", "size": 9.0}},
+            cell(2, 1, 10.1, 10.2, "日"),
+            cell(3, 1, 20.0, 10.0, "月曜"),
+            cell(4, 1, 31.0, 10.0, "火"),
+            cell(5, 2, 10.0, 10.0, "水"),
+            cell(6, 2, 100.0, 100.0, "Bだけ")
+        ]));
+
+        let report = diff(&a, &b, &DiffOptions::default());
+        let kinds: Vec<(u32, DiffKind, Option<String>)> = report.diffs.iter().map(|d| (d.page, d.kind, d.text_a.clone())).collect();
+        assert_eq!(
+            kinds,
+            [
+                (1, DiffKind::TextMismatch, Some(r#"{"family":"msmincho","size":10.0}"#.to_string())),
+                (1, DiffKind::TextMismatch, Some("月".to_string())),
+                (1, DiffKind::Drift, Some("火".to_string())),
+                (2, DiffKind::OnlyInA, Some("Aだけ".to_string())),
+                (2, DiffKind::OnlyInB, None),
+            ]
+        );
+        // 0.3mm以内のずれ（日）は差分にしない
+        assert_eq!(report.compared, 5);
+        assert_eq!(report.counts_by_page()[&1][&DiffKind::TextMismatch], 2);
+
+        let text = report.to_text();
+        assert!(text.contains("=== ページ 1: 3件"), "{}", text);
+        assert!(text.contains("位置ずれ MultiCell (30.0, 10.0) 1.00mm"), "{}", text);
+    }
+
+    #[test]
+    fn test_ignore_types_and_tolerance() {
+        let a = data(serde_json::json!([
+            {"seq": 1, "type": "SetFont", "page": 1, "params": {"size": 10.0}},
+            cell(2, 1, 30.0, 10.0, "火")
+        ]));
+        let b = data(serde_json::json!([
+            {"seq": 1, "type": "SetFont", "page": 1, "params": {"size": 9.0}},
+            cell(2, 1, 31.0, 10.0, "火")
+        ]));
+        let options = DiffOptions { tolerance_mm: 1.0, ignore_types: HashSet::from(["SetFont".to_string()]) };
+        let report = diff(&a, &b, &options);
+        assert!(report.diffs.is_empty());
+        assert_eq!(report.ignored_types, ["SetFont"]);
+    }
+}
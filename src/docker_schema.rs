@@ -0,0 +1,259 @@
+use mysql::prelude::*;
+use mysql::PooledConn;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Docker DBのスキーマのバージョン（テーブル定義を変えたら上げる）
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// 適用済みのバージョンを記録するテーブル
+const VERSION_TABLE: &str = "CREATE TABLE IF NOT EXISTS timecard_schema_version (
+    version INT NOT NULL PRIMARY KEY,
+    applied_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+) DEFAULT CHARSET = utf8mb4";
+
+/// Rustが書き込むテーブルの定義
+pub struct TableSpec {
+    /// テーブル名
+    pub name: &'static str,
+    create: &'static str,
+    /// 書き込みに使う列（既存のテーブルで欠けていないか確認する）
+    pub columns: &'static [&'static str],
+    /// ON DUPLICATE KEY UPDATE が前提にしている一意キー
+    pub unique_key: &'static [&'static str],
+}
+
+/// db.rs の UPSERT（TIME_CARD_ALLOWANCE_UPSERT・kosoku_upsert_statement）と合わせる
+pub const TABLES: &[TableSpec] = &[
+    TableSpec {
+        name: "time_card_kosoku",
+        create: "CREATE TABLE IF NOT EXISTS time_card_kosoku (
+            id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+            driver_id INT NOT NULL,
+            date DATE NOT NULL,
+            minutes INT NOT NULL,
+            type VARCHAR(32) NOT NULL,
+            UNIQUE KEY uk_driver_date_type (driver_id, date, type)
+        ) DEFAULT CHARSET = utf8mb4",
+        columns: &["driver_id", "date", "minutes", "type"],
+        unique_key: &["driver_id", "date", "type"],
+    },
+    TableSpec {
+        name: "time_card_allowance",
+        create: "CREATE TABLE IF NOT EXISTS time_card_allowance (
+            id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+            datetime DATE NOT NULL,
+            driver_id INT NOT NULL,
+            shukkin_count DOUBLE NOT NULL DEFAULT 0,
+            dayoff_count DOUBLE NOT NULL DEFAULT 0,
+            paidoff_count DOUBLE NOT NULL DEFAULT 0,
+            absence_count DOUBLE NOT NULL DEFAULT 0,
+            overtime_count DOUBLE NOT NULL DEFAULT 0,
+            holidaywork_count DOUBLE NOT NULL DEFAULT 0,
+            additionalwork_payment INT NOT NULL DEFAULT 0,
+            kachiku_payment INT NOT NULL DEFAULT 0,
+            trail_payment INT NOT NULL DEFAULT 0,
+            chikoku_count INT NOT NULL DEFAULT 0,
+            soutai_count INT NOT NULL DEFAULT 0,
+            tokukyu_count INT NOT NULL DEFAULT 0,
+            UNIQUE KEY uk_datetime_driver (datetime, driver_id)
+        ) DEFAULT CHARSET = utf8mb4",
+        columns: &[
+            "datetime",
+            "driver_id",
+            "shukkin_count",
+            "dayoff_count",
+            "paidoff_count",
+            "absence_count",
+            "overtime_count",
+            "holidaywork_count",
+            "additionalwork_payment",
+            "kachiku_payment",
+            "trail_payment",
+            "chikoku_count",
+            "soutai_count",
+            "tokukyu_count",
+        ],
+        unique_key: &["datetime", "driver_id"],
+    },
+    // 監査ログ（AUDIT_LOG=1 の時、audit::write が書き込む）
+    TableSpec {
+        name: "time_card_allowance_log",
+        create: "CREATE TABLE IF NOT EXISTS time_card_allowance_log (
+            id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+            logged_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            table_name VARCHAR(32) NOT NULL,
+            driver_id INT NOT NULL,
+            month DATE NOT NULL,
+            action VARCHAR(8) NOT NULL,
+            old_values JSON NULL,
+            new_values JSON NULL,
+            source VARCHAR(32) NOT NULL,
+            KEY idx_driver_month (driver_id, month)
+        ) DEFAULT CHARSET = utf8mb4",
+        columns: &["logged_at", "table_name", "driver_id", "month", "action", "old_values", "new_values", "source"],
+        unique_key: &["id"],
+    },
+];
+
+/// ensure の結果
+#[derive(Debug, Default, PartialEq)]
+pub struct SchemaReport {
+    /// 作成したテーブル
+    pub created: Vec<&'static str>,
+    /// 既存のテーブルの問題（一意キーがない・列が足りない）
+    pub problems: Vec<String>,
+}
+
+/// テーブルがなければ作成し、既存のテーブルは列と一意キーを確認する（何度実行してもよい）
+/// 問題は直さずに warn で報告する（既存のデータを消さないため）
+pub fn ensure(conn: &mut PooledConn) -> mysql::Result<SchemaReport> {
+    let mut report = SchemaReport::default();
+    conn.query_drop(VERSION_TABLE)?;
+    for table in TABLES {
+        if table_exists(conn, table.name)? {
+            let columns = existing_columns(conn, table.name)?;
+            let indexes = unique_indexes(conn, table.name)?;
+            report.problems.extend(check_table(table, &columns, &indexes));
+        } else {
+            conn.query_drop(table.create)?;
+            info!("{} を作成しました", table.name);
+            report.created.push(table.name);
+        }
+    }
+    conn.exec_drop("INSERT IGNORE INTO timecard_schema_version (version) VALUES (?)", (SCHEMA_VERSION,))?;
+    for problem in &report.problems {
+        warn!("Docker DBのスキーマに問題があります: {}", problem);
+    }
+    Ok(report)
+}
+
+/// 記録されている最新のバージョン（未作成なら None）
+pub fn current_version(conn: &mut PooledConn) -> mysql::Result<Option<u32>> {
+    if !table_exists(conn, "timecard_schema_version")? {
+        return Ok(None);
+    }
+    conn.query_first("SELECT MAX(version) FROM timecard_schema_version").map(Option::flatten)
+}
+
+fn table_exists(conn: &mut PooledConn, table: &str) -> mysql::Result<bool> {
+    let count: Option<i64> = conn.exec_first(
+        "SELECT COUNT(*) FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+        (table,),
+    )?;
+    Ok(count.unwrap_or(0) > 0)
+}
+
+fn existing_columns(conn: &mut PooledConn, table: &str) -> mysql::Result<Vec<String>> {
+    conn.exec(
+        "SELECT COLUMN_NAME FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+        (table,),
+    )
+}
+
+/// 一意キー（主キーを含む）ごとの列
+fn unique_indexes(conn: &mut PooledConn, table: &str) -> mysql::Result<Vec<Vec<String>>> {
+    let rows: Vec<(String, String)> = conn.exec(
+        "SELECT INDEX_NAME, COLUMN_NAME FROM information_schema.STATISTICS
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND NON_UNIQUE = 0
+         ORDER BY INDEX_NAME, SEQ_IN_INDEX",
+        (table,),
+    )?;
+    let mut indexes: Vec<(String, Vec<String>)> = Vec::new();
+    for (index, column) in rows {
+        match indexes.last_mut() {
+            Some((name, columns)) if *name == index => columns.push(column),
+            _ => indexes.push((index, vec![column])),
+        }
+    }
+    Ok(indexes.into_iter().map(|(_, columns)| columns).collect())
+}
+
+/// 既存のテーブルの問題
+/// 一意キーは列の順序を問わず、期待する列ちょうどのものが必要（余分な列があると別の行として追加される）
+pub fn check_table(table: &TableSpec, columns: &[String], unique_indexes: &[Vec<String>]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let existing: HashSet<String> = columns.iter().map(|c| c.to_lowercase()).collect();
+    let missing: Vec<&str> = table.columns.iter().copied().filter(|c| !existing.contains(*c)).collect();
+    if !missing.is_empty() {
+        problems.push(format!("{} に列 {} がありません", table.name, missing.join(", ")));
+    }
+    let expected: HashSet<&str> = table.unique_key.iter().copied().collect();
+    let has_key = unique_indexes.iter().any(|index| {
+        index.len() == expected.len() && index.iter().all(|c| expected.contains(c.to_lowercase().as_str()))
+    });
+    if !has_key {
+        problems.push(format!(
+            "{} に一意キー ({}) がありません。ON DUPLICATE KEY UPDATE で更新されずに行が重複します",
+            table.name,
+            table.unique_key.join(", ")
+        ));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_check_table_reports_wrong_unique_key() {
+        let kosoku = &TABLES[0];
+        let columns = names(&["id", "driver_id", "date", "minutes", "type"]);
+        // 列の順序は問わない
+        assert!(check_table(kosoku, &columns, &[names(&["id"]), names(&["type", "driver_id", "date"])]).is_empty());
+
+        // type が抜けた一意キーは別物（同じ日の TC_DC とデジタコが上書きし合う）
+        let problems = check_table(kosoku, &columns, &[names(&["id"]), names(&["driver_id", "date"])]);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("time_card_kosoku に一意キー (driver_id, date, type) がありません"));
+
+        let problems = check_table(kosoku, &names(&["id", "driver_id", "date", "type"]), &[names(&["driver_id", "date", "type"])]);
+        assert_eq!(problems, vec!["time_card_kosoku に列 minutes がありません"]);
+    }
+
+    /// 使い捨てのMySQLに対して作成・確認を実行（テーブルを削除するので Docker DB には向けない）
+    /// 実行例:
+    ///   docker run --rm -d --name migrate-test -p 13306:3306 -e MYSQL_ALLOW_EMPTY_PASSWORD=1 -e MYSQL_DATABASE=migrate_test mysql:8
+    ///   MIGRATE_TEST_DB_URL=mysql://root@127.0.0.1:13306/migrate_test cargo test docker_schema -- --ignored
+    #[test]
+    #[ignore]
+    fn test_ensure_against_throwaway_mysql() {
+        let url = std::env::var("MIGRATE_TEST_DB_URL").expect("MIGRATE_TEST_DB_URL を設定してください");
+        let mut conn = mysql::Pool::new(mysql::Opts::from_url(&url).unwrap()).unwrap().get_conn().unwrap();
+        conn.query_drop("DROP TABLE IF EXISTS time_card_kosoku, time_card_allowance, time_card_allowance_log, timecard_schema_version").unwrap();
+
+        // 1回目は作成、2回目は何もしない
+        let first = ensure(&mut conn).unwrap();
+        assert_eq!(first.created, vec!["time_card_kosoku", "time_card_allowance", "time_card_allowance_log"]);
+        assert!(first.problems.is_empty());
+        assert_eq!(ensure(&mut conn).unwrap(), SchemaReport::default());
+        assert_eq!(current_version(&mut conn).unwrap(), Some(SCHEMA_VERSION));
+
+        // 作成したテーブルで UPSERT が更新になる
+        for minutes in [480, 500] {
+            conn.exec_drop(
+                "INSERT INTO time_card_kosoku (driver_id, date, minutes, type) VALUES (1, '2025-12-01', ?, 'TC_DC')
+                 ON DUPLICATE KEY UPDATE minutes = VALUES(minutes)",
+                (minutes,),
+            )
+            .unwrap();
+        }
+        let rows: Vec<i32> = conn.query("SELECT minutes FROM time_card_kosoku").unwrap();
+        assert_eq!(rows, vec![500]);
+
+        // 一意キーを間違えたテーブルは報告する（直さない）
+        conn.query_drop("ALTER TABLE time_card_kosoku DROP INDEX uk_driver_date_type, ADD UNIQUE KEY uk_typo (driver_id, date)")
+            .unwrap();
+        let report = ensure(&mut conn).unwrap();
+        assert!(report.created.is_empty());
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].contains("time_card_kosoku に一意キー"));
+
+        conn.query_drop("DROP TABLE time_card_kosoku, time_card_allowance, time_card_allowance_log, timecard_schema_version").unwrap();
+    }
+}
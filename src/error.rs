@@ -0,0 +1,65 @@
+use std::fmt;
+
+/// CLIモードの失敗（終了コードを決める）
+/// バッチから実行した時に失敗が分かるよう、エラーを表示しただけで終了コード0にしない
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimecardError {
+    /// 引数の誤り（終了コード2）
+    Usage(String),
+    /// 入力ファイル（座標JSON・フィクスチャ）がない・読めない
+    Input(String),
+    /// DB接続・取得・Docker DBへの書き込みの失敗
+    Db(String),
+    /// PDF生成・ファイル保存の失敗
+    Output(String),
+    /// 比較で許容差を超える差分があった（compare・diff-coords）
+    Differences(String),
+    /// 一部のドライバーだけ失敗した（--allow-partial なら成功扱い）
+    Partial {
+        /// 成功したドライバー数
+        succeeded: usize,
+        /// 失敗したドライバーのID
+        failed_driver_ids: Vec<i32>,
+    },
+}
+
+impl TimecardError {
+    /// プロセスの終了コード（引数の誤りは2、それ以外は1）
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Usage(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for TimecardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(message) | Self::Input(message) | Self::Db(message) | Self::Output(message) | Self::Differences(message) => {
+                write!(f, "{}", message)
+            }
+            Self::Partial { succeeded, failed_driver_ids } => {
+                let ids: Vec<String> = failed_driver_ids.iter().map(|id| id.to_string()).collect();
+                write!(f, "{} success / {} failed（driver_id: {}）", succeeded, failed_driver_ids.len(), ids.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for TimecardError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code_and_summary() {
+        assert_eq!(TimecardError::Usage("月は1〜12で指定してください".to_string()).exit_code(), 2);
+        assert_eq!(TimecardError::Db("DB接続エラー".to_string()).exit_code(), 1);
+
+        let partial = TimecardError::Partial { succeeded: 98, failed_driver_ids: vec![1071, 1203] };
+        assert_eq!(partial.exit_code(), 1);
+        assert_eq!(partial.to_string(), "98 success / 2 failed（driver_id: 1071, 1203）");
+    }
+}
@@ -0,0 +1,158 @@
+use crate::timecard_data::{kosoku_mismatch_minutes_from_env, DayRecord, MonthlyTimecard};
+
+/// 分析用CSVの列（export_rows の並びと一致させる、pandas等で読むため列名は変えない）
+/// flags 以降はBI連携用の日別の列（DayRecord・JSON APIのフィールド名と同じ、真偽値は 1/0）
+pub const EXPORT_COLUMNS: &[&str] = &[
+    "driver_id", "name", "date",
+    "clock_in1", "clock_out1", "clock_in2", "clock_out2",
+    "zangyo", "kosoku_minutes", "remarks", "flags",
+    "detail_st", "is_sunday", "is_holiday",
+    "kosoku_tcdc", "kosoku_digitacho", "zangyo_sources",
+    "is_kachiku", "is_trailer", "has_digitacho", "has_daily_report", "tsuika_count",
+    "kosoku_mismatch", "warnings",
+];
+
+/// Excelで文字化けしないよう先頭に付けるBOM（UTF-8）
+const UTF8_BOM: &str = "\u{feff}";
+
+impl MonthlyTimecard {
+    /// 分析用CSVの行（1日1行、列は EXPORT_COLUMNS）
+    /// 未設定の数値は空文字、備考は「・」区切り、flags は該当するものを ; 区切り
+    /// zangyo_sources は「テーブル#行ID」、warnings は警告文を ; 区切り
+    /// kosoku_mismatch の閾値は KOSOKU_MISMATCH_MINUTES（JSON APIと同じ）
+    pub fn export_rows(&self) -> Vec<Vec<String>> {
+        let threshold = kosoku_mismatch_minutes_from_env();
+        let opt = |v: Option<String>| v.unwrap_or_default();
+        let punch = |list: &Vec<String>, i: usize| list.get(i).cloned().unwrap_or_default();
+        self.days
+            .iter()
+            .map(|d| {
+                let sources = d.zangyo_sources
+                    .iter()
+                    .map(|s| format!("{}#{}", s.table.as_str(), s.row_id))
+                    .collect::<Vec<_>>()
+                    .join(";");
+                vec![
+                    self.driver.id.to_string(),
+                    self.driver.name.clone(),
+                    format!("{}-{:02}-{:02}", self.year, self.month, d.day),
+                    punch(&d.clock_in, 0),
+                    punch(&d.clock_out, 0),
+                    punch(&d.clock_in, 1),
+                    punch(&d.clock_out, 1),
+                    opt(d.zangyo.map(|z| z.to_string())),
+                    opt(d.kosoku_minutes.map(|m| m.to_string())),
+                    d.remarks.join("・"),
+                    day_flags(d).join(";"),
+                    d.detail_st.clone(),
+                    flag(d.is_sunday),
+                    flag(d.is_holiday),
+                    opt(d.kosoku_tcdc.map(|m| m.to_string())),
+                    opt(d.kosoku_digitacho.map(|m| m.to_string())),
+                    sources,
+                    flag(d.is_kachiku),
+                    flag(d.is_trailer),
+                    flag(d.has_digitacho),
+                    flag(d.has_daily_report),
+                    d.tsuika_count.to_string(),
+                    flag(d.kosoku_mismatch(threshold)),
+                    d.warnings.join(";"),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// 真偽値の列（1/0）
+fn flag(b: bool) -> String {
+    if b { "1" } else { "0" }.to_string()
+}
+
+/// 日のフラグ（sunday / holiday / shutcho / kachiku / trailer / digitacho / daily_report）
+fn day_flags(day: &DayRecord) -> Vec<&'static str> {
+    [
+        (day.is_sunday, "sunday"),
+        (day.is_holiday, "holiday"),
+        (!day.detail_st.is_empty(), "shutcho"),
+        (day.is_kachiku, "kachiku"),
+        (day.is_trailer, "trailer"),
+        (day.has_digitacho, "digitacho"),
+        (day.has_daily_report, "daily_report"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect()
+}
+
+/// CSVの値をエスケープ（カンマ・引用符・改行を含む場合は引用符で囲み、引用符は二重にする）
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// タイムカードを分析用CSVに変換（ヘッダー行あり、bom なら先頭にBOM）
+pub fn to_csv(timecards: &[MonthlyTimecard], bom: bool) -> String {
+    let mut out = String::new();
+    if bom {
+        out.push_str(UTF8_BOM);
+    }
+    out.push_str(&EXPORT_COLUMNS.join(","));
+    out.push('\n');
+    for row in timecards.iter().flat_map(MonthlyTimecard::export_rows) {
+        out.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timecard_data::{Driver, TimecardSummary};
+
+    fn timecard(name: &str, day: DayRecord) -> MonthlyTimecard {
+        MonthlyTimecard {
+            driver: Driver { id: 1071, name: name.to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 12,
+            days: vec![day],
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        }
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("山田 太郎"), "山田 太郎");
+        assert_eq!(csv_field("山田, 太郎"), "\"山田, 太郎\"");
+        assert_eq!(csv_field("公休\n有休"), "\"公休\n有休\"");
+        assert_eq!(csv_field("a\r\nb"), "\"a\r\nb\"");
+        assert_eq!(csv_field("\"特\"休"), "\"\"\"特\"\"休\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn test_to_csv_rows_and_bom() {
+        let mut day = DayRecord::new(7, "日");
+        day.clock_in = vec!["08:00".to_string(), "19:00".to_string()];
+        day.clock_out = vec!["12:00".to_string(), "22:30".to_string()];
+        day.zangyo = Some(1.5);
+        day.kosoku_minutes = Some(810);
+        day.remarks = vec!["休出".to_string(), "メモ, 要確認".to_string()];
+        day.is_kachiku = true;
+        let csv = to_csv(&[timecard("山田, 太郎", day)], false);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], EXPORT_COLUMNS.join(","));
+        assert_eq!(lines[1], "1071,\"山田, 太郎\",2025-12-07,08:00,12:00,19:00,22:30,1.5,810,\"休出・メモ, 要確認\",sunday;kachiku,,1,0,,,,1,0,0,0,0,0,");
+        assert_eq!(lines.len(), 2);
+
+        // 打刻・数値のない日は空欄、BOMは先頭のみ
+        let csv = to_csv(&[timecard("テスト", DayRecord::new(1, "月"))], true);
+        assert!(csv.starts_with("\u{feff}driver_id,"));
+        assert_eq!(csv.lines().nth(1).unwrap(), "1071,テスト,2025-12-01,,,,,,,,,,0,0,,,,0,0,0,0,0,0,");
+    }
+}
@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// 備考を集計のどの項目に数えるか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryCategory {
+    /// 公休（1日1回）
+    Kyuka,
+    /// 有休（weightで半休に対応）
+    Yukyu,
+    /// 欠勤
+    Kekkin,
+    /// 遅刻
+    Chikoku,
+    /// 早退
+    Soutai,
+    /// 特休
+    Tokukyu,
+    /// 集計しない（仮乗・入社前など）
+    Ignore,
+}
+
+/// 備考1つ分の集計ルール
+/// weight は有休のみ有効（半休は0.5）、それ以外は1日単位で数える
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct HolidayRule {
+    /// 数える項目
+    pub category: SummaryCategory,
+    /// 1回あたりの日数（有休の半休は0.5）
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl HolidayRule {
+    fn new(category: SummaryCategory, weight: f64) -> Self {
+        Self { category, weight }
+    }
+}
+
+/// 備考文字列 → 集計ルールの対応表
+///   - HOLIDAY_MAPPING_FILE: JSONファイル（`{"半特休": {"category": "tokukyu"}}`）で追加・上書き
+#[derive(Debug, Clone, PartialEq)]
+pub struct HolidayMapping {
+    rules: HashMap<String, HolidayRule>,
+}
+
+impl Default for HolidayMapping {
+    /// PHPの_makeTimeCardDisplayArray switch文と同じ対応
+    fn default() -> Self {
+        use SummaryCategory::*;
+        let entries = [
+            ("公休", Kyuka, 1.0),
+            ("泊休", Kyuka, 1.0),
+            ("積置泊休", Kyuka, 1.0),
+            ("指休", Kyuka, 1.0),
+            ("有休", Yukyu, 1.0),
+            ("前休", Yukyu, 0.5),
+            ("後休", Yukyu, 0.5),
+            ("前休作", Yukyu, 0.5),
+            ("後休作", Yukyu, 0.5),
+            ("欠勤", Kekkin, 1.0),
+            ("遅刻", Chikoku, 1.0),
+            ("早退", Soutai, 1.0),
+            ("特休", Tokukyu, 1.0),
+            ("仮乗", Ignore, 1.0),
+            ("入社前", Ignore, 1.0),
+            ("退職後", Ignore, 1.0),
+            ("出張", Ignore, 1.0),
+            ("出", Ignore, 1.0),
+            ("※夜勤", Ignore, 1.0),
+        ];
+        let rules = entries
+            .into_iter()
+            .map(|(remark, category, weight)| (remark.to_string(), HolidayRule::new(category, weight)))
+            .collect();
+        Self { rules }
+    }
+}
+
+impl HolidayMapping {
+    /// JSONの対応表をデフォルトに追加・上書きして作成
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let overrides: HashMap<String, HolidayRule> =
+            serde_json::from_str(json).map_err(|e| format!("備考の対応表を読み込めません: {}", e))?;
+        let mut mapping = Self::default();
+        for (remark, rule) in overrides {
+            if rule.weight < 0.0 {
+                return Err(format!("備考「{}」のweightが負です", remark));
+            }
+            mapping.rules.insert(remark.trim().to_string(), rule);
+        }
+        Ok(mapping)
+    }
+
+    /// 環境変数 HOLIDAY_MAPPING_FILE から読み込み（未設定・読み込み失敗はデフォルト）
+    pub fn from_env() -> Self {
+        let Ok(path) = env::var("HOLIDAY_MAPPING_FILE") else {
+            return Self::default();
+        };
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| format!("{}: {}", path, e))
+            .and_then(|json| Self::from_json(&json));
+        match loaded {
+            Ok(mapping) => mapping,
+            Err(e) => {
+                warn!("HOLIDAY_MAPPING_FILE {}（デフォルトを使用）", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// プロセス全体で共有する対応表（初回に環境変数から読み込み）
+    pub fn global() -> &'static Self {
+        static MAPPING: OnceLock<HolidayMapping> = OnceLock::new();
+        MAPPING.get_or_init(Self::from_env)
+    }
+
+    /// 備考の集計ルール（未定義ならNone）
+    pub fn rule(&self, remark: &str) -> Option<HolidayRule> {
+        self.rules.get(remark.trim()).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json_overrides_defaults() {
+        let mapping = HolidayMapping::from_json(
+            r#"{"半特休": {"category": "tokukyu"}, "半有休": {"category": "yukyu", "weight": 0.5}, "仮乗": {"category": "kekkin"}}"#,
+        )
+        .unwrap();
+        assert_eq!(mapping.rule("半特休"), Some(HolidayRule::new(SummaryCategory::Tokukyu, 1.0)));
+        assert_eq!(mapping.rule("半有休").unwrap().weight, 0.5);
+        assert_eq!(mapping.rule("仮乗").unwrap().category, SummaryCategory::Kekkin);
+        // デフォルトは残る
+        assert_eq!(mapping.rule("前休作"), Some(HolidayRule::new(SummaryCategory::Yukyu, 0.5)));
+        assert_eq!(mapping.rule("未定義"), None);
+
+        assert!(HolidayMapping::from_json(r#"{"x": {"category": "unknown"}}"#).is_err());
+        assert!(HolidayMapping::from_json(r#"{"x": {"category": "yukyu", "weight": -1}}"#).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::sync::{Mutex, OnceLock};
+use tracing::warn;
+
+/// 同梱の祝日データ（内閣府「国民の祝日」CSVをUTF-8に変換したもの）
+const BUNDLED_CSV: &str = include_str!("../data/syukujitsu.csv");
+
+/// 祝日APIの既定のURL（{year} を年に置き換える）
+const DEFAULT_API_URL: &str = "https://holidays-jp.github.io/api/v1/{year}/date.json";
+
+/// 国民の祝日カレンダー
+///   - HOLIDAYS_CSV: 内閣府形式のCSV（`2025/1/1,元日`、UTF-8）のパス。未設定なら同梱データ
+///   - HOLIDAYS_TABLE: 祝日をDBのテーブル（列 p_date, name）から読む（db.rs、設定時はCSVより優先）
+///   - HOLIDAYS_API_URL: CSVにない年の祝日を取得するAPI（既定は holidays-jp、空なら取得しない）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Holidays {
+    dates: BTreeMap<NaiveDate, String>,
+}
+
+impl Holidays {
+    /// 内閣府形式のCSVを解析（見出し行・空行は読み飛ばす）
+    pub fn from_csv(csv: &str) -> Result<Self, String> {
+        let mut dates = BTreeMap::new();
+        for (i, line) in csv.lines().enumerate() {
+            let line = line.trim().trim_start_matches('\u{feff}');
+            if line.is_empty() {
+                continue;
+            }
+            let (date, name) = line.split_once(',').unwrap_or((line, ""));
+            match NaiveDate::parse_from_str(date.trim(), "%Y/%m/%d") {
+                Ok(date) => {
+                    dates.insert(date, name.trim().to_string());
+                }
+                // 1行目は見出し
+                Err(_) if i == 0 => {}
+                Err(_) => return Err(format!("{}行目の日付「{}」を解析できません", i + 1, date)),
+            }
+        }
+        Ok(Self { dates })
+    }
+
+    /// 日付と名称の一覧から作成（DBのテーブルから読んだ場合）
+    pub fn from_dates(dates: impl IntoIterator<Item = (NaiveDate, String)>) -> Self {
+        Self { dates: dates.into_iter().collect() }
+    }
+
+    /// 環境変数 HOLIDAYS_CSV から読み込み（未設定なら同梱データ、読み込み失敗は祝日なし）
+    pub fn from_env() -> Self {
+        let loaded = match env::var("HOLIDAYS_CSV") {
+            Ok(path) => std::fs::read_to_string(&path)
+                .map_err(|e| format!("{}: {}", path, e))
+                .and_then(|csv| Self::from_csv(&csv).map_err(|e| format!("{}: {}", path, e))),
+            Err(_) => Self::from_csv(BUNDLED_CSV),
+        };
+        match loaded {
+            Ok(holidays) => holidays,
+            Err(e) => {
+                warn!("祝日データを読み込めません {}（祝日なしで処理します）", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// プロセス全体で共有する祝日データ（初回に環境変数から読み込み）
+    pub fn global() -> &'static Self {
+        static HOLIDAYS: OnceLock<Holidays> = OnceLock::new();
+        HOLIDAYS.get_or_init(Self::from_env)
+    }
+
+    /// 国民の祝日（振替休日・国民の休日を含む）か
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.dates.contains_key(&date)
+    }
+
+    /// 祝日の名称
+    pub fn name(&self, date: NaiveDate) -> Option<&str> {
+        self.dates.get(&date).map(|s| s.as_str())
+    }
+
+    /// データがある年か（内閣府のCSVは年単位なので、最初と最後の祝日の年の間）
+    pub fn covers(&self, year: i32) -> bool {
+        match (self.dates.keys().next(), self.dates.keys().next_back()) {
+            (Some(first), Some(last)) => (first.year()..=last.year()).contains(&year),
+            _ => false,
+        }
+    }
+
+    /// 対象月の祝日（データがない年は警告を出して fallback から、それも失敗すれば祝日なし）
+    pub fn in_month_or_else(&self, year: i32, month: u32, fallback: impl FnOnce(i32) -> Result<Self, String>) -> HashMap<u32, String> {
+        if self.covers(year) {
+            return self.in_month(year, month);
+        }
+        match self.dates.keys().next_back() {
+            Some(last) => warn!("祝日データは{}年までのため、{}年{}月の祝日を祝日APIから取得します", last.year(), year, month),
+            None => warn!("祝日データがないため、{}年{}月の祝日を祝日APIから取得します", year, month),
+        }
+        match fallback(year) {
+            Ok(holidays) => holidays.in_month(year, month),
+            Err(e) => {
+                warn!("{}年の祝日を取得できません: {}（祝日なしで処理します）", year, e);
+                HashMap::new()
+            }
+        }
+    }
+
+    /// 祝日APIから1年分を取得（HOLIDAYS_API_URL、応答は {"2027-01-01": "元日", ...}）
+    pub fn from_api(year: i32) -> Result<Self, String> {
+        let url = env::var("HOLIDAYS_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+        if url.trim().is_empty() {
+            return Err("HOLIDAYS_API_URL が空のため取得しません".to_string());
+        }
+        let url = url.replace("{year}", &year.to_string());
+        let body: HashMap<String, String> = ureq::get(&url)
+            .call()
+            .map_err(|e| format!("{}: {}", url, e))?
+            .into_json()
+            .map_err(|e| format!("{}: {}", url, e))?;
+        Ok(Self::from_dates(
+            body.into_iter().filter_map(|(date, name)| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok().map(|d| (d, name))),
+        ))
+    }
+
+    /// 祝日APIの結果をプロセス内で年ごとに保存（失敗は保存しない）
+    pub fn from_api_cached(year: i32) -> Result<Self, String> {
+        static CACHE: OnceLock<Mutex<HashMap<i32, Holidays>>> = OnceLock::new();
+        let cache = CACHE.get_or_init(Mutex::default);
+        if let Some(holidays) = cache.lock().unwrap().get(&year) {
+            return Ok(holidays.clone());
+        }
+        let holidays = Self::from_api(year)?;
+        cache.lock().unwrap().insert(year, holidays.clone());
+        Ok(holidays)
+    }
+
+    /// 対象月の祝日（日 → 名称）
+    pub fn in_month(&self, year: i32, month: u32) -> HashMap<u32, String> {
+        let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+            return HashMap::new();
+        };
+        self.dates
+            .range(first..)
+            .take_while(|(date, _)| date.month() == month && date.year() == year)
+            .map(|(date, name)| (date.day(), name.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_bundled_csv() {
+        let holidays = Holidays::from_csv(BUNDLED_CSV).unwrap();
+        assert_eq!(holidays.name(date(2025, 1, 1)), Some("元日"));
+        assert!(holidays.is_holiday(date(2025, 11, 24))); // 振替休日
+        assert!(!holidays.is_holiday(date(2025, 11, 25)));
+        let may = holidays.in_month(2026, 5);
+        assert_eq!(may.len(), 4);
+        assert_eq!(may.get(&6).map(|s| s.as_str()), Some("休日"));
+        assert!(holidays.in_month(2026, 6).is_empty());
+    }
+
+    #[test]
+    fn test_out_of_range_year_uses_fallback() {
+        let holidays = Holidays::from_csv(BUNDLED_CSV).unwrap();
+        assert!(holidays.covers(2026));
+        assert!(!holidays.covers(2027));
+
+        // 範囲内なら fallback は呼ばない
+        let december = holidays.in_month_or_else(2026, 12, |_| panic!("fallback called"));
+        assert!(december.is_empty());
+
+        // 範囲外の年は fallback から
+        let january = holidays.in_month_or_else(2027, 1, |year| {
+            assert_eq!(year, 2027);
+            Ok(Holidays::from_dates([(date(2027, 1, 1), "元日".to_string()), (date(2027, 1, 11), "成人の日".to_string())]))
+        });
+        assert_eq!(january.len(), 2);
+        assert_eq!(january.get(&11).map(|s| s.as_str()), Some("成人の日"));
+
+        // fallback も失敗すれば祝日なし
+        assert!(holidays.in_month_or_else(2027, 1, |_| Err("offline".to_string())).is_empty());
+        // データがなければ常に fallback
+        assert!(!Holidays::default().covers(2026));
+    }
+
+    #[test]
+    fn test_from_csv_errors() {
+        assert!(Holidays::from_csv("見出し,名称\n2025/13/1,元日\n").is_err());
+        let holidays = Holidays::from_csv("\u{feff}国民の祝日・休日月日,国民の祝日・休日名称\r\n2030/1/1,元日\r\n\r\n").unwrap();
+        assert!(holidays.is_holiday(date(2030, 1, 1)));
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use crate::db::KosokuRecord;
+
+/// 拘束時間の比較結果（ドライバー・日単位）
+/// PHP = 本番DB、Rust = Docker DB の time_card_kosoku
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KosokuCompareRow {
+    pub driver_id: i32,
+    pub date: String,
+    pub php_tcdc: Option<i32>,
+    pub rust_tcdc: Option<i32>,
+    pub php_digitacho: Option<i32>,
+    pub rust_digitacho: Option<i32>,
+}
+
+impl KosokuCompareRow {
+    /// TC_DC・デジタコそれぞれの PHP と Rust の差（分）の大きい方
+    /// 片方にしか行がない場合はない方を0分として扱う
+    pub fn max_diff(&self) -> i32 {
+        let diff = |php: Option<i32>, rust: Option<i32>| (rust.unwrap_or(0) - php.unwrap_or(0)).abs();
+        diff(self.php_tcdc, self.rust_tcdc).max(diff(self.php_digitacho, self.rust_digitacho))
+    }
+
+    /// 差が閾値（分）を超えているか
+    pub fn exceeds(&self, threshold_minutes: i32) -> bool {
+        self.max_diff() > threshold_minutes
+    }
+}
+
+/// 本番DBとDocker DBの行を日ごとに突き合わせる（ドライバー順・日付順）
+pub fn build_rows(prod: &[KosokuRecord], docker: &[KosokuRecord]) -> Vec<KosokuCompareRow> {
+    let mut rows: BTreeMap<(i32, String), KosokuCompareRow> = BTreeMap::new();
+    for (records, is_php) in [(prod, true), (docker, false)] {
+        for (driver_id, date, minutes, kosoku_type) in records {
+            let row = rows.entry((*driver_id, date.clone())).or_insert_with(|| KosokuCompareRow {
+                driver_id: *driver_id,
+                date: date.clone(),
+                ..Default::default()
+            });
+            let slot = match (kosoku_type.as_str(), is_php) {
+                ("TC_DC", true) => &mut row.php_tcdc,
+                ("TC_DC", false) => &mut row.rust_tcdc,
+                ("デジタコ", true) => &mut row.php_digitacho,
+                ("デジタコ", false) => &mut row.rust_digitacho,
+                _ => continue,
+            };
+            *slot = Some(*minutes);
+        }
+    }
+    rows.into_values().collect()
+}
+
+/// 比較結果をCSVに変換
+pub fn to_csv(rows: &[KosokuCompareRow], threshold_minutes: i32) -> String {
+    let minutes = |m: Option<i32>| m.map(|m| m.to_string()).unwrap_or_default();
+    let mut out = String::from("driver_id,date,php_tc_dc,rust_tc_dc,php_digitacho,rust_digitacho,max_diff,exceeds\n");
+    for r in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            r.driver_id,
+            r.date,
+            minutes(r.php_tcdc),
+            minutes(r.rust_tcdc),
+            minutes(r.php_digitacho),
+            minutes(r.rust_digitacho),
+            r.max_diff(),
+            r.exceeds(threshold_minutes),
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(driver_id: i32, date: &str, minutes: i32, kosoku_type: &str) -> KosokuRecord {
+        (driver_id, date.to_string(), minutes, kosoku_type.to_string())
+    }
+
+    #[test]
+    fn test_build_rows_and_threshold() {
+        let prod = vec![
+            record(1071, "2025-12-01", 600, "TC_DC"),
+            record(1071, "2025-12-02", 480, "TC_DC"),
+            record(1071, "2025-12-02", 120, "デジタコ"),
+        ];
+        let docker = vec![
+            record(1071, "2025-12-01", 600, "TC_DC"),
+            record(1071, "2025-12-02", 470, "TC_DC"),
+            record(1071, "2025-12-02", 120, "デジタコ"),
+            record(1080, "2025-12-01", 45, "デジタコ"),
+        ];
+        let rows = build_rows(&prod, &docker);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].max_diff(), 0);
+        assert_eq!(rows[1].max_diff(), 10);
+        assert_eq!(rows[1].php_digitacho, Some(120));
+        // 本番にない行は0分との差
+        assert_eq!(rows[2].driver_id, 1080);
+        assert_eq!(rows[2].max_diff(), 45);
+
+        assert!(!rows[1].exceeds(10));
+        assert!(rows[1].exceeds(9));
+
+        let csv = to_csv(&rows, 10);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "driver_id,date,php_tc_dc,rust_tc_dc,php_digitacho,rust_digitacho,max_diff,exceeds");
+        assert_eq!(lines[2], "1071,2025-12-02,480,470,120,120,10,false");
+        assert_eq!(lines[3], "1080,2025-12-01,,,,45,45,true");
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::{NaiveDateTime, NaiveTime};
+use std::env;
+use std::fmt;
+use tracing::warn;
+
+/// 拘束時間計算（TC_DC版）のルール設定
+///   - KOSOKU_LUNCH_START / KOSOKU_LUNCH_END: 昼休みの時間帯（HH:MM、デフォルト12:00〜13:00）
+///   - KOSOKU_LUNCH_MINUTES: 時間帯を全て含む場合の控除（分、デフォルト60）
+///   - KOSOKU_LUNCH_PRORATE: 終業が時間帯の途中の場合に按分して控除するか（デフォルトtrue）
+#[derive(Debug, Clone, PartialEq)]
+pub struct KosokuRules {
+    /// 昼休みの開始
+    pub lunch_start: NaiveTime,
+    /// 昼休みの終了
+    pub lunch_end: NaiveTime,
+    /// 昼休みを全て含む場合の控除（分）
+    pub lunch_minutes: i32,
+    /// 終業が昼休みの途中なら按分して控除する
+    pub lunch_prorate: bool,
+}
+
+impl Default for KosokuRules {
+    /// PHPと同じ（12:00〜13:00、60分、終業が途中なら12時から終業までを控除）
+    fn default() -> Self {
+        Self {
+            lunch_start: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            lunch_end: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+            lunch_minutes: 60,
+            lunch_prorate: true,
+        }
+    }
+}
+
+impl KosokuRules {
+    /// 環境変数から設定を読み込み（不正な値はデフォルトのまま）
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let time = |name: &str, default: NaiveTime| {
+            env::var(name)
+                .ok()
+                .and_then(|v| NaiveTime::parse_from_str(v.trim(), "%H:%M").ok())
+                .unwrap_or(default)
+        };
+        let rules = Self {
+            lunch_start: time("KOSOKU_LUNCH_START", default.lunch_start),
+            lunch_end: time("KOSOKU_LUNCH_END", default.lunch_end),
+            lunch_minutes: env::var("KOSOKU_LUNCH_MINUTES")
+                .ok()
+                .and_then(|v| v.parse::<i32>().ok())
+                .filter(|m| *m >= 0)
+                .unwrap_or(default.lunch_minutes),
+            lunch_prorate: env::var("KOSOKU_LUNCH_PRORATE")
+                .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "off"))
+                .unwrap_or(default.lunch_prorate),
+        };
+        if rules.lunch_start >= rules.lunch_end {
+            warn!("KOSOKU_LUNCH_START は KOSOKU_LUNCH_END より前にしてください（デフォルトを使用）");
+            return default;
+        }
+        rules
+    }
+
+    /// 始業→終業（同じ日）の昼休み控除（分）
+    ///   - 始業が時間帯の開始以降: 控除なし（PHPと同じ）
+    ///   - 終業が時間帯の開始以前: 控除なし
+    ///   - 終業が時間帯の終了以降: lunch_minutes
+    ///   - 終業が時間帯の途中: 按分（重なった時間 × lunch_minutes / 時間帯の長さ）、按分しない設定なら控除なし
+    pub fn lunch_deduction(&self, clock_in: NaiveDateTime, clock_out: NaiveDateTime) -> i32 {
+        let window_start = clock_in.date().and_time(self.lunch_start);
+        let window_end = clock_in.date().and_time(self.lunch_end);
+        if clock_in >= window_start || clock_out <= window_start {
+            return 0;
+        }
+        if clock_out >= window_end {
+            return self.lunch_minutes;
+        }
+        if !self.lunch_prorate {
+            return 0;
+        }
+        let overlap = (clock_out - window_start).num_minutes();
+        let window = (window_end - window_start).num_minutes();
+        (overlap * self.lunch_minutes as i64 / window) as i32
+    }
+}
+
+impl fmt::Display for KosokuRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "昼休み {}〜{} {}分控除（途中終業: {}）",
+            self.lunch_start.format("%H:%M"),
+            self.lunch_end.format("%H:%M"),
+            self.lunch_minutes,
+            if self.lunch_prorate { "按分" } else { "控除なし" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("2025-12-01 {}", time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_default_lunch_deduction() {
+        let rules = KosokuRules::default();
+        // 時間帯を全て含む
+        assert_eq!(rules.lunch_deduction(at("08:00"), at("17:00")), 60);
+        assert_eq!(rules.lunch_deduction(at("08:00"), at("13:00")), 60);
+        // 終業が時間帯の途中
+        assert_eq!(rules.lunch_deduction(at("08:00"), at("12:20")), 20);
+        // 終業が時間帯より前
+        assert_eq!(rules.lunch_deduction(at("08:00"), at("11:30")), 0);
+        assert_eq!(rules.lunch_deduction(at("08:00"), at("12:00")), 0);
+        // 始業が時間帯の途中・後
+        assert_eq!(rules.lunch_deduction(at("12:30"), at("17:00")), 0);
+        assert_eq!(rules.lunch_deduction(at("13:30"), at("17:00")), 0);
+    }
+
+    #[test]
+    fn test_configured_lunch_window() {
+        // 事務: 12:30〜13:30
+        let office = KosokuRules {
+            lunch_start: NaiveTime::from_hms_opt(12, 30, 0).unwrap(),
+            lunch_end: NaiveTime::from_hms_opt(13, 30, 0).unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(office.lunch_deduction(at("08:00"), at("17:00")), 60);
+        assert_eq!(office.lunch_deduction(at("08:00"), at("12:45")), 15);
+        assert_eq!(office.lunch_deduction(at("08:00"), at("12:20")), 0);
+
+        // 45分休憩を12:00〜13:00のうち按分
+        let short = KosokuRules { lunch_minutes: 45, ..Default::default() };
+        assert_eq!(short.lunch_deduction(at("08:00"), at("17:00")), 45);
+        assert_eq!(short.lunch_deduction(at("08:00"), at("12:40")), 30);
+
+        // 按分しない
+        let no_prorate = KosokuRules { lunch_prorate: false, ..Default::default() };
+        assert_eq!(no_prorate.lunch_deduction(at("08:00"), at("12:20")), 0);
+        assert_eq!(no_prorate.lunch_deduction(at("08:00"), at("13:10")), 60);
+    }
+}
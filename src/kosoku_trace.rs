@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+/// 拘束時間の計算方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KosokuMethod {
+    /// 打刻・運行開始終了から（calculate_kosoku_from_punches、TC_DC版）
+    Tcdc,
+    /// デジタコの運行イベントから（calculate_kosoku_digitacho）
+    Digitacho,
+}
+
+/// 計算過程の記録の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceKind {
+    /// 入力のイベント（計上するかどうかに関係なく全て）
+    Event,
+    /// 拘束時間に計上した区間
+    Pair,
+    /// 昼休みの控除
+    Lunch,
+    /// 運行開始→始業の差し引き
+    Minus,
+    /// フェリーの控除
+    Ferry,
+    /// 除外期間（time_card_dtako の chng_state = 99、この間のイベントは計上しない）
+    Exclusion,
+}
+
+/// 拘束時間の計算過程の1行（kosoku-debug 用）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KosokuTrace {
+    /// TC_DC版かデジタコ版か
+    pub method: KosokuMethod,
+    /// 記録の種類
+    pub kind: TraceKind,
+    /// イベントの日時・区間の開始（YYYY-MM-DD HH:MM:SS）
+    pub start: String,
+    /// 区間の終了（イベントは None）
+    pub end: Option<String>,
+    /// イベント名・区間（例: 始業→終業）
+    pub name: String,
+    /// 元のテーブル
+    pub source: &'static str,
+    /// 計上した日（YYYY-MM-DD、イベント・除外期間は None）
+    pub date: Option<String>,
+    /// 計上した分（控除はマイナス）
+    pub minutes: Option<i32>,
+}
+
+impl KosokuTrace {
+    /// 対象の日に関係する行か（計上した日、計上しない行はイベントの日）
+    fn on_day(&self, day: NaiveDate) -> bool {
+        let day = day.format("%Y-%m-%d").to_string();
+        match &self.date {
+            Some(date) => *date == day,
+            None => self.start.starts_with(&day) || self.end.as_ref().is_some_and(|end| end.starts_with(&day)),
+        }
+    }
+}
+
+/// 計算過程の記録先（無効なら何も記録しない）
+#[derive(Debug, Default)]
+pub struct KosokuTracer {
+    records: Option<Vec<KosokuTrace>>,
+}
+
+impl KosokuTracer {
+    /// enabled が false なら記録しない（通常の計算）
+    pub fn new(enabled: bool) -> Self {
+        Self { records: enabled.then(Vec::new) }
+    }
+
+    /// 入力のイベント
+    pub(crate) fn event(&mut self, method: KosokuMethod, datetime: NaiveDateTime, end: Option<NaiveDateTime>, name: &str, source: &'static str) {
+        self.push(|| KosokuTrace {
+            method,
+            kind: TraceKind::Event,
+            start: format_datetime(datetime),
+            end: end.map(format_datetime),
+            name: name.to_string(),
+            source,
+            date: None,
+            minutes: None,
+        });
+    }
+
+    /// 計上・控除した区間（minutes は控除ならマイナス）
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn counted(
+        &mut self,
+        method: KosokuMethod,
+        kind: TraceKind,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        name: &str,
+        source: &'static str,
+        date: NaiveDate,
+        minutes: i32,
+    ) {
+        self.push(|| KosokuTrace {
+            method,
+            kind,
+            start: format_datetime(start),
+            end: Some(format_datetime(end)),
+            name: name.to_string(),
+            source,
+            date: Some(date.format("%Y-%m-%d").to_string()),
+            minutes: Some(minutes),
+        });
+    }
+
+    /// 除外期間
+    pub(crate) fn exclusion(&mut self, start: NaiveDateTime, end: NaiveDateTime, name: &str) {
+        self.push(|| KosokuTrace {
+            method: KosokuMethod::Digitacho,
+            kind: TraceKind::Exclusion,
+            start: format_datetime(start),
+            end: Some(format_datetime(end)),
+            name: name.to_string(),
+            source: "time_card_dtako",
+            date: None,
+            minutes: None,
+        });
+    }
+
+    fn push(&mut self, record: impl FnOnce() -> KosokuTrace) {
+        if let Some(records) = &mut self.records {
+            records.push(record());
+        }
+    }
+
+    /// 記録した行（無効なら空）
+    pub fn into_records(self) -> Vec<KosokuTrace> {
+        self.records.unwrap_or_default()
+    }
+}
+
+fn format_datetime(datetime: NaiveDateTime) -> String {
+    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// 日別の拘束時間（TC_DC版・デジタコ版を並べる）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KosokuDebugDay {
+    /// 日
+    pub day: u32,
+    /// TC_DC版の拘束時間（分）
+    pub tcdc: Option<i32>,
+    /// デジタコ版の拘束時間（分）
+    pub digitacho: Option<i32>,
+}
+
+/// kosoku-debug の結果（1ドライバー・1か月、day 指定時はその日だけ）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KosokuDebugReport {
+    /// ドライバーID
+    pub driver_id: i32,
+    /// 対象年
+    pub year: i32,
+    /// 対象月
+    pub month: u32,
+    /// --day で指定した日
+    pub day: Option<u32>,
+    /// TC_DC版の計算過程
+    pub tcdc: Vec<KosokuTrace>,
+    /// デジタコ版の計算過程
+    pub digitacho: Vec<KosokuTrace>,
+    /// 計算時の警告（日時を解析できずスキップした行など）
+    pub warnings: Vec<String>,
+    /// 日別の拘束時間
+    pub days: Vec<KosokuDebugDay>,
+}
+
+impl KosokuDebugReport {
+    /// 計算結果と記録から作る（day 指定時はその日の行だけ）
+    pub fn new(
+        driver_id: i32,
+        year: i32,
+        month: u32,
+        day: Option<u32>,
+        (tcdc, tcdc_trace): (Vec<(u32, i32)>, Vec<KosokuTrace>),
+        (digitacho, digitacho_trace): (Vec<(u32, i32)>, Vec<KosokuTrace>),
+        warnings: Vec<String>,
+    ) -> Self {
+        let mut days: BTreeMap<u32, KosokuDebugDay> = BTreeMap::new();
+        for (d, minutes) in tcdc {
+            days.entry(d).or_insert(KosokuDebugDay { day: d, tcdc: None, digitacho: None }).tcdc = Some(minutes);
+        }
+        for (d, minutes) in digitacho {
+            days.entry(d).or_insert(KosokuDebugDay { day: d, tcdc: None, digitacho: None }).digitacho = Some(minutes);
+        }
+        let target = day.and_then(|d| NaiveDate::from_ymd_opt(year, month, d));
+        let keep = |records: Vec<KosokuTrace>| -> Vec<KosokuTrace> {
+            match target {
+                Some(target) => records.into_iter().filter(|r| r.on_day(target)).collect(),
+                None => records,
+            }
+        };
+        Self {
+            driver_id,
+            year,
+            month,
+            day,
+            tcdc: keep(tcdc_trace),
+            digitacho: keep(digitacho_trace),
+            warnings,
+            days: days.into_values().filter(|d| day.is_none_or(|day| d.day == day)).collect(),
+        }
+    }
+
+    /// テキスト表示（イベントの時系列 → 計上した区間・控除 → 日別の合計）
+    pub fn to_text(&self) -> String {
+        let mut out = match self.day {
+            Some(day) => format!("driver {} {}年{}月{}日\n", self.driver_id, self.year, self.month, day),
+            None => format!("driver {} {}年{}月\n", self.driver_id, self.year, self.month),
+        };
+        for (title, records) in [("TC_DC版（打刻から）", &self.tcdc), ("デジタコ版", &self.digitacho)] {
+            out.push_str(&format!("\n== {} ==\n", title));
+            for r in records.iter().filter(|r| r.kind == TraceKind::Event) {
+                let end = r.end.as_ref().map(|e| format!(" → {}", e)).unwrap_or_default();
+                out.push_str(&format!("  {}{}  {}  [{}]\n", r.start, end, r.name, r.source));
+            }
+            for r in records.iter().filter(|r| r.kind != TraceKind::Event) {
+                let label = match r.kind {
+                    TraceKind::Pair => "計上",
+                    TraceKind::Lunch => "昼休み",
+                    TraceKind::Minus => "差引",
+                    TraceKind::Ferry => "フェリー",
+                    TraceKind::Exclusion => "除外期間",
+                    TraceKind::Event => unreachable!(),
+                };
+                let minutes = r.minutes.map(|m| format!("  {:+}分", m)).unwrap_or_default();
+                let date = r.date.as_ref().map(|d| format!("  ({})", d)).unwrap_or_default();
+                out.push_str(&format!(
+                    "  {} {} → {}  {}{}{}\n",
+                    label,
+                    r.start,
+                    r.end.as_deref().unwrap_or(""),
+                    r.name,
+                    date,
+                    minutes
+                ));
+            }
+        }
+        if !self.warnings.is_empty() {
+            out.push('\n');
+        }
+        for warning in &self.warnings {
+            out.push_str(&format!("警告: {}\n", warning));
+        }
+        out.push_str("\n== 日別の合計（分） ==\n  日   TC_DC  デジタコ\n");
+        let minutes = |m: Option<i32>| m.map(|m| m.to_string()).unwrap_or_else(|| "-".to_string());
+        for d in &self.days {
+            out.push_str(&format!("  {:>2}  {:>6}  {:>8}\n", d.day, minutes(d.tcdc), minutes(d.digitacho)));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_disabled_tracer_records_nothing() {
+        let mut tracer = KosokuTracer::new(false);
+        tracer.event(KosokuMethod::Tcdc, datetime("2025-12-01 08:00"), None, "始業", "time_card_dstate");
+        assert!(tracer.into_records().is_empty());
+    }
+
+    #[test]
+    fn test_report_filters_day() {
+        let mut tracer = KosokuTracer::new(true);
+        let (start, end) = (datetime("2025-12-01 08:00"), datetime("2025-12-01 17:00"));
+        tracer.event(KosokuMethod::Tcdc, start, None, "始業", "time_card_dstate");
+        tracer.event(KosokuMethod::Tcdc, end, None, "終業", "time_card_dstate");
+        tracer.counted(KosokuMethod::Tcdc, TraceKind::Pair, start, end, "始業→終業", "time_card_dstate", start.date(), 540);
+        tracer.counted(KosokuMethod::Tcdc, TraceKind::Lunch, start, end, "昼休み", "time_card_dstate", start.date(), -60);
+        let (start2, end2) = (datetime("2025-12-02 08:00"), datetime("2025-12-02 12:00"));
+        tracer.counted(KosokuMethod::Tcdc, TraceKind::Pair, start2, end2, "始業→終業", "time_card_dstate", start2.date(), 240);
+
+        let tcdc = (vec![(1, 480), (2, 240)], tracer.into_records());
+        let report = KosokuDebugReport::new(1523, 2025, 12, Some(1), tcdc, (vec![(1, 30)], vec![]), vec![]);
+        assert_eq!(report.tcdc.len(), 4);
+        assert_eq!(report.days, vec![KosokuDebugDay { day: 1, tcdc: Some(480), digitacho: Some(30) }]);
+
+        let text = report.to_text();
+        assert!(text.contains("  2025-12-01 08:00:00  始業  [time_card_dstate]\n"), "{}", text);
+        assert!(text.contains("昼休み 2025-12-01 08:00:00 → 2025-12-01 17:00:00  昼休み  (2025-12-01)  -60分"), "{}", text);
+        assert!(text.contains("   1     480        30\n"), "{}", text);
+        assert!(!text.contains("2025-12-02"), "{}", text);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["tcdc"][2]["kind"], "pair");
+        assert_eq!(json["tcdc"][2]["method"], "tcdc");
+    }
+}
@@ -0,0 +1,99 @@
+//! タイムカードPDF生成（PHP TCPDF互換）
+//!
+//! DBからの月次タイムカード取得、拘束時間・集計の計算、PDF出力、HTTPサーバーを提供する。
+//! MySQLを使わずに、手で組み立てた [`MonthlyTimecard`] から [`TcpdfCompat`] でPDFを出力することもできる。
+//!
+//! ```
+//! use timecard_pdf_rs::render_options::RenderOptions;
+//! use timecard_pdf_rs::{DayRecord, Driver, MonthlyTimecard, TcpdfCompat, TimecardSummary};
+//!
+//! let mut day = DayRecord::new(1, "月");
+//! day.clock_in.push("08:00".to_string());
+//! day.clock_out.push("17:00".to_string());
+//! let mut timecard = MonthlyTimecard {
+//!     driver: Driver { id: 1, name: "テスト".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+//!     year: 2025,
+//!     month: 12,
+//!     days: vec![day],
+//!     summary: TimecardSummary::default(),
+//!     hire_date: None,
+//!     retire_date: None,
+//! };
+//! timecard.calculate_summary();
+//!
+//! let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+//! pdf.render_timecards(&[timecard], &RenderOptions::default()).unwrap();
+//! let bytes = pdf.save_to_bytes().unwrap();
+//! assert!(bytes.starts_with(b"%PDF"));
+//! ```
+
+#![warn(missing_docs)]
+
+/// Docker DBへの書き込みの監査ログ（time_card_allowance_log）
+pub mod audit;
+#[doc(hidden)]
+pub mod cli;
+/// サブコマンドの実行（main.rs は引数を解析してここの run_* を呼ぶ）
+#[doc(hidden)]
+pub mod commands;
+/// PHPのTCPDFが出力する座標JSON
+pub mod coordinate_data;
+pub(crate) mod coords_diff;
+/// 本番DBからのタイムカード取得・拘束時間の計算・Docker DBへの同期
+pub mod db;
+/// Docker DBのテーブル定義とマイグレーション
+pub mod docker_schema;
+/// CLIモードの失敗と終了コード
+pub mod error;
+pub(crate) mod export;
+/// 備考と集計項目の対応（HOLIDAY_MAPPING_FILE）
+pub mod holiday_mapping;
+/// 国民の祝日（CSV、範囲外の年は holidays-jp API）
+pub mod holidays;
+pub(crate) mod kosoku_compare;
+/// 拘束時間（TC_DC版）の昼休みの控除ルール
+pub mod kosoku_rules;
+/// 拘束時間の計算過程の記録（kosoku-debug）
+pub mod kosoku_trace;
+#[doc(hidden)]
+pub mod logging;
+/// 基礎日数・ドライバー一覧のキャッシュ（サーバー用）
+pub mod lookup_cache;
+/// メンテナンスモード
+pub mod maintenance;
+/// DBクエリ・リクエストのメトリクス（/metrics）
+pub mod metrics;
+/// 対象月の範囲（--from/--to）
+pub mod month_range;
+/// 生成したPDFのキャッシュ（サーバー用）
+pub mod pdf_cache;
+/// PDFのパスワード暗号化（RC4 128bit）
+pub mod pdf_encryption;
+pub(crate) mod pdf_image;
+/// バックグラウンドのPDF生成ジョブ（サーバー用）
+pub mod pdf_jobs;
+pub(crate) mod pdf_split;
+pub(crate) mod progress;
+/// PDFのレンダリングオプション（RENDER_* / --render / リクエストの "render"）
+pub mod render_options;
+/// DBの一時的なエラーの再試行とタイムアウト
+pub mod retry;
+/// HTTPサーバー
+pub mod server;
+/// 遅刻・早退の判定に使う始業・終業
+pub mod shift_rules;
+/// サーバーの統計（定期ログと /metrics 用）
+pub mod stats;
+/// TCPDF互換のPDF描画
+pub mod tcpdf_compat;
+/// タイムカードのデータ構造と集計
+pub mod timecard_data;
+pub(crate) mod tls;
+pub(crate) mod verify;
+pub(crate) mod zangyo_report;
+pub(crate) mod zip_archive;
+
+pub use db::{DbConfig, TimecardDb};
+pub use server::run;
+pub use tcpdf_compat::TcpdfCompat;
+pub use timecard_data::{DayRecord, Driver, MonthlyTimecard, TimecardSummary};
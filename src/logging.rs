@@ -0,0 +1,377 @@
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{self, Write as _};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// 既定のログレベル（RUST_LOG 未設定時）
+const DEFAULT_FILTER: &str = "info";
+
+/// --quiet 指定時（進捗表示・経過のログを出さない）
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// --quiet が指定されたか（進捗バー・処理時間の表示を止める）
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// ログの出力形式（LOG_FORMAT）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// 1行のテキスト（既定）
+    Text,
+    /// 1行1オブジェクトのJSON（Loki等で集める時用）
+    Json,
+}
+
+impl LogFormat {
+    /// 環境変数 LOG_FORMAT（json 以外はテキスト）
+    pub fn from_env() -> Self {
+        match env::var("LOG_FORMAT").ok().as_deref().map(str::trim) {
+            Some(f) if f.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// ログの出力を開始（標準エラー出力、レベルは RUST_LOG、quiet なら warn 以上のみ）
+/// 標準出力はPDFの保存先・比較結果などコマンドの結果のために空けておく
+pub fn init(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    let filter = if quiet {
+        LogFilter::parse("warn")
+    } else {
+        LogFilter::parse(&env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_FILTER.to_string()))
+    };
+    let subscriber = LogSubscriber::new(filter, LogFormat::from_env(), Box::new(std::io::stderr()));
+    tracing::subscriber::set_global_default(subscriber).ok();
+}
+
+/// ログレベルの指定（RUST_LOG の "info" / "timecard_pdf_rs=debug,tower_http=warn" 形式）
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    default: Level,
+    /// (target の前方一致, レベル)、長いものから
+    targets: Vec<(String, Level)>,
+}
+
+impl LogFilter {
+    /// 解釈できない指定は無視する（全て不正なら既定の info）
+    pub fn parse(spec: &str) -> Self {
+        let mut default = Level::INFO;
+        let mut targets = Vec::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Ok(level) = level.trim().parse::<Level>() {
+                        targets.push((target.trim().replace('-', "_"), level));
+                    }
+                }
+                None => {
+                    if let Ok(level) = directive.parse::<Level>() {
+                        default = level;
+                    }
+                }
+            }
+        }
+        targets.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Self { default, targets }
+    }
+
+    /// target のログを level で出すか
+    pub fn enabled(&self, target: &str, level: &Level) -> bool {
+        let max = self
+            .targets
+            .iter()
+            .find(|(prefix, _)| target == prefix || target.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with("::")))
+            .map_or(self.default, |(_, level)| *level);
+        *level <= max
+    }
+
+    /// 最も詳しいレベル
+    fn max_level(&self) -> Level {
+        self.targets.iter().map(|(_, level)| *level).fold(self.default, |a, b| if b > a { b } else { a })
+    }
+}
+
+/// スパンの内容（名前と項目）
+struct SpanData {
+    name: &'static str,
+    fields: Vec<(&'static str, Value)>,
+    refs: usize,
+}
+
+thread_local! {
+    /// このスレッドで入っているスパン（外側から）
+    static CURRENT: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 1行ずつ書き出す Subscriber（tracing-subscriber の fmt 相当の最小限）
+pub struct LogSubscriber {
+    filter: LogFilter,
+    format: LogFormat,
+    writer: Mutex<Box<dyn Write + Send>>,
+    spans: Mutex<HashMap<u64, SpanData>>,
+    next_id: AtomicU64,
+}
+
+impl LogSubscriber {
+    pub fn new(filter: LogFilter, format: LogFormat, writer: Box<dyn Write + Send>) -> Self {
+        Self { filter, format, writer: Mutex::new(writer), spans: Mutex::new(HashMap::new()), next_id: AtomicU64::new(1) }
+    }
+
+    /// イベントを1行に整形（スパンは外側から順に）
+    fn format_event(&self, event: &Event<'_>) -> String {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let message = visitor.message.take().unwrap_or_default();
+        let metadata = event.metadata();
+        let now = chrono::Local::now();
+        let spans = self.spans.lock().unwrap();
+        let current: Vec<&SpanData> = CURRENT.with(|c| c.borrow().iter().filter_map(|id| spans.get(id)).collect());
+
+        match self.format {
+            LogFormat::Text => {
+                let mut line = format!("{} {:>5} ", now.format("%Y-%m-%d %H:%M:%S%.3f"), metadata.level());
+                for span in &current {
+                    line.push_str(span.name);
+                    if !span.fields.is_empty() {
+                        line.push('{');
+                        line.push_str(&text_fields(&span.fields));
+                        line.push('}');
+                    }
+                    line.push_str(": ");
+                }
+                line.push_str(&message);
+                if !visitor.fields.is_empty() {
+                    line.push(' ');
+                    line.push_str(&text_fields(&visitor.fields));
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut object = Map::new();
+                object.insert("timestamp".to_string(), Value::String(now.to_rfc3339()));
+                object.insert("level".to_string(), Value::String(metadata.level().to_string()));
+                object.insert("target".to_string(), Value::String(metadata.target().to_string()));
+                object.insert("message".to_string(), Value::String(message));
+                // スパンの項目（driver_id・year・month など）もイベントと同じ階層に入れて検索しやすくする
+                for (name, value) in current.iter().flat_map(|s| s.fields.iter()).chain(visitor.fields.iter()) {
+                    object.insert(name.to_string(), value.clone());
+                }
+                if !current.is_empty() {
+                    let names = current.iter().map(|s| Value::String(s.name.to_string())).collect();
+                    object.insert("spans".to_string(), Value::Array(names));
+                }
+                Value::Object(object).to_string()
+            }
+        }
+    }
+}
+
+impl Subscriber for LogSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.filter.enabled(metadata.target(), metadata.level())
+    }
+
+    fn max_level_hint(&self) -> Option<tracing::level_filters::LevelFilter> {
+        Some(self.filter.max_level().into())
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let data = SpanData { name: attrs.metadata().name(), fields: visitor.into_fields(), refs: 1 };
+        self.spans.lock().unwrap().insert(id, data);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            data.fields.extend(visitor.into_fields());
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let line = self.format_event(event);
+        writeln!(self.writer.lock().unwrap(), "{}", line).ok();
+    }
+
+    fn enter(&self, span: &Id) {
+        CURRENT.with(|c| c.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        CURRENT.with(|c| {
+            let mut current = c.borrow_mut();
+            if let Some(pos) = current.iter().rposition(|id| *id == span.into_u64()) {
+                current.remove(pos);
+            }
+        });
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+            data.refs += 1;
+        }
+        id.clone()
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        let mut spans = self.spans.lock().unwrap();
+        let Some(data) = spans.get_mut(&id.into_u64()) else {
+            return false;
+        };
+        data.refs -= 1;
+        if data.refs == 0 {
+            spans.remove(&id.into_u64());
+            return true;
+        }
+        false
+    }
+}
+
+/// イベント・スパンの項目を集める（message は本文として別に持つ）
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl FieldVisitor {
+    fn into_fields(self) -> Vec<(&'static str, Value)> {
+        let mut fields = self.fields;
+        if let Some(message) = self.message {
+            fields.insert(0, ("message", Value::String(message)));
+        }
+        fields
+    }
+
+    fn push(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = Some(match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            });
+        } else {
+            self.fields.push((field.name(), value));
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, Value::String(format!("{:?}", value)));
+    }
+}
+
+/// テキスト形式の項目（key=value を空白区切り、文字列は引用符なし）
+fn text_fields(fields: &[(&'static str, Value)]) -> String {
+    let mut out = String::new();
+    for (i, (name, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        match value {
+            Value::String(s) => write!(out, "{}={}", name, s),
+            other => write!(out, "{}={}", name, other),
+        }
+        .ok();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_filter_directives() {
+        let filter = LogFilter::parse("warn,timecard_pdf_rs=debug,tower-http=info,bogus=loud");
+        assert!(filter.enabled("timecard_pdf_rs::db", &Level::DEBUG));
+        assert!(!filter.enabled("timecard_pdf_rs::db", &Level::TRACE));
+        assert!(filter.enabled("tower_http::trace::on_response", &Level::INFO));
+        // 前方一致はモジュールの区切りで判定
+        assert!(!filter.enabled("timecard_pdf_rs_extra", &Level::INFO));
+        assert!(!filter.enabled("mysql", &Level::INFO));
+        assert!(filter.enabled("mysql", &Level::WARN));
+        assert_eq!(filter.max_level(), Level::DEBUG);
+        assert_eq!(LogFilter::parse(""), LogFilter::parse("info"));
+    }
+
+    /// テスト用の書き出し先（書いた内容を共有する）
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(format: LogFormat, log: impl FnOnce()) -> Vec<String> {
+        let buffer = Buffer::default();
+        let subscriber = LogSubscriber::new(LogFilter::parse("info"), format, Box::new(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, log);
+        let bytes = buffer.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_event_includes_span_fields() {
+        let log = || {
+            let _span = tracing::info_span!("fetch", year = 2026, month = 1).entered();
+            tracing::info!(driver_id = 1071, "タイムカード取得");
+            tracing::debug!("出ない");
+        };
+
+        let lines = capture(LogFormat::Json, log);
+        assert_eq!(lines.len(), 1);
+        let json: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(json["message"], "タイムカード取得");
+        assert_eq!((json["year"].as_i64(), json["month"].as_i64(), json["driver_id"].as_i64()), (Some(2026), Some(1), Some(1071)));
+        assert_eq!(json["spans"][0], "fetch");
+
+        let lines = capture(LogFormat::Text, log);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].ends_with(" INFO fetch{year=2026 month=1}: タイムカード取得 driver_id=1071"), "{}", lines[0]);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::db::DriverQuery;
+use crate::timecard_data::Driver;
+use std::collections::HashMap;
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// キャッシュの有効期間の既定値（秒）
+const DEFAULT_TTL_SECS: u64 = 300;
+
+/// 基礎日数・ドライバー一覧のキャッシュ（サーバー用、どちらも月に1回程度しか変わらない）
+/// /api/drivers やPDF生成のたびに本番DBに問い合わせない
+///   - LOOKUP_CACHE_TTL_SECS: 有効期間（秒、既定300、0で無効）
+///
+/// DELETE /api/cache で消せる（給与システムで基礎日数・入退社を直した直後など）
+pub struct LookupCache {
+    ttl: Duration,
+    kiso_dates: Mutex<HashMap<(i32, u32), (Instant, i32)>>,
+    /// キーは年月と絞り込み条件（DriverQuery の内容）
+    drivers: Mutex<HashMap<String, (Instant, Vec<Driver>)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl LookupCache {
+    /// ttl が0ならキャッシュしない
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, kiso_dates: Mutex::default(), drivers: Mutex::default(), hits: AtomicU64::new(0), misses: AtomicU64::new(0) }
+    }
+
+    /// 環境変数から作成（LOOKUP_CACHE_TTL_SECS）
+    pub fn from_env() -> Self {
+        let ttl = env::var("LOOKUP_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        Self::new(Duration::from_secs(ttl))
+    }
+
+    /// 基礎日数（有効期間内ならキャッシュ、なければ fetch して保存）
+    pub fn kiso_date<E>(&self, year: i32, month: u32, fetch: impl FnOnce() -> Result<i32, E>) -> Result<i32, E> {
+        self.get_or_fetch(&self.kiso_dates, (year, month), fetch)
+    }
+
+    /// ドライバー一覧（有効期間内ならキャッシュ、なければ fetch して保存）
+    pub fn drivers<E>(&self, query: &DriverQuery, fetch: impl FnOnce() -> Result<Vec<Driver>, E>) -> Result<Vec<Driver>, E> {
+        self.get_or_fetch(&self.drivers, format!("{:?}", query), fetch)
+    }
+
+    /// 全て削除して件数を返す
+    pub fn clear(&self) -> usize {
+        let kiso_dates = std::mem::take(&mut *self.kiso_dates.lock().unwrap()).len();
+        kiso_dates + std::mem::take(&mut *self.drivers.lock().unwrap()).len()
+    }
+
+    /// /metrics用テキスト（Prometheus形式）
+    pub fn render_metrics(&self) -> String {
+        format!(
+            "# TYPE timecard_lookup_cache_hits_total counter\ntimecard_lookup_cache_hits_total {}\n\
+             # TYPE timecard_lookup_cache_misses_total counter\ntimecard_lookup_cache_misses_total {}\n",
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed)
+        )
+    }
+
+    /// DBへの問い合わせ中はロックを持たない（同じキーが同時に来たら両方問い合わせ、後の結果で上書き）
+    fn get_or_fetch<K, V, E>(
+        &self,
+        map: &Mutex<HashMap<K, (Instant, V)>>,
+        key: K,
+        fetch: impl FnOnce() -> Result<V, E>,
+    ) -> Result<V, E>
+    where
+        K: std::hash::Hash + Eq,
+        V: Clone,
+    {
+        if self.ttl.is_zero() {
+            return fetch();
+        }
+        if let Some((created, value)) = map.lock().unwrap().get(&key) {
+            if created.elapsed() < self.ttl {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(value.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // 失敗は保存しない（次のリクエストで問い合わせ直す）
+        let value = fetch()?;
+        map.lock().unwrap().insert(key, (Instant::now(), value.clone()));
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_hits_until_ttl_or_clear() {
+        let cache = LookupCache::new(Duration::from_secs(60));
+        let mut calls = 0;
+        let mut fetch = |value: i32| {
+            calls += 1;
+            Ok::<_, String>(value)
+        };
+        assert_eq!(cache.kiso_date(2025, 12, || fetch(21)).unwrap(), 21);
+        assert_eq!(cache.kiso_date(2025, 12, || fetch(99)).unwrap(), 21);
+        // 別の月は別のキー
+        assert_eq!(cache.kiso_date(2026, 1, || fetch(20)).unwrap(), 20);
+        assert_eq!(calls, 2);
+
+        // 失敗は保存しない
+        assert!(cache.kiso_date(2026, 2, || Err::<i32, _>("down".to_string())).is_err());
+        assert_eq!(cache.kiso_date(2026, 2, || Ok::<_, String>(19)).unwrap(), 19);
+
+        // 絞り込み条件が違えば別のキー
+        let driver = |id: i32| Driver { id, name: "テスト".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None };
+        let query = DriverQuery::new(2025, 12);
+        cache.drivers(&query, || Ok::<_, String>(vec![driver(1)])).unwrap();
+        assert_eq!(cache.drivers(&query, || Ok::<_, String>(vec![])).unwrap().len(), 1);
+        assert!(cache.drivers(&query.clone().include_officers(true), || Ok::<_, String>(vec![])).unwrap().is_empty());
+
+        let metrics = cache.render_metrics();
+        assert!(metrics.contains("timecard_lookup_cache_hits_total 2\n"), "{}", metrics);
+        assert!(metrics.contains("timecard_lookup_cache_misses_total 6\n"), "{}", metrics);
+
+        assert_eq!(cache.clear(), 5);
+        assert_eq!(cache.kiso_date(2025, 12, || Ok::<_, String>(22)).unwrap(), 22);
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_cache() {
+        let cache = LookupCache::new(Duration::ZERO);
+        assert_eq!(cache.kiso_date(2025, 12, || Ok::<_, String>(21)).unwrap(), 21);
+        assert_eq!(cache.kiso_date(2025, 12, || Ok::<_, String>(22)).unwrap(), 22);
+        assert_eq!(cache.clear(), 0);
+    }
+}
@@ -1,433 +1,227 @@
-mod tcpdf_compat;
-mod coordinate_data;
-mod db;
-mod timecard_data;
-mod server;
-
-use std::fs;
-use std::env;
-use coordinate_data::CoordinateData;
-use tcpdf_compat::TcpdfCompat;
-use db::{DbConfig, TimecardDb};
+use timecard_pdf_rs::{cli, commands, logging};
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use timecard_pdf_rs::db::{DbConfig, KosokuSource};
+use timecard_pdf_rs::error::TimecardError;
+use timecard_pdf_rs::maintenance::MaintenanceConfig;
+use timecard_pdf_rs::month_range::MonthRange;
+use timecard_pdf_rs::pdf_encryption::PdfEncryption;
+use timecard_pdf_rs::render_options::{RenderOptions, RenderOverrides};
+use timecard_pdf_rs::tcpdf_compat::SaveOptions;
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() {
     // .envファイルから環境変数を読み込み
     dotenvy::dotenv().ok();
-    let args: Vec<String> = env::args().collect();
+    // 引数の誤りは使い方を表示して終了コード2（--help でサブコマンドごとの説明）
+    let matches = cli::command().get_matches();
+    let (mode, args) = match matches.subcommand() {
+        Some((mode, sub)) => (mode, sub),
+        None => ("", &matches),
+    };
+    // 経過・警告はログ（標準エラー出力、RUST_LOG / LOG_FORMAT）、--quiet なら警告・エラーのみ
+    logging::init(args.get_flag("quiet"));
+
+    let ignore_maintenance = args.get_flag("ignore-maintenance");
+    // --deterministic: 同じ入力から同じバイト列のPDFを出力（回帰比較用）
+    // --password: 出力PDFをパスワードで暗号化（開く時にパスワードが必要）
+    let encryption = match args.get_one::<String>("password").map(|p| PdfEncryption::new(p, None)) {
+        Some(Ok(e)) => Some(e),
+        Some(Err(e)) => cli::usage_error(mode, format!("--password: {}", e)),
+        None => None,
+    };
+    let save_options = SaveOptions {
+        deterministic: args.get_flag("deterministic"),
+        fixed_date: None,
+        encryption,
+    };
+    // --categories 2,3 / --include-officers: 対象ドライバーの給与区分
+    let driver_filter = cli::DriverFilterArgs::from_matches(args);
+    // --kosoku-source tcdc-rust など: 拘束列に表示する拘束時間の出典（auto 以外はフッターに表示）
+    let kosoku_source = args.get_one::<KosokuSource>("kosoku-source").copied().unwrap_or_default();
+
+    // --render key=value: レンダリングオプション（環境変数より優先）
+    let render_pairs: Vec<String> = args.get_many::<String>("render").into_iter().flatten().cloned().collect();
+    let render_cli = match RenderOverrides::from_cli_pairs(&render_pairs) {
+        Ok(r) => r,
+        Err(e) => cli::usage_error(mode, e),
+    };
+    // --per-page N: 1ページに並べる人数（--render drivers_per_page=N と同じ）
+    // --watermark 控え: 各ページに透かし（--render watermark=控え と同じ）
+    let render_cli = render_cli.merge(&RenderOverrides {
+        drivers_per_page: args.get_one::<usize>("per-page").copied(),
+        watermark: args.get_one::<String>("watermark").cloned(),
+        ..Default::default()
+    });
+    let render_base = RenderOverrides::from_env().merge(&render_cli);
+    let mut render_options = match RenderOptions::resolve(&[&render_base]) {
+        Ok(o) => o,
+        Err(e) => {
+            error!("レンダリングオプションが不正です: {}", e);
+            std::process::exit(1);
+        }
+    };
+    render_options.kosoku_source_label = kosoku_source.label();
+
+    // メンテナンス中はDBを使うモードを実行しない（--ignore-maintenance で強制実行）
+    let uses_db = cli::DB_MODES.contains(&mode);
+    if uses_db && !ignore_maintenance {
+        let status = MaintenanceConfig::from_env().status();
+        if status.maintenance {
+            error!("メンテナンス中のため実行できません: {}", status.message);
+            if let Some(until) = &status.until {
+                error!("終了予定: {}", until);
+            }
+            error!("強制実行する場合は --ignore-maintenance を指定してください");
+            std::process::exit(1);
+        }
+    }
 
-    // コマンドライン引数でモードを切り替え
-    let mode = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    // DBの設定が不足していれば何もせずに終了（Docker DBは書き込み・比較に使うモードのみ）
+    if uses_db {
+        let mut configs = Vec::new();
+        if !cli::DOCKER_ONLY_MODES.contains(&mode) {
+            configs.push(DbConfig::production());
+        }
+        if cli::DOCKER_DB_MODES.contains(&mode) {
+            configs.push(DbConfig::docker());
+        }
+        if let Err(e) = configs.iter().try_for_each(DbConfig::validate) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
 
-    match mode {
+    // 対象年月（--month YYYY-MM か位置引数の年月、未指定なら前月）
+    let mut target = (0, 0);
+    let mut target_range = MonthRange::single(0, 0);
+    let mut target_driver_id = None;
+    if uses_db {
+        let target_args = match cli::TargetArgs::from_matches(args) {
+            Ok(t) => t,
+            Err(e) => cli::usage_error(mode, e),
+        };
+        if target_args.positional_driver_id {
+            warn!("ドライバーIDの位置引数は非推奨です。--driver-id {} を使ってください", target_args.driver_id.unwrap_or_default());
+        }
+        let resolved = cli::resolve_target_month(&target_args, chrono::Utc::now());
+        // --from/--to（pdf・pdf-shukei）なら範囲、それ以外は1か月
+        target_range = target_args.range.unwrap_or(MonthRange::single(resolved.year, resolved.month));
+        info!("対象年月: {}", target_range);
+        // --yes: 範囲外の月の確認省略
+        if !cli::confirm_target_month(&resolved, args.get_flag("yes")) {
+            error!("中止しました");
+            std::process::exit(1);
+        }
+        target = (resolved.year, resolved.month);
+        target_driver_id = target_args.driver_id;
+    }
+    let output = args.try_get_one::<String>("output").ok().flatten().map(String::as_str);
+
+    let result = match mode {
         "server" => {
             // HTTPサーバーモード
-            let port: u16 = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(8080);
-            server::run(port).await;
+            // --skip-db-check: DBの設定・疎通を確認せずに起動
+            commands::run_server(args.get_one::<SocketAddr>("bind").copied(), args.get_flag("skip-db-check"), render_base).await
         }
         "db" => {
             // DBモード: タイムカードデータを取得して表示
-            run_db_mode(&args);
+            commands::run_db_mode(target, target_driver_id, &driver_filter, kosoku_source, &render_options)
         }
         "pdf" => {
             // PDFモード: DBからタイムカードを取得してPDF生成（3人/ページ）
-            run_pdf_mode(&args);
+            // --no-sync: Docker DBへの同期をしない / --keep-stale: 計算結果にない拘束時間の行を削除しない
+            // --dry-run: 同期で変わる内容を表示するだけ（書き込み・PDF生成はしない）
+            let sync = if args.get_flag("dry-run") { commands::SyncMode::DryRun } else if args.get_flag("no-sync") { commands::SyncMode::Skip } else { commands::SyncMode::Write };
+            // --jobs N: データ取得の並列数
+            // --from/--to: 複数月をまとめて1つのPDFに（--split-months なら月ごとのファイル）
+            let split_months = args.get_flag("split-months");
+            commands::run_pdf_mode(target_range, target_driver_id, save_options, &driver_filter, kosoku_source, &render_options, sync, !args.get_flag("keep-stale"), output, cli::fetch_jobs(args), split_months)
         }
         "pdf-shukei" => {
             // PDF集計モード: DBからタイムカードを取得してPDF生成（1人/ページ、日付横並び）
-            run_pdf_shukei_mode(&args);
-        }
-        "verify" => {
-            // 検証モード: 本番DBから計算してDocker DBにINSERT（TC_DC版）
-            run_verify_mode(&args);
-        }
-        "verify-dtako" => {
-            // 検証モード: デジタコ版計算 → Docker DBにINSERT
-            run_verify_digitacho_mode(&args);
-        }
-        _ => {
-            // JSONモード: 座標JSONからPDF生成（従来の動作）
-            run_json_mode();
-        }
-    }
-}
-
-/// DBモード: 本番DBからタイムカードデータを取得
-fn run_db_mode(args: &[String]) {
-    // 年月を引数から取得（デフォルト: 2025年12月）
-    let year: i32 = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(2025);
-    let month: u32 = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(12);
-    // 特定のドライバーIDを指定可能
-    let target_driver_id: Option<i32> = args.get(4).and_then(|s| s.parse().ok());
-
-    println!("=== タイムカードデータ取得 ===");
-    println!("対象: {}年{}月", year, month);
-    if let Some(id) = target_driver_id {
-        println!("ドライバーID: {}", id);
-    }
-    println!();
-
-    // 本番DBに接続
-    let config = DbConfig::production();
-    println!("接続先: {}:{}", config.host, config.port);
-
-    let db = match TimecardDb::connect(&config) {
-        Ok(db) => db,
-        Err(e) => {
-            eprintln!("DB接続エラー: {}", e);
-            return;
-        }
-    };
-    println!("接続成功！");
-    println!();
-
-    // ドライバー一覧を取得
-    let drivers = match db.get_active_drivers(year, month) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("ドライバー取得エラー: {}", e);
-            return;
-        }
-    };
-
-    println!("アクティブドライバー数: {}", drivers.len());
-    println!();
-
-    // 特定のドライバーIDが指定されていればそのドライバーを、なければ最初の3人を表示
-    let target_drivers: Vec<&timecard_data::Driver> = if let Some(id) = target_driver_id {
-        drivers.iter().filter(|d| d.id == id).collect()
-    } else {
-        drivers.iter().take(3).collect()
-    };
-
-    for driver in target_drivers {
-        let timecard = match db.get_monthly_timecard(driver, year, month) {
-            Ok(tc) => tc,
-            Err(e) => {
-                eprintln!("タイムカード取得エラー ({}): {}", driver.name, e);
-                continue;
+            commands::run_pdf_shukei_mode(target_range, target_driver_id, save_options, &driver_filter, kosoku_source, &render_options, output, cli::fetch_jobs(args), args.get_flag("split-months"))
+        }
+        "pdf-split" => {
+            // PDF分割モード: 1人1ファイル（timecard_YYYY_MM_<driver_id>_<氏名>.pdf）
+            let outdir = args.get_one::<String>("outdir").expect("既定値あり");
+            commands::run_pdf_split_mode(target, target_driver_id, save_options, &driver_filter, kosoku_source, &render_options, outdir)
+        }
+        "verify" | "verify-dtako" => {
+            // --sample N / --seed S / --drivers-from-file path: 対象ドライバー
+            // --keep-stale: 計算結果にない日の行を削除しない
+            let delete_stale = !args.get_flag("keep-stale");
+            match cli::driver_selection(args).map_err(TimecardError::Usage) {
+                // 検証モード: 本番DBから計算してDocker DBにINSERT（TC_DC版）
+                Ok(selection) if mode == "verify" => commands::run_verify_mode(target, &driver_filter, &selection, delete_stale),
+                // 検証モード: デジタコ版計算 → Docker DBにINSERT（--allow-partial: 一部のドライバーの失敗は終了コード0）
+                Ok(selection) => commands::run_verify_digitacho_mode(target, &driver_filter, &selection, args.get_flag("allow-partial"), delete_stale),
+                Err(e) => Err(e),
             }
-        };
-
-        println!("=== {} ({}) ===", timecard.driver.name, timecard.year_month_str());
-        println!("{:>2} {:>2} {:>5} {:>5} {:>5} {:>5} {:>5} {:>6} {}",
-            "日", "曜", "出勤1", "退社1", "出勤2", "退社2", "残業", "拘束", "備考");
-        println!("{}", "-".repeat(70));
-
-        for day in &timecard.days {
-            let in1 = day.clock_in.get(0).map(|s| s.as_str()).unwrap_or("");
-            let out1 = day.clock_out.get(0).map(|s| s.as_str()).unwrap_or("");
-            let in2 = day.clock_in.get(1).map(|s| s.as_str()).unwrap_or("");
-            let out2 = day.clock_out.get(1).map(|s| s.as_str()).unwrap_or("");
-            let zangyo = day.zangyo_with_tsuika_str();
-            let kosoku = day.kosoku_str();
-
-            let sunday_mark = if day.is_sunday { "*" } else { " " };
-            // 備考 = remarks + detail_st（PHPと同じ連結表示）
-            let remarks = format!("{}{}", day.remarks, day.detail_st);
-            println!("{}{:>2} {:>2} {:>5} {:>5} {:>5} {:>5} {:>5} {:>6} {}",
-                sunday_mark, day.day, day.weekday, in1, out1, in2, out2, zangyo, kosoku, remarks);
-        }
-        println!();
-    }
-}
-
-/// PDFモード: DBからタイムカードを取得してPDF生成
-fn run_pdf_mode(args: &[String]) {
-    // 年月を引数から取得（デフォルト: 2025年12月）
-    let year: i32 = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(2025);
-    let month: u32 = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(12);
-    // 特定のドライバーIDを指定可能
-    let target_driver_id: Option<i32> = args.get(4).and_then(|s| s.parse().ok());
-
-    println!("=== タイムカードPDF生成 ===");
-    println!("対象: {}年{}月", year, month);
-    if let Some(id) = target_driver_id {
-        println!("ドライバーID: {}", id);
-    }
-    println!();
-
-    // 本番DBに接続
-    let config = DbConfig::production();
-    println!("接続先: {}:{}", config.host, config.port);
-
-    let db = match TimecardDb::connect(&config) {
-        Ok(db) => db,
-        Err(e) => {
-            eprintln!("DB接続エラー: {}", e);
-            return;
-        }
-    };
-    println!("接続成功！");
-    println!();
-
-    // 基礎日数を取得
-    let kiso_date = match db.get_kiso_date(year, month) {
-        Ok(k) => k,
-        Err(e) => {
-            eprintln!("基礎日数取得エラー: {}", e);
-            return;
-        }
-    };
-    println!("基礎日数: {}", kiso_date);
-    println!();
-
-    // タイムカードを取得
-    let mut timecards = match db.get_all_monthly_timecards_with_kiso(year, month) {
-        Ok(tc) => tc,
-        Err(e) => {
-            eprintln!("タイムカード取得エラー: {}", e);
-            return;
         }
-    };
-
-    // 特定ドライバーのみにフィルタリング
-    if let Some(driver_id) = target_driver_id {
-        timecards.retain(|tc| tc.driver.id == driver_id);
-    }
-
-    println!("取得したタイムカード数: {}", timecards.len());
-    println!();
-
-    // time_card_allowanceテーブルを差分更新（Docker DB）
-    println!("time_card_allowance（Docker DB）を差分更新...");
-    match db.sync_all_timecard_allowances_to_docker(&timecards) {
-        Ok((inserted, updated, unchanged)) => {
-            println!("[OK] 追加: {}, 更新: {}, 変更なし: {}",
-                     inserted, updated, unchanged);
+        "report-zangyo-anomalies" => {
+            // 残業異常レポート: 複数ソース・上限超過の日をCSV/JSONで出力
+            let format = args.get_one::<String>("format").expect("既定値あり");
+            commands::run_report_zangyo_mode(target, format, &driver_filter)
         }
-        Err(e) => {
-            eprintln!("[ERROR] 同期失敗: {}", e);
+        "compare" => {
+            // 比較モード: 本番DB(PHP)とDocker DB(Rust)の拘束時間を日別に比較
+            let threshold = *args.get_one::<i32>("threshold").expect("既定値あり");
+            commands::run_compare_mode(target, target_driver_id, threshold, output)
         }
-    }
-
-    // time_card_kosokuテーブルを更新（Docker DB）- TC_DCとデジタコを別々に
-    println!("time_card_kosoku（Docker DB）を更新...");
-    match db.insert_kosoku_to_docker(&timecards) {
-        Ok(count) => {
-            println!("[OK] {}件INSERT/UPDATE完了", count);
+        "export-coords" => {
+            // 座標JSON出力モード: render_timecards の描画をPHPと同じ座標JSON形式で出力（レイアウト比較用）
+            commands::run_export_coords_mode(target, target_driver_id, &driver_filter, kosoku_source, &render_options, output)
         }
-        Err(e) => {
-            eprintln!("[ERROR] INSERT失敗: {}", e);
+        "csv" => {
+            // CSV出力モード: 日別のタイムカードを分析用CSVに出力（--bom: Excel用にBOMを付ける）
+            commands::run_csv_mode(target, target_driver_id, &driver_filter, kosoku_source, output, args.get_flag("bom"))
         }
-    }
-    println!();
-
-    // PDF生成
-    // A4横向き: 297mm x 210mm
-    let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
-    pdf.render_timecards(&timecards);
-
-    let output_path = if let Some(id) = target_driver_id {
-        format!("timecard_{}_{:02}_{}.pdf", year, month, id)
-    } else {
-        format!("timecard_{}_{:02}.pdf", year, month)
-    };
-    pdf.save(&output_path).expect("Failed to save PDF");
-
-    println!();
-    println!("PDF saved to {}", output_path);
-}
-
-/// PDF集計モード: DBからタイムカードを取得してPDF生成（1人/ページ、日付横並び）
-fn run_pdf_shukei_mode(args: &[String]) {
-    // 年月を引数から取得（デフォルト: 2025年12月）
-    let year: i32 = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(2025);
-    let month: u32 = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(12);
-    // 特定のドライバーIDを指定可能（テスト用）
-    let target_driver_id: Option<i32> = args.get(4).and_then(|s| s.parse().ok());
-
-    println!("=== タイムカードPDF生成（集計モード）===");
-    println!("対象: {}年{}月", year, month);
-    println!("形式: 1人1ページ、日付横並び");
-    if let Some(id) = target_driver_id {
-        println!("ドライバーID: {}", id);
-    }
-    println!();
-
-    // 本番DBに接続
-    let config = DbConfig::production();
-    println!("接続先: {}:{}", config.host, config.port);
-
-    let db = match TimecardDb::connect(&config) {
-        Ok(db) => db,
-        Err(e) => {
-            eprintln!("DB接続エラー: {}", e);
-            return;
+        "history" => {
+            // 履歴モード: Docker DBの監査ログ（AUDIT_LOG=1 で記録）を表示
+            commands::run_history_mode(target, target_driver_id)
         }
-    };
-    println!("接続成功！");
-    println!();
-
-    // 全ドライバーのタイムカードを取得（基礎日数付き）
-    let all_timecards = match db.get_all_monthly_timecards_with_kiso(year, month) {
-        Ok(tc) => tc,
-        Err(e) => {
-            eprintln!("タイムカード取得エラー: {}", e);
-            return;
+        "kosoku-debug" => {
+            // 拘束時間デバッグモード: TC_DC版・デジタコ版の計算過程を表示（--day N: その日だけ / --json）
+            commands::run_kosoku_debug_mode(target, target_driver_id, args.get_one::<u32>("day").copied(), args.get_flag("json"))
         }
-    };
-
-    // 特定のドライバーIDが指定されていればフィルタ
-    let timecards: Vec<_> = if let Some(id) = target_driver_id {
-        all_timecards.into_iter().filter(|tc| tc.driver.id == id).collect()
-    } else {
-        all_timecards
-    };
-
-    println!("取得したタイムカード数: {}", timecards.len());
-    println!();
-
-    // PDF生成（集計モード）
-    // A4横向き: 297mm x 210mm
-    let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
-    pdf.render_timecards_shukei(&timecards);
-
-    let output_path = format!("timecard_shukei_{}_{:02}.pdf", year, month);
-    pdf.save(&output_path).expect("Failed to save PDF");
-
-    println!();
-    println!("PDF saved to {}", output_path);
-}
-
-/// 検証モード: 本番DBから計算してDocker DBにINSERT
-fn run_verify_mode(args: &[String]) {
-    let year: i32 = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(2025);
-    let month: u32 = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(12);
-
-    println!("=== 検証モード: 拘束時間計算 → Docker DB INSERT ===");
-    println!("対象: {}年{}月", year, month);
-    println!();
-
-    // 本番DBに接続
-    let config = DbConfig::production();
-    println!("本番DB接続先: {}:{}", config.host, config.port);
-
-    let db = match TimecardDb::connect(&config) {
-        Ok(db) => db,
-        Err(e) => {
-            eprintln!("DB接続エラー: {}", e);
-            return;
+        "migrate" => {
+            // マイグレーションモード: Docker DBにテーブルを作成（既存のテーブルは一意キーを確認）
+            commands::run_migrate_mode()
         }
-    };
-    println!("本番DB接続成功！");
-    println!();
-
-    // 全ドライバーのタイムカードを取得（拘束時間計算含む）
-    let timecards = match db.get_all_monthly_timecards(year, month) {
-        Ok(tc) => tc,
-        Err(e) => {
-            eprintln!("タイムカード取得エラー: {}", e);
-            return;
+        "diff-coords" => {
+            // 座標JSON比較モード: 2つの座標JSON（PHPとRustなど）を要素ごとに比較
+            let path = |id: &str| args.get_one::<String>(id).expect("必須引数");
+            commands::run_diff_coords_mode(path("a"), path("b"), &cli::diff_options(args), args.get_flag("json"))
         }
-    };
-
-    println!("取得したタイムカード数: {}", timecards.len());
-
-    // Docker DBにINSERT
-    println!();
-    println!("Docker DBに拘束時間をINSERT...");
-    match db.insert_kosoku_to_docker(&timecards) {
-        Ok(count) => {
-            println!("[OK] {}件INSERT完了", count);
+        "render-fixture" => {
+            // フィクスチャモード: MonthlyTimecardの配列（JSON）からDBなしでPDF生成
+            let input = args.get_one::<String>("input").expect("必須引数");
+            commands::run_render_fixture_mode(input, output.expect("既定値あり"), save_options, &render_options)
         }
-        Err(e) => {
-            eprintln!("[ERROR] INSERT失敗: {}", e);
+        "json" => {
+            // JSONモード: 座標JSONからPDF生成（json <input> [output]）
+            let input = args.get_one::<String>("input").map(PathBuf::from).expect("必須引数");
+            let fonts: Vec<String> = args.get_many::<String>("font").into_iter().flatten().cloned().collect();
+            commands::run_json_mode(input, output.map(PathBuf::from), save_options, &fonts)
         }
-    }
-
-    println!();
-    println!("検証コマンド:");
-    println!("  python3 scripts/db_verify.py --compare --year {} --month {}", year, month);
-}
-
-/// 検証モード（デジタコ版）: 本番DBから計算してDocker DBにINSERT
-fn run_verify_digitacho_mode(args: &[String]) {
-    let year: i32 = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(2025);
-    let month: u32 = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(12);
-
-    println!("=== 検証モード（デジタコ版）: DtakoEvents計算 → Docker DB INSERT ===");
-    println!("対象: {}年{}月", year, month);
-    println!();
-
-    // 本番DBに接続
-    let config = DbConfig::production();
-    println!("本番DB接続先: {}:{}", config.host, config.port);
-
-    let db = match TimecardDb::connect(&config) {
-        Ok(db) => db,
-        Err(e) => {
-            eprintln!("DB接続エラー: {}", e);
-            return;
+        _ => {
+            // 引数なし: 従来の固定ファイルの座標JSONからPDF生成（非推奨）
+            warn!("引数なしの実行は非推奨です。json {} {} を使ってください", commands::LEGACY_JSON_INPUT, commands::LEGACY_JSON_OUTPUT);
+            commands::run_json_mode(PathBuf::from(commands::LEGACY_JSON_INPUT), Some(PathBuf::from(commands::LEGACY_JSON_OUTPUT)), save_options, &[])
         }
     };
-    println!("本番DB接続成功！");
-    println!();
 
-    // アクティブドライバーを取得
-    let drivers = match db.get_active_drivers(year, month) {
-        Ok(d) => d,
+    // 失敗したら終了コード1（引数の誤りは使い方を表示して2）、バッチから失敗が分かるようにする
+    match result {
+        Ok(()) => {}
+        Err(TimecardError::Usage(message)) => cli::usage_error(mode, message),
         Err(e) => {
-            eprintln!("ドライバー取得エラー: {}", e);
-            return;
-        }
-    };
-
-    println!("アクティブドライバー数: {}", drivers.len());
-    println!();
-
-    // Docker DBにデジタコ版拘束時間をINSERT
-    println!("Docker DBにデジタコ版拘束時間をINSERT...");
-    let mut total_inserted = 0;
-    let mut error_count = 0;
-
-    for (i, driver) in drivers.iter().enumerate() {
-        match db.insert_digitacho_kosoku_to_docker(driver.id, year, month) {
-            Ok(count) => {
-                total_inserted += count;
-                if (i + 1) % 10 == 0 {
-                    println!("  進捗: {}/{} ドライバー処理完了", i + 1, drivers.len());
-                }
-            }
-            Err(e) => {
-                eprintln!("[ERROR] driver_id={}: {}", driver.id, e);
-                error_count += 1;
-            }
+            error!("{}", e);
+            std::process::exit(e.exit_code());
         }
     }
-
-    println!();
-    println!("[OK] {}件INSERT完了 (エラー: {}件)", total_inserted, error_count);
-
-    println!();
-    println!("検証コマンド:");
-    println!("  python3 scripts/db_verify.py --compare-dtako --year {} --month {}", year, month);
 }
 
-/// JSONモード: 座標JSONからPDF生成
-fn run_json_mode() {
-    // PHPから出力された座標JSONを読み込む
-    let json_path = "pdf_coordinates_20251230_172511.json";
-    let json_str = fs::read_to_string(json_path)
-        .expect("Failed to read coordinate JSON");
-
-    let data: CoordinateData = serde_json::from_str(&json_str)
-        .expect("Failed to parse JSON");
-
-    println!("Page size: {}mm x {}mm", data.page_width_mm, data.page_height_mm);
-    println!("Orientation: {}", data.orientation);
-    println!("Total pages: {}", data.total_pages);
-    println!("Total elements: {}", data.elements.len());
-
-    // PDF生成
-    let mut pdf = TcpdfCompat::new(
-        data.page_width_mm,
-        data.page_height_mm,
-        &data.orientation,
-    );
-
-    pdf.render_elements(&data.elements);
-    pdf.save("output_y05.pdf").expect("Failed to save PDF");
-
-    println!("PDF saved to output_y05.pdf");
-}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// メンテナンス中のデフォルトメッセージ
+const DEFAULT_MESSAGE: &str = "現在メンテナンス中です。しばらくしてから再度お試しください。";
+
+/// メンテナンスモード設定
+/// 環境変数とフラグファイルを毎回読み直すため、切り替えに再起動は不要
+///   - MAINTENANCE_MODE: 1/true/on でメンテナンス中
+///   - MAINTENANCE_MESSAGE: 表示メッセージ
+///   - MAINTENANCE_UNTIL: 終了予定時刻（例: 2025-12-01T06:00:00+09:00）
+///   - MAINTENANCE_FILE: フラグファイルのパス（存在すればメンテナンス中）
+///
+/// フラグファイルの中身が {"message": ..., "until": ...} のJSONなら環境変数より優先する
+#[derive(Clone, Debug, Default)]
+pub struct MaintenanceConfig {
+    /// フラグファイルのパス
+    pub flag_file: Option<PathBuf>,
+    /// 環境変数（MAINTENANCE_*）を参照するか
+    pub use_env: bool,
+}
+
+/// 現在のメンテナンス状態
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStatus {
+    /// メンテナンス中か
+    pub maintenance: bool,
+    /// 利用者に表示するメッセージ
+    pub message: String,
+    /// 終了予定（設定されていれば）
+    pub until: Option<String>,
+}
+
+/// フラグファイルの中身（JSON形式の場合）
+#[derive(Deserialize, Default)]
+struct FlagFileContent {
+    message: Option<String>,
+    until: Option<String>,
+}
+
+impl MaintenanceConfig {
+    /// 環境変数から設定を読み込み
+    pub fn from_env() -> Self {
+        Self {
+            flag_file: env::var("MAINTENANCE_FILE").ok().filter(|s| !s.is_empty()).map(PathBuf::from),
+            use_env: true,
+        }
+    }
+
+    /// 現在のメンテナンス状態を取得（呼び出しごとに環境変数・ファイルを再確認）
+    pub fn status(&self) -> MaintenanceStatus {
+        let env_on = self.use_env
+            && env::var("MAINTENANCE_MODE")
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "on"))
+                .unwrap_or(false);
+        let env_message = if self.use_env { env::var("MAINTENANCE_MESSAGE").ok() } else { None };
+        let env_until = if self.use_env { env::var("MAINTENANCE_UNTIL").ok() } else { None };
+
+        // フラグファイル: 存在すればメンテナンス中
+        let file_content = self.flag_file.as_ref().and_then(|path| fs::read_to_string(path).ok());
+        let file_on = file_content.is_some();
+        let parsed: FlagFileContent = match file_content.as_deref().map(str::trim) {
+            Some(text) if text.starts_with('{') => serde_json::from_str(text).unwrap_or_default(),
+            // JSONでなければ中身をそのままメッセージとして扱う
+            Some(text) if !text.is_empty() => FlagFileContent { message: Some(text.to_string()), until: None },
+            _ => FlagFileContent::default(),
+        };
+
+        MaintenanceStatus {
+            maintenance: env_on || file_on,
+            message: parsed.message.or(env_message).unwrap_or_else(|| DEFAULT_MESSAGE.to_string()),
+            until: parsed.until.or(env_until),
+        }
+    }
+
+    /// メンテナンス中かどうか
+    pub fn is_active(&self) -> bool {
+        self.status().maintenance
+    }
+}
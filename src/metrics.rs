@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// PDF生成時間のバケット（秒、全ドライバー分で数十秒かかる）
+const RENDER_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// DBクエリ時間のバケット（秒）
+const QUERY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// ヒストグラム（累積ではなくバケットごとの件数を持ち、出力時に累積する）
+#[derive(Debug, Clone)]
+struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self { buckets, counts: vec![0; buckets.len()], sum: 0.0, count: 0 }
+    }
+
+    fn observe(&mut self, secs: f64) {
+        if let Some(i) = self.buckets.iter().position(|&le| secs <= le) {
+            self.counts[i] += 1;
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+
+    /// Prometheus形式の _bucket / _sum / _count（labels は "query=\"x\"," のように末尾にカンマ）
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (le, count) in self.buckets.iter().zip(&self.counts) {
+            cumulative += count;
+            writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, labels, le, cumulative).ok();
+        }
+        writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, labels, self.count).ok();
+        let labels = labels.trim_end_matches(',');
+        let labels = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        writeln!(out, "{}_sum{} {}", name, labels, self.sum).ok();
+        writeln!(out, "{}_count{} {}", name, labels, self.count).ok();
+    }
+}
+
+/// PDF生成・DBクエリ・Docker DB同期のメトリクス（/metrics、METRICS_ENABLED）
+/// ServerStats の区間集計と違い、起動からの累計（Prometheus の rate() で見る）
+#[derive(Debug)]
+pub struct Metrics {
+    /// PDF系エンドポイントのレスポンス数（endpoint, status）
+    pdf_requests: Mutex<BTreeMap<(String, u16), u64>>,
+    /// PDF（ZIP）の描画時間（DB取得を除く）
+    pdf_render: Mutex<Histogram>,
+    /// DBクエリの時間（クエリの種類別）
+    db_query: Mutex<BTreeMap<&'static str, Histogram>>,
+    /// 描画したタイムカード数（人・月）
+    timecards_rendered: AtomicU64,
+    /// Docker DBへの同期行数（insert / update / unchanged / delete）
+    docker_sync_rows: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            pdf_requests: Mutex::new(BTreeMap::new()),
+            pdf_render: Mutex::new(Histogram::new(RENDER_BUCKETS)),
+            db_query: Mutex::new(BTreeMap::new()),
+            timecards_rendered: AtomicU64::new(0),
+            docker_sync_rows: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Metrics {
+    /// 環境変数 METRICS_ENABLED（デフォルト有効、0/false/off で /metrics ごと無効）
+    pub fn from_env() -> Option<Self> {
+        let enabled = env::var("METRICS_ENABLED")
+            .map(|v| !matches!(v.trim().to_lowercase().as_str(), "0" | "false" | "off"))
+            .unwrap_or(true);
+        enabled.then(Self::default)
+    }
+
+    /// PDF系エンドポイントのレスポンスを記録
+    pub fn record_pdf_request(&self, endpoint: &str, status: u16) {
+        *self.pdf_requests.lock().unwrap().entry((endpoint.to_string(), status)).or_insert(0) += 1;
+    }
+
+    /// PDFの描画を記録（timecards は描画した人数、範囲指定なら全月の合計）
+    pub fn record_render(&self, elapsed: Duration, timecards: usize) {
+        self.pdf_render.lock().unwrap().observe(elapsed.as_secs_f64());
+        self.timecards_rendered.fetch_add(timecards as u64, Ordering::Relaxed);
+    }
+
+    /// DBクエリの時間を記録
+    pub fn record_db_query(&self, query: &'static str, elapsed: Duration) {
+        self.db_query
+            .lock()
+            .unwrap()
+            .entry(query)
+            .or_insert_with(|| Histogram::new(QUERY_BUCKETS))
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// DBクエリ query の計測を始める（戻り値をdropした時点までを記録、失敗も含む）
+    pub fn query_timer(self: &Arc<Self>, query: &'static str) -> QueryTimer {
+        QueryTimer { metrics: self.clone(), query, started: Instant::now() }
+    }
+
+    /// Docker DBへの同期行数を記録
+    pub fn record_sync_rows(&self, kind: &'static str, rows: usize) {
+        *self.docker_sync_rows.lock().unwrap().entry(kind).or_insert(0) += rows as u64;
+    }
+
+    /// Prometheus形式のテキスト
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP pdf_requests_total Responses from PDF endpoints\n");
+        out.push_str("# TYPE pdf_requests_total counter\n");
+        for ((endpoint, status), count) in self.pdf_requests.lock().unwrap().iter() {
+            writeln!(out, "pdf_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}", endpoint, status, count).ok();
+        }
+        out.push_str("# HELP pdf_render_seconds Time spent rendering PDFs (excluding DB fetch)\n");
+        out.push_str("# TYPE pdf_render_seconds histogram\n");
+        self.pdf_render.lock().unwrap().render(&mut out, "pdf_render_seconds", "");
+        out.push_str("# HELP db_query_seconds Time spent in DB queries\n");
+        out.push_str("# TYPE db_query_seconds histogram\n");
+        for (query, histogram) in self.db_query.lock().unwrap().iter() {
+            histogram.render(&mut out, "db_query_seconds", &format!("query=\"{}\",", query));
+        }
+        out.push_str("# TYPE timecards_rendered_total counter\n");
+        writeln!(out, "timecards_rendered_total {}", self.timecards_rendered.load(Ordering::Relaxed)).ok();
+        out.push_str("# HELP docker_sync_rows Rows synced to the Docker DB\n");
+        out.push_str("# TYPE docker_sync_rows counter\n");
+        for (kind, rows) in self.docker_sync_rows.lock().unwrap().iter() {
+            writeln!(out, "docker_sync_rows{{kind=\"{}\"}} {}", kind, rows).ok();
+        }
+        out
+    }
+}
+
+/// DBクエリの時間計測のガード
+pub struct QueryTimer {
+    metrics: Arc<Metrics>,
+    query: &'static str,
+    started: Instant,
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        self.metrics.record_db_query(self.query, self.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counters_and_histograms() {
+        let metrics = Metrics::default();
+        metrics.record_pdf_request("/api/pdf", 200);
+        metrics.record_pdf_request("/api/pdf", 200);
+        metrics.record_render(Duration::from_millis(300), 3);
+        metrics.record_db_query("active_drivers", Duration::from_millis(20));
+        metrics.record_sync_rows("insert", 5);
+        metrics.record_sync_rows("insert", 2);
+
+        let text = metrics.render();
+        assert!(text.contains("pdf_requests_total{endpoint=\"/api/pdf\",status=\"200\"} 2\n"));
+        // バケットは累積（0.3秒は 0.5 以上のバケットに入る）
+        assert!(text.contains("pdf_render_seconds_bucket{le=\"0.25\"} 0\n"));
+        assert!(text.contains("pdf_render_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(text.contains("pdf_render_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(text.contains("pdf_render_seconds_count 1\n"));
+        assert!(text.contains("db_query_seconds_bucket{query=\"active_drivers\",le=\"0.025\"} 1\n"));
+        assert!(text.contains("db_query_seconds_count{query=\"active_drivers\"} 1\n"));
+        assert!(text.contains("timecards_rendered_total 3\n"));
+        assert!(text.contains("docker_sync_rows{kind=\"insert\"} 7\n"));
+    }
+}
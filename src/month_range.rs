@@ -0,0 +1,134 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// 指定できる対象年
+pub const YEAR_RANGE: RangeInclusive<i32> = 2000..=2100;
+
+/// 1回で出力できる月数（給与の締めをまたぐ2〜3か月を想定、誤指定で何年分も取得しないように）
+pub const MAX_MONTHS: usize = 12;
+
+/// 対象月の範囲（from〜to、両端を含む）
+/// 給与が月の途中で締まるため、12月と1月をまとめて1つのPDFにする時などに使う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonthRange {
+    /// 開始月（年, 月）
+    pub from: (i32, u32),
+    /// 終了月（年, 月）
+    pub to: (i32, u32),
+}
+
+impl MonthRange {
+    /// 1か月だけ
+    pub fn single(year: i32, month: u32) -> Self {
+        Self { from: (year, month), to: (year, month) }
+    }
+
+    /// from〜to（from が to より後、または MAX_MONTHS か月を超える場合はエラー）
+    pub fn new(from: (i32, u32), to: (i32, u32)) -> Result<Self, String> {
+        let range = Self { from, to };
+        if from > to {
+            return Err(format!("開始月が終了月より後です: {} 〜 {}", format_month(from), format_month(to)));
+        }
+        if range.month_count() > MAX_MONTHS {
+            return Err(format!("一度に指定できるのは{}か月までです: {}", MAX_MONTHS, range));
+        }
+        Ok(range)
+    }
+
+    /// 月数
+    pub fn month_count(&self) -> usize {
+        (month_index(self.to) - month_index(self.from) + 1) as usize
+    }
+
+    /// 1か月だけか（区切りページ・範囲のファイル名を使わない）
+    pub fn is_single(&self) -> bool {
+        self.from == self.to
+    }
+
+    /// 対象月（古い順）
+    pub fn months(&self) -> Vec<(i32, u32)> {
+        (month_index(self.from)..=month_index(self.to))
+            .map(|index| (index.div_euclid(12), index.rem_euclid(12) as u32 + 1))
+            .collect()
+    }
+
+    /// ファイル名に入れる年月（1か月は "2025_12"、範囲は "2025-12_2026-01"）
+    pub fn file_label(&self) -> String {
+        if self.is_single() {
+            format!("{}_{:02}", self.from.0, self.from.1)
+        } else {
+            format!("{}_{}", format_month(self.from), format_month(self.to))
+        }
+    }
+}
+
+/// 表示用（例: "2025年12月"、"2025年12月〜2026年1月"）
+impl fmt::Display for MonthRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}年{}月", self.from.0, self.from.1)?;
+        if !self.is_single() {
+            write!(f, "〜{}年{}月", self.to.0, self.to.1)?;
+        }
+        Ok(())
+    }
+}
+
+/// 年・月の範囲を確認（年は YEAR_RANGE、月は1〜12）
+pub fn check_year_month(year: &str, month: &str) -> Result<(i32, u32), String> {
+    let year = year
+        .parse::<i32>()
+        .ok()
+        .filter(|y| YEAR_RANGE.contains(y))
+        .ok_or_else(|| format!("年は{}〜{}で指定してください: {}", YEAR_RANGE.start(), YEAR_RANGE.end(), year))?;
+    let month = month
+        .parse::<u32>()
+        .ok()
+        .filter(|m| (1..=12).contains(m))
+        .ok_or_else(|| format!("月は1〜12で指定してください: {}", month))?;
+    Ok((year, month))
+}
+
+/// "YYYY-MM" を解析
+pub fn parse_month(value: &str) -> Result<(i32, u32), String> {
+    let (y, m) = value.split_once('-').ok_or("YYYY-MM 形式で指定してください")?;
+    check_year_month(y, m)
+}
+
+/// "YYYY-MM"
+fn format_month((year, month): (i32, u32)) -> String {
+    format!("{}-{:02}", year, month)
+}
+
+/// 西暦0年1月からの月数
+fn month_index((year, month): (i32, u32)) -> i32 {
+    year * 12 + month as i32 - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_months_across_year_boundary() {
+        let range = MonthRange::new((2025, 11), (2026, 2)).unwrap();
+        assert_eq!(range.months(), vec![(2025, 11), (2025, 12), (2026, 1), (2026, 2)]);
+        assert_eq!(range.month_count(), 4);
+        assert_eq!(range.file_label(), "2025-11_2026-02");
+        assert_eq!(range.to_string(), "2025年11月〜2026年2月");
+
+        let single = MonthRange::single(2025, 12);
+        assert_eq!(single.months(), vec![(2025, 12)]);
+        assert_eq!(single.file_label(), "2025_12");
+        assert_eq!(single.to_string(), "2025年12月");
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        assert!(MonthRange::new((2026, 1), (2025, 12)).is_err());
+        assert!(MonthRange::new((2025, 1), (2025, 12)).is_ok());
+        assert!(MonthRange::new((2025, 1), (2026, 1)).is_err());
+        assert_eq!(parse_month("2026-01"), Ok((2026, 1)));
+        assert!(parse_month("2026-13").is_err());
+        assert!(parse_month("202601").is_err());
+    }
+}
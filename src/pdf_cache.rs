@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::warn;
+
+/// キャッシュの有効期間の既定値（秒）
+const DEFAULT_TTL_SECS: u64 = 600;
+
+/// キャッシュの上限の既定値（MB）
+const DEFAULT_MAX_MB: usize = 256;
+
+/// ディスクキャッシュのファイルの拡張子（clear でこの拡張子のファイルだけ消す）
+const DISK_EXTENSION: &str = "pdfcache";
+
+/// 生成済みPDF（ZIP）のキャッシュ（サーバーで同じ月を何度も開く時に作り直さない）
+/// キーはリクエストの内容（年月・ドライバー・絞り込み・レンダリングオプション）
+///   - PDF_CACHE_TTL_SECS: 有効期間（秒、既定600、0で無効）
+///   - PDF_CACHE_MAX_MB: メモリ上の合計サイズの上限（MB、既定256、超えたら最後に使ってから長いものから捨てる）
+///   - PDF_CACHE_DIR: 指定時はディスクにも保存（再起動後も有効期間内なら使う）
+pub struct PdfCache {
+    ttl: Duration,
+    max_bytes: usize,
+    dir: Option<PathBuf>,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<String, CacheEntry>,
+    total_bytes: usize,
+    /// 使うたびに増やす番号（LRUの順序）
+    tick: u64,
+}
+
+struct CacheEntry {
+    bytes: Arc<Vec<u8>>,
+    created: Instant,
+    last_used: u64,
+}
+
+impl PdfCache {
+    /// ttl が0ならキャッシュしない
+    pub fn new(ttl: Duration, max_bytes: usize, dir: Option<PathBuf>) -> Self {
+        Self { ttl, max_bytes, dir, inner: Mutex::new(CacheInner::default()) }
+    }
+
+    /// 環境変数から作成（PDF_CACHE_TTL_SECS / PDF_CACHE_MAX_MB / PDF_CACHE_DIR）
+    pub fn from_env() -> Self {
+        let ttl = env::var("PDF_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        let max_mb = env::var("PDF_CACHE_MAX_MB")
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_MAX_MB);
+        let dir = env::var("PDF_CACHE_DIR").ok().filter(|s| !s.is_empty()).map(PathBuf::from);
+        Self::new(Duration::from_secs(ttl), max_mb * 1024 * 1024, dir)
+    }
+
+    /// キャッシュが有効か（有効期間・容量が0なら無効）
+    pub fn enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_bytes > 0
+    }
+
+    /// 有効期間内のキャッシュ（メモリになければディスクを見る）
+    pub fn get(&self, key: &str) -> Option<Arc<Vec<u8>>> {
+        if !self.enabled() {
+            return None;
+        }
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.tick += 1;
+            let tick = inner.tick;
+            match inner.entries.get_mut(key) {
+                Some(entry) if entry.created.elapsed() < self.ttl => {
+                    entry.last_used = tick;
+                    return Some(entry.bytes.clone());
+                }
+                Some(_) => inner.remove(key),
+                None => {}
+            }
+        }
+        let bytes = self.read_disk(key)?;
+        self.insert_memory(key, bytes.clone());
+        Some(bytes)
+    }
+
+    /// キャッシュに追加（上限を超えるものは保存しない）
+    pub fn insert(&self, key: &str, bytes: Vec<u8>) {
+        if !self.enabled() || bytes.len() > self.max_bytes {
+            return;
+        }
+        if let Some(path) = self.disk_path(key) {
+            // 一時ファイルに書いてから置き換える（書き込み中に終了しても途中までのファイルを読まない）
+            let tmp = path.with_extension("tmp");
+            let written = fs::create_dir_all(path.parent().unwrap_or(&path))
+                .and_then(|_| fs::write(&tmp, &bytes))
+                .and_then(|_| fs::rename(&tmp, &path));
+            if let Err(e) = written {
+                warn!("PDFキャッシュを保存できません: {}: {}", path.display(), e);
+            }
+        }
+        self.insert_memory(key, Arc::new(bytes));
+    }
+
+    /// 全て削除（メモリ・ディスク）して件数を返す（ディスクにしかないものも数える）
+    pub fn clear(&self) -> usize {
+        let in_memory = std::mem::take(&mut *self.inner.lock().unwrap()).entries.len();
+        let mut on_disk = 0;
+        if let Some(dir) = &self.dir {
+            let files = fs::read_dir(dir).into_iter().flatten().flatten().map(|e| e.path());
+            for path in files.filter(|p| p.extension().is_some_and(|ext| ext == DISK_EXTENSION)) {
+                if fs::remove_file(&path).is_ok() {
+                    on_disk += 1;
+                }
+            }
+        }
+        in_memory.max(on_disk)
+    }
+
+    /// メモリ上の件数と合計サイズ
+    pub fn usage(&self) -> (usize, usize) {
+        let inner = self.inner.lock().unwrap();
+        (inner.entries.len(), inner.total_bytes)
+    }
+
+    fn insert_memory(&self, key: &str, bytes: Arc<Vec<u8>>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.remove(key);
+        // 上限に収まるまで最後に使ってから長いものを捨てる
+        while inner.total_bytes + bytes.len() > self.max_bytes {
+            let Some(oldest) = inner.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else {
+                break;
+            };
+            inner.remove(&oldest);
+        }
+        inner.tick += 1;
+        inner.total_bytes += bytes.len();
+        let entry = CacheEntry { bytes, created: Instant::now(), last_used: inner.tick };
+        inner.entries.insert(key.to_string(), entry);
+    }
+
+    /// ディスクのキャッシュ（有効期間を過ぎていれば削除してNone）
+    fn read_disk(&self, key: &str) -> Option<Arc<Vec<u8>>> {
+        let path = self.disk_path(key)?;
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        if SystemTime::now().duration_since(modified).unwrap_or_default() >= self.ttl {
+            fs::remove_file(&path).ok();
+            return None;
+        }
+        fs::read(&path).ok().map(Arc::new)
+    }
+
+    /// ディスクキャッシュのパス（キーのMD5、PDF_CACHE_DIR 未指定ならNone）
+    fn disk_path(&self, key: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        Some(dir.join(format!("{:x}.{}", md5::compute(key), DISK_EXTENSION)))
+    }
+}
+
+impl CacheInner {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_bytes -= entry.bytes.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction_by_bytes() {
+        let cache = PdfCache::new(Duration::from_secs(60), 10, None);
+        cache.insert("a", vec![0; 4]);
+        cache.insert("b", vec![0; 4]);
+        // a を使ったので、次に追加すると b が捨てられる
+        assert!(cache.get("a").is_some());
+        cache.insert("c", vec![0; 4]);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert_eq!(cache.usage(), (2, 8));
+
+        // 上限より大きいものは保存しない
+        cache.insert("big", vec![0; 11]);
+        assert!(cache.get("big").is_none());
+        assert_eq!(cache.clear(), 2);
+        assert_eq!(cache.usage(), (0, 0));
+    }
+
+    #[test]
+    fn test_ttl_and_disk() {
+        let cache = PdfCache::new(Duration::ZERO, 10, None);
+        cache.insert("a", vec![1]);
+        assert!(cache.get("a").is_none());
+
+        let dir = std::env::temp_dir().join(format!("timecard-pdf-cache-{}", std::process::id()));
+        let cache = PdfCache::new(Duration::from_secs(60), 10, Some(dir.clone()));
+        cache.insert("a", vec![1, 2, 3]);
+        // 再起動後（新しいインスタンス）もディスクから読める
+        let restarted = PdfCache::new(Duration::from_secs(60), 10, Some(dir.clone()));
+        assert_eq!(restarted.get("a").unwrap().as_slice(), &[1, 2, 3]);
+        restarted.clear();
+        assert!(PdfCache::new(Duration::from_secs(60), 10, Some(dir.clone())).get("a").is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,218 @@
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+
+/// パスワードのパディング（PDF仕様 Algorithm 3.2）
+const PAD_BYTES: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+    0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// 鍵の長さ（128bit）
+const KEY_LEN: usize = 16;
+
+/// 利用者パスワードで開いた場合に許可する操作
+/// 印刷・コピー・アクセシビリティ・高品質印刷のみ（編集・注釈・フォーム入力・ページの組み替えは不可）
+const PERMISSIONS: i32 = -1324; // 0xFFFFFAD4
+
+/// PDFの暗号化（標準セキュリティハンドラ、RC4 128bit・リビジョン3）
+///   - user_password: PDFを開くためのパスワード
+///   - owner_password: 権限の制限を解除するパスワード（未指定なら毎回ランダムに生成し、誰も制限を解除できない）
+///
+/// AES-128（V4/AESV2）は対象外（lopdf 0.31 がRC4しか復号できず、往復の検証ができないため）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfEncryption {
+    user_password: String,
+    owner_password: String,
+}
+
+impl PdfEncryption {
+    /// パスワードを検証して作成（1〜32文字のASCII、仕様上それより長い部分は使われない）
+    /// owner_password を省略した場合に user_password と同じにすると、開ける人が誰でも制限を解除できてしまう
+    pub fn new(user_password: &str, owner_password: Option<&str>) -> Result<Self, String> {
+        let owner_password = match owner_password {
+            Some(password) => password.to_string(),
+            None => random_password()?,
+        };
+        for password in [user_password, owner_password.as_str()] {
+            if password.is_empty() || password.len() > 32 || !password.chars().all(|c| c.is_ascii_graphic()) {
+                return Err("パスワードは1〜32文字の半角英数字・記号で指定してください".to_string());
+            }
+        }
+        Ok(Self {
+            user_password: user_password.to_string(),
+            owner_password,
+        })
+    }
+
+    /// 全オブジェクトの文字列・ストリームを暗号化し、トレーラーに /Encrypt を追加する
+    /// 保存直前（決定的出力のID生成より後）に呼ぶこと
+    pub fn encrypt(&self, doc: &mut Document) {
+        let id = file_id(doc);
+        let owner = owner_value(self.owner_password.as_bytes(), self.user_password.as_bytes());
+        let key = encryption_key(self.user_password.as_bytes(), &owner, PERMISSIONS, &id);
+        let user = user_value(&key, &id);
+
+        for (&object_id, object) in doc.objects.iter_mut() {
+            encrypt_object(object, &object_key(&key, object_id));
+        }
+
+        let mut dict = Dictionary::new();
+        dict.set("Filter", Object::Name(b"Standard".to_vec()));
+        dict.set("V", Object::Integer(2));
+        dict.set("R", Object::Integer(3));
+        dict.set("Length", Object::Integer((KEY_LEN * 8) as i64));
+        dict.set("O", Object::String(owner, StringFormat::Hexadecimal));
+        dict.set("U", Object::String(user, StringFormat::Hexadecimal));
+        dict.set("P", Object::Integer(PERMISSIONS as i64));
+        let encrypt_id = doc.add_object(Object::Dictionary(dict));
+        doc.trailer.set("Encrypt", Object::Reference(encrypt_id));
+    }
+}
+
+/// ランダムな所有者パスワード（OSの乱数16バイトを32桁の16進に）
+fn random_password() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("所有者パスワードを生成できません: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// トレーラーのIDの1つ目（なければ内容から作って設定）
+fn file_id(doc: &mut Document) -> Vec<u8> {
+    let existing = doc.trailer.get(b"ID").and_then(|o| o.as_array()).ok().and_then(|ids| ids.first()).and_then(|id| id.as_str().ok());
+    if let Some(id) = existing {
+        return id.to_vec();
+    }
+    let id = md5::compute(format!("{:?}", doc.objects.keys().collect::<Vec<_>>())).to_vec();
+    doc.trailer.set(
+        "ID",
+        Object::Array(vec![
+            Object::String(id.clone(), StringFormat::Hexadecimal),
+            Object::String(id.clone(), StringFormat::Hexadecimal),
+        ]),
+    );
+    id
+}
+
+/// パスワードを32バイトにパディング
+fn padded(password: &[u8]) -> Vec<u8> {
+    let len = password.len().min(32);
+    [&password[..len], &PAD_BYTES[..32 - len]].concat()
+}
+
+/// /O の値（Algorithm 3.3）
+fn owner_value(owner_password: &[u8], user_password: &[u8]) -> Vec<u8> {
+    let mut hash = md5::compute(padded(owner_password)).to_vec();
+    for _ in 0..50 {
+        hash = md5::compute(&hash).to_vec();
+    }
+    let key = &hash[..KEY_LEN];
+    let mut value = rc4(key, &padded(user_password));
+    for i in 1..=19u8 {
+        let round_key: Vec<u8> = key.iter().map(|b| b ^ i).collect();
+        value = rc4(&round_key, &value);
+    }
+    value
+}
+
+/// 文書の暗号化鍵（Algorithm 3.2）
+fn encryption_key(user_password: &[u8], owner: &[u8], permissions: i32, id: &[u8]) -> Vec<u8> {
+    let input = [&padded(user_password), owner, &permissions.to_le_bytes(), id].concat();
+    let mut key = md5::compute(input).to_vec();
+    for _ in 0..50 {
+        key = md5::compute(&key[..KEY_LEN]).to_vec();
+    }
+    key.truncate(KEY_LEN);
+    key
+}
+
+/// /U の値（Algorithm 3.5、後半16バイトは任意）
+fn user_value(key: &[u8], id: &[u8]) -> Vec<u8> {
+    let hash = md5::compute([&PAD_BYTES[..], id].concat());
+    let mut value = rc4(key, &hash[..]);
+    for i in 1..=19u8 {
+        let round_key: Vec<u8> = key.iter().map(|b| b ^ i).collect();
+        value = rc4(&round_key, &value);
+    }
+    value.extend_from_slice(&PAD_BYTES[..16]);
+    value
+}
+
+/// オブジェクトごとの鍵（Algorithm 3.1）
+fn object_key(key: &[u8], (number, generation): ObjectId) -> Vec<u8> {
+    let input = [key, &number.to_le_bytes()[..3], &generation.to_le_bytes()[..2]].concat();
+    md5::compute(input)[..(key.len() + 5).min(16)].to_vec()
+}
+
+/// 文字列とストリームの中身を暗号化（辞書・配列の中の文字列も含む）
+fn encrypt_object(object: &mut Object, key: &[u8]) {
+    match object {
+        Object::String(bytes, _) => *bytes = rc4(key, bytes),
+        Object::Array(items) => items.iter_mut().for_each(|item| encrypt_object(item, key)),
+        Object::Dictionary(dict) => dict.iter_mut().for_each(|(_, value)| encrypt_object(value, key)),
+        Object::Stream(stream) => {
+            stream.dict.iter_mut().for_each(|(_, value)| encrypt_object(value, key));
+            let content = rc4(key, &stream.content);
+            stream.set_content(content);
+        }
+        _ => {}
+    }
+}
+
+/// RC4（暗号化・復号は同じ処理）
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rc4_known_vector() {
+        assert_eq!(rc4(b"Key", b"Plaintext"), [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]);
+    }
+
+    #[test]
+    fn test_owner_password_recovers_user_password() {
+        // Algorithm 3.7: 所有者パスワードの鍵で /O を逆順に復号すると利用者パスワード（パディング済み）になる
+        let owner = owner_value(b"owner", b"user");
+        let mut hash = md5::compute(padded(b"owner")).to_vec();
+        for _ in 0..50 {
+            hash = md5::compute(&hash).to_vec();
+        }
+        let mut value = owner;
+        for i in (0..=19u8).rev() {
+            let round_key: Vec<u8> = hash[..KEY_LEN].iter().map(|b| b ^ i).collect();
+            value = rc4(&round_key, &value);
+        }
+        assert_eq!(value, padded(b"user"));
+    }
+
+    #[test]
+    fn test_password_validation() {
+        assert!(PdfEncryption::new("secret", None).is_ok());
+        assert!(PdfEncryption::new("", None).is_err());
+        assert!(PdfEncryption::new("パスワード", None).is_err());
+        assert!(PdfEncryption::new("secret", Some("has space")).is_err());
+        assert_eq!(PdfEncryption::new("a", Some("b")).unwrap().owner_password, "b");
+
+        // 所有者パスワードを省略すると利用者パスワードとは別のランダムな値（毎回変わる）
+        let generated = PdfEncryption::new("a", None).unwrap().owner_password;
+        assert_eq!(generated.len(), 32);
+        assert_ne!(generated, "a");
+        assert_ne!(generated, PdfEncryption::new("a", None).unwrap().owner_password);
+    }
+}
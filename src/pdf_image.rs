@@ -0,0 +1,245 @@
+use flate2::read::ZlibDecoder;
+use printpdf::{ColorBits, ColorSpace, ImageFilter, ImageXObject, Px};
+use std::io::Read;
+
+/// 画像ファイル（JPEG・PNG）をPDFの画像XObjectにする
+///   - JPEG: 中身はそのまま（DCTDecode）、SOFからサイズと色数だけ読む
+///   - PNG: 展開してRGB/グレーの生データにする（8bitのみ、インターレース非対応）
+///     透過（アルファ）は白い紙に重ねた色にする
+pub fn load_image(bytes: &[u8]) -> Result<ImageXObject, String> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        load_jpeg(bytes)
+    } else if bytes.starts_with(PNG_SIGNATURE) {
+        load_png(bytes)
+    } else {
+        Err("JPEG・PNG以外の画像です".to_string())
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn load_jpeg(bytes: &[u8]) -> Result<ImageXObject, String> {
+    let (width, height, components) = jpeg_size(bytes).ok_or("JPEGのサイズを読み取れません")?;
+    let color_space = match components {
+        1 => ColorSpace::Greyscale,
+        3 => ColorSpace::Rgb,
+        4 => ColorSpace::Cmyk,
+        n => return Err(format!("JPEGの色数（{}）は未対応です", n)),
+    };
+    Ok(image_object(width, height, color_space, bytes.to_vec(), Some(ImageFilter::DCT)))
+}
+
+/// JPEGのSOFマーカーから（幅, 高さ, 色数）を読む
+fn jpeg_size(bytes: &[u8]) -> Option<(usize, usize, u8)> {
+    let mut pos = 2;
+    loop {
+        // マーカーの前の埋め草（0xFF の連続）を飛ばす
+        while *bytes.get(pos)? == 0xFF && *bytes.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        let length = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+        // SOF0〜SOF15（DHT=C4・JPG=C8・DAC=CC を除く）
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let segment = bytes.get(pos + 4..pos + 2 + length)?;
+            let height = u16::from_be_bytes([segment[1], segment[2]]) as usize;
+            let width = u16::from_be_bytes([segment[3], segment[4]]) as usize;
+            return Some((width, height, segment[5]));
+        }
+        if marker == 0xD9 || marker == 0xDA {
+            return None;
+        }
+        pos += 2 + length;
+    }
+}
+
+fn load_png(bytes: &[u8]) -> Result<ImageXObject, String> {
+    let mut pos = PNG_SIGNATURE.len();
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut compressed = Vec::new();
+    while pos + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let data = bytes.get(pos + 8..pos + 8 + length).ok_or("PNGのチャンクが途中で切れています")?;
+        match kind {
+            b"IHDR" if length >= 13 => header = Some(data.to_vec()),
+            b"PLTE" => palette = data.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        pos += 12 + length; // 長さ・種類・CRC
+    }
+    let header = header.ok_or("PNGのIHDRがありません")?;
+
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+    let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if bit_depth != 8 {
+        return Err(format!("{}bitのPNGは未対応です（8bitのみ）", bit_depth));
+    }
+    if interlace != 0 {
+        return Err("インターレースPNGは未対応です".to_string());
+    }
+    let channels = match color_type {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        t => return Err(format!("PNGの色の種類（{}）が不正です", t)),
+    };
+
+    let mut filtered = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .read_to_end(&mut filtered)
+        .map_err(|e| format!("PNGを展開できません: {}", e))?;
+    let pixels = unfilter(&filtered, width, height, channels)?;
+
+    let (color_space, data) = match color_type {
+        0 => (ColorSpace::Greyscale, pixels),
+        2 => (ColorSpace::Rgb, pixels),
+        3 => {
+            let mut rgb = Vec::with_capacity(pixels.len() * 3);
+            for &index in &pixels {
+                let color = palette.get(index as usize * 3..index as usize * 3 + 3).ok_or("PNGのパレットにない色です")?;
+                rgb.extend_from_slice(color);
+            }
+            (ColorSpace::Rgb, rgb)
+        }
+        4 => (ColorSpace::Greyscale, pixels.chunks(2).map(|p| on_white(p[0], p[1])).collect()),
+        _ => (ColorSpace::Rgb, pixels.chunks(4).flat_map(|p| [on_white(p[0], p[3]), on_white(p[1], p[3]), on_white(p[2], p[3])]).collect()),
+    };
+    Ok(image_object(width, height, color_space, data, None))
+}
+
+/// PNGのフィルター（行ごとの先頭1バイト）を戻して画素データにする
+fn unfilter(filtered: &[u8], width: usize, height: usize, channels: usize) -> Result<Vec<u8>, String> {
+    let stride = width * channels;
+    if filtered.len() < (stride + 1) * height {
+        return Err("PNGの画素データが足りません".to_string());
+    }
+    let mut pixels = vec![0u8; stride * height];
+    for row in 0..height {
+        let line = &filtered[row * (stride + 1)..(row + 1) * (stride + 1)];
+        let (done, rest) = pixels.split_at_mut(row * stride);
+        let previous = if row > 0 { &done[(row - 1) * stride..] } else { &[][..] };
+        let current = &mut rest[..stride];
+        for i in 0..stride {
+            let a = if i >= channels { current[i - channels] } else { 0 };
+            let b = previous.get(i).copied().unwrap_or(0);
+            let c = if i >= channels { previous.get(i - channels).copied().unwrap_or(0) } else { 0 };
+            let predicted = match line[0] {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                f => return Err(format!("PNGのフィルター（{}）が不正です", f)),
+            };
+            current[i] = line[i + 1].wrapping_add(predicted);
+        }
+    }
+    Ok(pixels)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// 不透明度 alpha の色を白地に重ねた色
+fn on_white(value: u8, alpha: u8) -> u8 {
+    ((value as u16 * alpha as u16 + 255 * (255 - alpha as u16)) / 255) as u8
+}
+
+fn image_object(width: usize, height: usize, color_space: ColorSpace, image_data: Vec<u8>, image_filter: Option<ImageFilter>) -> ImageXObject {
+    ImageXObject {
+        width: Px(width),
+        height: Px(height),
+        color_space,
+        bits_per_component: ColorBits::Bit8,
+        interpolate: true,
+        image_data,
+        image_filter,
+        smask: None,
+        clipping_bbox: None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// テスト用のPNG（行ごとにフィルターを変える）
+    pub(crate) fn png(width: u32, height: u32, color_type: u8, rows: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let chunk = |out: &mut Vec<u8>, kind: &[u8], data: &[u8]| {
+            out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            out.extend_from_slice(kind);
+            out.extend_from_slice(data);
+            out.extend_from_slice(&crc32fast::hash(&[kind, data].concat()).to_be_bytes());
+        };
+        let mut header = [width.to_be_bytes(), height.to_be_bytes()].concat();
+        header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        for (filter, row) in rows {
+            encoder.write_all(&[*filter]).unwrap();
+            encoder.write_all(row).unwrap();
+        }
+        let mut out = PNG_SIGNATURE.to_vec();
+        chunk(&mut out, b"IHDR", &header);
+        chunk(&mut out, b"IDAT", &encoder.finish().unwrap());
+        chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    /// テスト用のJPEG（SOF0までのヘッダーのみ）
+    pub(crate) fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut out = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 8];
+        out.extend_from_slice(&height.to_be_bytes());
+        out.extend_from_slice(&width.to_be_bytes());
+        out.push(3);
+        out.extend_from_slice(&[1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+        out.extend_from_slice(&[0xFF, 0xD9]);
+        out
+    }
+
+    #[test]
+    fn test_jpeg_header() {
+        let image = load_image(&jpeg(640, 480)).unwrap();
+        assert_eq!((image.width.0, image.height.0), (640, 480));
+        assert!(matches!(image.color_space, ColorSpace::Rgb));
+        assert!(matches!(image.image_filter, Some(ImageFilter::DCT)));
+    }
+
+    #[test]
+    fn test_png_filters() {
+        // 2×3 のRGB: None・Sub・Up で同じ画素になる
+        let pixels = vec![10, 20, 30, 40, 50, 60];
+        let sub = vec![10, 20, 30, 30, 30, 30];
+        let up = vec![0; 6];
+        let image = load_image(&png(2, 3, 2, &[(0, pixels.clone()), (1, sub), (2, up)])).unwrap();
+        assert_eq!(image.image_data, [pixels.clone(), pixels.clone(), pixels].concat());
+    }
+
+    #[test]
+    fn test_png_alpha_on_white() {
+        let image = load_image(&png(2, 1, 6, &[(0, vec![0, 0, 0, 255, 0, 0, 0, 0])])).unwrap();
+        assert!(matches!(image.color_space, ColorSpace::Rgb));
+        assert_eq!(image.image_data, [0, 0, 0, 255, 255, 255]);
+        assert!(load_image(b"GIF89a").is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 終わったジョブを残す時間の既定値（秒）
+const DEFAULT_TTL_SECS: u64 = 3600;
+
+/// ジョブの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    /// 実行枠の空き待ち
+    Pending,
+    /// 生成中
+    Running,
+    /// 完了（出力をダウンロードできる）
+    Done,
+    /// 失敗
+    Failed,
+}
+
+impl JobState {
+    /// 状態確認のレスポンスに使う名前
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// ジョブの進み具合（処理済み/対象ドライバー数、範囲指定では全月の合計）
+#[derive(Debug, Default)]
+pub struct JobProgress {
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl JobProgress {
+    /// 1か月分の対象ドライバー数を加える
+    pub fn add_total(&self, drivers: usize) {
+        self.total.fetch_add(drivers, Ordering::Relaxed);
+    }
+
+    /// 1人分の処理が終わった
+    pub fn inc(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// (処理済み, 対象)
+    pub fn get(&self) -> (usize, usize) {
+        (self.done.load(Ordering::Relaxed), self.total.load(Ordering::Relaxed))
+    }
+}
+
+/// 完了したジョブの出力
+#[derive(Debug)]
+pub struct JobOutput {
+    /// PDF・ZIPの中身
+    pub bytes: Vec<u8>,
+    /// ダウンロード時のファイル名
+    pub filename: String,
+    /// Content-Type
+    pub content_type: &'static str,
+}
+
+/// ジョブのある時点の状態（状態確認・ダウンロード用）
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    /// ジョブの状態
+    pub state: JobState,
+    /// 処理済み・対象ドライバー数
+    pub progress: (usize, usize),
+    /// 失敗時のエラー
+    pub error: Option<String>,
+    /// 完了時の出力
+    pub output: Option<Arc<JobOutput>>,
+}
+
+struct Job {
+    state: JobState,
+    progress: Arc<JobProgress>,
+    error: Option<String>,
+    output: Option<Arc<JobOutput>>,
+    /// 終わった時刻（ここから ttl を過ぎたら消す）
+    finished: Option<Instant>,
+}
+
+/// PDF生成ジョブ（全月分のPDFはプロキシのタイムアウトを超えるため、バックグラウンドで作って後から取りに来る）
+/// サーバーのメモリ上だけに持ち、終わったジョブは PDF_JOB_TTL_SECS（既定3600秒）で消す
+pub struct JobStore {
+    ttl: Duration,
+    jobs: Mutex<HashMap<String, Job>>,
+    counter: AtomicU64,
+}
+
+impl JobStore {
+    /// 終わったジョブを ttl で消すストア
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, jobs: Mutex::new(HashMap::new()), counter: AtomicU64::new(0) }
+    }
+
+    /// 環境変数 PDF_JOB_TTL_SECS から作成
+    pub fn from_env() -> Self {
+        let ttl = env::var("PDF_JOB_TTL_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        Self::new(Duration::from_secs(ttl))
+    }
+
+    /// ジョブを登録（Pending）して ID と進捗を返す（期限切れのジョブはここで消す）
+    pub fn create(&self) -> (String, Arc<JobProgress>) {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let id = format!("{:x}", md5::compute(format!("{}-{}-{}", std::process::id(), nanos, n)));
+        let progress = Arc::new(JobProgress::default());
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, job| !self.expired(job));
+        jobs.insert(id.clone(), Job { state: JobState::Pending, progress: progress.clone(), error: None, output: None, finished: None });
+        (id, progress)
+    }
+
+    /// 実行枠を取れて処理を始めた
+    pub fn start(&self, id: &str) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(id) {
+            job.state = JobState::Running;
+        }
+    }
+
+    /// 処理が終わった（成功なら Done、失敗なら Failed でエラーを残す）
+    pub fn finish(&self, id: &str, result: Result<JobOutput, String>) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(id) {
+            match result {
+                Ok(output) => {
+                    job.state = JobState::Done;
+                    job.output = Some(Arc::new(output));
+                }
+                Err(e) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(e);
+                }
+            }
+            job.finished = Some(Instant::now());
+        }
+    }
+
+    /// ジョブの状態（存在しない・期限切れならNone）
+    pub fn get(&self, id: &str) -> Option<JobInfo> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.get(id).is_some_and(|job| self.expired(job)) {
+            jobs.remove(id);
+        }
+        jobs.get(id).map(|job| JobInfo {
+            state: job.state,
+            progress: job.progress.get(),
+            error: job.error.clone(),
+            output: job.output.clone(),
+        })
+    }
+
+    fn expired(&self, job: &Job) -> bool {
+        job.finished.is_some_and(|at| at.elapsed() >= self.ttl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_lifecycle_and_expiry() {
+        let store = JobStore::new(Duration::from_secs(60));
+        let (id, progress) = store.create();
+        let (other, _) = store.create();
+        assert_ne!(id, other);
+        assert_eq!(store.get(&id).unwrap().state, JobState::Pending);
+
+        store.start(&id);
+        progress.add_total(3);
+        progress.inc();
+        let info = store.get(&id).unwrap();
+        assert_eq!((info.state, info.progress), (JobState::Running, (1, 3)));
+
+        store.finish(&id, Ok(JobOutput { bytes: b"%PDF".to_vec(), filename: "a.pdf".to_string(), content_type: "application/pdf" }));
+        assert_eq!(store.get(&id).unwrap().output.unwrap().bytes, b"%PDF");
+        store.finish(&other, Err("DB connection failed".to_string()));
+        let info = store.get(&other).unwrap();
+        assert_eq!((info.state, info.error.as_deref()), (JobState::Failed, Some("DB connection failed")));
+        assert!(store.get("unknown").is_none());
+
+        // 終わったジョブは ttl を過ぎたら消える（実行中は消さない）
+        let store = JobStore::new(Duration::ZERO);
+        let (done, _) = store.create();
+        let (running, _) = store.create();
+        store.start(&running);
+        store.finish(&done, Err("x".to_string()));
+        assert!(store.get(&done).is_none());
+        assert_eq!(store.get(&running).unwrap().state, JobState::Running);
+    }
+}
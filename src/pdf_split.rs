@@ -0,0 +1,92 @@
+use crate::render_options::RenderOptions;
+use crate::tcpdf_compat::{SaveOptions, TcpdfCompat};
+use crate::timecard_data::MonthlyTimecard;
+use tracing::info_span;
+
+/// ドライバーごとのPDFのファイル名（timecard_YYYY_MM_<driver_id>_<氏名>.pdf）
+pub fn split_filename(timecard: &MonthlyTimecard) -> String {
+    format!(
+        "timecard_{}_{:02}_{}_{}.pdf",
+        timecard.year,
+        timecard.month,
+        timecard.driver.id,
+        sanitize_filename(&timecard.driver.name)
+    )
+}
+
+/// ファイル名に使えない文字（パス区切り・空白・制御文字など）を "_" に置き換える
+fn sanitize_filename(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_whitespace() || c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // "." や ".." だけの名前は親ディレクトリを指すので使わない
+    if sanitized.chars().all(|c| c == '.') {
+        return "_".repeat(sanitized.len().max(1));
+    }
+    sanitized
+}
+
+/// 1人1ファイルでPDFを生成し、（ファイル名, PDF）の一覧を返す
+/// フォントファイルは最初の1回だけ読み込み、以降は読み込み済みのバイト列を使う
+pub fn render_per_driver(
+    timecards: &[MonthlyTimecard],
+    options: &RenderOptions,
+    save_options: &SaveOptions,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    timecards
+        .iter()
+        .map(|timecard| {
+            let _span = info_span!("render_per_driver", driver_id = timecard.driver.id, year = timecard.year, month = timecard.month).entered();
+            let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+            pdf.set_save_options(save_options.clone());
+            pdf.render_timecards(std::slice::from_ref(timecard), options)?;
+            let bytes = pdf
+                .save_to_bytes()
+                .map_err(|e| format!("{}: {}", timecard.driver.name, e))?;
+            Ok((split_filename(timecard), bytes))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timecard_data::{DayRecord, Driver, TimecardSummary};
+
+    fn timecard(id: i32, name: &str) -> MonthlyTimecard {
+        MonthlyTimecard {
+            driver: Driver { id, name: name.to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 12,
+            days: (1..=31).map(|d| DayRecord::new(d, "月")).collect(),
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        }
+    }
+
+    #[test]
+    fn test_render_per_driver() {
+        let files = render_per_driver(&[timecard(7, "山田 太郎"), timecard(12, "佐藤/花子")], &RenderOptions::default(), &SaveOptions::default()).unwrap();
+        let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["timecard_2025_12_7_山田_太郎.pdf", "timecard_2025_12_12_佐藤_花子.pdf"]);
+        for (_, bytes) in &files {
+            assert_eq!(lopdf::Document::load_mem(bytes).unwrap().get_pages().len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("山田 太郎"), "山田_太郎");
+        assert_eq!(sanitize_filename("山田\u{3000}太郎"), "山田_太郎");
+        assert_eq!(sanitize_filename("a/b\\c"), "a_b_c");
+        assert_eq!(sanitize_filename(".."), "__");
+        assert_eq!(sanitize_filename(""), "_");
+    }
+}
@@ -0,0 +1,187 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// 端末でないとき（ログに出す時など）に1行出力する間隔（人数）
+const PLAIN_LINE_EVERY: usize = 10;
+
+/// バーの幅（文字数）
+const BAR_WIDTH: usize = 30;
+
+/// ドライバーごとの処理の進捗表示（処理済み/全体・処理中のドライバー・経過時間・残り時間）
+///   - 標準出力が端末: 同じ行を書き換えるバー
+///   - それ以外: PLAIN_LINE_EVERY 人ごとと最後に1行ずつログに出す
+///   - --quiet: 何も出さない
+pub struct Progress {
+    label: String,
+    total: usize,
+    done: usize,
+    current: String,
+    started: Instant,
+    tty: bool,
+    quiet: bool,
+}
+
+impl Progress {
+    pub fn new(label: &str, total: usize) -> Self {
+        Self {
+            label: label.to_string(),
+            total,
+            done: 0,
+            current: String::new(),
+            started: Instant::now(),
+            tty: io::stdout().is_terminal(),
+            quiet: crate::logging::is_quiet(),
+        }
+    }
+
+    /// 1人分の処理が終わった（current は処理したドライバーの名前）
+    pub fn inc(&mut self, current: &str) {
+        self.done += 1;
+        self.current = current.to_string();
+        if self.quiet {
+            return;
+        }
+        if self.tty {
+            self.draw();
+        } else if self.done.is_multiple_of(PLAIN_LINE_EVERY) || self.done == self.total {
+            info!("{}", self.line());
+        }
+    }
+
+    /// バーを消してから警告を出す（警告・エラーがバーと混ざらないように）
+    pub fn warn(&self, message: &str) {
+        self.around_bar(|| warn!("{}", message));
+    }
+
+    /// バーを消してからエラーを出す
+    pub fn error(&self, message: &str) {
+        self.around_bar(|| error!("{}", message));
+    }
+
+    /// バーを終えて改行する
+    pub fn finish(&self) {
+        if self.bar_drawn() {
+            println!();
+        }
+    }
+
+    fn around_bar(&self, log: impl FnOnce()) {
+        if self.bar_drawn() {
+            print!("\r\x1b[K");
+            io::stdout().flush().ok();
+        }
+        log();
+        if self.bar_drawn() {
+            self.draw();
+        }
+    }
+
+    fn bar_drawn(&self) -> bool {
+        self.tty && !self.quiet && self.done > 0
+    }
+
+    fn draw(&self) {
+        print!("\r\x1b[K{}", self.line());
+        io::stdout().flush().ok();
+    }
+
+    fn line(&self) -> String {
+        progress_line(&self.label, self.done, self.total, &self.current, self.started.elapsed(), self.tty)
+    }
+}
+
+/// 進捗の1行（例: "データ取得 [#####-----] 50/100 テスト 太郎 経過 0:12 残り 0:12"）
+fn progress_line(label: &str, done: usize, total: usize, current: &str, elapsed: Duration, bar: bool) -> String {
+    let eta = if done > 0 && total > done {
+        elapsed.mul_f64((total - done) as f64 / done as f64)
+    } else {
+        Duration::ZERO
+    };
+    let bar = if bar {
+        let filled = (BAR_WIDTH * done.min(total)).checked_div(total).unwrap_or(BAR_WIDTH);
+        format!(" [{}{}]", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled))
+    } else {
+        String::new()
+    };
+    format!(
+        "{}{} {}/{} {} 経過 {} 残り {}",
+        label,
+        bar,
+        done,
+        total,
+        current,
+        clock(elapsed),
+        clock(eta)
+    )
+}
+
+/// 経過時間を m:ss で
+fn clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// 処理の段階ごとの所要時間（最後にまとめて表示して、どこに時間がかかっているか見る）
+pub struct PhaseTimings {
+    phases: Vec<(String, Duration)>,
+    last: Instant,
+}
+
+impl PhaseTimings {
+    pub fn start() -> Self {
+        Self { phases: Vec::new(), last: Instant::now() }
+    }
+
+    /// 前回の lap（最初は start）からの時間を name の段階として記録
+    pub fn lap(&mut self, name: &str) {
+        let now = Instant::now();
+        self.phases.push((name.to_string(), now - self.last));
+        self.last = now;
+    }
+
+    /// 段階ごとの時間と合計
+    pub fn report(&self) -> String {
+        let total: Duration = self.phases.iter().map(|(_, d)| *d).sum();
+        let rows: Vec<(&str, Duration)> = self.phases.iter().map(|(name, d)| (name.as_str(), *d)).chain([("合計", total)]).collect();
+        let width = rows.iter().map(|(name, _)| display_width(name)).max().unwrap_or(0);
+        let mut out = String::from("処理時間:\n");
+        for (name, duration) in rows {
+            let pad = " ".repeat(width - display_width(name));
+            out.push_str(&format!("  {}{} {:>8.2}s\n", name, pad, duration.as_secs_f64()));
+        }
+        out
+    }
+}
+
+/// 端末での表示幅（全角は2文字分）
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_line() {
+        let line = progress_line("データ取得", 25, 100, "テスト 太郎", Duration::from_secs(30), true);
+        assert_eq!(line, format!("データ取得 [{}{}] 25/100 テスト 太郎 経過 0:30 残り 1:30", "#".repeat(7), "-".repeat(23)));
+        // 端末でない時はバーなし
+        assert_eq!(progress_line("INSERT", 10, 10, "a", Duration::from_secs(65), false), "INSERT 10/10 a 経過 1:05 残り 0:00");
+    }
+
+    #[test]
+    fn test_phase_timings_report() {
+        let mut timings = PhaseTimings::start();
+        timings.lap("ドライバー取得");
+        timings.lap("保存");
+        let report = timings.report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("  ドライバー取得 "));
+        // 全角は2文字分として揃える
+        assert!(lines[2].starts_with(&format!("  保存{} ", " ".repeat(10))));
+        assert!(lines[3].starts_with("  合計"));
+    }
+}
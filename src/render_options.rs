@@ -0,0 +1,446 @@
+use serde::Deserialize;
+use std::env;
+
+use crate::timecard_data::{PunchSymbols, RemarkSymbols};
+
+/// 拘束時間セルをグレーにする既定の閾値（14時間）
+const DEFAULT_KOSOKU_HIGHLIGHT_MINUTES: i32 = 840;
+
+/// 1ページの人数の上限（表の幅93mmがA4横に3人分まで）
+const MAX_DRIVERS_PER_PAGE: usize = 3;
+
+/// 透かし（「控え」など、各ページ中央に斜めの半透明グレーで描画）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watermark {
+    /// 透かしの文字
+    pub text: String,
+    /// 文字サイズ（pt）
+    pub font_size: f32,
+    /// 不透明度（0より大きく1以下）
+    pub opacity: f32,
+    /// 角度（度、反時計回り）
+    pub angle: f32,
+}
+
+impl Watermark {
+    fn new(text: String) -> Self {
+        Self { text, font_size: 100.0, opacity: 0.15, angle: 35.0 }
+    }
+}
+
+/// PDFの文書情報（Info辞書のTitle/Author/Subject/Keywords、Noneは書き込まない）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PdfMetadata {
+    /// タイトル
+    pub title: Option<String>,
+    /// 作成者
+    pub author: Option<String>,
+    /// サブタイトル
+    pub subject: Option<String>,
+    /// カンマ区切り
+    pub keywords: Option<String>,
+}
+
+impl PdfMetadata {
+    /// 未指定の項目を other で補う
+    pub fn or(&self, other: &Self) -> Self {
+        Self {
+            title: self.title.clone().or_else(|| other.title.clone()),
+            author: self.author.clone().or_else(|| other.author.clone()),
+            subject: self.subject.clone().or_else(|| other.subject.clone()),
+            keywords: self.keywords.clone().or_else(|| other.keywords.clone()),
+        }
+    }
+
+    /// 全項目が未指定か
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// レンダリングオプションの指定（環境変数・CLI・APIリクエストの各層）
+///
+/// 未指定（None）の項目は下の層の値を使う。優先順位は リクエスト > CLI > 環境変数 > デフォルト
+/// 未知のキーはエラーにする（タイプミスを黙って無視しない）
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderOverrides {
+    /// 出退勤時刻の囲み記号（"off" または "[]<>" 形式）
+    pub punch_symbols: Option<String>,
+    /// 集計PDFの備考記号の上書き（"積置泊休=積,指休=指"）
+    pub remark_symbols: Option<String>,
+    /// 残業セルのリンク先（{id} を旅費行IDに置換、空文字でリンクなし）
+    pub zangyo_link_template: Option<String>,
+    /// 拘束時間セルをグレーにする閾値（分、0でグレーにしない）
+    pub kosoku_highlight_minutes: Option<i32>,
+    /// ページ番号・作成日時のフッター（falseでPHPと同じ出力）
+    pub footer: Option<bool>,
+    /// 1ページに並べる人数（1〜3）
+    pub drivers_per_page: Option<usize>,
+    /// 上マージン（mm）
+    pub margin_top: Option<f64>,
+    /// 各人の枠の左端から表までの余白（mm）
+    pub margin_left: Option<f64>,
+    /// 拘束時間の列を表示する
+    pub show_kosoku_column: Option<bool>,
+    /// 日別の表の文字サイズ（pt、拘束時間の列はその8割）
+    pub font_size: Option<f32>,
+    /// 氏名の文字サイズ（pt）
+    pub header_font_size: Option<f32>,
+    /// 氏名のリンク先の前に付けるURL（例: https://example.local、未指定なら相対パス）
+    pub link_base: Option<String>,
+    /// デジタコのある日の日付・曜日セルにもWebのタイムカードへのリンクを付ける
+    pub digitacho_links: Option<bool>,
+    /// 集計PDFの先頭に目次ページ（氏名から各人のページへ移動）を付ける
+    pub shukei_index: Option<bool>,
+    /// 在籍していない日（入社前・退職後）の行をグレーにする（falseでPHPと同じ出力）
+    pub shade_not_employed: Option<bool>,
+    /// PDFのタイトル（未指定なら「タイムカード YYYY年MM月」）
+    pub title: Option<String>,
+    /// PDFの作成者
+    pub author: Option<String>,
+    /// PDFのサブタイトル
+    pub subject: Option<String>,
+    /// PDFのキーワード（カンマ区切り）
+    pub keywords: Option<String>,
+    /// 透かしの文字（例: 控え、空文字で透かしなし）
+    pub watermark: Option<String>,
+    /// 透かしの文字サイズ（pt）
+    pub watermark_font_size: Option<f32>,
+    /// 透かしの不透明度（0〜1）
+    pub watermark_opacity: Option<f32>,
+    /// 透かしの角度（度）
+    pub watermark_angle: Option<f32>,
+}
+
+impl RenderOverrides {
+    /// 環境変数から読み込み
+    pub fn from_env() -> Self {
+        Self {
+            punch_symbols: env::var("PUNCH_SYMBOLS").ok(),
+            remark_symbols: env::var("SHUKEI_REMARK_SYMBOLS").ok(),
+            zangyo_link_template: env::var("ZANGYO_LINK_TEMPLATE").ok(),
+            kosoku_highlight_minutes: env::var("KOSOKU_HIGHLIGHT_MINUTES").ok().and_then(|v| v.parse().ok()),
+            footer: env::var("PDF_FOOTER").ok().and_then(|v| match v.trim() {
+                "1" | "true" | "on" => Some(true),
+                "0" | "false" | "off" => Some(false),
+                _ => None,
+            }),
+            link_base: env::var("TIMECARD_LINK_BASE").ok(),
+            title: env::var("PDF_TITLE").ok(),
+            author: env::var("PDF_AUTHOR").ok(),
+            subject: env::var("PDF_SUBJECT").ok(),
+            keywords: env::var("PDF_KEYWORDS").ok(),
+            watermark: env::var("PDF_WATERMARK").ok(),
+            ..Default::default()
+        }
+    }
+
+    /// CLIの --render key=value（複数指定可）から読み込み
+    /// 値はJSONとして解釈できればJSON、できなければ文字列として扱う
+    pub fn from_cli_pairs(pairs: &[String]) -> Result<Self, String> {
+        let mut map = serde_json::Map::new();
+        for pair in pairs {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("--render は key=value 形式で指定してください: {}", pair))?;
+            let value = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+            map.insert(key.trim().to_string(), value);
+        }
+        serde_json::from_value(serde_json::Value::Object(map)).map_err(|e| format!("--render: {}", e))
+    }
+
+    /// other の指定がある項目で上書き
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            punch_symbols: other.punch_symbols.clone().or_else(|| self.punch_symbols.clone()),
+            remark_symbols: other.remark_symbols.clone().or_else(|| self.remark_symbols.clone()),
+            zangyo_link_template: other.zangyo_link_template.clone().or_else(|| self.zangyo_link_template.clone()),
+            kosoku_highlight_minutes: other.kosoku_highlight_minutes.or(self.kosoku_highlight_minutes),
+            footer: other.footer.or(self.footer),
+            drivers_per_page: other.drivers_per_page.or(self.drivers_per_page),
+            margin_top: other.margin_top.or(self.margin_top),
+            margin_left: other.margin_left.or(self.margin_left),
+            show_kosoku_column: other.show_kosoku_column.or(self.show_kosoku_column),
+            font_size: other.font_size.or(self.font_size),
+            header_font_size: other.header_font_size.or(self.header_font_size),
+            link_base: other.link_base.clone().or_else(|| self.link_base.clone()),
+            digitacho_links: other.digitacho_links.or(self.digitacho_links),
+            shukei_index: other.shukei_index.or(self.shukei_index),
+            shade_not_employed: other.shade_not_employed.or(self.shade_not_employed),
+            title: other.title.clone().or_else(|| self.title.clone()),
+            author: other.author.clone().or_else(|| self.author.clone()),
+            subject: other.subject.clone().or_else(|| self.subject.clone()),
+            keywords: other.keywords.clone().or_else(|| self.keywords.clone()),
+            watermark: other.watermark.clone().or_else(|| self.watermark.clone()),
+            watermark_font_size: other.watermark_font_size.or(self.watermark_font_size),
+            watermark_opacity: other.watermark_opacity.or(self.watermark_opacity),
+            watermark_angle: other.watermark_angle.or(self.watermark_angle),
+        }
+    }
+}
+
+/// 検証済みのレンダリングオプション（render_timecards / render_timecards_shukei に渡す）
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// 打刻の囲み記号
+    pub punch_symbols: PunchSymbols,
+    /// 備考の記号
+    pub remark_symbols: RemarkSymbols,
+    /// 残業欄のリンク先（{id} を旅費行のIDに置き換える）
+    pub zangyo_link_template: Option<String>,
+    /// 拘束時間をこの分数以上で強調する
+    pub kosoku_highlight_minutes: Option<i32>,
+    /// 各ページ下部に「ページ n / N」と作成日時・対象年月を表示する
+    pub footer: bool,
+    /// 1ページに並べる人数（render_timecards のみ）
+    pub drivers_per_page: usize,
+    /// 上余白（mm）
+    pub margin_top: f64,
+    /// 左余白（mm）
+    pub margin_left: f64,
+    /// 拘束時間の列を表示する
+    pub show_kosoku_column: bool,
+    /// 本文の文字サイズ（pt）
+    pub font_size: f32,
+    /// 氏名の文字サイズ（pt）
+    pub header_font_size: f32,
+    /// 氏名のリンク先の前に付けるURL（空文字なら相対パス）
+    pub link_base: String,
+    /// デジタコのある日の日付にリンクを付ける
+    pub digitacho_links: bool,
+    /// 集計PDFの先頭に目次ページを付ける
+    pub shukei_index: bool,
+    /// 在籍していない日の行をグレーにする（render_timecards のみ）
+    pub shade_not_employed: bool,
+    /// PDFの文書情報（空文字の指定は未指定扱い）
+    pub metadata: PdfMetadata,
+    /// 各ページに描画する透かし
+    pub watermark: Option<Watermark>,
+    /// フッターに表示する拘束時間の出典（--kosoku-source / "kosoku_source" から設定、auto は None）
+    pub kosoku_source_label: Option<&'static str>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            punch_symbols: PunchSymbols::default(),
+            remark_symbols: RemarkSymbols::default(),
+            zangyo_link_template: None,
+            kosoku_highlight_minutes: Some(DEFAULT_KOSOKU_HIGHLIGHT_MINUTES),
+            footer: true,
+            drivers_per_page: MAX_DRIVERS_PER_PAGE,
+            margin_top: 5.0,
+            margin_left: 6.0,
+            show_kosoku_column: true,
+            font_size: 10.0,
+            header_font_size: 12.0,
+            link_base: String::new(),
+            digitacho_links: false,
+            shukei_index: false,
+            shade_not_employed: true,
+            metadata: PdfMetadata::default(),
+            watermark: None,
+            kosoku_source_label: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// 各層を下から順に重ねて検証（layers は 環境変数, CLI, リクエスト の順）
+    pub fn resolve(layers: &[&RenderOverrides]) -> Result<Self, String> {
+        let merged = layers
+            .iter()
+            .fold(RenderOverrides::default(), |acc, layer| acc.merge(layer));
+        let mut options = Self::default();
+
+        if let Some(spec) = &merged.punch_symbols {
+            options.punch_symbols = PunchSymbols::try_parse(spec)
+                .ok_or_else(|| format!("punch_symbols は off か4文字（例: []<>）で指定してください: {}", spec))?;
+            if options.punch_symbols.enabled && options.punch_symbols.digitacho == options.punch_symbols.reader {
+                return Err("punch_symbols: デジタコあり・なしの記号が同じだと区別できません".to_string());
+            }
+        }
+        if let Some(spec) = &merged.remark_symbols {
+            options.remark_symbols = RemarkSymbols::try_parse(spec)
+                .ok_or_else(|| format!("remark_symbols は 備考=記号 をカンマ区切りで指定してください: {}", spec))?;
+        }
+        if let Some(template) = merged.zangyo_link_template.filter(|t| !t.is_empty()) {
+            if !template.contains("{id}") {
+                return Err(format!("zangyo_link_template には {{id}} を含めてください: {}", template));
+            }
+            options.zangyo_link_template = Some(template);
+        }
+        if let Some(minutes) = merged.kosoku_highlight_minutes {
+            if !(0..=24 * 60).contains(&minutes) {
+                return Err(format!("kosoku_highlight_minutes は0〜1440で指定してください: {}", minutes));
+            }
+            options.kosoku_highlight_minutes = (minutes > 0).then_some(minutes);
+        }
+        if let Some(footer) = merged.footer {
+            options.footer = footer;
+        }
+        if let Some(n) = merged.drivers_per_page {
+            if !(1..=MAX_DRIVERS_PER_PAGE).contains(&n) {
+                return Err(format!("drivers_per_page は1〜{}で指定してください: {}", MAX_DRIVERS_PER_PAGE, n));
+            }
+            options.drivers_per_page = n;
+        }
+        for (name, value, target) in [
+            ("margin_top", merged.margin_top, &mut options.margin_top),
+            ("margin_left", merged.margin_left, &mut options.margin_left),
+        ] {
+            if let Some(mm) = value {
+                if !(0.0..=30.0).contains(&mm) {
+                    return Err(format!("{} は0〜30mmで指定してください: {}", name, mm));
+                }
+                *target = mm;
+            }
+        }
+        if let Some(show) = merged.show_kosoku_column {
+            options.show_kosoku_column = show;
+        }
+        for (name, value, target) in [
+            ("font_size", merged.font_size, &mut options.font_size),
+            ("header_font_size", merged.header_font_size, &mut options.header_font_size),
+        ] {
+            if let Some(size) = value {
+                if !(4.0..=20.0).contains(&size) {
+                    return Err(format!("{} は4〜20ptで指定してください: {}", name, size));
+                }
+                *target = size;
+            }
+        }
+        if let Some(base) = merged.link_base {
+            options.link_base = base.trim_end_matches('/').to_string();
+        }
+        if let Some(links) = merged.digitacho_links {
+            options.digitacho_links = links;
+        }
+        if let Some(index) = merged.shukei_index {
+            options.shukei_index = index;
+        }
+        if let Some(shade) = merged.shade_not_employed {
+            options.shade_not_employed = shade;
+        }
+        let text = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        options.metadata = PdfMetadata {
+            title: text(merged.title),
+            author: text(merged.author),
+            subject: text(merged.subject),
+            keywords: text(merged.keywords),
+        };
+        let mut watermark = Watermark::new(String::new());
+        if let Some(size) = merged.watermark_font_size {
+            if !(10.0..=200.0).contains(&size) {
+                return Err(format!("watermark_font_size は10〜200ptで指定してください: {}", size));
+            }
+            watermark.font_size = size;
+        }
+        if let Some(opacity) = merged.watermark_opacity {
+            if !(opacity > 0.0 && opacity <= 1.0) {
+                return Err(format!("watermark_opacity は0より大きく1以下で指定してください: {}", opacity));
+            }
+            watermark.opacity = opacity;
+        }
+        if let Some(angle) = merged.watermark_angle {
+            if !(-90.0..=90.0).contains(&angle) {
+                return Err(format!("watermark_angle は-90〜90度で指定してください: {}", angle));
+            }
+            watermark.angle = angle;
+        }
+        options.watermark = text(merged.watermark).map(|text| Watermark { text, ..watermark });
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(punch: Option<&str>, minutes: Option<i32>) -> RenderOverrides {
+        RenderOverrides {
+            punch_symbols: punch.map(String::from),
+            kosoku_highlight_minutes: minutes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_precedence_request_over_cli_over_env() {
+        let env = layer(Some("()<>"), Some(600));
+        let cli = layer(Some("{}<>"), None);
+        let request = layer(None, Some(720));
+
+        // デフォルト
+        let options = RenderOptions::resolve(&[]).unwrap();
+        assert_eq!(options.punch_symbols.digitacho, ('[', ']'));
+        assert_eq!(options.kosoku_highlight_minutes, Some(840));
+
+        // 環境変数 > デフォルト
+        let options = RenderOptions::resolve(&[&env]).unwrap();
+        assert_eq!(options.punch_symbols.digitacho, ('(', ')'));
+        assert_eq!(options.kosoku_highlight_minutes, Some(600));
+
+        // CLI > 環境変数、リクエスト > CLI
+        let options = RenderOptions::resolve(&[&env, &cli, &request]).unwrap();
+        assert_eq!(options.punch_symbols.digitacho, ('{', '}'));
+        assert_eq!(options.kosoku_highlight_minutes, Some(720));
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let err = serde_json::from_str::<RenderOverrides>(r#"{"punch_symbol": "off"}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown field"));
+        assert!(RenderOverrides::from_cli_pairs(&["monochrom=true".to_string()]).is_err());
+
+        let cli = RenderOverrides::from_cli_pairs(&["kosoku_highlight_minutes=0".to_string(), "punch_symbols=off".to_string()]).unwrap();
+        let options = RenderOptions::resolve(&[&cli]).unwrap();
+        assert_eq!(options.kosoku_highlight_minutes, None);
+        assert!(!options.punch_symbols.enabled);
+    }
+
+    #[test]
+    fn test_layout_options() {
+        let cli = RenderOverrides::from_cli_pairs(&[
+            "drivers_per_page=2".to_string(),
+            "show_kosoku_column=false".to_string(),
+            "link_base=https://example.local/".to_string(),
+        ])
+        .unwrap();
+        let options = RenderOptions::resolve(&[&cli]).unwrap();
+        assert_eq!(options.drivers_per_page, 2);
+        assert!(!options.show_kosoku_column);
+        assert_eq!(options.link_base, "https://example.local");
+        assert_eq!(options.margin_top, 5.0);
+
+        let invalid = |pair: &str| RenderOptions::resolve(&[&RenderOverrides::from_cli_pairs(&[pair.to_string()]).unwrap()]).is_err();
+        assert!(invalid("drivers_per_page=4"));
+        assert!(invalid("drivers_per_page=0"));
+        assert!(invalid("margin_top=-1"));
+        assert!(invalid("font_size=30"));
+
+        let cli = RenderOverrides::from_cli_pairs(&["author=総務部".to_string(), "title= ".to_string()]).unwrap();
+        let metadata = RenderOptions::resolve(&[&cli]).unwrap().metadata;
+        assert_eq!(metadata.author.as_deref(), Some("総務部"));
+        assert_eq!(metadata.title, None);
+
+        let cli = RenderOverrides::from_cli_pairs(&["watermark=控え".to_string(), "watermark_opacity=0.3".to_string()]).unwrap();
+        let watermark = RenderOptions::resolve(&[&cli]).unwrap().watermark.unwrap();
+        assert_eq!((watermark.text.as_str(), watermark.opacity, watermark.angle), ("控え", 0.3, 35.0));
+        assert!(invalid("watermark_opacity=0"));
+        assert!(invalid("watermark_angle=120"));
+        let cli = RenderOverrides::from_cli_pairs(&["watermark=".to_string()]).unwrap();
+        assert_eq!(RenderOptions::resolve(&[&cli]).unwrap().watermark, None);
+    }
+
+    #[test]
+    fn test_invalid_combinations() {
+        assert!(RenderOptions::resolve(&[&layer(Some("[][]"), None)]).is_err());
+        assert!(RenderOptions::resolve(&[&layer(Some("[]"), None)]).is_err());
+        assert!(RenderOptions::resolve(&[&layer(None, Some(-1))]).is_err());
+        let template = RenderOverrides { zangyo_link_template: Some("/ryohi-rows/view".to_string()), ..Default::default() };
+        assert!(RenderOptions::resolve(&[&template]).is_err());
+    }
+}
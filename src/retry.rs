@@ -0,0 +1,188 @@
+use mysql::{DriverError, Error};
+use std::env;
+use std::io::ErrorKind;
+use std::time::Duration;
+use tracing::warn;
+
+/// 再試行の待ち時間の上限
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// DB接続・クエリの再試行（本番DBのバックアップ中に接続が切れても月全体を失敗させない）
+/// 1回目の失敗後 delay 待ち、以降は倍にしていく（上限30秒）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// 最初の1回を含む試行回数（1なら再試行しない）
+    pub attempts: u32,
+    /// 最初の再試行までの待ち時間
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { attempts: 3, delay: Duration::from_millis(500) }
+    }
+}
+
+impl RetryPolicy {
+    /// 再試行しない
+    pub fn none() -> Self {
+        Self { attempts: 1, delay: Duration::ZERO }
+    }
+
+    /// 環境変数 DB_RETRY_ATTEMPTS（デフォルト3）/ DB_RETRY_DELAY_MS（デフォルト500）
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let attempts = env::var("DB_RETRY_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .map(|n| n.max(1))
+            .unwrap_or(default.attempts);
+        let delay = env::var("DB_RETRY_DELAY_MS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(default.delay);
+        Self { attempts, delay }
+    }
+
+    /// attempt 回目（1始まり）の失敗後の待ち時間
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        self.delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))).min(MAX_DELAY)
+    }
+
+    /// f を実行し、一時的なエラーなら待って再試行する（what はログ用）
+    /// f は何度実行しても同じ結果になるもの（読み取り、またはトランザクション全体）に限る
+    pub fn run<T>(&self, what: &str, mut f: impl FnMut() -> mysql::Result<T>) -> mysql::Result<T> {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(e) if attempt < self.attempts && is_transient(&e) => {
+                    let delay = self.delay_after(attempt);
+                    warn!(
+                        "{}に失敗しました（{}/{}回目）、{}ms後に再試行します: {}",
+                        what,
+                        attempt,
+                        self.attempts,
+                        delay.as_millis(),
+                        e
+                    );
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// 再試行で直る可能性があるエラー（接続断・タイムアウト・デッドロックなど）
+/// SQLの誤りや設定の誤り（証明書が読めない等）は再試行しない
+pub fn is_transient(e: &Error) -> bool {
+    match e {
+        Error::IoError(e) => matches!(
+            e.kind(),
+            ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::NotConnected
+                | ErrorKind::BrokenPipe
+                | ErrorKind::TimedOut
+                | ErrorKind::WouldBlock
+                | ErrorKind::UnexpectedEof
+                | ErrorKind::Interrupted
+        ),
+        Error::DriverError(e) => {
+            matches!(e, DriverError::ConnectTimeout | DriverError::CouldNotConnect(_) | DriverError::Timeout)
+        }
+        // 1040: Too many connections, 1053: Server shutdown, 1205: Lock wait timeout, 1213: Deadlock,
+        // 2006: MySQL server has gone away, 2013: Lost connection
+        Error::MySqlError(e) => matches!(e.code, 1040 | 1053 | 1205 | 1213 | 2006 | 2013),
+        _ => false,
+    }
+}
+
+/// DB接続のタイムアウト（ネットワークが止まった時に待ち続けない）
+/// 環境変数 DB_CONNECT_TIMEOUT_SECS（デフォルト10）/ DB_READ_TIMEOUT_SECS（300）/ DB_WRITE_TIMEOUT_SECS（60）、0で無制限
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DbTimeouts {
+    /// 接続のタイムアウト（Noneなら無制限）
+    pub connect: Option<Duration>,
+    /// 読み込みのタイムアウト（Noneなら無制限）
+    pub read: Option<Duration>,
+    /// 書き込みのタイムアウト（Noneなら無制限）
+    pub write: Option<Duration>,
+}
+
+impl Default for DbTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: Some(Duration::from_secs(10)),
+            // 全ドライバーの打刻の一括取得は数十秒かかる
+            read: Some(Duration::from_secs(300)),
+            write: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl DbTimeouts {
+    /// 環境変数から読む（未設定・不正な値はデフォルト）
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let secs = |name: &str, default: Option<Duration>| match env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => default,
+        };
+        Self {
+            connect: secs("DB_CONNECT_TIMEOUT_SECS", default.connect),
+            read: secs("DB_READ_TIMEOUT_SECS", default.read),
+            write: secs("DB_WRITE_TIMEOUT_SECS", default.write),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_run_retries_transient_errors_only() {
+        let policy = RetryPolicy { attempts: 3, delay: Duration::ZERO };
+        let reset = || Error::IoError(io::Error::new(ErrorKind::ConnectionReset, "reset"));
+
+        // 2回失敗して3回目で成功
+        let mut calls = 0;
+        let result = policy.run("テスト", || {
+            calls += 1;
+            if calls < 3 { Err(reset()) } else { Ok(calls) }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        // 試行回数を超えたら最後のエラー
+        let mut calls = 0;
+        assert!(policy.run("テスト", || -> mysql::Result<()> {
+            calls += 1;
+            Err(reset())
+        })
+        .is_err());
+        assert_eq!(calls, 3);
+
+        // 一時的でないエラー（設定の誤り）は再試行しない
+        let mut calls = 0;
+        let _ = policy.run("テスト", || -> mysql::Result<()> {
+            calls += 1;
+            Err(Error::IoError(io::Error::new(ErrorKind::NotFound, "CA証明書を読み込めません")))
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_delay_backoff() {
+        let policy = RetryPolicy { attempts: 10, delay: Duration::from_millis(500) };
+        assert_eq!(policy.delay_after(1), Duration::from_millis(500));
+        assert_eq!(policy.delay_after(2), Duration::from_secs(1));
+        assert_eq!(policy.delay_after(3), Duration::from_secs(2));
+        assert_eq!(policy.delay_after(9), MAX_DELAY);
+    }
+}
@@ -1,210 +1,2173 @@
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router, Json,
     http::StatusCode,
     response::{IntoResponse, Response},
-    extract::State,
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        FromRequest, MatchedPath, Path, Query, Request, State,
+    },
+    middleware::{self, Next},
 };
-use serde::{Deserialize, Serialize};
+use chrono::{Datelike, NaiveDate};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use std::future::{Future, IntoFuture};
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tower_http::cors::{CorsLayer, Any};
+use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::Level;
 
-use crate::db::{DbConfig, TimecardDb};
-use crate::tcpdf_compat::TcpdfCompat;
+use crate::audit::SyncSource;
+use crate::db::{self, DbConfig, DriverQuery, FetchProgress, KosokuSource, LazyPool, TimecardDb};
+use crate::export;
+use crate::maintenance::MaintenanceConfig;
+use crate::lookup_cache::LookupCache;
+use crate::metrics::Metrics;
+use crate::month_range::{self, MonthRange};
+use crate::pdf_cache::PdfCache;
+use crate::pdf_jobs::{JobOutput, JobProgress, JobState, JobStore};
+use crate::render_options::{RenderOptions, RenderOverrides};
+use crate::stats::{self, IntervalTicker, ServerStats};
+use crate::pdf_encryption::PdfEncryption;
+use crate::pdf_split;
+use crate::tcpdf_compat::{SaveOptions, TcpdfCompat};
+use crate::timecard_data::{Driver, MonthlyTimecard, ZangyoSource};
+use crate::tls::TlsStream;
+use crate::zangyo_report::{self, ZangyoReportConfig};
+use crate::zip_archive;
+use tracing::{debug, error, info, warn};
 
-/// アプリケーション状態（DBの設定情報を共有）
+/// アプリケーション状態（DBの接続プールを共有）
 #[derive(Clone)]
 pub struct AppState {
     /// 本番DB（読み取り専用）
-    pub read_db_config: DbConfig,
+    pub read_db: LazyPool,
     /// Docker DB（書き込み用）
-    pub write_db_config: DbConfig,
+    pub write_db: LazyPool,
+    /// メンテナンスモード設定
+    pub maintenance: MaintenanceConfig,
+    /// サーバー統計（定期ログ・/metrics）
+    pub stats: Arc<ServerStats>,
+    /// レンダリングオプションの既定値（環境変数 + CLIの --render）
+    pub render_base: RenderOverrides,
+    /// /health のDB疎通確認のタイムアウト
+    pub health_timeout: Duration,
+    /// タイムカード取得の並列数（PARALLEL_FETCH、上限 MAX_FETCH_JOBS）
+    pub fetch_jobs: usize,
+    /// 生成済みPDFのキャッシュ（PDF_CACHE_*）
+    pub pdf_cache: Arc<PdfCache>,
+    /// PDF生成の同時実行数の制限（PDF_CONCURRENCY / PDF_QUEUE_TIMEOUT_SECS）
+    pub pdf_limit: PdfLimit,
+    /// バックグラウンドのPDF生成ジョブ（PDF_JOB_TTL_SECS）
+    pub pdf_jobs: Arc<JobStore>,
+    /// PDF生成・DBクエリ・同期のメトリクス（METRICS_ENABLED、None なら /metrics なし）
+    pub metrics: Option<Arc<Metrics>>,
+    /// 基礎日数・ドライバー一覧のキャッシュ（LOOKUP_CACHE_TTL_SECS）
+    pub lookup_cache: Arc<LookupCache>,
+}
+
+/// PDF生成の同時実行数の制限
+/// 全ドライバーのPDFが同時に複数来るとメモリと本番DBの負荷が跳ね上がるため、
+/// 実行枠を超えた分は queue_timeout まで待たせ、それでも空かなければ429を返す
+/// （/health やJSONのAPIは制限しない）
+#[derive(Clone)]
+pub struct PdfLimit {
+    semaphore: Arc<Semaphore>,
+    queue_timeout: Duration,
+}
+
+impl PdfLimit {
+    /// permits は最低1
+    pub fn new(permits: usize, queue_timeout: Duration) -> Self {
+        Self { semaphore: Arc::new(Semaphore::new(permits.max(1))), queue_timeout }
+    }
+
+    /// 環境変数 PDF_CONCURRENCY（デフォルト1）、PDF_QUEUE_TIMEOUT_SECS（デフォルト30秒）
+    pub fn from_env() -> Self {
+        let permits = std::env::var("PDF_CONCURRENCY")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(1);
+        let timeout = std::env::var("PDF_QUEUE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(30);
+        Self::new(permits, Duration::from_secs(timeout))
+    }
+
+    /// 429の Retry-After（秒、最低1）
+    fn retry_after_secs(&self) -> u64 {
+        self.queue_timeout.as_secs().max(1)
+    }
 }
 
 /// PDF生成リクエスト
 #[derive(Deserialize)]
 pub struct PdfRequest {
+    /// レンダリングオプション（未知のキーは400）
+    #[serde(default)]
+    pub render: RenderOverrides,
+    /// 対象年月（year + month か、from + to の範囲のどちらか）
+    pub year: Option<i32>,
+    /// 対象月
+    pub month: Option<u32>,
+    /// 範囲の開始月・終了月（"YYYY-MM"、例: "2025-12"〜"2026-01"を1つのPDFに）
+    pub from: Option<String>,
+    /// 範囲の終了月
+    pub to: Option<String>,
+    /// ドライバーID（未指定なら全員）
+    pub driver_id: Option<i32>,
+    /// 給与区分で絞り込み（例: [2, 3]）
+    #[serde(default)]
+    pub categories: Option<Vec<i32>>,
+    /// 役員（category_c = 1）を含める
+    #[serde(default, alias = "include_yakuin")]
+    pub include_officers: bool,
+    /// 営業所コードで絞り込み（省略時は営業所1のみ、例: [1, 2]）
+    #[serde(default)]
+    pub eigyosho: Option<Vec<i32>>,
+    /// Docker DBの time_card_allowance / time_card_kosoku に同期する（デフォルトはしない）
+    #[serde(default)]
+    pub sync: bool,
+    /// PDFを作らず、Docker DBへの同期で変わる内容をJSONで返す（書き込みはしない）
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 透かしの文字（例: "控え"、render.watermark より優先）
+    #[serde(default)]
+    pub watermark: Option<String>,
+    /// PDFを開くためのパスワード（指定時はRC4 128bitで暗号化）
+    #[serde(default)]
+    pub password: Option<String>,
+    /// 拘束列に表示する拘束時間の出典（"tcdc-rust" / "php-digitacho" など、省略時は "auto"）
+    #[serde(default)]
+    pub kosoku_source: Option<String>,
+}
+
+/// PDF生成リクエスト（GET、ブラウザのリンク用）
+/// 例: /api/pdf?year=2026&month=1&driver_id=1523
+#[derive(Deserialize)]
+pub struct PdfQuery {
+    /// 対象年
     pub year: i32,
+    /// 対象月
     pub month: u32,
+    /// ドライバーID（未指定なら全員）
     pub driver_id: Option<i32>,
+    /// Docker DBに同期する
+    #[serde(default)]
+    pub sync: bool,
+}
+
+impl From<PdfQuery> for PdfRequest {
+    fn from(q: PdfQuery) -> Self {
+        Self {
+            render: RenderOverrides::default(),
+            year: Some(q.year),
+            month: Some(q.month),
+            from: None,
+            to: None,
+            driver_id: q.driver_id,
+            categories: None,
+            include_officers: false,
+            eigyosho: None,
+            sync: q.sync,
+            dry_run: false,
+            watermark: None,
+            password: None,
+            kosoku_source: None,
+        }
+    }
+}
+
+/// 分析用CSVリクエスト（GET）
+/// 例: /api/csv?year=2026&month=1&driver_id=1523&bom=1
+#[derive(Deserialize)]
+pub struct CsvQuery {
+    /// 対象年
+    pub year: i32,
+    /// 対象月
+    pub month: u32,
+    /// ドライバーID（未指定なら全員）
+    pub driver_id: Option<i32>,
+    /// 1 / true で先頭にBOMを付ける（Excel用）
+    pub bom: Option<String>,
+}
+
+/// ?refresh=1 / ?refresh=true でキャッシュを使わずに作り直す（作ったPDFはキャッシュし直す）
+#[derive(Deserialize, Default)]
+pub struct RefreshParam {
+    /// "1" / "true" なら作り直す
+    pub refresh: Option<String>,
+}
+
+impl RefreshParam {
+    fn requested(param: Option<Query<Self>>) -> bool {
+        param.is_some_and(|Query(p)| matches!(p.refresh.as_deref(), Some("1" | "true")))
+    }
+}
+
+/// キャッシュ削除のレスポンス
+#[derive(Serialize)]
+pub struct CacheClearResponse {
+    /// 削除したPDFキャッシュの件数
+    pub cleared: usize,
+    /// 削除した基礎日数・ドライバー一覧のキャッシュの件数
+    pub lookups_cleared: usize,
+}
+
+/// PDF生成ジョブの登録リクエスト（PdfRequest の項目 + layout）
+/// 例: {"year":2026,"month":1,"layout":"shukei"}
+#[derive(Deserialize)]
+pub struct JobRequest {
+    /// PDF生成リクエストの項目
+    #[serde(flatten)]
+    pub pdf: PdfRequest,
+    /// timecard（3人/ページ、デフォルト） / shukei / zip
+    #[serde(default)]
+    pub layout: PdfLayout,
+}
+
+/// PDF生成ジョブの登録結果
+#[derive(Serialize)]
+pub struct JobCreatedResponse {
+    /// 状態確認・ダウンロードに使うID
+    pub job_id: String,
+}
+
+/// PDF生成ジョブの状態
+#[derive(Serialize)]
+pub struct JobStatusResponse {
+    /// ジョブID
+    pub job_id: String,
+    /// pending（実行枠の空き待ち） / running / done / failed
+    pub status: JobState,
+    /// 処理済み・対象ドライバー数（範囲指定では全月の合計）
+    pub drivers_done: usize,
+    /// 対象ドライバー数
+    pub drivers_total: usize,
+    /// failed の時のエラー
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// done の時のダウンロードURL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download: Option<String>,
+}
+
+/// ドライバー一覧リクエスト（GET）
+/// 例: /api/drivers?year=2026&month=1&bumon=3
+#[derive(Deserialize)]
+pub struct DriversQuery {
+    /// 対象年
+    pub year: i32,
+    /// 対象月
+    pub month: u32,
+    /// 部門で絞り込み
+    pub bumon: Option<i32>,
+}
+
+/// ドライバー一覧（PDFと同じ並び順）
+#[derive(Serialize)]
+pub struct DriversResponse {
+    /// 件数
+    pub count: usize,
+    /// ドライバー
+    pub drivers: Vec<DriverEntry>,
+}
+
+/// ドライバー一覧の1件
+#[derive(Serialize)]
+pub struct DriverEntry {
+    /// ドライバーID
+    pub id: i32,
+    /// 氏名
+    pub name: String,
+    /// 部門
+    pub bumon: Option<i32>,
+    /// 給与区分
+    pub category_c: Option<i32>,
+    /// 給与社員ID
+    pub kyuyo_shain_id: Option<i32>,
+}
+
+impl From<Driver> for DriverEntry {
+    fn from(d: Driver) -> Self {
+        Self {
+            id: d.id,
+            name: d.name,
+            bumon: d.bumon,
+            category_c: d.category_c,
+            kyuyo_shain_id: d.kyuyo_shain_id,
+        }
+    }
+}
+
+/// 年月を検証（yearは2000〜2100、monthは1〜12）
+fn validate_year_month(year: i32, month: u32) -> Result<(), ApiError> {
+    if !(2000..=2100).contains(&year) {
+        return Err(ApiError(StatusCode::BAD_REQUEST, format!("year must be 2000-2100: {}", year)));
+    }
+    if !(1..=12).contains(&month) {
+        return Err(ApiError(StatusCode::BAD_REQUEST, format!("month must be 1-12: {}", month)));
+    }
+    Ok(())
+}
+
+/// driver_id を検証（指定時は1以上）
+fn validate_driver_id(driver_id: Option<i32>) -> Result<(), ApiError> {
+    match driver_id {
+        Some(id) if id <= 0 => Err(ApiError(StatusCode::BAD_REQUEST, format!("driver_id must be > 0: {}", id))),
+        _ => Ok(()),
+    }
+}
+
+impl PdfRequest {
+    /// 対象月と driver_id を検証（不正なら400）
+    fn validate(&self) -> Result<MonthRange, ApiError> {
+        validate_driver_id(self.driver_id)?;
+        self.kosoku_source()?;
+        self.months()
+    }
+
+    /// 拘束時間の出典（不正なら400）
+    fn kosoku_source(&self) -> Result<KosokuSource, ApiError> {
+        match self.kosoku_source.as_deref() {
+            None => Ok(KosokuSource::Auto),
+            Some(s) => KosokuSource::parse(s).ok_or_else(|| {
+                ApiError(
+                    StatusCode::BAD_REQUEST,
+                    format!("kosoku_source must be auto, digitacho-rust, tcdc-rust, php-tcdc or php-digitacho: {}", s),
+                )
+            }),
+        }
+    }
+
+    /// 対象月（year + month か from + to、どちらでもない・不正なら400）
+    fn months(&self) -> Result<MonthRange, ApiError> {
+        let invalid = |e: String| ApiError(StatusCode::BAD_REQUEST, format!("Invalid month range: {}", e));
+        match (self.year, self.month, self.from.as_deref(), self.to.as_deref()) {
+            (Some(year), Some(month), None, None) => {
+                validate_year_month(year, month)?;
+                Ok(MonthRange::single(year, month))
+            }
+            (None, None, Some(from), Some(to)) => {
+                MonthRange::new(month_range::parse_month(from).map_err(invalid)?, month_range::parse_month(to).map_err(invalid)?).map_err(invalid)
+            }
+            _ => Err(ApiError(StatusCode::BAD_REQUEST, "Specify either year and month, or from and to (YYYY-MM)".to_string())),
+        }
+    }
+
+    /// パスワード指定時の暗号化（不正なら400）
+    fn encryption(&self) -> Result<Option<PdfEncryption>, ApiError> {
+        self.password
+            .as_deref()
+            .map(|p| PdfEncryption::new(p, None))
+            .transpose()
+            .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("Invalid password: {}", e)))
+    }
+
+    /// ダウンロード時のファイル名（例: timecard_2026_01_1523.pdf、範囲は timecard_2025-12_2026-01.pdf）
+    fn filename(&self, prefix: &str, range: &MonthRange) -> String {
+        match self.driver_id {
+            Some(id) => format!("{}_{}_{}.pdf", prefix, range.file_label(), id),
+            None => format!("{}_{}.pdf", prefix, range.file_label()),
+        }
+    }
+
+    /// キャッシュのキー（同期・パスワード付きはキャッシュしないのでNone）
+    fn cache_key(&self, layout: PdfLayout, range: &MonthRange, options: &RenderOptions) -> Option<String> {
+        if self.sync || self.password.is_some() {
+            return None;
+        }
+        Some(format!(
+            "{:?}|{}|{:?}|{:?}|{}|{:?}|{:?}",
+            layout, range.file_label(), self.driver_id, self.categories, self.include_officers, self.eigyosho, options
+        ))
+    }
+
+    /// ドライバー取得条件（範囲指定では月ごと）
+    fn driver_query(&self, year: i32, month: u32) -> DriverQuery {
+        DriverQuery::new(year, month)
+            .categories(self.categories.clone())
+            .include_officers(self.include_officers)
+            .eigyosho(self.eigyosho.clone())
+    }
+}
+
+/// 残業異常レポートリクエスト
+#[derive(Deserialize)]
+pub struct ZangyoReportRequest {
+    /// 対象年
+    pub year: i32,
+    /// 対象月
+    pub month: u32,
+    /// 出力形式（csv / json、デフォルトjson）
+    #[serde(default)]
+    pub format: Option<String>,
 }
 
 /// エラーレスポンス
 #[derive(Serialize)]
 pub struct ErrorResponse {
+    /// エラーメッセージ
+    pub error: String,
+}
+
+/// メンテナンス中レスポンス（503）
+#[derive(Serialize)]
+pub struct MaintenanceResponse {
+    /// "maintenance"
     pub error: String,
+    /// メンテナンスのメッセージ
+    pub message: String,
+    /// 終了予定
+    pub until: Option<String>,
+}
+
+/// ヘルスチェックレスポンス
+#[derive(Serialize)]
+pub struct HealthResponse {
+    /// "OK"（どちらかのDBに接続できなければ "NG"）
+    pub status: String,
+    /// メンテナンス中か
+    pub maintenance: bool,
+    /// 本番DB（"ok" / "error" / "timeout"）
+    pub prod: String,
+    /// Docker DB（"ok" / "error" / "timeout"）
+    pub docker: String,
+}
+
+/// 生存確認のレスポンス（DBに接続しない）
+#[derive(Serialize)]
+pub struct LiveResponse {
+    /// "OK"
+    pub status: String,
+}
+
+/// 環境変数 HEALTH_DB_TIMEOUT_MS（デフォルト1000ミリ秒）
+fn health_timeout_from_env() -> Duration {
+    let ms = std::env::var("HEALTH_DB_TIMEOUT_MS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(1000);
+    Duration::from_millis(ms)
+}
+
+/// 起動時にDocker DBのテーブルを作成・確認するか（環境変数 AUTO_MIGRATE=1）
+pub fn auto_migrate_from_env() -> bool {
+    std::env::var("AUTO_MIGRATE").is_ok_and(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "on"))
+}
+
+/// 待ち受けアドレスの既定値
+pub const DEFAULT_BIND_ADDR: &str = "0.0.0.0:8080";
+
+/// 待ち受けアドレス（"8080" のようにポートだけなら全インターフェース）
+pub fn parse_bind_addr(s: &str) -> Result<SocketAddr, String> {
+    let s = s.trim();
+    if let Ok(port) = s.parse::<u16>() {
+        return Ok(SocketAddr::from(([0, 0, 0, 0], port)));
+    }
+    s.parse::<SocketAddr>()
+        .map_err(|_| format!("待ち受けアドレスは PORT か HOST:PORT で指定してください: {}", s))
+}
+
+/// 待ち受けアドレス（CLIの引数 > 環境変数 BIND_ADDR > 0.0.0.0:8080）
+pub fn bind_addr(cli: Option<SocketAddr>) -> Result<SocketAddr, String> {
+    if let Some(addr) = cli {
+        return Ok(addr);
+    }
+    match std::env::var("BIND_ADDR").ok().filter(|v| !v.trim().is_empty()) {
+        Some(v) => parse_bind_addr(&v).map_err(|e| format!("BIND_ADDR: {}", e)),
+        None => Ok(DEFAULT_BIND_ADDR.parse().expect("既定値は正しいアドレス")),
+    }
 }
 
-/// HTTPサーバーを起動
-pub async fn run(port: u16) {
+/// 起動時のDB疎通確認のタイムアウト
+const STARTUP_DB_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 待ち受けを始める前のDB確認（設定の不足 → 疎通の順、失敗したら起動しない）
+/// 確認しないと起動は成功し、全リクエストがDBエラーの500になってから気づくことになる
+pub async fn check_databases(configs: &[DbConfig]) -> Result<(), String> {
+    for config in configs {
+        config.validate()?;
+    }
+    for config in configs {
+        let pool = LazyPool::new(config.clone());
+        let ping = tokio::time::timeout(STARTUP_DB_CHECK_TIMEOUT, tokio::task::spawn_blocking(move || pool.ping())).await;
+        match ping {
+            Ok(Ok(Ok(()))) => info!("{}に接続しました: {}", config.label(), config),
+            Ok(Ok(Err(e))) => return Err(format!("{}に接続できません ({}): {}", config.label(), config, e)),
+            Ok(Err(e)) => return Err(format!("{}の接続確認に失敗しました: {}", config.label(), e)),
+            Err(_) => {
+                return Err(format!(
+                    "{}に{}秒以内に接続できません ({})",
+                    config.label(),
+                    STARTUP_DB_CHECK_TIMEOUT.as_secs(),
+                    config
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// HTTPサーバーを起動（終了するまで戻らない）
+/// tls があればHTTPS（TLS_CERT / TLS_KEY、証明書は起動前に読み込んでおく）
+pub async fn run(bind: SocketAddr, tls: Option<Arc<rustls::ServerConfig>>, render_base: RenderOverrides) {
     let state = AppState {
-        read_db_config: DbConfig::production(),
-        write_db_config: DbConfig::docker(),
+        read_db: LazyPool::new(DbConfig::production()),
+        write_db: LazyPool::new(DbConfig::docker()),
+        maintenance: MaintenanceConfig::from_env(),
+        stats: Arc::new(ServerStats::default()),
+        render_base,
+        health_timeout: health_timeout_from_env(),
+        fetch_jobs: db::parallel_fetch_jobs_from_env(),
+        pdf_cache: Arc::new(PdfCache::from_env()),
+        pdf_limit: PdfLimit::from_env(),
+        pdf_jobs: Arc::new(JobStore::from_env()),
+        metrics: Metrics::from_env().map(Arc::new),
+        lookup_cache: Arc::new(LookupCache::from_env()),
+    };
+
+    // 統計の定期ログ（STATS_INTERVAL_SECS 毎、0で無効）
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let reporter = stats::interval_from_env().map(|interval| {
+        let stats = state.stats.clone();
+        tokio::spawn(async move {
+            stats::run_reporter(&stats, IntervalTicker::new(interval), shutdown_rx, |s| {
+                info!("{}", s.log_line());
+            })
+            .await;
+        })
+    });
+
+    let stats = state.stats.clone();
+    let app = router(state);
+
+    let listener = match tokio::net::TcpListener::bind(bind).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("{} で待ち受けできません: {}", bind, e);
+            std::process::exit(1);
+        }
+    };
+
+    let addr = listener.local_addr().unwrap_or(bind);
+    info!(
+        "Server listening on {}://{} (TLS: {})",
+        if tls.is_some() { "https" } else { "http" },
+        addr,
+        if tls.is_some() { "on" } else { "off" }
+    );
+    // SIGTERM / Ctrl+C で新しい接続の受付をやめ、処理中のリクエストとPDF生成（ジョブを含む）を最大 grace 待つ
+    let grace = shutdown_grace_from_env();
+    let (signal_tx, signal_rx) = tokio::sync::oneshot::channel();
+    let shutdown = async move {
+        shutdown_signal().await;
+        info!("Shutting down... (処理中の作業を最大{}秒待ちます)", grace.as_secs());
+        signal_tx.send(()).ok();
+    };
+    let server = match tls {
+        Some(config) => tokio::spawn(serve_tls(listener, app, config, shutdown)),
+        None => tokio::spawn(axum::serve(listener, app).with_graceful_shutdown(shutdown).into_future()),
+    };
+    // サーバーが先にエラーで止まった場合も送信側がdropされて進む
+    signal_rx.await.ok();
+    let drained = tokio::time::timeout(grace, async {
+        server.await.expect("Server task panicked").expect("Server failed");
+        stats.wait_idle(Duration::from_millis(100)).await;
+    })
+    .await;
+
+    // 統計タスクを停止して終了を待つ
+    shutdown_tx.send(true).ok();
+    if let Some(reporter) = reporter {
+        reporter.await.ok();
+    }
+
+    if drained.is_err() {
+        // DBへの書き込みはトランザクション内なので、打ち切っても月の一部だけ書かれることはない
+        let aborted = stats.active_work();
+        error!("[shutdown] {}秒以内に終わらなかったため打ち切ります ({}件)", grace.as_secs(), aborted.len());
+        for work in &aborted {
+            error!("[shutdown]   {}", work);
+        }
+        // ランタイムの終了はブロッキング処理の終わりを待ってしまうため、ここでプロセスを終える
+        std::process::exit(1);
+    }
+    info!("Shutdown complete");
+}
+
+/// TLSで待ち受ける（axum::serve は平文のTCPだけなので、接続ごとに hyper のHTTP/1.1で処理する）
+/// shutdown が終わったら新しい接続の受付をやめ、処理中の接続が終わるのを待つ
+pub async fn serve_tls(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    config: Arc<rustls::ServerConfig>,
+    shutdown: impl Future<Output = ()>,
+) -> std::io::Result<()> {
+    let graceful = hyper_util::server::graceful::GracefulShutdown::new();
+    tokio::pin!(shutdown);
+    loop {
+        let tcp = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((tcp, _)) => tcp,
+                Err(e) => {
+                    // ファイルディスクリプタ不足などは少し待って続ける
+                    warn!("接続を受け付けられません: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+        let stream = match TlsStream::new(tcp, config.clone()) {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("TLS接続を作れません: {}", e);
+                continue;
+            }
+        };
+        let conn = hyper::server::conn::http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), TowerToHyperService::new(app.clone()));
+        let conn = graceful.watch(conn);
+        tokio::spawn(async move {
+            // ハンドシェイクの失敗（証明書を信頼しないクライアントなど）もここに来る
+            if let Err(e) = conn.await {
+                debug!("TLS接続を終了: {}", e);
+            }
+        });
+    }
+    graceful.shutdown().await;
+    Ok(())
+}
+
+/// 終了シグナル（Ctrl+C、Unixでは SIGTERM も）
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
     };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// 環境変数 SHUTDOWN_GRACE_SECS（デフォルト30秒）
+fn shutdown_grace_from_env() -> Duration {
+    let secs = std::env::var("SHUTDOWN_GRACE_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(30);
+    Duration::from_secs(secs)
+}
+
+/// ルーターを構築（/api/* はメンテナンス中なら503を返す）
+pub fn router(state: AppState) -> Router {
+    let state = Arc::new(state);
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    let app = Router::new()
+    let api = Router::new()
+        .route("/pdf", post(generate_pdf).get(generate_pdf_get))
+        .route("/pdf-shukei", post(generate_pdf_shukei).get(generate_pdf_shukei_get))
+        .route("/pdf-zip", post(generate_pdf_zip))
+        .route("/jobs/pdf", post(submit_pdf_job))
+        // ここより上のPDF系のルートだけ pdf_requests_total を数える
+        .route_layer(middleware::from_fn_with_state(state.clone(), count_pdf_requests))
+        .route("/timecards", get(get_timecards))
+        .route("/timecards/:driver_id/:date/zangyo", get(get_day_zangyo))
+        .route("/csv", get(get_csv))
+        .route("/drivers", get(get_drivers))
+        .route("/report/zangyo", post(report_zangyo))
+        .route("/cache", delete(clear_cache))
+        .route("/jobs/:id", get(get_job))
+        .route("/jobs/:id/download", get(download_job))
+        .layer(middleware::from_fn_with_state(state.clone(), maintenance_guard));
+
+    let mut app = Router::new()
         .route("/health", get(health_check))
-        .route("/api/pdf", post(generate_pdf))
-        .route("/api/pdf-shukei", post(generate_pdf_shukei))
+        .route("/live", get(live));
+    if state.metrics.is_some() {
+        app = app.route("/metrics", get(metrics));
+    }
+    app.nest("/api", api)
+        .layer(middleware::from_fn_with_state(state.clone(), count_requests))
         .layer(cors)
-        .with_state(Arc::new(state));
+        // 1リクエスト1行（メソッド・URI・ステータス・所要時間）
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                .on_request(())
+                .on_response(DefaultOnResponse::new().level(Level::INFO).latency_unit(LatencyUnit::Millis))
+                // 5xx もステータスは上の行に出るので、別の行は出さない
+                .on_failure(()),
+        )
+        .with_state(state)
+}
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
-        .await
-        .expect("Failed to bind to port");
+/// ルート別のリクエスト数と処理中数を記録
+async fn count_requests(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // 未定義のパスはまとめて数える（任意のパスでラベルが増えないように）
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let _guard = state.stats.request_started(&route);
+    let _work = state.stats.work_started(format!("{} {}", req.method(), req.uri()));
+    next.run(req).await
+}
 
-    println!("Server listening on port {}", port);
-    axum::serve(listener, app).await.expect("Server failed");
+/// PDF系エンドポイントのレスポンス数（endpoint, status）を記録
+async fn count_pdf_requests(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let endpoint = req.extensions().get::<MatchedPath>().map(|p| p.as_str().to_string());
+    let res = next.run(req).await;
+    if let (Some(metrics), Some(endpoint)) = (&state.metrics, endpoint) {
+        metrics.record_pdf_request(&endpoint, res.status().as_u16());
+    }
+    res
 }
 
-/// ヘルスチェック
-async fn health_check() -> &'static str {
-    "OK"
+/// サーバー統計とメトリクス（Prometheus形式）
+async fn metrics(State(state): State<Arc<AppState>>) -> Response {
+    let mut body = state.stats.render_metrics();
+    body.push_str(&state.lookup_cache.render_metrics());
+    if let Some(metrics) = &state.metrics {
+        body.push_str(&metrics.render());
+    }
+    (
+        StatusCode::OK,
+        [("content-type", "text/plain; version=0.0.4")],
+        body,
+    ).into_response()
 }
 
-/// PDF生成（3人/ページ）
-async fn generate_pdf(
+/// メンテナンス中は/api/*へのリクエストを503で返す
+async fn maintenance_guard(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<PdfRequest>,
+    req: Request,
+    next: Next,
 ) -> Response {
-    // 読み取り用DBに接続
-    let db = match TimecardDb::connect(&state.read_db_config) {
-        Ok(db) => db,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: format!("DB connection failed: {}", e) }),
-            ).into_response();
+    let status = state.maintenance.status();
+    if status.maintenance {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(MaintenanceResponse {
+                error: "maintenance".to_string(),
+                message: status.message,
+                until: status.until,
+            }),
+        ).into_response();
+    }
+    next.run(req).await
+}
+
+/// ヘルスチェック（本番DB・Docker DBの両方に SELECT 1、どちらかが失敗したら503）
+async fn health_check(State(state): State<Arc<AppState>>) -> Response {
+    let (prod, docker) = tokio::join!(
+        ping_db(&state, state.read_db.clone()),
+        ping_db(&state, state.write_db.clone()),
+    );
+    let healthy = prod == "ok" && docker == "ok";
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        status,
+        Json(HealthResponse {
+            status: if healthy { "OK" } else { "NG" }.to_string(),
+            maintenance: state.maintenance.is_active(),
+            prod: prod.to_string(),
+            docker: docker.to_string(),
+        }),
+    ).into_response()
+}
+
+/// DBの疎通確認（health_timeout を超えたら "timeout"）
+async fn ping_db(state: &AppState, pool: LazyPool) -> &'static str {
+    match tokio::time::timeout(state.health_timeout, tokio::task::spawn_blocking(move || pool.ping())).await {
+        Ok(Ok(Ok(()))) => "ok",
+        Ok(_) => {
+            state.stats.record_db_error();
+            "error"
         }
+        Err(_) => "timeout",
+    }
+}
+
+/// 生存確認（liveness probe用、DBには接続しない）
+async fn live() -> Json<LiveResponse> {
+    Json(LiveResponse { status: "OK".to_string() })
+}
+
+/// ハンドラーのエラー（ステータスコード + ErrorResponseのJSON）
+#[derive(Debug)]
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(ErrorResponse { error: self.1 })).into_response()
+    }
+}
+
+/// JSONボディの抽出（形式・型の誤りも ErrorResponse のJSONで400を返す）
+/// axum の Json は text/plain で422を返すため、クライアントが同じ形でエラーを扱えるようにする
+struct ApiJson<T>(T);
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Json::<T>::from_request(req, state)
+            .await
+            .map(|Json(value)| Self(value))
+            .map_err(|e: JsonRejection| ApiError(StatusCode::BAD_REQUEST, format!("Invalid request body: {}", e.body_text())))
+    }
+}
+
+/// DBアクセスやPDF生成をブロッキング用スレッドで実行（/health などの他のリクエストを止めない）
+async fn run_blocking<T, F>(f: F) -> Result<T, ApiError>
+where
+    F: FnOnce() -> Result<T, ApiError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| Err(ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("Worker failed: {}", e))))
+}
+
+/// 読み取り用DBからタイムカードを取得（ブロッキング）
+fn load_timecards(state: &AppState, query: &DriverQuery) -> Result<(TimecardDb, Vec<MonthlyTimecard>), ApiError> {
+    load_timecards_with_progress(state, query, KosokuSource::Auto, &mut |_| {})
+}
+
+/// 読み取り用DBからタイムカードを取得（ブロッキング、ジョブの進捗用に1人ごとに on_progress を呼ぶ）
+fn load_timecards_with_progress(
+    state: &AppState,
+    query: &DriverQuery,
+    kosoku_source: KosokuSource,
+    on_progress: &mut (dyn FnMut(FetchProgress) + Send),
+) -> Result<(TimecardDb, Vec<MonthlyTimecard>), ApiError> {
+    let db = TimecardDb::from_pools(&state.read_db, &state.write_db).map_err(|e| {
+        state.stats.record_db_error();
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("DB connection failed: {}", e))
+    })?.with_metrics(state.metrics.clone())
+        .with_lookup_cache(Some(state.lookup_cache.clone()))
+        .with_kosoku_source(kosoku_source);
+    let fetched = db.get_all_monthly_timecards_with_kiso_parallel(query, state.fetch_jobs, on_progress).map_err(|e| {
+        state.stats.record_db_error();
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get timecards: {}", e))
+    })?;
+    // 一部のドライバーが欠けたPDFは返さない
+    if !fetched.failed.is_empty() {
+        state.stats.record_db_error();
+        let failed: Vec<String> = fetched.failed.iter().map(|f| format!("{} ({})", f.driver_id, f.message)).collect();
+        return Err(ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get timecards for drivers: {}", failed.join(", "))));
+    }
+    Ok((db, fetched.timecards))
+}
+
+/// Docker DBへの同期結果（レスポンスヘッダー x-timecard-sync で返す）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SyncStatus {
+    /// sync指定なし
+    Skipped,
+    Ok,
+    Failed,
+}
+
+impl SyncStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skipped => "skipped",
+            Self::Ok => "ok",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// 月ごとのタイムカード（範囲指定のPDF用）
+type MonthTimecards = ((i32, u32), Vec<MonthlyTimecard>);
+
+/// PDF生成の共通処理（ブロッキング）
+/// 月ごとに タイムカード取得 → ドライバー絞り込み → Docker DBへ保存（sync指定時のみ） → PDF（ZIP）のバイト列
+fn build_pdf(
+    state: &AppState,
+    req: &PdfRequest,
+    range: MonthRange,
+    on_progress: &mut (dyn FnMut(FetchProgress) + Send),
+    render: impl FnOnce(&[MonthTimecards]) -> Result<Vec<u8>, String>,
+) -> Result<(Vec<u8>, SyncStatus), ApiError> {
+    let mut months = Vec::new();
+    let mut sync = SyncStatus::Skipped;
+    let kosoku_source = req.kosoku_source()?;
+    for (year, month) in range.months() {
+        let (db, mut timecards) = load_timecards_with_progress(state, &req.driver_query(year, month), kosoku_source, on_progress)?;
+
+        // 特定ドライバーのみにフィルタリング
+        if let Some(driver_id) = req.driver_id {
+            timecards.retain(|tc| tc.driver.id == driver_id);
+        }
+
+        // 書き込み用DBにallowance・拘束時間を差分更新（計算結果にない日の拘束時間の行は削除、失敗してもPDFは返す）
+        if req.sync && !timecards.is_empty() {
+            let allowance = db.sync_all_timecard_allowances_to_docker(&timecards, SyncSource::ServerPdf);
+            let kosoku = db.sync_kosoku_to_docker(&timecards, true, SyncSource::ServerPdf);
+            info!(
+                "[sync] {}年{}月 {}人: allowance {} / kosoku {}",
+                year, month, timecards.len(),
+                allowance.as_ref()
+                    .map(|(i, u, n)| format!("追加: {}, 更新: {}, 変更なし: {}", i, u, n))
+                    .unwrap_or_else(|e| format!("失敗 ({})", e)),
+                kosoku.as_ref()
+                    .map(|(i, u, n, d)| format!("追加: {}, 更新: {}, 変更なし: {}, 削除: {}", i, u, n, d))
+                    .unwrap_or_else(|e| format!("失敗 ({})", e)),
+            );
+            // 1か月でも失敗したら Failed
+            if allowance.is_err() || kosoku.is_err() {
+                sync = SyncStatus::Failed;
+            } else if sync == SyncStatus::Skipped {
+                sync = SyncStatus::Ok;
+            }
+        }
+        months.push(((year, month), timecards));
+    }
+
+    if months.iter().all(|(_, timecards)| timecards.is_empty()) {
+        return Err(ApiError(StatusCode::NOT_FOUND, "No timecards found".to_string()));
+    }
+
+    // PDFをメモリ上で生成
+    let started = Instant::now();
+    let bytes = render(&months)
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("PDF generation failed: {}", e)))?;
+    if let Some(metrics) = &state.metrics {
+        metrics.record_render(started.elapsed(), months.iter().map(|(_, timecards)| timecards.len()).sum());
+    }
+    Ok((bytes, sync))
+}
+
+/// PDFキャッシュの利用結果（レスポンスヘッダー x-cache で返す）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CacheStatus {
+    Hit,
+    /// キャッシュになかった（?refresh=1 を含む）ので作ってキャッシュした
+    Miss,
+    /// キャッシュしないリクエスト（同期・パスワード付き・キャッシュ無効）
+    Bypass,
+}
+
+impl CacheStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hit => "HIT",
+            Self::Miss => "MISS",
+            Self::Bypass => "BYPASS",
+        }
+    }
+}
+
+/// PDF（ZIP）レスポンス
+fn pdf_response(result: Result<(Vec<u8>, SyncStatus), ApiError>, filename: &str, content_type: &str, cache: CacheStatus) -> Response {
+    match result {
+        Ok((bytes, sync)) => (
+            StatusCode::OK,
+            [
+                ("content-type", content_type.to_string()),
+                ("content-disposition", format!("attachment; filename=\"{}\"", filename)),
+                ("x-timecard-sync", sync.as_str().to_string()),
+                ("x-cache", cache.as_str().to_string()),
+            ],
+            bytes,
+        ).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// リクエストを検証してレンダリングオプションを決める（不正なら400）
+fn resolve_render_options(state: &AppState, req: &PdfRequest) -> Result<RenderOptions, ApiError> {
+    req.validate()?;
+    let watermark = RenderOverrides { watermark: req.watermark.clone(), ..Default::default() };
+    let mut options = RenderOptions::resolve(&[&state.render_base, &req.render, &watermark])
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("Invalid render options: {}", e)))?;
+    options.kosoku_source_label = req.kosoku_source()?.label();
+    Ok(options)
+}
+
+/// PDFの種類
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfLayout {
+    /// 3人/ページ
+    #[default]
+    Timecard,
+    /// 集計モード（1人/ページ）
+    Shukei,
+    /// 1人1ファイルのPDFをまとめたZIP
+    Zip,
+}
+
+/// 検証済みのPDF生成リクエスト（同期のAPIとジョブで共通）
+struct PdfTask {
+    req: PdfRequest,
+    layout: PdfLayout,
+    range: MonthRange,
+    options: RenderOptions,
+    encryption: Option<PdfEncryption>,
+    filename: String,
+    content_type: &'static str,
+}
+
+impl PdfTask {
+    /// リクエストを検証してファイル名を決める（不正なら400、DBには接続しない）
+    fn new(state: &AppState, req: PdfRequest, layout: PdfLayout) -> Result<Self, ApiError> {
+        let options = resolve_render_options(state, &req)?;
+        let encryption = req.encryption()?;
+        let range = req.months()?;
+        let (filename, content_type) = match layout {
+            PdfLayout::Timecard => (req.filename("timecard", &range), "application/pdf"),
+            PdfLayout::Shukei => (req.filename("timecard_shukei", &range), "application/pdf"),
+            PdfLayout::Zip => (format!("{}.zip", req.filename("timecard", &range).trim_end_matches(".pdf")), "application/zip"),
+        };
+        Ok(Self { req, layout, range, options, encryption, filename, content_type })
+    }
+
+    /// キャッシュのキー
+    fn cache_key(&self) -> Option<String> {
+        self.req.cache_key(self.layout, &self.range, &self.options)
+    }
+
+    /// タイムカード取得 → PDF（ZIP）生成（ブロッキング）
+    fn run(self, state: &AppState, on_progress: &mut (dyn FnMut(FetchProgress) + Send)) -> Result<(Vec<u8>, SyncStatus), ApiError> {
+        let Self { req, layout, range, options, encryption, .. } = self;
+        build_pdf(state, &req, range, on_progress, |months| {
+            let save_options = SaveOptions { encryption, ..Default::default() };
+            if let PdfLayout::Zip = layout {
+                let timecards: Vec<MonthlyTimecard> = months.iter().flat_map(|(_, timecards)| timecards.iter().cloned()).collect();
+                let files = pdf_split::render_per_driver(&timecards, &options, &save_options)?;
+                return zip_archive::write_zip(&files, chrono::Local::now().naive_local()).map_err(|e| e.to_string());
+            }
+            let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+            pdf.set_save_options(save_options);
+            // 範囲指定なら月ごとに区切りページを入れて1つのPDFに
+            match layout {
+                PdfLayout::Shukei => pdf.render_months(months, &options, TcpdfCompat::render_timecards_shukei)?,
+                _ => pdf.render_months(months, &options, TcpdfCompat::render_timecards)?,
+            }
+            pdf.save_to_bytes().map_err(|e| e.to_string())
+        })
+    }
+}
+
+/// 同期のドライラン結果（"dry_run": true）
+#[derive(Serialize)]
+pub struct DryRunResponse {
+    /// 月ごとの変更内容
+    pub months: Vec<db::SyncChangeSet>,
+}
+
+/// 同期のドライラン（ブロッキング）: 月ごとに タイムカード取得 → ドライバー絞り込み → Docker DBの既存行と比較
+/// 同期（sync）と同じく、計算結果にない拘束時間の行の削除も含める
+fn build_dry_run(state: &AppState, req: &PdfRequest, range: MonthRange) -> Result<DryRunResponse, ApiError> {
+    let mut months = Vec::new();
+    for (year, month) in range.months() {
+        let (db, mut timecards) = load_timecards(state, &req.driver_query(year, month))?;
+        if let Some(driver_id) = req.driver_id {
+            timecards.retain(|tc| tc.driver.id == driver_id);
+        }
+        let changes = db.sync_to_docker_dry_run(year, month, &timecards, true).map_err(|e| {
+            state.stats.record_db_error();
+            ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to compare with Docker DB: {}", e))
+        })?;
+        months.push(changes);
+    }
+    Ok(DryRunResponse { months })
+}
+
+/// PDF生成（POST/GET共通）
+async fn pdf_handler(state: Arc<AppState>, req: PdfRequest, layout: PdfLayout, refresh: bool) -> Response {
+    let task = match PdfTask::new(&state, req, layout) {
+        Ok(t) => t,
+        Err(e) => return e.into_response(),
     };
+    // ドライランはPDFを作らず変更内容を返す（キャッシュは使わない）
+    if task.req.dry_run {
+        let permit = match acquire_pdf_permit(&state, &task.filename).await {
+            Ok(p) => p,
+            Err(res) => return res,
+        };
+        let PdfTask { req, range, .. } = task;
+        return run_blocking(move || {
+            let _permit = permit;
+            build_dry_run(&state, &req, range)
+        })
+            .await
+            .map(|res| Json(res).into_response())
+            .unwrap_or_else(|e| e.into_response());
+    }
+    let (filename, content_type) = (task.filename.clone(), task.content_type);
 
-    // タイムカードを取得
-    let mut timecards = match db.get_all_monthly_timecards_with_kiso(req.year, req.month) {
-        Ok(tc) => tc,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: format!("Failed to get timecards: {}", e) }),
-            ).into_response();
+    // 同じ内容のPDFはキャッシュから返す（?refresh=1 なら作り直す）
+    let cache_key = if state.pdf_cache.enabled() { task.cache_key() } else { None };
+    if let Some(key) = cache_key.as_deref().filter(|_| !refresh) {
+        let cached = state.pdf_cache.get(key);
+        state.stats.record_cache(cached.is_some());
+        if let Some(bytes) = cached {
+            return pdf_response(Ok((bytes.to_vec(), SyncStatus::Skipped)), &filename, content_type, CacheStatus::Hit);
         }
+    }
+    let cache = if cache_key.is_some() { CacheStatus::Miss } else { CacheStatus::Bypass };
+
+    // キャッシュにない時だけ実行枠を使う（クライアントが切断しても生成が終わるまで枠を返さない）
+    let permit = match acquire_pdf_permit(&state, &filename).await {
+        Ok(p) => p,
+        Err(res) => return res,
     };
+    let result = run_blocking(move || {
+        let _permit = permit;
+        // クライアントが切断しても終了時は生成が終わるのを待つ
+        let _work = state.stats.work_started(format!("pdf {}", task.filename));
+        let built = task.run(&state, &mut |_| {})?;
+        if let Some(key) = cache_key {
+            state.pdf_cache.insert(&key, built.0.clone());
+        }
+        Ok(built)
+    }).await;
+    pdf_response(result, &filename, content_type, cache)
+}
 
-    // 特定ドライバーのみにフィルタリング
-    if let Some(driver_id) = req.driver_id {
-        timecards.retain(|tc| tc.driver.id == driver_id);
+/// PDF生成の実行枠を待つ（queue_timeout を過ぎたら429 + Retry-After、待ち時間はログに出す）
+async fn acquire_pdf_permit(state: &AppState, filename: &str) -> Result<OwnedSemaphorePermit, Response> {
+    let limit = &state.pdf_limit;
+    let started = Instant::now();
+    let acquired = {
+        let _queued = state.stats.queue_entered();
+        tokio::time::timeout(limit.queue_timeout, limit.semaphore.clone().acquire_owned()).await
+    };
+    let waited = started.elapsed().as_millis();
+    match acquired {
+        Ok(Ok(permit)) => {
+            info!("[pdf] {} queue_wait={}ms", filename, waited);
+            Ok(permit)
+        }
+        _ => {
+            warn!("[pdf] {} queue_wait={}ms rejected", filename, waited);
+            let retry_after = limit.retry_after_secs();
+            Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                [("retry-after", retry_after.to_string())],
+                Json(ErrorResponse { error: format!("Too many PDF requests, retry after {} seconds", retry_after) }),
+            ).into_response())
+        }
     }
+}
 
-    if timecards.is_empty() {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse { error: "No timecards found".to_string() }),
-        ).into_response();
+/// クエリパラメータの不足・不正は400（JSON）
+fn pdf_query(query: Result<Query<PdfQuery>, QueryRejection>) -> Result<PdfRequest, ApiError> {
+    query
+        .map(|Query(q)| q.into())
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("Invalid query: {}", e.body_text())))
+}
+
+/// PDF生成ジョブの登録（検証だけしてすぐに job_id を返し、生成はバックグラウンドで行う）
+async fn submit_pdf_job(
+    State(state): State<Arc<AppState>>,
+    ApiJson(req): ApiJson<JobRequest>,
+) -> Response {
+    if req.pdf.dry_run {
+        return ApiError(StatusCode::BAD_REQUEST, "dry_run is not supported for jobs, use POST /api/pdf".to_string()).into_response();
     }
+    let task = match PdfTask::new(&state, req.pdf, req.layout) {
+        Ok(t) => t,
+        Err(e) => return e.into_response(),
+    };
+    let (job_id, progress) = state.pdf_jobs.create();
+    info!("[job] {} submitted: {}", job_id, task.filename);
+    tokio::spawn(run_pdf_job(state, job_id.clone(), task, progress));
+    (StatusCode::ACCEPTED, Json(JobCreatedResponse { job_id })).into_response()
+}
 
-    // 書き込み用DBに接続してallowanceをINSERT
-    if let Ok(write_db) = TimecardDb::connect(&state.write_db_config) {
-        let _ = write_db.insert_all_timecard_allowances_to_docker(&timecards);
-        let _ = write_db.insert_kosoku_to_docker(&timecards);
+/// ジョブの実行（実行枠は429にせず空くまで待つ、待っている間は pending）
+async fn run_pdf_job(state: Arc<AppState>, job_id: String, task: PdfTask, progress: Arc<JobProgress>) {
+    let _work = state.stats.work_started(format!("job {} {}", job_id, task.filename));
+    let permit = {
+        let _queued = state.stats.queue_entered();
+        state.pdf_limit.semaphore.clone().acquire_owned().await
+    };
+    state.pdf_jobs.start(&job_id);
+    let (filename, content_type) = (task.filename.clone(), task.content_type);
+    let worker = state.clone();
+    let result = run_blocking(move || {
+        let _permit = permit;
+        task.run(&worker, &mut |p| match p {
+            FetchProgress::Drivers { total } => progress.add_total(total),
+            FetchProgress::Driver { .. } => progress.inc(),
+        })
+    }).await;
+    match &result {
+        Ok(_) => info!("[job] {} done: {}", job_id, filename),
+        Err(e) => error!("[job] {} failed: {}", job_id, e.1),
     }
+    let output = result
+        .map(|(bytes, _)| JobOutput { bytes, filename, content_type })
+        .map_err(|e| e.1);
+    state.pdf_jobs.finish(&job_id, output);
+}
 
-    // PDF生成
-    let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
-    pdf.render_timecards(&timecards);
+/// PDF生成ジョブの状態
+async fn get_job(State(state): State<Arc<AppState>>, Path(job_id): Path<String>) -> Response {
+    let Some(info) = state.pdf_jobs.get(&job_id) else {
+        return ApiError(StatusCode::NOT_FOUND, format!("Job not found: {}", job_id)).into_response();
+    };
+    let download = (info.state == JobState::Done).then(|| format!("/api/jobs/{}/download", job_id));
+    Json(JobStatusResponse {
+        job_id,
+        status: info.state,
+        drivers_done: info.progress.0,
+        drivers_total: info.progress.1,
+        error: info.error,
+        download,
+    }).into_response()
+}
 
-    // PDFをメモリ上で生成
-    match pdf.save_to_bytes() {
-        Ok(bytes) => {
-            (
-                StatusCode::OK,
-                [
-                    ("content-type", "application/pdf"),
-                    ("content-disposition", "attachment; filename=\"timecard.pdf\""),
-                ],
-                bytes,
-            ).into_response()
-        }
-        Err(e) => {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: format!("PDF generation failed: {}", e) }),
-            ).into_response()
+/// 完了したジョブのPDF（ZIP）をダウンロード（終わっていなければ409）
+async fn download_job(State(state): State<Arc<AppState>>, Path(job_id): Path<String>) -> Response {
+    let Some(info) = state.pdf_jobs.get(&job_id) else {
+        return ApiError(StatusCode::NOT_FOUND, format!("Job not found: {}", job_id)).into_response();
+    };
+    match info.output {
+        Some(output) => (
+            StatusCode::OK,
+            [
+                ("content-type", output.content_type.to_string()),
+                ("content-disposition", format!("attachment; filename=\"{}\"", output.filename)),
+            ],
+            output.bytes.clone(),
+        ).into_response(),
+        None => {
+            let message = match info.error {
+                Some(e) => format!("Job failed: {}", e),
+                None => format!("Job is not finished: {}", info.state.as_str()),
+            };
+            ApiError(StatusCode::CONFLICT, message).into_response()
         }
     }
 }
 
+/// PDF生成（3人/ページ）
+async fn generate_pdf(
+    State(state): State<Arc<AppState>>,
+    refresh: Option<Query<RefreshParam>>,
+    ApiJson(req): ApiJson<PdfRequest>,
+) -> Response {
+    pdf_handler(state, req, PdfLayout::Timecard, RefreshParam::requested(refresh)).await
+}
+
+/// PDF生成（1人1ファイルのZIP、ファイル名は timecard_YYYY_MM_<driver_id>_<氏名>.pdf）
+async fn generate_pdf_zip(
+    State(state): State<Arc<AppState>>,
+    refresh: Option<Query<RefreshParam>>,
+    ApiJson(req): ApiJson<PdfRequest>,
+) -> Response {
+    pdf_handler(state, req, PdfLayout::Zip, RefreshParam::requested(refresh)).await
+}
+
 /// PDF生成（集計モード: 1人/ページ）
 async fn generate_pdf_shukei(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<PdfRequest>,
+    refresh: Option<Query<RefreshParam>>,
+    ApiJson(req): ApiJson<PdfRequest>,
 ) -> Response {
-    // 読み取り用DBに接続
-    let db = match TimecardDb::connect(&state.read_db_config) {
-        Ok(db) => db,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: format!("DB connection failed: {}", e) }),
-            ).into_response();
+    pdf_handler(state, req, PdfLayout::Shukei, RefreshParam::requested(refresh)).await
+}
+
+/// PDF生成（GET: 3人/ページ）
+async fn generate_pdf_get(
+    State(state): State<Arc<AppState>>,
+    query: Result<Query<PdfQuery>, QueryRejection>,
+    refresh: Option<Query<RefreshParam>>,
+) -> Response {
+    match pdf_query(query) {
+        Ok(req) => pdf_handler(state, req, PdfLayout::Timecard, RefreshParam::requested(refresh)).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// PDF生成（GET: 集計モード）
+async fn generate_pdf_shukei_get(
+    State(state): State<Arc<AppState>>,
+    query: Result<Query<PdfQuery>, QueryRejection>,
+    refresh: Option<Query<RefreshParam>>,
+) -> Response {
+    match pdf_query(query) {
+        Ok(req) => pdf_handler(state, req, PdfLayout::Shukei, RefreshParam::requested(refresh)).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// PDFキャッシュを全て削除（DBを直した後などに使う）
+async fn clear_cache(State(state): State<Arc<AppState>>) -> Json<CacheClearResponse> {
+    Json(CacheClearResponse { cleared: state.pdf_cache.clear(), lookups_cleared: state.lookup_cache.clear() })
+}
+
+/// タイムカードデータ（JSON、PDFを作らずにプレビュー表示する用）
+/// driver_id 指定時はそのドライバーのオブジェクト、省略時は全アクティブドライバーの配列
+async fn get_timecards(
+    State(state): State<Arc<AppState>>,
+    query: Result<Query<PdfQuery>, QueryRejection>,
+) -> Response {
+    let (req, (year, month)) = match pdf_query(query).and_then(|req| req.validate().map(|range| (req, range.from))) {
+        Ok(target) => target,
+        Err(e) => return e.into_response(),
+    };
+
+    let result = run_blocking(move || {
+        let (_, timecards) = load_timecards(&state, &req.driver_query(year, month))?;
+        match req.driver_id {
+            Some(driver_id) => timecards
+                .into_iter()
+                .find(|tc| tc.driver.id == driver_id)
+                .map(|tc| Json(tc).into_response())
+                .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, "No timecards found".to_string())),
+            None => Ok(Json(timecards).into_response()),
         }
+    }).await;
+    result.unwrap_or_else(|e| e.into_response())
+}
+
+/// 1日分の残業の内訳（GET /api/timecards/:driver_id/:date/zangyo）
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ZangyoBreakdown {
+    /// ドライバーID
+    pub driver_id: i32,
+    /// YYYY-MM-DD
+    pub date: String,
+    /// 表示している残業時間（元の行の合計）
+    pub zangyo: Option<f64>,
+    /// 追加作業の件数
+    pub tsuika_count: i32,
+    /// 元の行（ryohi_rows / time_card_zangyo の行IDと時間）
+    pub sources: Vec<ZangyoSource>,
+}
+
+/// タイムカードから1人・1日分の残業の内訳を取り出す（ドライバーがいなければNone）
+fn zangyo_breakdown(timecards: Vec<MonthlyTimecard>, driver_id: i32, date: NaiveDate) -> Option<ZangyoBreakdown> {
+    let timecard = timecards.into_iter().find(|tc| tc.driver.id == driver_id)?;
+    let day = timecard.days.into_iter().find(|d| d.day as u32 == date.day())?;
+    Some(ZangyoBreakdown {
+        driver_id,
+        date: date.format("%Y-%m-%d").to_string(),
+        zangyo: day.zangyo,
+        tsuika_count: day.tsuika_count,
+        sources: day.zangyo_sources,
+    })
+}
+
+/// 1日分の残業の内訳（PDFの残業が何の行から来たかの確認用）
+async fn get_day_zangyo(
+    State(state): State<Arc<AppState>>,
+    Path((driver_id, date)): Path<(String, String)>,
+) -> Response {
+    let driver_id = match driver_id.parse::<i32>() {
+        Ok(id) if id > 0 => id,
+        _ => return ApiError(StatusCode::BAD_REQUEST, format!("driver_id must be > 0: {}", driver_id)).into_response(),
+    };
+    let date = match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+        Ok(date) => date,
+        Err(_) => return ApiError(StatusCode::BAD_REQUEST, format!("date must be YYYY-MM-DD: {}", date)).into_response(),
+    };
+    if let Err(e) = validate_year_month(date.year(), date.month()) {
+        return e.into_response();
+    }
+
+    let result = run_blocking(move || {
+        let query = DriverQuery::new(date.year(), date.month()).include_officers(true).driver_ids(Some(vec![driver_id]));
+        let (_, timecards) = load_timecards(&state, &query)?;
+        zangyo_breakdown(timecards, driver_id, date)
+            .map(|breakdown| Json(breakdown).into_response())
+            .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, "No timecards found".to_string()))
+    }).await;
+    result.unwrap_or_else(|e| e.into_response())
+}
+
+/// 日別のタイムカードの分析用CSV（列は export::EXPORT_COLUMNS、UTF-8）
+async fn get_csv(
+    State(state): State<Arc<AppState>>,
+    query: Result<Query<CsvQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(q)) => q,
+        Err(e) => return ApiError(StatusCode::BAD_REQUEST, format!("Invalid query: {}", e.body_text())).into_response(),
+    };
+    if let Err(e) = validate_year_month(query.year, query.month).and_then(|_| validate_driver_id(query.driver_id)) {
+        return e.into_response();
+    }
+    let bom = matches!(query.bom.as_deref(), Some("1" | "true"));
+    let filename = match query.driver_id {
+        Some(id) => format!("timecard_{}_{:02}_{}.csv", query.year, query.month, id),
+        None => format!("timecard_{}_{:02}.csv", query.year, query.month),
     };
 
-    // タイムカードを取得
-    let all_timecards = match db.get_all_monthly_timecards_with_kiso(req.year, req.month) {
-        Ok(tc) => tc,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: format!("Failed to get timecards: {}", e) }),
-            ).into_response();
+    let result = run_blocking(move || {
+        let (_, mut timecards) = load_timecards(&state, &DriverQuery::new(query.year, query.month))?;
+        if let Some(driver_id) = query.driver_id {
+            timecards.retain(|tc| tc.driver.id == driver_id);
         }
+        Ok(export::to_csv(&timecards, bom))
+    }).await;
+
+    match result {
+        Ok(csv) => (
+            StatusCode::OK,
+            [
+                ("content-type", "text/csv; charset=utf-8".to_string()),
+                ("content-disposition", format!("attachment; filename=\"{}\"", filename)),
+            ],
+            csv,
+        ).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// 対象月のアクティブドライバー一覧（画面のドロップダウン用）
+async fn get_drivers(
+    State(state): State<Arc<AppState>>,
+    query: Result<Query<DriversQuery>, QueryRejection>,
+) -> Response {
+    let query = match query {
+        Ok(Query(q)) => q,
+        Err(e) => return ApiError(StatusCode::BAD_REQUEST, format!("Invalid query: {}", e.body_text())).into_response(),
     };
+    if let Err(e) = validate_year_month(query.year, query.month) {
+        return e.into_response();
+    }
 
-    // 特定ドライバーのみにフィルタリング
-    let timecards: Vec<_> = if let Some(driver_id) = req.driver_id {
-        all_timecards.into_iter().filter(|tc| tc.driver.id == driver_id).collect()
-    } else {
-        all_timecards
+    // キャッシュにあればDBに接続しない
+    let result = run_blocking(move || {
+        let driver_query = DriverQuery::new(query.year, query.month).bumon(query.bumon);
+        state.lookup_cache.drivers(&driver_query, || {
+            let db = TimecardDb::from_pools(&state.read_db, &state.write_db).map_err(|e| {
+                state.stats.record_db_error();
+                ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("DB connection failed: {}", e))
+            })?.with_metrics(state.metrics.clone());
+            db.get_active_drivers(&driver_query).map_err(|e| {
+                state.stats.record_db_error();
+                ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get drivers: {}", e))
+            })
+        })
+    }).await;
+
+    match result {
+        Ok(drivers) => Json(DriversResponse {
+            count: drivers.len(),
+            drivers: drivers.into_iter().map(DriverEntry::from).collect(),
+        }).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// 残業異常レポート（全ドライバー、複数ソース・上限超過の日）
+async fn report_zangyo(
+    State(state): State<Arc<AppState>>,
+    ApiJson(req): ApiJson<ZangyoReportRequest>,
+) -> Response {
+    if let Err(e) = validate_year_month(req.year, req.month) {
+        return e.into_response();
+    }
+    let format = req.format.clone().unwrap_or_else(|| "json".to_string());
+    if format != "csv" && format != "json" {
+        return ApiError(StatusCode::BAD_REQUEST, format!("Unsupported format: {}", format)).into_response();
+    }
+
+    let result = run_blocking(move || {
+        let (_, timecards) = load_timecards(&state, &DriverQuery::new(req.year, req.month))?;
+        Ok(zangyo_report::find_anomalies(&timecards, &ZangyoReportConfig::from_env()))
+    }).await;
+    let anomalies = match result {
+        Ok(a) => a,
+        Err(e) => return e.into_response(),
     };
 
-    if timecards.is_empty() {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse { error: "No timecards found".to_string() }),
-        ).into_response();
+    if format == "csv" {
+        (
+            StatusCode::OK,
+            [
+                ("content-type", "text/csv; charset=utf-8"),
+                ("content-disposition", "attachment; filename=\"zangyo_anomalies.csv\""),
+            ],
+            zangyo_report::to_csv(&anomalies),
+        ).into_response()
+    } else {
+        (StatusCode::OK, Json(anomalies)).into_response()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
 
-    // 書き込み用DBに接続してallowanceをINSERT
-    if let Ok(write_db) = TimecardDb::connect(&state.write_db_config) {
-        let _ = write_db.insert_all_timecard_allowances_to_docker(&timecards);
-        let _ = write_db.insert_kosoku_to_docker(&timecards);
+    /// テスト用の状態（環境変数を参照せず、フラグファイルのみで切り替え）
+    fn test_state(flag_file: &std::path::Path) -> AppState {
+        let db = DbConfig {
+            host: "127.0.0.1".to_string(),
+            port: 1,
+            user: "test".to_string(),
+            password: String::new(),
+            database: "test".to_string(),
+            prefix: "TEST",
+            socket: None,
+            charset: None,
+            ssl: db::SslConfig::default(),
+            timeouts: Default::default(),
+            read_only: false,
+            env_error: None,
+        };
+        AppState {
+            read_db: LazyPool::new(db.clone()),
+            write_db: LazyPool::new(db),
+            maintenance: MaintenanceConfig {
+                flag_file: Some(flag_file.to_path_buf()),
+                use_env: false,
+            },
+            stats: Arc::new(ServerStats::default()),
+            render_base: RenderOverrides::default(),
+            health_timeout: Duration::from_millis(200),
+            fetch_jobs: 1,
+            pdf_cache: Arc::new(PdfCache::new(Duration::from_secs(60), 1024 * 1024, None)),
+            pdf_limit: PdfLimit::new(1, Duration::from_millis(100)),
+            pdf_jobs: Arc::new(JobStore::new(Duration::from_secs(60))),
+            metrics: Some(Arc::new(Metrics::default())),
+            lookup_cache: Arc::new(LookupCache::new(Duration::from_secs(60))),
+        }
     }
 
-    // PDF生成（集計モード）
-    let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
-    pdf.render_timecards_shukei(&timecards);
+    fn temp_flag_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("timecard_maintenance_{}_{}", name, std::process::id()))
+    }
 
-    // PDFをメモリ上で生成
-    match pdf.save_to_bytes() {
-        Ok(bytes) => {
-            (
-                StatusCode::OK,
-                [
-                    ("content-type", "application/pdf"),
-                    ("content-disposition", "attachment; filename=\"timecard_shukei.pdf\""),
-                ],
-                bytes,
-            ).into_response()
+    async fn body_json(res: Response) -> serde_json::Value {
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api_returns_503_during_maintenance() {
+        let flag = temp_flag_path("api");
+        std::fs::write(&flag, r#"{"message": "月次DBメンテナンス中", "until": "2025-12-01T06:00:00+09:00"}"#).unwrap();
+
+        let res = router(test_state(&flag))
+            .oneshot(
+                Request::post("/api/pdf")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"year": 2025, "month": 12}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        std::fs::remove_file(&flag).unwrap();
+
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = body_json(res).await;
+        assert_eq!(body["error"], "maintenance");
+        assert_eq!(body["message"], "月次DBメンテナンス中");
+        assert_eq!(body["until"], "2025-12-01T06:00:00+09:00");
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_maintenance_flag() {
+        let flag = temp_flag_path("health");
+        let app = router(test_state(&flag));
+
+        // フラグファイルなし → maintenance: false（テストのDBには接続できないので503）
+        let res = app.clone().oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body_json(res).await["maintenance"], false);
+
+        // 再起動せずにフラグファイルを置くと maintenance: true
+        std::fs::write(&flag, "").unwrap();
+        let res = app.oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
+        std::fs::remove_file(&flag).unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body_json(res).await["maintenance"], true);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_counts_requests_per_route() {
+        let flag = temp_flag_path("metrics");
+        let state = test_state(&flag);
+        let stats = state.stats.clone();
+        let app = router(state);
+
+        for _ in 0..2 {
+            app.clone().oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
         }
-        Err(e) => {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse { error: format!("PDF generation failed: {}", e) }),
-            ).into_response()
+        app.clone().oneshot(Request::get("/nope").body(Body::empty()).unwrap()).await.unwrap();
+
+        let snapshot = stats.rotate();
+        assert_eq!(snapshot.requests.get("/health"), Some(&2));
+        assert_eq!(snapshot.requests.get("unmatched"), Some(&1));
+        assert_eq!(snapshot.in_flight, 0);
+
+        let res = app.oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+        let text = String::from_utf8(to_bytes(res.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert!(text.contains("timecard_requests_last_interval{route=\"/health\"} 2"));
+    }
+
+    #[test]
+    fn test_parse_bind_addr() {
+        assert_eq!(parse_bind_addr("8080").unwrap(), "0.0.0.0:8080".parse::<SocketAddr>().unwrap());
+        assert_eq!(parse_bind_addr("127.0.0.1:9000").unwrap(), "127.0.0.1:9000".parse::<SocketAddr>().unwrap());
+        assert_eq!(parse_bind_addr("[::1]:443").unwrap(), "[::1]:443".parse::<SocketAddr>().unwrap());
+        assert!(parse_bind_addr("http").is_err());
+        assert_eq!(bind_addr(Some("10.0.0.1:80".parse().unwrap())).unwrap(), "10.0.0.1:80".parse::<SocketAddr>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_serve_tls() {
+        use rustls::pki_types::pem::PemObject;
+        use std::io::{Read, Write};
+
+        let flag = temp_flag_path("tls");
+        let files = crate::tls::TlsFiles { cert: "fixtures/tls/localhost.crt".into(), key: "fixtures/tls/localhost.key".into() };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        // PDF並みの大きさのレスポンスも返せる
+        let app = router(test_state(&flag)).route("/big", get(|| async { "x".repeat(4 << 20) }));
+        let server = tokio::spawn(serve_tls(listener, app, files.load().unwrap(), async {
+            shutdown_rx.await.ok();
+        }));
+
+        // 証明書を信頼するクライアントから /live と /big
+        let responses = tokio::task::spawn_blocking(move || {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in rustls::pki_types::CertificateDer::pem_file_iter(&files.cert).unwrap() {
+                roots.add(cert.unwrap()).unwrap();
+            }
+            let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let config = Arc::new(config);
+            ["/live", "/big"].map(|path| {
+                let conn = rustls::ClientConnection::new(config.clone(), "localhost".try_into().unwrap()).unwrap();
+                let mut tls = rustls::StreamOwned::new(conn, std::net::TcpStream::connect(addr).unwrap());
+                write!(tls, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+                let mut response = String::new();
+                tls.read_to_string(&mut response).unwrap();
+                response
+            })
+        })
+        .await
+        .unwrap();
+        assert!(responses[0].starts_with("HTTP/1.1 200 OK"), "{}", responses[0]);
+        assert!(responses[0].contains(r#""status":"OK""#), "{}", responses[0]);
+        assert!(responses[1].ends_with(&"x".repeat(4 << 20)));
+
+        // 平文のHTTPはハンドシェイクで失敗して切断される（サーバーは止まらない）
+        let mut plain = tokio::net::TcpStream::connect(addr).await.unwrap();
+        tokio::io::AsyncWriteExt::write_all(&mut plain, b"GET /live HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut buf = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut plain, &mut buf).await.ok();
+        assert!(!String::from_utf8_lossy(&buf).contains("200 OK"));
+
+        shutdown_tx.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_pdf_requests() {
+        let flag = temp_flag_path("pdf_metrics");
+        let app = router(test_state(&flag));
+        let scrape = |app: Router| async move {
+            let res = app.oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+            String::from_utf8(to_bytes(res.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap()
+        };
+        assert!(!scrape(app.clone()).await.contains("pdf_requests_total{"));
+
+        // DBに接続できない生成は500、クエリ不正は400（どちらもPDF系として数える）
+        let req = Request::post("/api/pdf").header("content-type", "application/json").body(Body::from(r#"{"year": 2026, "month": 1}"#)).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let res = app.clone().oneshot(Request::get("/api/pdf-shukei?year=2026&month=13").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        app.clone().oneshot(Request::get("/api/drivers?year=2026&month=13").body(Body::empty()).unwrap()).await.unwrap();
+
+        let text = scrape(app).await;
+        assert!(text.contains("pdf_requests_total{endpoint=\"/api/pdf\",status=\"500\"} 1\n"), "{}", text);
+        assert!(text.contains("pdf_requests_total{endpoint=\"/api/pdf-shukei\",status=\"400\"} 1\n"));
+        assert!(!text.contains("endpoint=\"/api/drivers\""));
+        assert!(text.contains("timecards_rendered_total 0\n"));
+
+        // METRICS_ENABLED=0 なら /metrics はない
+        let mut state = test_state(&flag);
+        state.metrics = None;
+        let res = router(state).oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_pdf_request_rejects_unknown_render_option() {
+        let flag = temp_flag_path("render");
+        let app = router(test_state(&flag));
+
+        // 未知のキーは無視せず400（JSON）
+        let res = app.clone()
+            .oneshot(
+                Request::post("/api/pdf")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"year": 2025, "month": 12, "render": {"monochrom": true}}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert!(body_json(res).await["error"].as_str().unwrap().contains("monochrom"));
+
+        // 値が不正なら400（DBに接続する前に返す）
+        let res = app.clone()
+            .oneshot(
+                Request::post("/api/pdf-shukei")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"year": 2025, "month": 12, "render": {"punch_symbols": "[][]"}}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // パスワードが不正（全角）なら400
+        let res = app
+            .oneshot(
+                Request::post("/api/pdf-zip")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"year": 2025, "month": 12, "password": "パスワード"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_live_responds_during_slow_pdf_request() {
+        use std::time::Instant;
+
+        // 接続を受け付けて1秒間応答しないDB（PDFリクエストはDB接続で止まる）
+        let silent_db = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = silent_db.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let conn = silent_db.accept();
+            std::thread::sleep(Duration::from_secs(1));
+            drop(conn);
+        });
+
+        let flag = temp_flag_path("slow");
+        let mut state = test_state(&flag);
+        state.read_db = LazyPool::new(DbConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            user: "test".to_string(),
+            password: String::new(),
+            database: "test".to_string(),
+            prefix: "TEST",
+            socket: None,
+            charset: None,
+            ssl: db::SslConfig::default(),
+            timeouts: Default::default(),
+            read_only: false,
+            env_error: None,
+        });
+        let app = router(state);
+
+        let started = Instant::now();
+        let slow = tokio::spawn(app.clone().oneshot(
+            Request::post("/api/pdf")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"year": 2025, "month": 12}"#))
+                .unwrap(),
+        ));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let res = tokio::time::timeout(
+            Duration::from_millis(100),
+            app.oneshot(Request::get("/live").body(Body::empty()).unwrap()),
+        )
+        .await
+        .expect("/live did not answer within 100ms")
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        // PDFリクエストがランタイムを止めていれば、ここまでに1秒かかる
+        assert!(started.elapsed() < Duration::from_millis(500), "{:?}", started.elapsed());
+
+        // DBが切断するとPDFリクエストはErrorResponseのJSONで終わる
+        let res = slow.await.unwrap().unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body_json(res).await["error"].as_str().unwrap().starts_with("DB connection failed"));
+    }
+
+    #[tokio::test]
+    async fn test_drivers_served_from_lookup_cache() {
+        let state = test_state(&temp_flag_path("lookup"));
+        let driver = Driver { id: 1523, name: "テスト".to_string(), bumon: None, category_c: Some(2), eigyosho_c: Some(1), kyuyo_shain_id: None };
+        state.lookup_cache.drivers(&DriverQuery::new(2025, 12).bumon(None), || Ok::<_, String>(vec![driver])).unwrap();
+        let app = router(state);
+        let get = || Request::get("/api/drivers?year=2025&month=12").body(Body::empty()).unwrap();
+
+        // キャッシュにあればDBに接続しない（テストのDBには接続できない）
+        let res = app.clone().oneshot(get()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_json(res).await["count"], 1);
+        let metrics = to_bytes(app.clone().oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap().into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&metrics).contains("timecard_lookup_cache_hits_total 1\n"));
+
+        // DELETE /api/cache で消すとDBに問い合わせる
+        let res = app.clone().oneshot(Request::delete("/api/cache").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(body_json(res).await["lookups_cleared"], 1);
+        let res = app.oneshot(get()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_pdf_cache_hit_refresh_and_clear() {
+        let state = test_state(&temp_flag_path("cache"));
+        let req: PdfRequest = PdfQuery { year: 2026, month: 1, driver_id: Some(1523), sync: false }.into();
+        let options = resolve_render_options(&state, &req).unwrap();
+        let key = req.cache_key(PdfLayout::Timecard, &req.months().unwrap(), &options).unwrap();
+        state.pdf_cache.insert(&key, b"%PDF-cached".to_vec());
+        let cache = state.pdf_cache.clone();
+        let app = router(state);
+
+        // キャッシュにあればDBに接続せずに返す
+        let res = app.clone().oneshot(Request::get("/api/pdf?year=2026&month=1&driver_id=1523").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["x-cache"], "HIT");
+        assert_eq!(&to_bytes(res.into_body(), usize::MAX).await.unwrap()[..], b"%PDF-cached");
+
+        // refresh=1 は作り直す（テストのDBには接続できない）
+        let res = app.clone().oneshot(Request::get("/api/pdf?year=2026&month=1&driver_id=1523&refresh=1").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        // 同期・パスワード付きはキャッシュしない
+        let sync: PdfRequest = serde_json::from_str(r#"{"year":2026,"month":1,"sync":true}"#).unwrap();
+        assert!(sync.cache_key(PdfLayout::Timecard, &sync.months().unwrap(), &options).is_none());
+
+        let res = app.oneshot(Request::delete("/api/cache").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(body_json(res).await["cleared"], 1);
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pdf_concurrency_limit_returns_429() {
+        let flag = temp_flag_path("limit");
+        let state = test_state(&flag);
+        let semaphore = state.pdf_limit.semaphore.clone();
+        let stats = state.stats.clone();
+        let app = router(state);
+
+        // 実行枠が埋まっている間は待ってから429（Retry-After付き）
+        let _held = semaphore.acquire_owned().await.unwrap();
+        let started = Instant::now();
+        let res = app.clone().oneshot(Request::get("/api/pdf?year=2026&month=1").body(Body::empty()).unwrap()).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()["retry-after"], "1");
+        assert!(body_json(res).await["error"].is_string());
+        assert_eq!(stats.rotate().queue_len, 0);
+
+        // 検証エラーは枠を待たずに400、PDF以外は制限しない
+        let res = app.clone().oneshot(Request::get("/api/pdf-shukei?year=2026&month=13").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = app.oneshot(Request::get("/live").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_pdf_job_failure_is_reported() {
+        let flag = temp_flag_path("jobs");
+        let app = router(test_state(&flag));
+        let post = |body: &'static str| {
+            Request::post("/api/jobs/pdf").header("content-type", "application/json").body(Body::from(body)).unwrap()
+        };
+
+        // 不正なリクエストはジョブにせず400
+        let res = app.clone().oneshot(post(r#"{"year": 2026, "month": 13}"#)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = app.clone().oneshot(post(r#"{"year": 2026, "month": 1, "layout": "a3"}"#)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        // ドライランはジョブにしない
+        let res = app.clone().oneshot(post(r#"{"year": 2026, "month": 1, "dry_run": true}"#)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // すぐに job_id を返し、DBに接続できなければ failed でエラーを残す
+        let res = app.clone().oneshot(post(r#"{"year": 2026, "month": 1, "layout": "shukei"}"#)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let job_id = body_json(res).await["job_id"].as_str().unwrap().to_string();
+        let mut status = serde_json::Value::Null;
+        for _ in 0..100 {
+            let res = app.clone().oneshot(Request::get(format!("/api/jobs/{}", job_id)).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            status = body_json(res).await;
+            if status["status"] == "failed" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(status["status"], "failed", "{}", status);
+        assert!(status["error"].as_str().unwrap().contains("DB connection failed"));
+        assert!(status.get("download").is_none());
+
+        let res = app.clone().oneshot(Request::get(format!("/api/jobs/{}/download", job_id)).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let res = app.oneshot(Request::get("/api/jobs/unknown").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_pdf_validates_query() {
+        let flag = temp_flag_path("get");
+        let app = router(test_state(&flag));
+
+        for uri in ["/api/pdf?year=2026", "/api/pdf?year=2026&month=13", "/api/pdf-shukei?year=2026&month=x"] {
+            let res = app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", uri);
+            assert!(body_json(res).await["error"].is_string(), "{}", uri);
+        }
+
+        let req: PdfRequest = PdfQuery { year: 2026, month: 1, driver_id: Some(1523), sync: false }.into();
+        assert_eq!(req.filename("timecard", &req.months().unwrap()), "timecard_2026_01_1523.pdf");
+        let req: PdfRequest = PdfQuery { year: 2026, month: 1, driver_id: None, sync: false }.into();
+        assert_eq!(req.filename("timecard_shukei", &req.months().unwrap()), "timecard_shukei_2026_01.pdf");
+    }
+
+    #[test]
+    fn test_pdf_request_month_range() {
+        let req: PdfRequest = serde_json::from_str(r#"{"from":"2025-12","to":"2026-01"}"#).unwrap();
+        let range = req.months().unwrap();
+        assert_eq!(range.months(), vec![(2025, 12), (2026, 1)]);
+        assert_eq!(req.filename("timecard", &range), "timecard_2025-12_2026-01.pdf");
+
+        for body in [
+            r#"{"year":2026}"#,
+            r#"{"from":"2026-02","to":"2026-01"}"#,
+            r#"{"from":"2025-12"}"#,
+            r#"{"year":2026,"month":1,"from":"2025-12","to":"2026-01"}"#,
+            r#"{"from":"2025-13","to":"2026-01"}"#,
+        ] {
+            let req: PdfRequest = serde_json::from_str(body).unwrap();
+            assert_eq!(req.months().unwrap_err().0, StatusCode::BAD_REQUEST, "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_validates_fields_with_json_errors() {
+        let flag = temp_flag_path("validate");
+        let app = router(test_state(&flag));
+
+        // DBに接続する前に400、メッセージに不正な項目名を含める
+        for (uri, body, field) in [
+            ("/api/pdf", r#"{"year": 2026, "month": 0}"#, "month"),
+            ("/api/pdf-shukei", r#"{"year": 2026, "month": 13}"#, "month"),
+            ("/api/pdf-zip", r#"{"year": "2026", "month": 1}"#, "year"),
+            ("/api/pdf", r#"{"year": 2026, "month": 1, "driver_id": 0}"#, "driver_id"),
+            ("/api/report/zangyo", r#"{"year": 2026, "month": 13}"#, "month"),
+            ("/api/report/zangyo", r#"{"year": 1999, "month": 1}"#, "year"),
+            ("/api/pdf", r#"{"year": 2026, "month": 1"#, "JSON"),
+        ] {
+            let res = app.clone()
+                .oneshot(Request::post(uri).header("content-type", "application/json").body(Body::from(body)).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{} {}", uri, body);
+            let error = body_json(res).await["error"].as_str().unwrap().to_string();
+            assert!(error.contains(field), "{} {}: {}", uri, body, error);
+        }
+
+        // Content-Type がなくてもJSONのエラー
+        let res = app
+            .oneshot(Request::post("/api/pdf").body(Body::from(r#"{"year": 2026, "month": 1}"#)).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert!(body_json(res).await["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_get_timecards_validates_query() {
+        let flag = temp_flag_path("timecards");
+        let app = router(test_state(&flag));
+
+        for uri in ["/api/timecards?month=1", "/api/timecards?year=2026&month=0", "/api/csv?year=2026&month=13", "/api/csv?year=2026",
+            "/api/timecards?year=2026&month=1&driver_id=-1", "/api/csv?year=2026&month=1&driver_id=0",
+            "/api/timecards/0/2026-01-05/zangyo", "/api/timecards/x/2026-01-05/zangyo", "/api/timecards/1523/2026-01-32/zangyo",
+            "/api/timecards/1523/1999-12-01/zangyo"] {
+            let res = app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", uri);
+            assert!(body_json(res).await["error"].is_string(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test_zangyo_breakdown_lists_sources() {
+        use crate::timecard_data::{DayRecord, TimecardSummary, ZangyoTable};
+        let mut day = DayRecord::new(5, "月");
+        day.add_zangyo(ZangyoSource { table: ZangyoTable::RyohiRows, row_id: "R-42".to_string(), amount: 2.0 });
+        day.add_zangyo(ZangyoSource { table: ZangyoTable::TimeCardZangyo, row_id: "7".to_string(), amount: 0.5 });
+        let timecard = MonthlyTimecard {
+            driver: Driver { id: 1523, name: "テスト".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2026,
+            month: 1,
+            days: vec![DayRecord::new(4, "日"), day],
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        };
+        let date = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap();
+
+        let breakdown = zangyo_breakdown(vec![timecard.clone()], 1523, date).unwrap();
+        assert_eq!(breakdown.date, "2026-01-05");
+        assert_eq!(breakdown.zangyo, Some(2.5));
+        let json = serde_json::to_value(&breakdown).unwrap();
+        assert_eq!(json["sources"][0]["table"], "ryohi_rows");
+        assert_eq!(json["sources"][1]["row_id"], "7");
+        assert_eq!(json["sources"][1]["amount"], 0.5);
+
+        assert!(zangyo_breakdown(vec![timecard], 1071, date).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_drivers_validates_query() {
+        let flag = temp_flag_path("drivers");
+        let app = router(test_state(&flag));
+
+        for uri in ["/api/drivers?year=2026", "/api/drivers?year=26&month=1", "/api/drivers?year=2026&month=1&bumon=x"] {
+            let res = app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", uri);
+            assert!(body_json(res).await["error"].is_string(), "{}", uri);
         }
     }
+
+    #[test]
+    fn test_sync_defaults_to_false() {
+        let req: PdfRequest = serde_json::from_str(r#"{"year":2026,"month":1}"#).unwrap();
+        assert!(!req.sync);
+        let req: PdfRequest = serde_json::from_str(r#"{"year":2026,"month":1,"sync":true}"#).unwrap();
+        assert!(req.sync);
+
+        let uri: axum::http::Uri = "/api/pdf?year=2026&month=1".parse().unwrap();
+        assert!(!PdfRequest::from(Query::<PdfQuery>::try_from_uri(&uri).unwrap().0).sync);
+        let uri: axum::http::Uri = "/api/pdf?year=2026&month=1&sync=true".parse().unwrap();
+        assert!(PdfRequest::from(Query::<PdfQuery>::try_from_uri(&uri).unwrap().0).sync);
+    }
+
+    #[test]
+    fn test_watermark_request_field() {
+        let state = test_state(&temp_flag_path("watermark"));
+        let req: PdfRequest = serde_json::from_str(r#"{"year":2026,"month":1,"watermark":"控え","render":{"watermark":"複写"}}"#).unwrap();
+        let options = resolve_render_options(&state, &req).unwrap();
+        assert_eq!(options.watermark.unwrap().text, "控え");
+
+        let req: PdfRequest = serde_json::from_str(r#"{"year":2026,"month":1}"#).unwrap();
+        assert!(resolve_render_options(&state, &req).unwrap().watermark.is_none());
+    }
+
+    #[test]
+    fn test_kosoku_source_request_field() {
+        let state = test_state(&temp_flag_path("kosoku_source"));
+        let req: PdfRequest = serde_json::from_str(r#"{"year":2026,"month":1,"kosoku_source":"php-tcdc"}"#).unwrap();
+        assert_eq!(req.kosoku_source().unwrap(), KosokuSource::PhpTcDc);
+        let options = resolve_render_options(&state, &req).unwrap();
+        assert_eq!(options.kosoku_source_label, Some("拘束時間: TC_DC（PHP）"));
+        // 出典が違えば別のキャッシュ
+        let auto: PdfRequest = serde_json::from_str(r#"{"year":2026,"month":1}"#).unwrap();
+        let range = MonthRange::single(2026, 1);
+        assert_ne!(
+            req.cache_key(PdfLayout::Timecard, &range, &options),
+            auto.cache_key(PdfLayout::Timecard, &range, &resolve_render_options(&state, &auto).unwrap())
+        );
+
+        let req: PdfRequest = serde_json::from_str(r#"{"year":2026,"month":1,"kosoku_source":"php"}"#).unwrap();
+        let err = resolve_render_options(&state, &req).err().unwrap();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_failing_database() {
+        use std::time::Instant;
+
+        // Docker DB側は接続を受け付けて応答しない → timeout
+        let silent_db = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = silent_db.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let conn = silent_db.accept();
+            std::thread::sleep(Duration::from_secs(1));
+            drop(conn);
+        });
+
+        let flag = temp_flag_path("health_db");
+        let mut state = test_state(&flag);
+        state.write_db = LazyPool::new(DbConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            user: "test".to_string(),
+            password: String::new(),
+            database: "test".to_string(),
+            prefix: "TEST",
+            socket: None,
+            charset: None,
+            ssl: db::SslConfig::default(),
+            timeouts: Default::default(),
+            read_only: false,
+            env_error: None,
+        });
+        let app = router(state);
+
+        let started = Instant::now();
+        let res = app.clone().oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(800), "{:?}", started.elapsed());
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = body_json(res).await;
+        assert_eq!(body["prod"], "error");
+        assert_eq!(body["docker"], "timeout");
+
+        // /live はDBに関係なく200
+        let res = app.oneshot(Request::get("/live").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_json(res).await["status"], "OK");
+    }
 }
@@ -0,0 +1,178 @@
+use chrono::{Duration, NaiveTime};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// 始業・終業時刻
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShiftTimes {
+    /// 始業
+    pub start: NaiveTime,
+    /// 終業
+    pub end: NaiveTime,
+}
+
+impl ShiftTimes {
+    /// "HH:MM-HH:MM" を解析
+    fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.trim().split_once('-')?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+        (start < end).then_some(Self { start, end })
+    }
+}
+
+/// 打刻から遅刻・早退を判定するルール
+///   - SHIFT_START / SHIFT_END: 始業・終業（HH:MM、デフォルト08:00〜17:00）
+///   - SHIFT_GRACE_MINUTES: 始業から何分までの出社を遅刻にしないか（デフォルト0）
+///   - SHIFT_BY_CATEGORY: 給与区分ごとの始業・終業（例: `3=09:00-18:00,5=06:00-15:00`）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShiftRules {
+    /// 給与区分の指定がない場合の始業・終業
+    pub default: ShiftTimes,
+    /// 始業から何分までの出社を遅刻にしないか
+    pub grace_minutes: i64,
+    /// 給与区分ごとの始業・終業
+    pub by_category: HashMap<i32, ShiftTimes>,
+}
+
+impl Default for ShiftRules {
+    fn default() -> Self {
+        Self {
+            default: ShiftTimes {
+                start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            },
+            grace_minutes: 0,
+            by_category: HashMap::new(),
+        }
+    }
+}
+
+impl ShiftRules {
+    /// 環境変数から設定を読み込み（不正な値はデフォルトのまま）
+    pub fn from_env() -> Self {
+        let mut rules = Self::default();
+        let time = |name: &str, default: NaiveTime| {
+            env::var(name)
+                .ok()
+                .and_then(|v| NaiveTime::parse_from_str(v.trim(), "%H:%M").ok())
+                .unwrap_or(default)
+        };
+        let shift = ShiftTimes {
+            start: time("SHIFT_START", rules.default.start),
+            end: time("SHIFT_END", rules.default.end),
+        };
+        if shift.start < shift.end {
+            rules.default = shift;
+        } else {
+            warn!("SHIFT_START は SHIFT_END より前にしてください（デフォルトを使用）");
+        }
+        if let Some(grace) = env::var("SHIFT_GRACE_MINUTES").ok().and_then(|v| v.trim().parse::<i64>().ok()) {
+            rules.grace_minutes = grace.max(0);
+        }
+        if let Ok(value) = env::var("SHIFT_BY_CATEGORY") {
+            match Self::parse_by_category(&value) {
+                Ok(by_category) => rules.by_category = by_category,
+                Err(e) => warn!("SHIFT_BY_CATEGORY {}（区分別の設定なし）", e),
+            }
+        }
+        rules
+    }
+
+    /// "区分=HH:MM-HH:MM,..." を解析
+    fn parse_by_category(value: &str) -> Result<HashMap<i32, ShiftTimes>, String> {
+        value
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|entry| {
+                let (category, times) = entry.split_once('=').ok_or_else(|| format!("「{}」は 区分=HH:MM-HH:MM の形式にしてください", entry))?;
+                let category = category.trim().parse::<i32>().map_err(|_| format!("区分「{}」が数値ではありません", category))?;
+                let times = ShiftTimes::parse(times).ok_or_else(|| format!("「{}」の時刻が不正です", entry))?;
+                Ok((category, times))
+            })
+            .collect()
+    }
+
+    /// プロセス全体で共有する設定（初回に環境変数から読み込み）
+    pub fn global() -> &'static Self {
+        static RULES: OnceLock<ShiftRules> = OnceLock::new();
+        RULES.get_or_init(Self::from_env)
+    }
+
+    /// 給与区分の始業・終業（区分別の設定がなければデフォルト）
+    pub fn times_for(&self, category_c: Option<i32>) -> ShiftTimes {
+        category_c
+            .and_then(|c| self.by_category.get(&c))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// 最初の出社が始業＋猶予より遅いか
+    pub fn is_late(&self, shift: ShiftTimes, first_in: NaiveTime) -> bool {
+        first_in > shift.start + Duration::minutes(self.grace_minutes)
+    }
+
+    /// 最後の退社が終業より早いか
+    pub fn is_early(&self, shift: ShiftTimes, last_out: NaiveTime) -> bool {
+        last_out < shift.end
+    }
+}
+
+impl fmt::Display for ShiftRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "始業 {} 終業 {}（遅刻の猶予 {}分",
+            self.default.start.format("%H:%M"),
+            self.default.end.format("%H:%M"),
+            self.grace_minutes
+        )?;
+        let mut categories: Vec<_> = self.by_category.iter().collect();
+        categories.sort_by_key(|(c, _)| **c);
+        for (category, times) in categories {
+            write!(f, "、区分{} {}〜{}", category, times.start.format("%H:%M"), times.end.format("%H:%M"))?;
+        }
+        write!(f, "）")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_grace_period_boundary() {
+        let rules = ShiftRules { grace_minutes: 5, ..Default::default() };
+        let shift = rules.times_for(None);
+        assert!(!rules.is_late(shift, t("08:00")));
+        assert!(!rules.is_late(shift, t("08:05")));
+        assert!(rules.is_late(shift, t("08:06")));
+        assert!(rules.is_early(shift, t("16:59")));
+        assert!(!rules.is_early(shift, t("17:00")));
+    }
+
+    #[test]
+    fn test_by_category() {
+        let rules = ShiftRules {
+            by_category: ShiftRules::parse_by_category("3=09:00-18:00, 5=06:00-15:00").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(rules.times_for(Some(3)).start, t("09:00"));
+        assert_eq!(rules.times_for(Some(5)).end, t("15:00"));
+        assert_eq!(rules.times_for(Some(1)), rules.default);
+        assert_eq!(rules.times_for(None), rules.default);
+        assert!(rules.is_early(rules.times_for(Some(3)), t("17:30")));
+        assert!(!rules.is_early(rules.times_for(Some(5)), t("15:00")));
+
+        assert!(ShiftRules::parse_by_category("3=18:00-09:00").is_err());
+        assert!(ShiftRules::parse_by_category("x=09:00-18:00").is_err());
+        assert!(ShiftRules::parse_by_category("3").is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// サーバー統計（定期ログと/metrics用）
+/// リクエスト数は区間ごと（rotateでリセット）、実行中数などはその時点の値
+#[derive(Default)]
+pub struct ServerStats {
+    /// 現在の区間のルート別リクエスト数
+    requests: Mutex<BTreeMap<String, u64>>,
+    /// 処理中のリクエスト数
+    in_flight: AtomicI64,
+    /// PDF生成の同時実行制限の待ち行列長
+    queue_len: AtomicI64,
+    /// PDFキャッシュのヒット/ミス
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    /// 最後にDBエラーが発生した時刻
+    last_db_error: Mutex<Option<DateTime<Local>>>,
+    /// 直前の区間の集計（/metricsで返す）
+    last_snapshot: Mutex<StatsSnapshot>,
+    /// 処理中の作業（リクエスト・PDF生成、終了時に打ち切ったものをログに出す）
+    active_work: Mutex<BTreeMap<u64, String>>,
+    next_work_id: AtomicU64,
+}
+
+/// 1区間分の統計
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatsSnapshot {
+    /// エンドポイント別のリクエスト数
+    pub requests: BTreeMap<String, u64>,
+    /// 処理中のリクエスト数
+    pub in_flight: i64,
+    /// 実行枠の空き待ちの数
+    pub queue_len: i64,
+    /// PDFキャッシュのヒット率（参照がなければ None）
+    pub cache_hit_rate: Option<f64>,
+    /// 最後にDBエラーが起きた日時
+    pub last_db_error: Option<DateTime<Local>>,
+}
+
+impl ServerStats {
+    /// リクエスト開始（戻り値をdropすると処理中数を戻す）
+    pub fn request_started(&self, route: &str) -> InFlightGuard<'_> {
+        *self.requests.lock().unwrap().entry(route.to_string()).or_insert(0) += 1;
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard { stats: self }
+    }
+
+    /// 作業の開始（戻り値をdropすると終了、label はログ用の説明）
+    pub fn work_started(&self, label: String) -> WorkGuard<'_> {
+        let id = self.next_work_id.fetch_add(1, Ordering::Relaxed);
+        self.active_work.lock().unwrap().insert(id, label);
+        WorkGuard { stats: self, id }
+    }
+
+    /// 処理中の作業（開始順）
+    pub fn active_work(&self) -> Vec<String> {
+        self.active_work.lock().unwrap().values().cloned().collect()
+    }
+
+    /// 処理中の作業がなくなるまで待つ（poll 間隔で確認）
+    pub async fn wait_idle(&self, poll: Duration) {
+        while !self.active_work.lock().unwrap().is_empty() {
+            tokio::time::sleep(poll).await;
+        }
+    }
+
+    /// PDF生成の実行枠待ちに入る（戻り値をdropすると待ち行列長を戻す）
+    pub fn queue_entered(&self) -> QueueGuard<'_> {
+        self.queue_len.fetch_add(1, Ordering::Relaxed);
+        QueueGuard { stats: self }
+    }
+
+    /// PDFキャッシュを引いた結果を記録
+    pub fn record_cache(&self, hit: bool) {
+        let counter = if hit { &self.cache_hits } else { &self.cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// DBエラーを記録
+    pub fn record_db_error(&self) {
+        *self.last_db_error.lock().unwrap() = Some(Local::now());
+    }
+
+    /// 区間を締めて集計を返す（リクエスト数はリセット）
+    pub fn rotate(&self) -> StatsSnapshot {
+        let requests = std::mem::take(&mut *self.requests.lock().unwrap());
+        let hits = self.cache_hits.load(Ordering::Relaxed);
+        let misses = self.cache_misses.load(Ordering::Relaxed);
+        let snapshot = StatsSnapshot {
+            requests,
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            queue_len: self.queue_len.load(Ordering::Relaxed),
+            cache_hit_rate: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+            last_db_error: *self.last_db_error.lock().unwrap(),
+        };
+        *self.last_snapshot.lock().unwrap() = snapshot.clone();
+        snapshot
+    }
+
+    /// /metrics用テキスト（Prometheus形式）
+    /// リクエスト数は直前の区間、それ以外は現在値
+    pub fn render_metrics(&self) -> String {
+        let last = self.last_snapshot.lock().unwrap().clone();
+        let mut out = String::new();
+        out.push_str("# HELP timecard_requests_last_interval Requests served per route in the last stats interval\n");
+        out.push_str("# TYPE timecard_requests_last_interval gauge\n");
+        for (route, count) in &last.requests {
+            out.push_str(&format!("timecard_requests_last_interval{{route=\"{}\"}} {}\n", route, count));
+        }
+        out.push_str("# TYPE timecard_in_flight gauge\n");
+        out.push_str(&format!("timecard_in_flight {}\n", self.in_flight.load(Ordering::Relaxed)));
+        out.push_str("# TYPE timecard_queue_length gauge\n");
+        out.push_str(&format!("timecard_queue_length {}\n", self.queue_len.load(Ordering::Relaxed)));
+        out.push_str("# TYPE timecard_pdf_cache_hits_total counter\n");
+        out.push_str(&format!("timecard_pdf_cache_hits_total {}\n", self.cache_hits.load(Ordering::Relaxed)));
+        out.push_str("# TYPE timecard_pdf_cache_misses_total counter\n");
+        out.push_str(&format!("timecard_pdf_cache_misses_total {}\n", self.cache_misses.load(Ordering::Relaxed)));
+        if let Some(at) = *self.last_db_error.lock().unwrap() {
+            out.push_str("# TYPE timecard_last_db_error_timestamp_seconds gauge\n");
+            out.push_str(&format!("timecard_last_db_error_timestamp_seconds {}\n", at.timestamp()));
+        }
+        out
+    }
+}
+
+/// 処理中リクエストのガード
+pub struct InFlightGuard<'a> {
+    stats: &'a ServerStats,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 処理中の作業のガード
+pub struct WorkGuard<'a> {
+    stats: &'a ServerStats,
+    id: u64,
+}
+
+impl Drop for WorkGuard<'_> {
+    fn drop(&mut self) {
+        self.stats.active_work.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// 実行枠待ちのガード
+pub struct QueueGuard<'a> {
+    stats: &'a ServerStats,
+}
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.stats.queue_len.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl StatsSnapshot {
+    /// ログ出力用の1行
+    pub fn log_line(&self) -> String {
+        let total: u64 = self.requests.values().sum();
+        let routes = self.requests.iter()
+            .map(|(route, count)| format!("{}={}", route, count))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let hit_rate = self.cache_hit_rate
+            .map(|r| format!("{:.1}%", r * 100.0))
+            .unwrap_or_else(|| "-".to_string());
+        let db_error = self.last_db_error
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        format!(
+            "[stats] requests={} [{}] in_flight={} queue={} cache_hit={} last_db_error={}",
+            total, routes, self.in_flight, self.queue_len, hit_rate, db_error
+        )
+    }
+}
+
+/// 統計の区切りタイミング（本番はtokioのinterval、テストでは手動で進める）
+pub trait Ticker {
+    /// 次の区切りまで待つ
+    fn tick(&mut self) -> impl Future<Output = ()> + Send;
+}
+
+/// 一定間隔のTicker
+pub struct IntervalTicker {
+    interval: tokio::time::Interval,
+}
+
+impl IntervalTicker {
+    /// period ごとに区切るTicker
+    pub fn new(period: Duration) -> Self {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // 最初のtickは即時に完了するので、1区間後から開始する
+        interval.reset();
+        Self { interval }
+    }
+}
+
+impl Ticker for IntervalTicker {
+    async fn tick(&mut self) {
+        self.interval.tick().await;
+    }
+}
+
+/// 環境変数 STATS_INTERVAL_SECS（デフォルト60秒、0で無効）
+pub fn interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("STATS_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(60);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// 定期的に統計を締めて出力する（shutdownがtrueになったら終了）
+pub async fn run_reporter<T, F>(stats: &ServerStats, mut ticker: T, mut shutdown: watch::Receiver<bool>, mut report: F)
+where
+    T: Ticker,
+    F: FnMut(&StatsSnapshot),
+{
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let snapshot = stats.rotate();
+                report(&snapshot);
+            }
+            _ = shutdown.changed() => break,
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    /// 手動で進めるTicker
+    struct ManualTicker {
+        rx: mpsc::UnboundedReceiver<()>,
+    }
+
+    impl Ticker for ManualTicker {
+        async fn tick(&mut self) {
+            if self.rx.recv().await.is_none() {
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_counters_reset_per_interval() {
+        let stats = Arc::new(ServerStats::default());
+        let (tick_tx, tick_rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (report_tx, mut report_rx) = mpsc::unbounded_channel();
+
+        let reporter_stats = stats.clone();
+        let reporter = tokio::spawn(async move {
+            run_reporter(&reporter_stats, ManualTicker { rx: tick_rx }, shutdown_rx, |s| {
+                report_tx.send(s.clone()).unwrap();
+            })
+            .await;
+        });
+
+        // 1区間目: /api/pdf 2件、/health 1件（1件は処理中のまま）
+        drop(stats.request_started("/api/pdf"));
+        let in_flight = stats.request_started("/api/pdf");
+        drop(stats.request_started("/health"));
+        tick_tx.send(()).unwrap();
+        let first = report_rx.recv().await.unwrap();
+        assert_eq!(first.requests.get("/api/pdf"), Some(&2));
+        assert_eq!(first.requests.get("/health"), Some(&1));
+        assert_eq!(first.in_flight, 1);
+
+        // 2区間目: カウンタはリセットされている
+        drop(in_flight);
+        drop(stats.request_started("/api/pdf-shukei"));
+        tick_tx.send(()).unwrap();
+        let second = report_rx.recv().await.unwrap();
+        assert_eq!(second.requests.len(), 1);
+        assert_eq!(second.requests.get("/api/pdf-shukei"), Some(&1));
+        assert_eq!(second.in_flight, 0);
+
+        // /metrics は直前の区間を返す
+        assert!(stats.render_metrics().contains("timecard_requests_last_interval{route=\"/api/pdf-shukei\"} 1"));
+
+        // シャットダウンで終了する
+        shutdown_tx.send(true).unwrap();
+        reporter.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_idle_for_active_work() {
+        let stats = Arc::new(ServerStats::default());
+        let request = stats.work_started("GET /api/pdf?year=2026&month=1".to_string());
+        let pdf = stats.work_started("pdf timecard_2026_01.pdf".to_string());
+        drop(request);
+        assert_eq!(stats.active_work(), vec!["pdf timecard_2026_01.pdf"]);
+
+        // 作業が残っている間は待ち続ける
+        let idle = tokio::time::timeout(Duration::from_millis(50), stats.wait_idle(Duration::from_millis(5))).await;
+        assert!(idle.is_err());
+        drop(pdf);
+        stats.wait_idle(Duration::from_millis(5)).await;
+        assert!(stats.active_work().is_empty());
+    }
+}
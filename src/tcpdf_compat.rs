@@ -1,15 +1,42 @@
 use printpdf::*;
 use printpdf::path::{PaintMode, WindingOrder};
 use lopdf::{Document, Object, Dictionary, StringFormat};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufWriter, Cursor};
+use std::io::{BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use crate::coordinate_data::*;
-use crate::timecard_data::MonthlyTimecard;
-
-/// 埋め込みフォント（MS明朝）- バイナリに静的に埋め込む
+use crate::pdf_encryption::PdfEncryption;
+use crate::pdf_image::load_image;
+use crate::render_options::{PdfMetadata, RenderOptions};
+use crate::timecard_data::{target_month, DayRecord, LeaveType, MonthlyTimecard, ZangyoTable};
+use tracing::{debug, info_span, warn};
+
+/// 埋め込みフォント（MS明朝）- バイナリに静的に埋め込む（embedded-font フィーチャー）
+#[cfg(feature = "embedded-font")]
 static MSMINCHO_FONT: &[u8] = include_bytes!("../fonts/msmincho01.ttf");
 
+/// 埋め込みフォントなしでビルドした場合のデフォルトフォントファイル
+#[cfg(not(feature = "embedded-font"))]
+const DEFAULT_FONT_PATH: &str = "fonts/msmincho01.ttf";
+
+/// フォールバック時にPDFのInfo辞書に書き込むキー
+const FONT_FALLBACK_INFO_KEY: &str = "TimecardFontFallback";
+
+/// 透かしの不透明度を指定するExtGStateの名前（printpdfでgs演算子を書き、lopdfで各ページのResourcesに追加）
+const WATERMARK_GS_NAME: &str = "GSTimecardWatermark";
+
+/// デフォルトのフォントファミリー（埋め込みMS明朝）
+const DEFAULT_FONT_FAMILY: &str = "msmincho";
+
+/// ページの余白（mm、TCPDFの既定値 1cm）。AddPage・改行後のカーソル位置
+const PAGE_MARGIN_MM: f64 = 10.0;
+
 /// mm → Mm型
 fn mm(val: f64) -> Mm {
     Mm(val as f32)
@@ -38,16 +65,7 @@ fn get_text_from_value(value: &serde_json::Value) -> Option<String> {
 /// テキストのX座標を計算（align対応）
 /// align: "L" = 左揃え, "C" = 中央揃え, "R" = 右揃え
 fn calc_text_x(cell_x: f64, cell_w: f64, text: &str, font_size_pt: f32, align: &str) -> f64 {
-    // 文字幅の概算（日本語は全角、英数字は半角として計算）
-    let char_width_mm = font_size_pt as f64 * 0.352778; // 1pt = 0.352778mm
-    let text_width: f64 = text.chars().map(|c| {
-        if c.is_ascii() {
-            char_width_mm * 0.5 // 半角
-        } else {
-            char_width_mm // 全角
-        }
-    }).sum();
-
+    let text_width = measure_text(text, font_size_pt);
     let padding = 0.5; // パディング
 
     match align {
@@ -57,8 +75,229 @@ fn calc_text_x(cell_x: f64, cell_w: f64, text: &str, font_size_pt: f32, align: &
     }
 }
 
+/// デフォルトフォントの文字幅（TTFのhmtxから取得）
+struct FontMetrics {
+    face: ttf_parser::Face<'static>,
+    units_per_em: f64,
+}
+
+impl FontMetrics {
+    /// デフォルトフォントと同じもの（FONT_PATH、なければ埋め込みフォント）を読み込む
+    fn load() -> Option<Self> {
+        let path = std::env::var("FONT_PATH").ok().filter(|s| !s.is_empty());
+        #[cfg(not(feature = "embedded-font"))]
+        let path = path.or_else(|| Some(DEFAULT_FONT_PATH.to_string()));
+        let data: &'static [u8] = match path.and_then(|p| std::fs::read(p).ok()) {
+            Some(bytes) => Box::leak(bytes.into_boxed_slice()),
+            #[cfg(feature = "embedded-font")]
+            None => MSMINCHO_FONT,
+            #[cfg(not(feature = "embedded-font"))]
+            None => return None,
+        };
+        let face = ttf_parser::Face::parse(data, 0).ok()?;
+        let units_per_em = face.units_per_em() as f64;
+        Some(Self { face, units_per_em })
+    }
+
+    fn global() -> Option<&'static Self> {
+        static METRICS: OnceLock<Option<FontMetrics>> = OnceLock::new();
+        METRICS.get_or_init(Self::load).as_ref()
+    }
+
+    /// 1文字の幅（em単位、フォントにない文字はNone）
+    fn advance_em(&self, c: char) -> Option<f64> {
+        let glyph = self.face.glyph_index(c)?;
+        let advance = self.face.glyph_hor_advance(glyph)?;
+        Some(advance as f64 / self.units_per_em)
+    }
+}
+
+/// 文字幅の概算（フォントにない文字用、日本語は全角、英数字は半角）
+fn approx_advance_em(c: char) -> f64 {
+    if c.is_ascii() {
+        0.5
+    } else {
+        1.0
+    }
+}
+
+/// 文字列の幅（mm）をデフォルトフォントの文字幅から計算
+/// フォントを読み込めない場合や、フォントにない文字は概算（全角1em、半角0.5em）
+pub fn measure_text(text: &str, font_size_pt: f32) -> f64 {
+    let em_mm = font_size_pt as f64 * 0.352778; // 1pt = 0.352778mm
+    let metrics = FontMetrics::global();
+    text.chars()
+        .map(|c| metrics.and_then(|m| m.advance_em(c)).unwrap_or_else(|| approx_advance_em(c)) * em_mm)
+        .sum()
+}
+
+/// フォントファイルを読み込む（プロセス内で1回だけ、1人1ファイルの出力などでPDFごとに読み直さない）
+fn read_font_file(path: &Path) -> Result<Arc<Vec<u8>>, String> {
+    static FONT_FILES: OnceLock<Mutex<HashMap<PathBuf, Arc<Vec<u8>>>>> = OnceLock::new();
+    let mut files = FONT_FILES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(bytes) = files.get(path) {
+        return Ok(bytes.clone());
+    }
+    let bytes = Arc::new(std::fs::read(path).map_err(|e| e.to_string())?);
+    files.insert(path.to_path_buf(), bytes.clone());
+    Ok(bytes)
+}
+
+/// 縮小して収める場合のフォントサイズの下限（pt）
+const MIN_FONT_SIZE: f32 = 4.0;
+
+/// セル幅（左右パディング込み）に収まるフォントサイズ（最大 max_size_pt、下限 MIN_FONT_SIZE）
+fn fit_font_size(text: &str, cell_w: f64, max_size_pt: f32) -> f32 {
+    let available = cell_w - 1.0;
+    let width = measure_text(text, max_size_pt);
+    if width <= available || width <= 0.0 {
+        max_size_pt
+    } else {
+        (max_size_pt * (available / width) as f32).max(MIN_FONT_SIZE.min(max_size_pt))
+    }
+}
+
+/// セル幅に収まるテキストとフォントサイズ（TCPDFと同じく縮小し、下限でも収まらなければ末尾を「…」にする）
+fn fit_text(text: &str, cell_w: f64, max_size_pt: f32) -> (String, f32) {
+    let size = fit_font_size(text, cell_w, max_size_pt);
+    // 縮小したサイズはちょうど収まる幅なので、f32の丸め誤差分の余裕を見る
+    let available = cell_w - 1.0 + 1e-3;
+    if measure_text(text, size) <= available {
+        return (text.to_string(), size);
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let truncated: String = chars.iter().chain(std::iter::once(&'…')).collect();
+        if measure_text(&truncated, size) <= available {
+            return (truncated, size);
+        }
+    }
+    (String::new(), size)
+}
+
+/// MultiCellの行の高さ（mm、TCPDFのK_CELL_HEIGHT_RATIO=1.25）
+fn line_height_mm(font_size_pt: f32) -> f64 {
+    font_size_pt as f64 * 0.352778 * 1.25
+}
+
+/// MultiCellの折り返し（TCPDFと同じく英単語はスペースで、日本語はどの文字の間でも改行）
+/// 幅に収まらない英単語は文字の途中で改行する。改行文字は強制改行
+fn wrap_text(text: &str, width_mm: f64, font_size_pt: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        // 英数字の単語・スペース・日本語1文字を単位に分割
+        let mut units: Vec<String> = Vec::new();
+        for c in paragraph.chars() {
+            match units.last_mut() {
+                Some(word) if c.is_ascii_graphic() && word.chars().all(|w| w.is_ascii_graphic()) => word.push(c),
+                _ => units.push(c.to_string()),
+            }
+        }
+
+        let mut line = String::new();
+        for unit in units {
+            let candidate = format!("{}{}", line, unit);
+            if measure_text(&candidate, font_size_pt) <= width_mm + 1e-3 {
+                line = candidate;
+                continue;
+            }
+            if unit == " " {
+                // 行末のスペースは次の行に持ち越さない
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line).trim_end().to_string());
+            }
+            // 1行に収まらない単語は文字単位で改行
+            for c in unit.chars() {
+                line.push(c);
+                if measure_text(&line, font_size_pt) > width_mm + 1e-3 && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::take(&mut line));
+                    line.push(c);
+                }
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
 /// TCPDF座標系(左上原点) → PDF座標系(左下原点) 変換
 /// テキストはベースライン基準、セル内で垂直中央揃え
+/// リンク用の対象年月（YYYY-MM）
+fn timecard_month(timecard: &MonthlyTimecard) -> String {
+    format!("{}-{:02}", timecard.year, timecard.month)
+}
+
+/// setFillColor / SetTextColor の値から色を作成（col2省略時はグレー）
+fn color_from_params(p: &SetFillColorParams) -> Color {
+    if p.col2 == -1 {
+        let gray = p.col1 as f32 / 255.0;
+        Color::Rgb(Rgb::new(gray, gray, gray, None))
+    } else {
+        let r = p.col1 as f32 / 255.0;
+        let g = p.col2 as f32 / 255.0;
+        let b = p.col3 as f32 / 255.0;
+        Color::Rgb(Rgb::new(r, g, b, None))
+    }
+}
+
+/// セルの枠線（TCPDFのborder: 0=なし、1=四辺、"LTRB" の組み合わせ=指定した辺）
+/// L・T・R・B 以外の文字は無視する
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct CellBorder {
+    left: bool,
+    top: bool,
+    right: bool,
+    bottom: bool,
+}
+
+impl CellBorder {
+    fn from_value(value: &serde_json::Value) -> Self {
+        let all = CellBorder { left: true, top: true, right: true, bottom: true };
+        match value {
+            serde_json::Value::Number(n) if n.as_f64() == Some(1.0) => all,
+            serde_json::Value::String(s) if s.trim() == "1" => all,
+            serde_json::Value::String(s) => {
+                let s = s.to_uppercase();
+                CellBorder {
+                    left: s.contains('L'),
+                    top: s.contains('T'),
+                    right: s.contains('R'),
+                    bottom: s.contains('B'),
+                }
+            }
+            _ => CellBorder::default(),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.left && self.top && self.right && self.bottom
+    }
+}
+
+/// 日曜日・国民の祝日の日付・曜日・備考の文字色（PHPと同じ赤）
+/// 色を0〜255のRGBにする（座標JSONの SetTextColor 用）
+fn color_to_rgb255(color: &Color) -> (i32, i32, i32) {
+    let to_255 = |v: f32| (v * 255.0).round() as i32;
+    match color {
+        Color::Rgb(rgb) => (to_255(rgb.r), to_255(rgb.g), to_255(rgb.b)),
+        Color::Greyscale(grey) => (to_255(grey.percent), to_255(grey.percent), to_255(grey.percent)),
+        _ => (0, 0, 0),
+    }
+}
+
+fn day_text_color(day: &DayRecord) -> Color {
+    if day.is_sunday || day.shukujitsu.is_some() {
+        Color::Rgb(Rgb::new(1.0, 0.0, 0.0, None))
+    } else {
+        Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None))
+    }
+}
+
 fn y_convert_text(y_mm: f64, h_mm: f64, font_size_pt: f32, page_height_mm: f64) -> Mm {
     // フォントサイズ(pt)をmmに変換: 1pt = 0.352778mm
     let font_size_mm = font_size_pt as f64 * 0.352778;
@@ -70,36 +309,177 @@ fn y_convert_text(y_mm: f64, h_mm: f64, font_size_pt: f32, page_height_mm: f64)
 }
 
 /// 矩形・線用のY座標変換
+/// Imageの画像データ（file のパスから読むか、data のBase64を復号）
+fn image_bytes(p: &ImageParams) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+    if let Some(data) = &p.data {
+        // "data:image/png;base64,..." 形式も受け付ける
+        let encoded = data.split_once("base64,").map_or(data.as_str(), |(_, rest)| rest);
+        return base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("Base64を復号できません: {}", e));
+    }
+    let path = p.file.as_deref().ok_or("file も data も指定されていません")?;
+    std::fs::read(path).map_err(|e| format!("読み込めません: {}", e))
+}
+
+/// 向きの指定を "P"・"L" に揃える（TCPDFと同じく portrait・landscape も受け付ける、それ以外はそのまま）
+fn normalize_orientation(orientation: &str) -> String {
+    match orientation.trim().to_uppercase().as_str() {
+        "P" | "PORTRAIT" => "P".to_string(),
+        "L" | "LANDSCAPE" => "L".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// 向きに合わせて（幅, 高さ）を入れ替える（"P" は高さ≧幅、"L" は幅≧高さ）
+fn oriented_size(width_mm: f64, height_mm: f64, orientation: &str) -> (f64, f64) {
+    match orientation {
+        "P" if width_mm > height_mm => (height_mm, width_mm),
+        "L" if height_mm > width_mm => (height_mm, width_mm),
+        _ => (width_mm, height_mm),
+    }
+}
+
 fn y_convert(y_mm: f64, page_height_mm: f64) -> Mm {
     mm(page_height_mm - y_mm)
 }
 
+/// リンク先
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    /// 外部URL（URIアクション）
+    Uri(String),
+    /// 文書内のページ（GoToアクション）
+    Page {
+        /// ページ番号（1-indexed）
+        index: u32,
+        /// ページ上端からの位置（mm）
+        y_mm: f64,
+    },
+}
+
 /// リンク情報を保持する構造体
 #[derive(Debug, Clone)]
 pub struct LinkInfo {
-    pub page: u32,      // 1-indexed
+    /// ページ番号（1-indexed）
+    pub page: u32,
+    /// 左端（mm）
     pub x_mm: f64,
+    /// 上端（mm）
     pub y_mm: f64,
+    /// 幅（mm）
     pub w_mm: f64,
+    /// 高さ（mm）
     pub h_mm: f64,
-    pub url: String,
+    /// リンク先
+    pub target: LinkTarget,
+}
+
+/// しおり（アウトライン）の1項目
+#[derive(Debug, Clone)]
+pub struct OutlineEntry {
+    /// しおりの文字（氏名など）
+    pub title: String,
+    /// ページ番号（1-indexed）
+    pub page: u32,
+    /// ページ上端からの位置（mm）
+    pub y_mm: f64,
+}
+
+/// 保存オプション
+#[derive(Debug, Clone, Default)]
+pub struct SaveOptions {
+    /// 決定的出力（同じ入力なら同じバイト列）
+    /// 作成日時・更新日時を固定し、ドキュメントIDを内容のハッシュから生成する
+    pub deterministic: bool,
+    /// 固定する日付（未指定ならタイムカード対象月の1日）
+    pub fixed_date: Option<NaiveDate>,
+    /// パスワードで暗号化する（Noneなら暗号化しない）
+    pub encryption: Option<PdfEncryption>,
 }
 
+/// TCPDF互換のPDF描画（座標JSONの再生とタイムカードの描画）
 pub struct TcpdfCompat {
     doc: PdfDocumentReference,
     page_width_mm: f64,
     page_height_mm: f64,
+    /// 用紙の向き（"P": 縦、"L": 横、それ以外は指定どおりのサイズ）。AddPageの指定で切り替わる
+    orientation: String,
+    /// PdfDocument::newで作成した1ページ目のサイズ（mm、最初のAddPageで変わった場合は保存時にMediaBoxを直す）
+    first_page_size: (f64, f64),
     current_layer: Option<PdfLayerReference>,
     font: Option<IndirectFontRef>,
     font_size: f32,
     fill_color: Color,
+    /// 文字色（SetTextColorで変更、デフォルトは黒）
+    text_color: Color,
+    /// 線の色（SetDrawColorで変更、Noneは黒）
+    draw_color: Option<Color>,
+    /// 線幅（mm、SetLineWidthで変更、Noneは従来の枠線の太さ）
+    line_width_mm: Option<f64>,
     page_count: u32,
     first_page_layer: Option<PdfLayerReference>,
+    /// 追加したページのレイヤー（透かしを全ページに描画する）
+    page_layers: Vec<PdfLayerReference>,
+    /// 追加したページのサイズ（mm、page_layersと同じ順）
+    page_sizes: Vec<(f64, f64)>,
+    /// カーソル位置（mm、x・yを省略したCell/MultiCellの描画位置）
+    current_x: f64,
+    current_y: f64,
+    /// 直前のCell/MultiCellの高さ（mm、hを省略したLnの改行幅）
+    last_h: f64,
     links: Vec<LinkInfo>,  // リンク情報を保存
+    outlines: Vec<OutlineEntry>,  // しおり（ドライバーごと）
+    /// フォント登録（family名 → フォントファイル、Noneは埋め込みMS明朝）
+    font_paths: HashMap<String, Option<PathBuf>>,
+    /// 読み込み済みフォント（キー: フォントファイルのパス、埋め込みは空文字）
+    loaded_fonts: HashMap<String, IndirectFontRef>,
+    /// 警告済みの未登録family名（同じ警告を繰り返さない）
+    warned_families: HashSet<String>,
+    /// 保存オプション
+    save_options: SaveOptions,
+    /// 描画したタイムカードの対象月（1日）
+    document_month: Option<NaiveDate>,
+    /// 文書情報（set_metadata の指定 > RenderOptions > 既定のタイトル）
+    metadata: PdfMetadata,
+    /// レンダリングオプション（render_timecards / render_timecards_shukei で設定）
+    render: RenderOptions,
+    /// デフォルトフォントのファイル（環境変数 FONT_PATH、Noneなら埋め込みフォント）
+    default_font_path: Option<PathBuf>,
+    /// デフォルトフォントを読み込めず代替フォントを使った場合、その名前（PDFのInfoに記録）
+    font_fallback: Option<&'static str>,
+    /// 太字フォントのファイル（環境変数 FONT_BOLD_PATH、Noneなら擬似太字）
+    bold_font_path: Option<PathBuf>,
+    /// 読み込み済みの太字フォント（読み込めなければNoneで擬似太字）
+    bold_font: Option<IndirectFontRef>,
+    /// 現在のスタイルが太字か（SetFontのstyleに"B"を含む）
+    bold: bool,
+    /// 座標JSONの記録（record_coordinates で開始、描画メソッドが&selfなのでRefCell）
+    recorder: Option<RefCell<CoordinateRecorder>>,
+}
+
+/// 座標JSONに記録するセル（MultiCell 1つ分）
+struct CellRecord<'a> {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    text: &'a str,
+    font_size: f32,
+    bold: bool,
+    /// 0: なし、1: 四辺
+    border: i32,
+    align: &'a str,
+    fill: bool,
 }
 
 impl TcpdfCompat {
-    pub fn new(page_width_mm: f64, page_height_mm: f64, _orientation: &str) -> Self {
+    /// 用紙サイズ（mm）と向きを指定して空のPDFを作成（タイムカードはA4横 297×210）
+    /// 向きが "P" なら縦長、"L" なら横長になるよう幅と高さを入れ替える
+    pub fn new(page_width_mm: f64, page_height_mm: f64, orientation: &str) -> Self {
+        let orientation = normalize_orientation(orientation);
+        let (page_width_mm, page_height_mm) = oriented_size(page_width_mm, page_height_mm, &orientation);
         let (doc, page, layer) = PdfDocument::new(
             "TimeCard PDF",
             mm(page_width_mm),
@@ -114,25 +494,253 @@ impl TcpdfCompat {
             doc,
             page_width_mm,
             page_height_mm,
+            orientation,
+            first_page_size: (page_width_mm, page_height_mm),
             current_layer: None,
             font: None,
             font_size: 10.0,
             fill_color: Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)),
+            text_color: Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)),
+            draw_color: None,
+            line_width_mm: None,
             page_count: 0,
             first_page_layer: Some(first_layer),
+            page_layers: Vec::new(),
+            page_sizes: Vec::new(),
+            current_x: PAGE_MARGIN_MM,
+            current_y: PAGE_MARGIN_MM,
+            last_h: 0.0,
             links: Vec::new(),
+            outlines: Vec::new(),
+            font_paths: HashMap::from([(DEFAULT_FONT_FAMILY.to_string(), None)]),
+            loaded_fonts: HashMap::new(),
+            warned_families: HashSet::new(),
+            save_options: SaveOptions::default(),
+            document_month: None,
+            metadata: PdfMetadata::default(),
+            render: RenderOptions::default(),
+            default_font_path: std::env::var("FONT_PATH").ok().filter(|s| !s.is_empty()).map(PathBuf::from),
+            font_fallback: None,
+            bold_font_path: std::env::var("FONT_BOLD_PATH").ok().filter(|s| !s.is_empty()).map(PathBuf::from),
+            bold_font: None,
+            bold: false,
+            recorder: None,
+        }
+    }
+
+    /// 保存オプションを設定
+    pub fn set_save_options(&mut self, options: SaveOptions) {
+        self.save_options = options;
+    }
+
+    /// 座標JSONの記録を開始（以降の render_timecards の描画をPHPの座標JSONと同じ形式で記録する）
+    pub fn record_coordinates(&mut self) {
+        self.recorder = Some(RefCell::new(CoordinateRecorder::default()));
+    }
+
+    /// 記録した座標JSONを取り出す（record_coordinates していなければNone）
+    pub fn take_coordinates(&mut self) -> Option<CoordinateData> {
+        let recorder = self.recorder.take()?.into_inner();
+        Some(recorder.into_data(self.page_width_mm, self.page_height_mm, &self.orientation))
+    }
+
+    /// セルを記録（記録中のみ、文字色が変われば SetTextColor、塗りつぶしがあれば setFillColor も）
+    fn record_cell(&self, cell: CellRecord, color: &Color) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+        let mut recorder = recorder.borrow_mut();
+        recorder.set_font(DEFAULT_FONT_FAMILY, if cell.bold { "B" } else { "" }, cell.font_size as f64);
+        recorder.set_text_color(color_to_rgb255(color));
+        if cell.fill {
+            recorder.set_fill_gray(200);
+        }
+        recorder.push("MultiCell", MultiCellParams {
+            x: Some(cell.x),
+            y: Some(cell.y),
+            w: cell.w,
+            h: cell.h,
+            text: serde_json::Value::String(cell.text.to_string()),
+            border: serde_json::Value::from(cell.border),
+            align: cell.align.to_string(),
+            fill: cell.fill,
+            ln: 0,
+        });
+    }
+
+    /// 線を記録（記録中のみ）
+    fn record_line(&self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        if let Some(recorder) = &self.recorder {
+            recorder.borrow_mut().push("Line", LineParams { x1, y1, x2, y2 });
+        }
+    }
+
+    /// リンクを記録（記録中のみ）
+    fn record_link(&self, x: f64, y: f64, w: f64, h: f64, url: &str) {
+        if let Some(recorder) = &self.recorder {
+            recorder.borrow_mut().push("Link", LinkParams { x, y, w, h, link: url.to_string() });
+        }
+    }
+
+    /// 文書情報（Title/Author/Subject/Keywords）を設定
+    /// 未指定の項目は描画時に RenderOptions の値と既定のタイトルで補う
+    pub fn set_metadata(&mut self, metadata: PdfMetadata) {
+        self.metadata = metadata;
+    }
+
+    /// 描画時の文書情報を確定（タイトルの既定値は「{label} YYYY年MM月」）
+    fn resolve_metadata(&mut self, label: &str) {
+        let defaults = PdfMetadata {
+            title: self.document_month.map(|m| format!("{} {}", label, m.format("%Y年%m月"))),
+            ..Default::default()
+        };
+        self.metadata = self.metadata.or(&self.render.metadata).or(&defaults);
+    }
+
+    /// フォントファミリーを登録（例: "msgothic" → fonts/msgothic.ttc）
+    /// 別名も同じパスで登録すればよい。フォントは最初に使われた時に読み込む
+    pub fn register_font(&mut self, family: &str, path: impl Into<PathBuf>) {
+        self.font_paths.insert(family.to_lowercase(), Some(path.into()));
+    }
+
+    /// デフォルトフォントを取得
+    /// デフォルトフォント（FONT_PATH → 埋め込みMS明朝の順、初回だけ読み込む）
+    /// どちらも使えない場合はエラー（日本語を描画できないPDFは出力しない）
+    fn default_font(&mut self) -> Result<IndirectFontRef, String> {
+        if let Some(font) = self.loaded_fonts.get("") {
+            return Ok(font.clone());
+        }
+        let font = self.load_default_font()?;
+        self.loaded_fonts.insert(String::new(), font.clone());
+        Ok(font)
+    }
+
+    fn load_default_font(&mut self) -> Result<IndirectFontRef, String> {
+        #[cfg(feature = "embedded-font")]
+        let configured = self.default_font_path.clone();
+        #[cfg(not(feature = "embedded-font"))]
+        let configured = Some(self.default_font_path.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_FONT_PATH)));
+
+        let Some(path) = configured else {
+            return self.embedded_font().ok_or_else(|| "埋め込みフォントを読み込めません".to_string());
+        };
+        let error = match self.load_font_file(&path) {
+            Ok(font) => return Ok(font),
+            Err(e) => e,
+        };
+
+        // 代替フォント: 埋め込みMS明朝（embedded-font フィーチャーが無効ならエラー）
+        match self.embedded_font() {
+            Some(font) => {
+                warn!("{} 埋め込みフォントで出力します。表示が崩れる可能性があります", error);
+                self.font_fallback = Some("msmincho (embedded)");
+                Ok(font)
+            }
+            None => Err(format!("{}。FONT_PATH を確認してください", error)),
         }
     }
 
-    pub fn render_elements(&mut self, elements: &[Element]) {
+    /// フォントファイルを読み込んでPDFに追加
+    fn load_font_file(&mut self, path: &std::path::Path) -> Result<IndirectFontRef, String> {
+        read_font_file(path)
+            .and_then(|bytes| self.doc.add_external_font(Cursor::new(&bytes[..])).map_err(|e| e.to_string()))
+            .map_err(|e| format!("フォントを読み込めません: {} ({})", path.display(), e))
+    }
+
+    /// 埋め込みフォント（embedded-font フィーチャーが無効ならNone）
+    #[cfg(feature = "embedded-font")]
+    fn embedded_font(&mut self) -> Option<IndirectFontRef> {
+        self.doc.add_external_font(Cursor::new(MSMINCHO_FONT)).ok()
+    }
+
+    #[cfg(not(feature = "embedded-font"))]
+    fn embedded_font(&mut self) -> Option<IndirectFontRef> {
+        None
+    }
+
+    /// 太字フォントを読み込む（FONT_BOLD_PATH 未設定・読み込み失敗時はNoneで擬似太字）
+    fn load_bold_font(&mut self) -> Option<IndirectFontRef> {
+        let path = self.bold_font_path.clone()?;
+        let key = format!("bold:{}", path.display());
+        if let Some(font) = self.loaded_fonts.get(&key) {
+            return Some(font.clone());
+        }
+        match self.load_font_file(&path) {
+            Ok(font) => {
+                self.loaded_fonts.insert(key, font.clone());
+                Some(font)
+            }
+            Err(e) => {
+                if self.warned_families.insert(key) {
+                    warn!("{}、太字は擬似太字で出力します", e);
+                }
+                None
+            }
+        }
+    }
+
+    /// テキストを描画（bold: デフォルトフォントなら太字フォント、太字フォントがない・別familyなら輪郭線を重ねた擬似太字）
+    #[allow(clippy::too_many_arguments)]
+    fn draw_text(&self, layer: &PdfLayerReference, text: &str, size: f32, x: Mm, y: Mm, font: &IndirectFontRef, bold: bool) {
+        if !bold {
+            layer.use_text(text, size, x, y, font);
+            return;
+        }
+        if let (Some(bold_font), Some(default)) = (&self.bold_font, self.loaded_fonts.get("")) {
+            if font == default {
+                layer.use_text(text, size, x, y, bold_font);
+                return;
+            }
+        }
+        layer.save_graphics_state();
+        layer.set_text_rendering_mode(TextRenderingMode::FillStroke);
+        layer.set_outline_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+        layer.set_outline_thickness(size * 0.03);
+        layer.use_text(text, size, x, y, font);
+        layer.restore_graphics_state();
+    }
+
+    /// family名からフォントを取得（未登録・読み込み失敗時は警告してデフォルトに戻す）
+    /// デフォルトフォントは描画の開始時に読み込み済み
+    fn font_for_family(&mut self, family: &str) -> Option<IndirectFontRef> {
+        let key = family.to_lowercase();
+        let path = match self.font_paths.get(&key) {
+            Some(Some(path)) => path.clone(),
+            Some(None) => return self.loaded_fonts.get("").cloned(),
+            None => {
+                if self.warned_families.insert(key) {
+                    warn!("unknown font family '{}', using {}", family, DEFAULT_FONT_FAMILY);
+                }
+                return self.loaded_fonts.get("").cloned();
+            }
+        };
+
+        let path_key = path.to_string_lossy().to_string();
+        if let Some(font) = self.loaded_fonts.get(&path_key) {
+            return Some(font.clone());
+        }
+        match self.load_font_file(&path) {
+            Ok(font) => {
+                self.loaded_fonts.insert(path_key, font.clone());
+                Some(font)
+            }
+            Err(e) => {
+                if self.warned_families.insert(key) {
+                    warn!("family '{}': {}, using {}", family, e, DEFAULT_FONT_FAMILY);
+                }
+                self.loaded_fonts.get("").cloned()
+            }
+        }
+    }
+
+    /// 座標JSONの要素を描画（フォントを読み込めなければエラー）
+    pub fn render_elements(&mut self, elements: &[Element]) -> Result<(), String> {
         // 埋め込みフォントを使用
-        let cursor = Cursor::new(MSMINCHO_FONT.to_vec());
-        self.font = Some(
-            self.doc
-                .add_external_font(cursor)
-                .expect("Failed to add font"),
-        );
+        self.font = Some(self.default_font()?);
+        self.bold_font = self.load_bold_font();
 
+        // 未対応の要素（種類ごとの件数、最後にまとめて警告）
+        let mut unknown: BTreeMap<&str, usize> = BTreeMap::new();
         for element in elements {
             match element.element_type.as_str() {
                 "AddPage" => self.handle_add_page(&element.params),
@@ -143,16 +751,49 @@ impl TcpdfCompat {
                 "SetFont" => self.handle_set_font(&element.params),
                 "setFontSize" => self.handle_set_font_size(&element.params),
                 "setFillColor" => self.handle_set_fill_color(&element.params),
-                "setAbsX" => {}
-                "setAbsY" => {}
-                "Ln" => {}
-                _ => {}
+                "SetTextColor" | "setTextColor" => self.handle_set_text_color(&element.params),
+                "SetDrawColor" | "setDrawColor" => self.handle_set_draw_color(&element.params),
+                "SetLineWidth" | "setLineWidth" => self.handle_set_line_width(&element.params),
+                "Rect" => self.handle_rect(&element.params),
+                "Text" => self.handle_text(&element.params),
+                "Image" => self.handle_image(&element.params),
+                "setAbsX" => self.handle_set_abs_x(&element.params),
+                "setAbsY" => self.handle_set_abs_y(&element.params),
+                "Ln" => self.handle_ln(&element.params),
+                other => *unknown.entry(other).or_default() += 1,
+            }
+        }
+        if !unknown.is_empty() {
+            let counts: Vec<String> = unknown.iter().map(|(kind, count)| format!("{} x{}", kind, count)).collect();
+            warn!("unsupported element types skipped: {}", counts.join(", "));
+        }
+        Ok(())
+    }
+
+    /// AddPage: orientation・format の指定があればそのページから用紙を切り替える
+    /// 空の orientation や format は直前のページと同じ（TCPDFのAddPageと同じ）
+    fn handle_add_page(&mut self, params: &serde_json::Value) {
+        if let Ok(p) = serde_json::from_value::<AddPageParams>(params.clone()) {
+            let (mut width, mut height) = (self.page_width_mm, self.page_height_mm);
+            if let [w, h, ..] = p.format[..] {
+                if w > 0.0 && h > 0.0 {
+                    (width, height) = (w, h);
+                }
             }
+            if !p.orientation.trim().is_empty() {
+                self.orientation = normalize_orientation(&p.orientation);
+            }
+            (self.page_width_mm, self.page_height_mm) = oriented_size(width, height, &self.orientation);
         }
+        self.add_page();
     }
 
-    fn handle_add_page(&mut self, _params: &serde_json::Value) {
+    /// ページを追加して描画先にする
+    fn add_page(&mut self) {
         self.page_count += 1;
+        if let Some(recorder) = &self.recorder {
+            recorder.borrow_mut().add_page(&self.orientation, self.page_width_mm, self.page_height_mm);
+        }
 
         if self.page_count == 1 {
             // 最初のAddPageは、PdfDocument::newで作成済みのページを使う
@@ -166,6 +807,46 @@ impl TcpdfCompat {
             );
             self.current_layer = Some(self.doc.get_page(page).get_layer(layer));
         }
+        (self.current_x, self.current_y) = (PAGE_MARGIN_MM, PAGE_MARGIN_MM);
+        if let Some(layer) = self.current_layer.clone() {
+            self.page_layers.push(layer);
+            self.page_sizes.push((self.page_width_mm, self.page_height_mm));
+        }
+    }
+
+    /// 全ページの中央に透かしを描画（保存直前に呼ぶので各ページの最後のテキストになる）
+    fn draw_watermarks(&mut self) -> Result<(), String> {
+        let Some(watermark) = self.render.watermark.clone() else {
+            return Ok(());
+        };
+        let font = match &self.font {
+            Some(font) => font.clone(),
+            None => self.default_font()?,
+        };
+        // 文字列の中心をページの中心に合わせる（回転後の原点を逆算）
+        let width = mm_to_pt(measure_text(&watermark.text, watermark.font_size));
+        let half_height = watermark.font_size as f64 * 0.35;
+        let (sin, cos) = (watermark.angle as f64).to_radians().sin_cos();
+
+        let pages = self.page_layers.iter().zip(self.page_sizes.iter().copied());
+        let first = self.first_page_layer.iter().map(|layer| (layer, self.first_page_size));
+        for (layer, (page_width_mm, page_height_mm)) in pages.chain(first) {
+            let x = mm_to_pt(page_width_mm) / 2.0 - (width / 2.0 * cos - half_height * sin);
+            let y = mm_to_pt(page_height_mm) / 2.0 - (width / 2.0 * sin + half_height * cos);
+            layer.save_graphics_state();
+            layer.add_operation(printpdf::lopdf::content::Operation::new(
+                "gs",
+                vec![Object::Name(WATERMARK_GS_NAME.as_bytes().to_vec())],
+            ));
+            layer.set_fill_color(Color::Rgb(Rgb::new(0.5, 0.5, 0.5, None)));
+            layer.begin_text_section();
+            layer.set_font(&font, watermark.font_size);
+            layer.set_text_matrix(TextMatrix::TranslateRotate(Pt(x as f32), Pt(y as f32), watermark.angle));
+            layer.write_text(watermark.text.as_str(), &font);
+            layer.end_text_section();
+            layer.restore_graphics_state();
+        }
+        Ok(())
     }
 
     fn handle_multi_cell(&mut self, params: &serde_json::Value) {
@@ -173,30 +854,63 @@ impl TcpdfCompat {
             Ok(p) => p,
             Err(_) => return,
         };
+        let (x, y) = (p.x.unwrap_or(self.current_x), p.y.unwrap_or(self.current_y));
 
         // Y座標を5mm単位のグリッドに揃える（セルの高さは5mm）
         // 例: 15.93 → 15, 16.0 → 15, 16.1 → 15
-        let y_adjusted = (p.y / 5.0).floor() * 5.0;
+        let y_adjusted = (y / 5.0).floor() * 5.0;
+
+        // セル幅で折り返し（左右パディング0.5mm）
+        // 高さの指定（h>0）に収まらない場合は1行にして縮小・省略する
+        let mut font_size = self.font_size;
+        let mut lines = get_text_from_value(&p.text)
+            .map(|text| wrap_text(&text, p.w - 1.0, font_size))
+            .unwrap_or_default();
+        let line_h = line_height_mm(font_size);
+        // h=0（自動）はTCPDFと同じく行数分の高さにする
+        let h = if p.h > 0.0 { p.h } else { line_h * lines.len().max(1) as f64 };
+        self.advance_cursor(x, y, p.w, h, p.ln);
+        if lines.len() > 1 && line_h * lines.len() as f64 > h + 1e-3 {
+            let (text, size) = fit_text(&lines.concat(), p.w, font_size);
+            lines = vec![text];
+            font_size = size;
+        } else if let [line] = lines.as_mut_slice() {
+            let (text, size) = fit_text(line, p.w, font_size);
+            *line = text;
+            font_size = size;
+        }
 
         if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
-            // 塗りつぶし描画（テキストや枠線より先に描画）
+            // 塗りつぶし描画（テキストや枠線より先に描画し、枠線が上に来るようにする）
             if p.fill {
-                self.draw_filled_rect(p.x, y_adjusted, p.w, p.h);
+                self.draw_rect(x, y_adjusted, p.w, h, PaintMode::Fill);
             }
 
-            // テキスト描画（塗りつぶし後は色を黒に戻す）
-            if let Some(text) = get_text_from_value(&p.text) {
-                layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
-                let x = calc_text_x(p.x, p.w, &text, self.font_size, &p.align);
-                let x_mm = mm(x);
-                let y_mm = y_convert_text(y_adjusted, p.h, self.font_size, self.page_height_mm);
-                layer.use_text(&text, self.font_size, x_mm, y_mm, font);
+            // テキスト描画（塗りつぶし後は色をSetTextColorの文字色に戻す）
+            // 複数行はブロックごとセル内で上下中央に揃え、各行を行の高さの中で中央に置く
+            layer.set_fill_color(self.text_color.clone());
+            let line_h = if lines.len() > 1 { line_height_mm(font_size) } else { h };
+            let top = y_adjusted + (h - line_h * lines.len() as f64) / 2.0;
+            for (i, line) in lines.iter().enumerate().filter(|(_, l)| !l.is_empty()) {
+                let text_x = calc_text_x(x, p.w, line, font_size, &p.align);
+                let y_mm = y_convert_text(top + i as f64 * line_h, line_h, font_size, self.page_height_mm);
+                self.draw_text(layer, line, font_size, mm(text_x), y_mm, font, self.bold);
             }
 
-            // 枠線描画
-            if let Some(border) = p.border.as_i64() {
-                if border == 1 {
-                    self.draw_rect(p.x, y_adjusted, p.w, p.h);
+            // 枠線描画（1は四辺、"LTRB" の文字列は指定した辺のみ）
+            let border = CellBorder::from_value(&p.border);
+            if border.is_full() {
+                self.draw_rect(x, y_adjusted, p.w, h, PaintMode::Stroke);
+            } else {
+                let (right, bottom) = (x + p.w, y_adjusted + h);
+                let sides = [
+                    (border.left, (x, y_adjusted, x, bottom)),
+                    (border.top, (x, y_adjusted, right, y_adjusted)),
+                    (border.right, (right, y_adjusted, right, bottom)),
+                    (border.bottom, (x, bottom, right, bottom)),
+                ];
+                for (_, (x1, y1, x2, y2)) in sides.into_iter().filter(|(draw, _)| *draw) {
+                    self.draw_segment(x1, y1, x2, y2);
                 }
             }
         }
@@ -211,8 +925,41 @@ impl TcpdfCompat {
         };
 
         // Cellは枠線を描画しない（MultiCellで描画済み）
-        // テキストも重複するのでスキップ
-        let _ = p; // unused warning を抑制
+        // テキストも重複するのでスキップし、カーソルだけ進める
+        let (x, y) = (p.x.unwrap_or(self.current_x), p.y.unwrap_or(self.current_y));
+        self.advance_cursor(x, y, p.w, p.h, p.ln);
+    }
+
+    /// Cell/MultiCellの後のカーソル位置（TCPDFの ln: 0=右、1=次の行の左端、2=真下）
+    fn advance_cursor(&mut self, x: f64, y: f64, w: f64, h: f64, ln: i32) {
+        self.last_h = h;
+        (self.current_x, self.current_y) = match ln {
+            1 => (PAGE_MARGIN_MM, y + h),
+            2 => (x, y + h),
+            _ => (x + w, y),
+        };
+    }
+
+    fn handle_set_abs_x(&mut self, params: &serde_json::Value) {
+        if let Ok(p) = serde_json::from_value::<SetAbsXParams>(params.clone()) {
+            self.current_x = p.x;
+        }
+    }
+
+    fn handle_set_abs_y(&mut self, params: &serde_json::Value) {
+        if let Ok(p) = serde_json::from_value::<SetAbsYParams>(params.clone()) {
+            self.current_y = p.y;
+        }
+    }
+
+    /// Ln: 左端に戻して改行（hが数値でなければ直前のセルの高さ分）
+    fn handle_ln(&mut self, params: &serde_json::Value) {
+        let Ok(p) = serde_json::from_value::<LnParams>(params.clone()) else {
+            return;
+        };
+        let h = p.h.as_f64().unwrap_or(self.last_h);
+        self.current_x = PAGE_MARGIN_MM;
+        self.current_y = p.y_before.unwrap_or(self.current_y) + h;
     }
 
     fn handle_line(&mut self, params: &serde_json::Value) {
@@ -222,6 +969,13 @@ impl TcpdfCompat {
         };
 
         if let Some(layer) = &self.current_layer {
+            // SetLineWidth・SetDrawColorの指定があれば使う
+            if let Some(width) = self.line_width_mm {
+                layer.set_outline_thickness(mm_to_pt(width) as f32);
+            }
+            if let Some(color) = &self.draw_color {
+                layer.set_outline_color(color.clone());
+            }
             let points = vec![
                 (Point::new(mm(p.x1), y_convert(p.y1, self.page_height_mm)), false),
                 (Point::new(mm(p.x2), y_convert(p.y2, self.page_height_mm)), false),
@@ -247,7 +1001,7 @@ impl TcpdfCompat {
             y_mm: p.y,
             w_mm: p.w,
             h_mm: p.h,
-            url: p.link,
+            target: LinkTarget::Uri(p.link),
         });
     }
 
@@ -256,9 +1010,17 @@ impl TcpdfCompat {
             Ok(p) => p,
             Err(_) => return,
         };
+        // family名でフォントを切り替え（空の場合は現在のフォントのまま）
+        if !p.family.is_empty() {
+            if let Some(font) = self.font_for_family(&p.family) {
+                self.font = Some(font);
+            }
+        }
         if let Some(size) = p.size {
             self.font_size = size as f32;
         }
+        // スタイル（"B" を含めば太字、I/U などは未対応）
+        self.bold = p.style.to_uppercase().contains('B');
     }
 
     fn handle_set_font_size(&mut self, params: &serde_json::Value) {
@@ -274,70 +1036,164 @@ impl TcpdfCompat {
             Ok(p) => p,
             Err(_) => return,
         };
-        if p.col2 == -1 {
-            let gray = p.col1 as f32 / 255.0;
-            self.fill_color = Color::Rgb(Rgb::new(gray, gray, gray, None));
-        } else {
-            let r = p.col1 as f32 / 255.0;
-            let g = p.col2 as f32 / 255.0;
-            let b = p.col3 as f32 / 255.0;
-            self.fill_color = Color::Rgb(Rgb::new(r, g, b, None));
+        self.fill_color = color_from_params(&p);
+    }
+
+    fn handle_set_text_color(&mut self, params: &serde_json::Value) {
+        let p: SetTextColorParams = match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        self.text_color = color_from_params(&p);
+    }
+
+    fn handle_set_draw_color(&mut self, params: &serde_json::Value) {
+        let p: SetDrawColorParams = match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        self.draw_color = Some(color_from_params(&p));
+    }
+
+    fn handle_set_line_width(&mut self, params: &serde_json::Value) {
+        let p: SetLineWidthParams = match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        self.line_width_mm = Some(p.width);
+    }
+
+    /// Rect: style に "F" があれば塗りつぶし、"D" があるか空なら枠線
+    fn handle_rect(&mut self, params: &serde_json::Value) {
+        let p: RectParams = match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        let style = p.style.to_uppercase();
+        let mode = match (style.contains('F'), style.contains('D') || style.is_empty()) {
+            (true, true) => PaintMode::FillStroke,
+            (true, false) => PaintMode::Fill,
+            _ => PaintMode::Stroke,
+        };
+        self.draw_rect(p.x, p.y, p.w, p.h, mode);
+    }
+
+    /// Text: (x, y) を左上として1行の文字列を描画（折り返し・縮小なし）
+    fn handle_text(&mut self, params: &serde_json::Value) {
+        let p: TextParams = match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        let Some(text) = get_text_from_value(&p.text).filter(|t| !t.is_empty()) else {
+            return;
+        };
+        if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
+            layer.set_fill_color(self.text_color.clone());
+            let y_mm = y_convert_text(p.y, line_height_mm(self.font_size), self.font_size, self.page_height_mm);
+            self.draw_text(layer, &text, self.font_size, mm(p.x), y_mm, font, self.bold);
         }
     }
 
-    fn draw_rect(&self, x: f64, y: f64, w: f64, h: f64) {
-        if let Some(layer) = &self.current_layer {
-            // 線幅を設定（TCPDFのデフォルトは約0.2mm）
-            layer.set_outline_thickness(0.2);
+    /// Image: JPEG・PNGを (x, y) を左上として w×h mm で配置（読み込めなければ警告して飛ばす）
+    fn handle_image(&mut self, params: &serde_json::Value) {
+        let p: ImageParams = match serde_json::from_value(params.clone()) {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        let image = match image_bytes(&p).and_then(|bytes| load_image(&bytes)) {
+            Ok(image) => image,
+            Err(e) => {
+                warn!("Image {}: {}", p.file.as_deref().unwrap_or("(data)"), e);
+                return;
+            }
+        };
+        let Some(layer) = &self.current_layer else {
+            return;
+        };
 
-            let points = vec![
-                (Point::new(mm(x), y_convert(y, self.page_height_mm)), false),
-                (Point::new(mm(x + w), y_convert(y, self.page_height_mm)), false),
-                (Point::new(mm(x + w), y_convert(y + h, self.page_height_mm)), false),
-                (Point::new(mm(x), y_convert(y + h, self.page_height_mm)), false),
-            ];
-            let rect = Line {
-                points,
-                is_closed: true,
-            };
-            layer.add_line(rect);
+        // 72dpi（1px = 1pt）の大きさを基準に拡大・縮小する
+        let (px_w, px_h) = (image.width.0 as f64, image.height.0 as f64);
+        let natural_w = px_w / mm_to_pt(1.0);
+        let natural_h = px_h / mm_to_pt(1.0);
+        let (w, h) = match (p.w > 0.0, p.h > 0.0) {
+            (true, true) => (p.w, p.h),
+            (true, false) => (p.w, p.w * natural_h / natural_w),
+            (false, true) => (p.h * natural_w / natural_h, p.h),
+            (false, false) => (natural_w, natural_h),
+        };
+        Image::from(image).add_to_layer(
+            layer.clone(),
+            ImageTransform {
+                translate_x: Some(mm(p.x)),
+                translate_y: Some(y_convert(p.y + h, self.page_height_mm)),
+                scale_x: Some((mm_to_pt(w) / px_w) as f32),
+                scale_y: Some((mm_to_pt(h) / px_h) as f32),
+                dpi: Some(72.0),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// 枠線の太さと色を設定（TCPDFのデフォルトは約0.2mm、SetLineWidth・SetDrawColorの指定があればそれを使う）
+    fn set_border_style(&self, layer: &PdfLayerReference) {
+        layer.set_outline_thickness(self.line_width_mm.map_or(0.2, |w| mm_to_pt(w) as f32));
+        if let Some(color) = &self.draw_color {
+            layer.set_outline_color(color.clone());
         }
     }
 
-    fn draw_filled_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+    /// 枠線の1辺を描画（座標はTCPDF座標系のmm）
+    fn draw_segment(&self, x1: f64, y1: f64, x2: f64, y2: f64) {
         if let Some(layer) = &self.current_layer {
-            let points = vec![
-                (Point::new(mm(x), y_convert(y, self.page_height_mm)), false),
-                (Point::new(mm(x + w), y_convert(y, self.page_height_mm)), false),
-                (Point::new(mm(x + w), y_convert(y + h, self.page_height_mm)), false),
-                (Point::new(mm(x), y_convert(y + h, self.page_height_mm)), false),
-            ];
-            let polygon = Polygon {
-                rings: vec![points],
-                mode: PaintMode::Fill,
-                winding_order: WindingOrder::NonZero,
-            };
-            layer.set_fill_color(self.fill_color.clone());
-            layer.add_polygon(polygon);
+            self.set_border_style(layer);
+            layer.add_line(Line {
+                points: vec![
+                    (Point::new(mm(x1), y_convert(y1, self.page_height_mm)), false),
+                    (Point::new(mm(x2), y_convert(y2, self.page_height_mm)), false),
+                ],
+                is_closed: false,
+            });
+        }
+    }
+
+    /// 矩形を描画（Stroke: 枠線、Fill: setFillColorの色で塗りつぶし、FillStroke: 両方）
+    /// 塗りつぶしの後に文字を書く場合は呼び出し側で色を黒に戻す
+    fn draw_rect(&self, x: f64, y: f64, w: f64, h: f64, mode: PaintMode) {
+        if let Some(layer) = &self.current_layer {
+            if matches!(mode, PaintMode::Fill | PaintMode::FillStroke) {
+                layer.set_fill_color(self.fill_color.clone());
+            }
+            if matches!(mode, PaintMode::Stroke | PaintMode::FillStroke) {
+                self.set_border_style(layer);
+            }
+            let rect = Rect::new(
+                mm(x),
+                y_convert(y + h, self.page_height_mm),
+                mm(x + w),
+                y_convert(y, self.page_height_mm),
+            )
+            .with_mode(mode)
+            .with_winding(WindingOrder::NonZero);
+            layer.add_rect(rect);
         }
     }
 
     /// タイムカードデータからPDFを生成
     /// 1ページに3人分のタイムカードを配置
-    pub fn render_timecards(&mut self, timecards: &[MonthlyTimecard]) {
+    /// 引数とフォント以外（DB・環境変数）は参照しない
+    pub fn render_timecards(&mut self, timecards: &[MonthlyTimecard], options: &RenderOptions) -> Result<(), String> {
+        let (year, month) = target_month(timecards);
+        let _span = info_span!("render_timecards", year, month, drivers = timecards.len()).entered();
+        self.render = options.clone();
         // 埋め込みフォントを使用
-        let cursor = Cursor::new(MSMINCHO_FONT.to_vec());
-        self.font = Some(
-            self.doc
-                .add_external_font(cursor)
-                .expect("Failed to add font"),
-        );
+        self.font = Some(self.default_font()?);
+        self.bold_font = self.load_bold_font();
+        self.document_month = timecards.first().and_then(|tc| NaiveDate::from_ymd_opt(tc.year, tc.month, 1));
+        self.resolve_metadata("タイムカード");
 
         // レイアウト定数
-        const PERSON_WIDTH: f64 = 99.0;  // 1人分の幅（297mm / 3）
         const HEADER_HEIGHT: f64 = 10.0; // ヘッダー高さ
         const ROW_HEIGHT: f64 = 5.0;     // 行高さ
-        const TOP_MARGIN: f64 = 5.0;     // 上マージン
 
         // カラム幅（合計 = 93mm）
         const COL_DAY: f64 = 8.0;        // 日
@@ -345,92 +1201,190 @@ impl TcpdfCompat {
         const COL_TIME: f64 = 11.0;      // 出勤/退社（4列 = 44mm）
         const COL_OVERTIME: f64 = 11.0;  // 残業
         const COL_REMARKS: f64 = 11.0;   // 備考
-        const COL_KOSOKU: f64 = 13.0;    // 拘束時間
-        const TABLE_WIDTH: f64 = COL_DAY + COL_WEEKDAY + COL_TIME * 4.0 + COL_OVERTIME + COL_REMARKS + COL_KOSOKU; // 93mm
-        const LEFT_MARGIN: f64 = PERSON_WIDTH - TABLE_WIDTH;  // 右寄せ
 
-        // 3人ずつページを作成
-        for (chunk_idx, chunk) in timecards.chunks(3).enumerate() {
+        // 1人分の幅（既定は297mm / 3 = 99mm）と拘束時間の列（非表示なら幅0）
+        let per_page = self.render.drivers_per_page.max(1);
+        let person_width = self.page_width_mm / per_page as f64;
+        let top_margin = self.render.margin_top;
+        let left_margin = self.render.margin_left;
+        let col_kosoku = self.kosoku_column_width();
+        let table_width = COL_DAY + COL_WEEKDAY + COL_TIME * 4.0 + COL_OVERTIME + COL_REMARKS + col_kosoku; // 93mm
+
+        // per_page人ずつページを作成（フッターの総ページ数は人数から計算）
+        let total_pages = timecards.len().div_ceil(per_page);
+        let generated_at = self.generated_at();
+        for (chunk_idx, chunk) in timecards.chunks(per_page).enumerate() {
             // ページ追加
-            self.page_count += 1;
-            if self.page_count == 1 {
-                self.current_layer = self.first_page_layer.take();
-            } else {
-                let (page, layer) = self.doc.add_page(
-                    mm(self.page_width_mm),
-                    mm(self.page_height_mm),
-                    "Layer 1",
-                );
-                self.current_layer = Some(self.doc.get_page(page).get_layer(layer));
-            }
+            self.add_page();
 
-            // ページを3等分する縦線を描画（PHPのmakeIniLine相当）
-            self.draw_vertical_line(PERSON_WIDTH, 0.0, self.page_height_mm);
-            self.draw_vertical_line(PERSON_WIDTH * 2.0, 0.0, self.page_height_mm);
+            // ページを人数分に等分する縦線を描画（PHPのmakeIniLine相当）
+            for i in 1..per_page {
+                self.draw_vertical_line(person_width * i as f64, 0.0, self.page_height_mm);
+            }
 
             // 各人のタイムカードを描画
             for (person_idx, timecard) in chunk.iter().enumerate() {
-                let x_offset = person_idx as f64 * PERSON_WIDTH + LEFT_MARGIN;
+                let x_offset = person_idx as f64 * person_width + left_margin;
+                self.add_outline(timecard, top_margin);
 
                 // ヘッダー描画
-                self.render_timecard_header(timecard, x_offset, TOP_MARGIN, TABLE_WIDTH, HEADER_HEIGHT);
+                self.render_timecard_header(timecard, x_offset, top_margin, table_width, HEADER_HEIGHT);
 
                 // カラムヘッダー描画
-                let col_header_y = TOP_MARGIN + HEADER_HEIGHT;
+                let col_header_y = top_margin + HEADER_HEIGHT;
                 self.render_column_headers(x_offset, col_header_y, ROW_HEIGHT,
-                    COL_DAY, COL_WEEKDAY, COL_TIME, COL_OVERTIME, COL_REMARKS, COL_KOSOKU);
+                    COL_DAY, COL_WEEKDAY, COL_TIME, COL_OVERTIME, COL_REMARKS, col_kosoku);
 
                 // データ行描画
                 let data_start_y = col_header_y + ROW_HEIGHT;
                 self.render_timecard_data(timecard, x_offset, data_start_y, ROW_HEIGHT,
-                    COL_DAY, COL_WEEKDAY, COL_TIME, COL_OVERTIME, COL_REMARKS, COL_KOSOKU);
+                    COL_DAY, COL_WEEKDAY, COL_TIME, COL_OVERTIME, COL_REMARKS, col_kosoku);
 
                 // 集計部分を描画（31日分のデータの下）
                 let summary_y = data_start_y + 31.0 * ROW_HEIGHT;
-                self.render_timecard_summary(timecard, x_offset, summary_y, ROW_HEIGHT, TABLE_WIDTH);
+                self.render_timecard_summary(timecard, x_offset, summary_y, ROW_HEIGHT, table_width);
+            }
+
+            if self.render.footer {
+                self.render_footer(chunk_idx + 1, total_pages, generated_at);
+            }
+
+            debug!("Page {} rendered ({} people)", chunk_idx + 1, chunk.len());
+        }
+        Ok(())
+    }
+
+    /// 複数月を1つのPDFに描画（タイムカードのある月が2つ以上なら、各月の前に区切りページを入れる）
+    /// render は render_timecards か render_timecards_shukei（フッターのページ番号は月ごと）
+    pub fn render_months(
+        &mut self,
+        months: &[((i32, u32), Vec<MonthlyTimecard>)],
+        options: &RenderOptions,
+        render: fn(&mut Self, &[MonthlyTimecard], &RenderOptions) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let months: Vec<_> = months.iter().filter(|(_, timecards)| !timecards.is_empty()).collect();
+        let separate = months.len() > 1;
+        for ((year, month), timecards) in months {
+            if separate {
+                self.render_month_separator(*year, *month)?;
             }
+            render(self, timecards, options)?;
+        }
+        Ok(())
+    }
+
+    /// 月の区切りページ（中央に「YYYY年M月分」、しおりも追加）
+    fn render_month_separator(&mut self, year: i32, month: u32) -> Result<(), String> {
+        const SEPARATOR_FONT_SIZE: f32 = 28.0;
+        self.font = Some(self.default_font()?);
+        self.bold_font = self.load_bold_font();
+        self.add_page();
 
-            println!("Page {} rendered ({} people)", chunk_idx + 1, chunk.len());
+        let title = format!("{}年{}月分", year, month);
+        if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
+            let h = 20.0;
+            let y = (self.page_height_mm - h) / 2.0;
+            let x = calc_text_x(0.0, self.page_width_mm, &title, SEPARATOR_FONT_SIZE, "C");
+            let black = Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None));
+            layer.set_fill_color(black.clone());
+            self.draw_text(layer, &title, SEPARATOR_FONT_SIZE, mm(x), y_convert_text(y, h, SEPARATOR_FONT_SIZE, self.page_height_mm), font, true);
+            self.record_cell(CellRecord { x: 0.0, y, w: self.page_width_mm, h, text: &title, font_size: SEPARATOR_FONT_SIZE, bold: true, border: 0, align: "C", fill: false }, &black);
+        }
+        self.outlines.push(OutlineEntry { title, page: self.page_count, y_mm: 0.0 });
+        Ok(())
+    }
+
+    /// 現在のページにドライバーのしおり（「給与番号 名前」）を追加
+    fn add_outline(&mut self, timecard: &MonthlyTimecard, y_mm: f64) {
+        let title = match timecard.driver.kyuyo_shain_id {
+            Some(id) => format!("{} {}", id, timecard.driver.name),
+            None => timecard.driver.name.clone(),
+        };
+        self.outlines.push(OutlineEntry { title, page: self.page_count, y_mm });
+    }
+
+    /// Webのタイムカード（TIMECARD_LINK_BASE + /time-card?driver_id=...&month=YYYY-MM）
+    fn timecard_url(&self, timecard: &MonthlyTimecard) -> String {
+        format!("{}/time-card?driver_id={}&month={}", self.render.link_base, timecard.driver.id, timecard_month(timecard))
+    }
+
+    /// 拘束時間の列の幅（show_kosoku_column=false なら0で描画しない）
+    fn kosoku_column_width(&self) -> f64 {
+        if self.render.show_kosoku_column { 13.0 } else { 0.0 }
+    }
+
+    /// フッターの作成日時（決定的出力では固定日付の0時）
+    fn generated_at(&self) -> NaiveDateTime {
+        if self.save_options.deterministic {
+            let date = self.save_options.fixed_date.or(self.document_month).unwrap_or_default();
+            date.and_hms_opt(0, 0, 0).unwrap()
+        } else {
+            Local::now().naive_local()
+        }
+    }
+
+    /// ページ下部のフッター（左: 作成日時・対象年月、右: ページ n / N）
+    fn render_footer(&self, page: usize, total_pages: usize, generated_at: NaiveDateTime) {
+        if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
+            const FOOTER_FONT_SIZE: f32 = 7.0;
+            let y = self.page_height_mm - 5.0;
+            let h = 4.0;
+            layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+
+            let mut left = format!("作成 {}", generated_at.format("%Y-%m-%d %H:%M"));
+            if let Some(month) = self.document_month {
+                left.push_str(&format!("  {}分", month.format("%Y年%m月")));
+            }
+            if let Some(label) = self.render.kosoku_source_label {
+                left.push_str(&format!("  {}", label));
+            }
+            layer.use_text(&left, FOOTER_FONT_SIZE, mm(5.0), y_convert_text(y, h, FOOTER_FONT_SIZE, self.page_height_mm), font);
+            let black = Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None));
+            let record = |x: f64, w: f64, text: &str, align: &str| {
+                self.record_cell(CellRecord { x, y, w, h, text, font_size: FOOTER_FONT_SIZE, bold: false, border: 0, align, fill: false }, &black);
+            };
+            record(5.0, self.page_width_mm / 2.0, &left, "L");
+
+            let right = format!("ページ {} / {}", page, total_pages);
+            let right_x = calc_text_x(0.0, self.page_width_mm - 5.0, &right, FOOTER_FONT_SIZE, "R");
+            layer.use_text(&right, FOOTER_FONT_SIZE, mm(right_x), y_convert_text(y, h, FOOTER_FONT_SIZE, self.page_height_mm), font);
+            record(0.0, self.page_width_mm - 5.0, &right, "R");
         }
     }
 
     /// タイムカードヘッダー（氏名、年月）を描画
-    fn render_timecard_header(&self, timecard: &MonthlyTimecard, x: f64, y: f64, w: f64, h: f64) {
+    fn render_timecard_header(&mut self, timecard: &MonthlyTimecard, x: f64, y: f64, w: f64, h: f64) {
         if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
             // 枠線
-            self.draw_rect(x, y, w, h);
+            self.draw_rect(x, y, w, h, PaintMode::Stroke);
+            let black = Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None));
+            let header_size = self.render.header_font_size;
+            self.record_cell(CellRecord { x, y, w, h, text: "", font_size: header_size, bold: false, border: 1, align: "L", fill: false }, &black);
 
-            // 氏名（左側）
-            let name = &timecard.driver.name;
+            // 氏名（左側、年月の手前までに収める）
+            let (name, name_size) = fit_text(&timecard.driver.name, w - 37.0, header_size);
             layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
             let name_x = mm(x + 2.0);
-            let name_y = y_convert_text(y, h, 12.0, self.page_height_mm);
-            layer.use_text(name, 12.0, name_x, name_y, font);
-
-            // 氏名にリンクを追加（PHPのTimeCardController.php:3629相当）
-            let year_month_link = format!("{}-{:02}", timecard.year, timecard.month);
-            let link_w = 30.0;
-            layer.add_link_annotation(printpdf::LinkAnnotation::new(
-                printpdf::Rect::new(
-                    mm(x + 2.0),
-                    mm(self.page_height_mm - y - h),
-                    mm(x + 2.0 + link_w),
-                    mm(self.page_height_mm - y),
-                ),
-                None,
-                None,
-                printpdf::Actions::uri(format!("/time-card?driver_id={}&month={}", timecard.driver.id, year_month_link)),
-                None,
-            ));
+            self.draw_text(layer, &name, name_size, name_x, y_convert_text(y, h, name_size, self.page_height_mm), font, true);
+            self.record_cell(CellRecord { x: x + 2.0, y, w: w - 37.0, h, text: &name, font_size: name_size, bold: true, border: 0, align: "L", fill: false }, &black);
+            let name_y = y_convert_text(y, h, header_size, self.page_height_mm);
 
             // 年月（右側）
             let year_month = timecard.year_month_str();
             let ym_x = mm(x + w - 35.0);
             layer.use_text(&year_month, 10.0, ym_x, name_y, font);
+            self.record_cell(CellRecord { x: x + w - 35.0, y, w: 35.0, h, text: &year_month, font_size: 10.0, bold: false, border: 0, align: "L", fill: false }, &black);
         }
+
+        // 氏名セル（年月の手前まで）にWebのタイムカードへのリンクを追加（PHPのTimeCardController.php:3629相当）
+        // 現在のページ番号で保持するので、2人目・3人目もそのページに載る
+        let url = self.timecard_url(timecard);
+        self.record_link(x, y, w - 35.0, h, &url);
+        self.links.push(LinkInfo { page: self.page_count, x_mm: x, y_mm: y, w_mm: w - 35.0, h_mm: h, target: LinkTarget::Uri(url) });
     }
 
     /// カラムヘッダーを描画
+    #[allow(clippy::too_many_arguments)]
     fn render_column_headers(&self, x: f64, y: f64, h: f64,
         col_day: f64, col_weekday: f64, col_time: f64, col_overtime: f64, col_remarks: f64, col_kosoku: f64) {
 
@@ -440,15 +1394,19 @@ impl TcpdfCompat {
             let headers = ["日", "曜", "出勤1", "退社1", "出勤2", "退社2", "残業", "備考", "拘束"];
             let widths = [col_day, col_weekday, col_time, col_time, col_time, col_time, col_overtime, col_remarks, col_kosoku];
 
+            let font_size = self.render.font_size;
             let mut current_x = x;
-            for (header, width) in headers.iter().zip(widths.iter()) {
+            // 幅0の列（非表示の拘束時間）は描画しない
+            for (header, width) in headers.iter().zip(widths.iter()).filter(|(_, w)| **w > 0.0) {
                 // 枠線
-                self.draw_rect(current_x, y, *width, h);
+                self.draw_rect(current_x, y, *width, h, PaintMode::Stroke);
 
                 // テキスト（中央揃え）
-                let text_x = calc_text_x(current_x, *width, header, 10.0, "C");
-                let text_y = y_convert_text(y, h, 10.0, self.page_height_mm);
-                layer.use_text(*header, 10.0, mm(text_x), text_y, font);
+                let text_x = calc_text_x(current_x, *width, header, font_size, "C");
+                let text_y = y_convert_text(y, h, font_size, self.page_height_mm);
+                self.draw_text(layer, header, font_size, mm(text_x), text_y, font, true);
+                let cell = CellRecord { x: current_x, y, w: *width, h, text: header, font_size, bold: true, border: 1, align: "C", fill: false };
+                self.record_cell(cell, &Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
 
                 current_x += width;
             }
@@ -456,74 +1414,102 @@ impl TcpdfCompat {
     }
 
     /// タイムカードデータ行を描画
-    fn render_timecard_data(&self, timecard: &MonthlyTimecard, x: f64, start_y: f64, row_h: f64,
+    #[allow(clippy::too_many_arguments)]
+    fn render_timecard_data(&mut self, timecard: &MonthlyTimecard, x: f64, start_y: f64, row_h: f64,
         col_day: f64, col_weekday: f64, col_time: f64, col_overtime: f64, col_remarks: f64, col_kosoku: f64) {
 
+        // 残業欄・日付欄のリンク（保存時にlopdfで追加するので、用紙サイズ・回転に合わせた位置になる）
+        let mut links = Vec::new();
         if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
             let widths = [col_day, col_weekday, col_time, col_time, col_time, col_time, col_overtime, col_remarks, col_kosoku];
 
             for (row_idx, day) in timecard.days.iter().enumerate() {
                 let y = start_y + row_idx as f64 * row_h;
 
-                // データ配列を作成
-                let in1 = day.clock_in.get(0).map(|s| s.as_str()).unwrap_or("");
-                let out1 = day.clock_out.get(0).map(|s| s.as_str()).unwrap_or("");
-                let in2 = day.clock_in.get(1).map(|s| s.as_str()).unwrap_or("");
-                let out2 = day.clock_out.get(1).map(|s| s.as_str()).unwrap_or("");
+                // データ配列を作成（時刻はデジタコ有無で [/] か </> で囲む）
+                let [in1, out1, in2, mut out2] = self.render.punch_symbols.punches(day);
+                // 3ペア目以降は退社2の後ろに件数だけ表示
+                if day.extra_punch_pairs() > 0 {
+                    out2.push_str(&format!("+{}", day.extra_punch_pairs()));
+                }
 
                 // 備考（PHPでは畜/引マークを備考に出力していない）
                 // remarks + detail_st + 作マークを連結（PHPと同じ）
-                let mut remarks = format!("{}{}", day.remarks, day.detail_st);
+                // 備考が複数ある日は「・」で区切る（セルに収まらなければ区切りなし）
+                let mut remarks = day.remarks_text("・");
+                if measure_text(&remarks, self.render.font_size) > col_remarks {
+                    remarks = day.remarks_text("");
+                }
                 if day.has_daily_report {
-                    remarks.push_str("作");
+                    remarks.push('作');
                 }
 
                 let values = [
                     day.day.to_string(),
-                    day.weekday.clone(),
-                    in1.to_string(),
-                    out1.to_string(),
-                    in2.to_string(),
-                    out2.to_string(),
+                    day.weekday_label(),    // 祝日は「祝」付き
+                    in1,
+                    out1,
+                    in2,
+                    out2,
                     day.zangyo_with_tsuika_str(),  // 残業+追加作業
                     remarks,                // 備考
                     day.kosoku_str(),       // 拘束時間（別列）
                 ];
 
-                // 各セルを描画
+                // 各セルを描画（幅0の列は描画しない）
                 let mut current_x = x;
-                for (col_idx, (value, width)) in values.iter().zip(widths.iter()).enumerate() {
+                for (col_idx, (value, width)) in values.iter().zip(widths.iter()).enumerate().filter(|(_, (_, w))| **w > 0.0) {
                     // 曜日列（col_idx=1）で日曜日・祝日の場合はグレー背景
-                    if col_idx == 1 && (day.is_sunday || day.is_holiday) {
+                    // 拘束時間列（col_idx=8）で閾値（既定14時間=840分）超えの場合はグレー背景
+                    // 在籍していない日（入社前・退職後）は行全体をグレー背景（shade_not_employed=false なら付けない）
+                    let over_limit = matches!((day.kosoku_minutes, self.render.kosoku_highlight_minutes), (Some(minutes), Some(limit)) if minutes > limit);
+                    let fill = (col_idx == 1 && (day.is_sunday || day.is_holiday))
+                        || (col_idx == 8 && over_limit)
+                        || (self.render.shade_not_employed && day.is_not_employed());
+                    if fill {
                         self.draw_filled_rect_gray(current_x, y, *width, row_h);
                     }
 
-                    // 拘束時間列（col_idx=8）で14時間（840分）超えの場合はグレー背景
-                    if col_idx == 8 {
-                        if let Some(minutes) = day.kosoku_minutes {
-                            if minutes > 840 {
-                                self.draw_filled_rect_gray(current_x, y, *width, row_h);
-                            }
-                        }
-                    }
-
                     // 枠線
-                    self.draw_rect(current_x, y, *width, row_h);
-
-                    // テキスト描画 - 色を黒に設定してから描画
+                    self.draw_rect(current_x, y, *width, row_h, PaintMode::Stroke);
+
+                    // テキスト描画 - 色を設定してから描画（日曜日・祝日は日付・曜日・備考を赤）
+                    // 拘束時間列（col_idx=8）は本文の8割（既定8pt）、それ以外は本文（既定10pt）
+                    // 収まらなければ縮小・省略（時刻列の囲み記号、長い備考など）
+                    let color = if matches!(col_idx, 0 | 1 | 7) { day_text_color(day) } else { Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)) };
+                    let size = self.render.font_size;
+                    let (value, font_size) = fit_text(value, *width, if col_idx == 8 { size * 0.8 } else { size });
                     if !value.is_empty() {
-                        layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
-                        // 拘束時間列（col_idx=8）は8pt、それ以外は10pt
-                        let font_size = if col_idx == 8 { 8.0 } else { 10.0 };
-                        let text_x = calc_text_x(current_x, *width, value, font_size, "C");
+                        layer.set_fill_color(color.clone());
+                        let text_x = calc_text_x(current_x, *width, &value, font_size, "C");
                         let text_y = y_convert_text(y, row_h, font_size, self.page_height_mm);
-                        layer.use_text(value, font_size, mm(text_x), text_y, font);
+                        layer.use_text(&value, font_size, mm(text_x), text_y, font);
+                    }
+                    let cell = CellRecord { x: current_x, y, w: *width, h: row_h, text: &value, font_size, bold: false, border: 1, align: "C", fill };
+                    self.record_cell(cell, &color);
+
+                    // 残業欄（col_idx=6）は元の旅費行へのリンクを追加（ZANGYO_LINK_TEMPLATE設定時のみ）
+                    if col_idx == 6 {
+                        let ryohi_row = day.zangyo_sources.iter().find(|s| s.table == ZangyoTable::RyohiRows);
+                        if let (Some(template), Some(source)) = (&self.render.zangyo_link_template, ryohi_row) {
+                            let url = template.replace("{id}", &source.row_id);
+                            self.record_link(current_x, y, *width, row_h, &url);
+                            links.push(LinkInfo { page: self.page_count, x_mm: current_x, y_mm: y, w_mm: *width, h_mm: row_h, target: LinkTarget::Uri(url) });
+                        }
+                    }
+
+                    // 日付欄（col_idx=0）はデジタコのある日に日付・曜日セルへリンクを追加（digitacho_links指定時のみ）
+                    if col_idx == 0 && day.has_digitacho && self.render.digitacho_links {
+                        let url = format!("{}&day={}", self.timecard_url(timecard), day.day);
+                        self.record_link(current_x, y, widths[0] + widths[1], row_h, &url);
+                        links.push(LinkInfo { page: self.page_count, x_mm: current_x, y_mm: y, w_mm: widths[0] + widths[1], h_mm: row_h, target: LinkTarget::Uri(url) });
                     }
 
                     // 備考欄（col_idx=7）で作業日報がある場合はリンクを追加
                     if col_idx == 7 && day.has_daily_report {
                         let act_date = format!("{}-{:02}-{:02}", timecard.year, timecard.month, day.day);
                         let url = format!("/daily-report/search-report/{}/{}", act_date, timecard.driver.id);
+                        self.record_link(current_x, y, *width, row_h, &url);
                         layer.add_link_annotation(printpdf::LinkAnnotation::new(
                             printpdf::Rect::new(
                                 mm(current_x),
@@ -542,6 +1528,7 @@ impl TcpdfCompat {
                 }
             }
         }
+        self.links.extend(links);
     }
 
     /// 集計部分を描画
@@ -556,15 +1543,24 @@ impl TcpdfCompat {
                 .map(|id| id.to_string())
                 .unwrap_or_default();
 
+            // 座標JSONには10ptの本文セルとして記録
+            let black = Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None));
+            let record = |x: f64, y: f64, w: f64, text: &str, border: i32, align: &str| {
+                self.record_cell(CellRecord { x, y, w, h: row_h, text, font_size: 10.0, bold: false, border, align, fill: false }, &black);
+            };
+
             // 社員番号
             layer.use_text(&kyuyo_id, 10.0, mm(x + 2.0), y_convert_text(y, row_h, 10.0, self.page_height_mm), font);
+            record(x + 2.0, y, 13.0, &kyuyo_id, 0, "L");
 
             // 氏名
             layer.use_text(&timecard.driver.name, 10.0, mm(x + 15.0), y_convert_text(y, row_h, 10.0, self.page_height_mm), font);
+            record(x + 15.0, y, width - 33.0, &timecard.driver.name, 0, "L");
 
             // 拘束時間合計（右端）
             let kosoku_str = summary.total_kosoku_str();
             layer.use_text(&kosoku_str, 10.0, mm(x + width - 18.0), y_convert_text(y, row_h, 10.0, self.page_height_mm), font);
+            record(x + width - 18.0, y, 18.0, &kosoku_str, 0, "L");
 
             // 2行目: ヘッダー（出、休、有、欠、遅、早、特）
             let y2 = y + row_h;
@@ -572,9 +1568,10 @@ impl TcpdfCompat {
             let headers = ["出", "休", "有", "欠", "遅", "早", "特"];
             for (i, header) in headers.iter().enumerate() {
                 let cell_x = x + i as f64 * col_w;
-                self.draw_rect(cell_x, y2, col_w, row_h);
+                self.draw_rect(cell_x, y2, col_w, row_h, PaintMode::Stroke);
                 let text_x = calc_text_x(cell_x, col_w, header, 10.0, "C");
                 layer.use_text(*header, 10.0, mm(text_x), y_convert_text(y2, row_h, 10.0, self.page_height_mm), font);
+                record(cell_x, y2, col_w, header, 1, "C");
             }
 
             // 3行目: 値（出勤、休日、有休、欠勤、遅刻、早退、特休）
@@ -590,9 +1587,10 @@ impl TcpdfCompat {
             ];
             for (i, value) in values.iter().enumerate() {
                 let cell_x = x + i as f64 * col_w;
-                self.draw_rect(cell_x, y3, col_w, row_h);
+                self.draw_rect(cell_x, y3, col_w, row_h, PaintMode::Stroke);
                 let text_x = calc_text_x(cell_x, col_w, value, 10.0, "C");
                 layer.use_text(value, 10.0, mm(text_x), y_convert_text(y3, row_h, 10.0, self.page_height_mm), font);
+                record(cell_x, y3, col_w, value, 1, "C");
             }
 
             // 4行目: ヘッダー（残業、休出、引、畜、追）
@@ -601,9 +1599,10 @@ impl TcpdfCompat {
             let widths2 = [14.0, 10.0, 10.0, 10.0, 10.0];
             let mut cx = x;
             for (header, w) in headers2.iter().zip(widths2.iter()) {
-                self.draw_rect(cx, y4, *w, row_h);
+                self.draw_rect(cx, y4, *w, row_h, PaintMode::Stroke);
                 let text_x = calc_text_x(cx, *w, header, 10.0, "C");
                 layer.use_text(*header, 10.0, mm(text_x), y_convert_text(y4, row_h, 10.0, self.page_height_mm), font);
+                record(cx, y4, *w, header, 1, "C");
                 cx += w;
             }
 
@@ -627,9 +1626,10 @@ impl TcpdfCompat {
             ];
             let mut cx = x;
             for (value, w) in values2.iter().zip(widths2.iter()) {
-                self.draw_rect(cx, y5, *w, row_h);
+                self.draw_rect(cx, y5, *w, row_h, PaintMode::Stroke);
                 let text_x = calc_text_x(cx, *w, value, 10.0, "C");
                 layer.use_text(value, 10.0, mm(text_x), y_convert_text(y5, row_h, 10.0, self.page_height_mm), font);
+                record(cx, y5, *w, value, 1, "C");
                 cx += w;
             }
         }
@@ -637,28 +1637,31 @@ impl TcpdfCompat {
 
     /// 集計モード: タイムカードデータからPDFを生成
     /// 1人1ページ、日付を横並びで表示
-    pub fn render_timecards_shukei(&mut self, timecards: &[MonthlyTimecard]) {
+    pub fn render_timecards_shukei(&mut self, timecards: &[MonthlyTimecard], options: &RenderOptions) -> Result<(), String> {
+        let (year, month) = target_month(timecards);
+        let _span = info_span!("render_timecards_shukei", year, month, drivers = timecards.len()).entered();
+        self.render = options.clone();
         // 埋め込みフォントを使用
-        let cursor = Cursor::new(MSMINCHO_FONT.to_vec());
-        self.font = Some(
-            self.doc
-                .add_external_font(cursor)
-                .expect("Failed to add font"),
-        );
+        self.font = Some(self.default_font()?);
+        self.bold_font = self.load_bold_font();
+        self.document_month = timecards.first().and_then(|tc| NaiveDate::from_ymd_opt(tc.year, tc.month, 1));
+        self.resolve_metadata("タイムカード集計");
+        let remark_symbols = self.render.remark_symbols.clone();
+        let generated_at = self.generated_at();
+
+        // 目次ページ（shukei_index指定時、フッターのページ番号は目次を含めて数える）
+        let index_pages = if self.render.shukei_index {
+            self.render_shukei_index(timecards, generated_at)
+        } else {
+            0
+        };
+        let total_pages = index_pages + timecards.len();
 
-        for timecard in timecards {
+        for (page_idx, timecard) in timecards.iter().enumerate() {
             // ページ追加
-            self.page_count += 1;
-            if self.page_count == 1 {
-                self.current_layer = self.first_page_layer.take();
-            } else {
-                let (page, layer) = self.doc.add_page(
-                    mm(self.page_width_mm),
-                    mm(self.page_height_mm),
-                    "Layer 1",
-                );
-                self.current_layer = Some(self.doc.get_page(page).get_layer(layer));
-            }
+            self.add_page();
+
+            self.add_outline(timecard, 0.0);
 
             let days_in_month = timecard.days.len();
             let cell_w = 8.0;  // 日付セルの幅（8mm × 31日 = 248mm）
@@ -678,24 +1681,8 @@ impl TcpdfCompat {
             }
 
             // 氏名にリンクを追加
-            let year_month_link = format!("{}-{:02}", timecard.year, timecard.month);
-            if let Some(layer) = &self.current_layer {
-                let name_x = ind_x + 30.0;
-                let name_w = 40.0;
-                let name_h = 6.0;
-                layer.add_link_annotation(printpdf::LinkAnnotation::new(
-                    printpdf::Rect::new(
-                        mm(name_x),
-                        mm(self.page_height_mm - y - name_h),
-                        mm(name_x + name_w),
-                        mm(self.page_height_mm - y),
-                    ),
-                    None,
-                    None,
-                    printpdf::Actions::uri(format!("/time-card?driver_id={}&month={}", timecard.driver.id, year_month_link)),
-                    None,
-                ));
-            }
+            let url = self.timecard_url(timecard);
+            self.links.push(LinkInfo { page: self.page_count, x_mm: ind_x + 30.0, y_mm: y, w_mm: 40.0, h_mm: 6.0, target: LinkTarget::Uri(url) });
 
             // ===== リンクボタン: TC, 集計, 出勤簿, DrV（名前の右側） =====
             let link_w = 30.0;
@@ -710,11 +1697,11 @@ impl TcpdfCompat {
                 // 背景（グレー）
                 self.draw_filled_rect_gray(x, link_y, link_w, link_h);
                 // 枠線
-                self.draw_rect(x, link_y, link_w, link_h);
+                self.draw_rect(x, link_y, link_w, link_h, PaintMode::Stroke);
                 // テキスト
                 if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
                     layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
-                    let text_x = calc_text_x(x, link_w, *label, 10.0, "C");
+                    let text_x = calc_text_x(x, link_w, label, 10.0, "C");
                     layer.use_text(*label, 10.0, mm(text_x), y_convert_text(link_y, link_h, 10.0, self.page_height_mm), font);
                 }
             }
@@ -728,7 +1715,7 @@ impl TcpdfCompat {
                     format!("/time-card/create-shukkinbo/{}/{}", year_month_str, driver_id),
                     format!("/drivers/view/{}", driver_id),
                 ];
-                for (i, url) in links.iter().enumerate() {
+                for (i, url) in links.iter().map(|path| format!("{}{}", self.render.link_base, path)).enumerate() {
                     let x = link_x + i as f64 * link_w;
                     layer.add_link_annotation(printpdf::LinkAnnotation::new(
                         printpdf::Rect::new(
@@ -739,7 +1726,7 @@ impl TcpdfCompat {
                         ),
                         None, // border
                         None, // color
-                        printpdf::Actions::uri(url.clone()),
+                        printpdf::Actions::uri(url),
                         None, // highlighting mode
                     ));
                 }
@@ -758,30 +1745,31 @@ impl TcpdfCompat {
                 }
 
                 // 枠線
-                self.draw_rect(x, y, cell_w, 4.0);
+                self.draw_rect(x, y, cell_w, 4.0, PaintMode::Stroke);
 
                 // 曜日テキスト
                 if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
-                    layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
-                    let text_x = calc_text_x(x, cell_w, &day.weekday, 10.0, "C");
-                    layer.use_text(&day.weekday, 10.0, mm(text_x), y_convert_text(y, 4.0, 10.0, self.page_height_mm), font);
+                    layer.set_fill_color(day_text_color(day));
+                    let (weekday, font_size) = fit_text(&day.weekday_label(), cell_w, 10.0);
+                    let text_x = calc_text_x(x, cell_w, &weekday, font_size, "C");
+                    layer.use_text(&weekday, font_size, mm(text_x), y_convert_text(y, 4.0, font_size, self.page_height_mm), font);
                 }
             }
             y += 4.0;
 
-            // ===== 日付行 =====
+            // ===== 日付行（備考の記号は日付に続けて表示、凡例はページ下部） =====
             for (i, day) in timecard.days.iter().enumerate() {
                 let x = ind_x + i as f64 * cell_w;
 
                 // 枠線
-                self.draw_rect(x, y, cell_w, 4.0);
+                self.draw_rect(x, y, cell_w, 4.0, PaintMode::Stroke);
 
-                // 日付テキスト
+                // 日付テキスト（記号付きで収まらなければ縮小）
                 if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
-                    layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
-                    let day_str = day.day.to_string();
-                    let text_x = calc_text_x(x, cell_w, &day_str, 10.0, "C");
-                    layer.use_text(&day_str, 10.0, mm(text_x), y_convert_text(y, 4.0, 10.0, self.page_height_mm), font);
+                    layer.set_fill_color(day_text_color(day));
+                    let (day_str, font_size) = fit_text(&format!("{}{}", day.day, remark_symbols.day_symbols(day)), cell_w, 10.0);
+                    let text_x = calc_text_x(x, cell_w, &day_str, font_size, "C");
+                    layer.use_text(&day_str, font_size, mm(text_x), y_convert_text(y, 4.0, font_size, self.page_height_mm), font);
                 }
             }
             y += 4.0;
@@ -793,13 +1781,13 @@ impl TcpdfCompat {
                 let x = ind_x + i as f64 * cell_w;
 
                 // 休暇の場合は背景をグレー
-                let is_kyuka = matches!(day.remarks.as_str(), "公休" | "泊休" | "有休" | "特休" | "欠勤");
+                let is_kyuka = day.has_remark(|t| t.is_day_off());
                 if is_kyuka {
                     self.draw_filled_rect_gray(x, y, cell_w, 4.0);
                 }
 
                 // 枠線
-                self.draw_rect(x, y, cell_w, 4.0);
+                self.draw_rect(x, y, cell_w, 4.0, PaintMode::Stroke);
 
                 // 勤務状態テキスト
                 // PHPロジック: デジタコがある日は[/]、ない日は</>
@@ -812,9 +1800,9 @@ impl TcpdfCompat {
                 // X = 休暇
                 let mut st = String::new();
 
-                // デジタコデータまたは備考が「仮乗」の場合は[/]を使用
-                let drive_st = day.has_digitacho || day.remarks == "仮乗";
-                let (arrow_left, arrow_right) = if drive_st { ('[', ']') } else { ('<', '>') };
+                // デジタコデータまたは備考が「仮乗」の場合は[/]を使用（PUNCH_SYMBOLS で変更可能）
+                let drive_st = day.has_digitacho || day.has_remark(|t| *t == LeaveType::Karinori);
+                let (arrow_left, arrow_right) = self.render.punch_symbols.pair(day);
 
                 // 出勤/退勤マーク（最大2回分）
                 if !day.clock_in.is_empty() {
@@ -859,7 +1847,7 @@ impl TcpdfCompat {
                 let x = ind_x + i as f64 * cell_w;
 
                 // 枠線
-                self.draw_rect(x, y, cell_w, 4.0);
+                self.draw_rect(x, y, cell_w, 4.0, PaintMode::Stroke);
 
                 // 手当マーク
                 let mut teate = String::new();
@@ -876,6 +1864,21 @@ impl TcpdfCompat {
                     layer.use_text(&teate, 9.0, mm(text_x), y_convert_text(y, 4.0, 9.0, self.page_height_mm), font);
                 }
             }
+
+            // ===== 凡例（このページで使った記号のみ） =====
+            let legend = remark_symbols.legend(&timecard.days);
+            if !legend.is_empty() {
+                let legend_text = legend.iter()
+                    .map(|e| format!("{}={}", e.symbol, e.labels.join("/")))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
+                    layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+                    let legend_y = self.page_height_mm - 8.0;
+                    layer.use_text(format!("凡例: {}", legend_text), 8.0, mm(ind_x), y_convert_text(legend_y, 4.0, 8.0, self.page_height_mm), font);
+                }
+            }
+
             // ===== 左下: 日別タイムカード（カレンダーの下、Y=30.0から開始） =====
             // render_timecardsと同じ関数を使用
             let daily_list_y = 30.0;
@@ -885,7 +1888,7 @@ impl TcpdfCompat {
             let col_time = 11.0;
             let col_overtime = 11.0;
             let col_remarks = 11.0;
-            let col_kosoku = 13.0;
+            let col_kosoku = self.kosoku_column_width();
 
             // カラムヘッダー描画
             self.render_column_headers(ind_x, daily_list_y, row_h,
@@ -924,7 +1927,7 @@ impl TcpdfCompat {
             let count_headers = ["運転", "作業", "休暇", "不明"];
             for (i, header) in count_headers.iter().enumerate() {
                 let x = count_x + i as f64 * count_w;
-                self.draw_rect(x, count_y, count_w, 5.0);
+                self.draw_rect(x, count_y, count_w, 5.0, PaintMode::Stroke);
                 if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
                     layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
                     let text_x = calc_text_x(x, count_w, header, 9.0, "C");
@@ -938,7 +1941,9 @@ impl TcpdfCompat {
             let mut kyuka = 0;  // 休暇
 
             for day in &timecard.days {
-                let is_kyuka = matches!(day.remarks.as_str(), "公休" | "泊休" | "有休" | "特休" | "欠勤" | "入社前" | "退職後");
+                let is_kyuka = day.has_remark(|t| {
+                    t.is_day_off() || matches!(t, LeaveType::NyushaMae | LeaveType::TaishokuGo)
+                });
                 if is_kyuka {
                     kyuka += 1;
                 } else if day.kosoku_minutes.is_some() {
@@ -965,7 +1970,7 @@ impl TcpdfCompat {
                     self.draw_filled_rect_gray(x, vy, count_w, 5.0);
                 }
 
-                self.draw_rect(x, vy, count_w, 5.0);
+                self.draw_rect(x, vy, count_w, 5.0, PaintMode::Stroke);
                 if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
                     layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
                     let text_x = calc_text_x(x, count_w, value, 9.0, "C");
@@ -973,8 +1978,65 @@ impl TcpdfCompat {
                 }
             }
 
-            println!("Page {} rendered: {}", self.page_count, timecard.driver.name);
+            if self.render.footer {
+                self.render_footer(index_pages + page_idx + 1, total_pages, generated_at);
+            }
+
+            debug!("Page {} rendered: {}", self.page_count, timecard.driver.name);
+        }
+        Ok(())
+    }
+
+    /// 集計PDFの目次ページを描画し、ページ数を返す
+    /// 氏名（「給与番号 名前」）をクリックすると、目次の後に続くその人のページへ移動する
+    fn render_shukei_index(&mut self, timecards: &[MonthlyTimecard], generated_at: NaiveDateTime) -> usize {
+        const ROWS: usize = 30;     // 1列の行数
+        const COLUMNS: usize = 4;   // 1ページの列数
+        const TOP: f64 = 15.0;      // 一覧の開始Y座標
+        const ROW_H: f64 = 6.0;
+        const LEFT: f64 = 10.0;
+        let per_page = ROWS * COLUMNS;
+        let index_pages = timecards.len().div_ceil(per_page);
+        let total_pages = index_pages + timecards.len();
+        let first_driver_page = self.page_count as usize + index_pages + 1;
+        let col_w = (self.page_width_mm - LEFT * 2.0) / COLUMNS as f64;
+
+        for (page_idx, chunk) in timecards.chunks(per_page).enumerate() {
+            self.add_page();
+            if let (Some(layer), Some(font)) = (&self.current_layer, &self.font) {
+                layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+                let title = match timecards.first() {
+                    Some(tc) => format!("目次 {}", tc.year_month_str()),
+                    None => "目次".to_string(),
+                };
+                self.draw_text(layer, &title, 14.0, mm(LEFT), y_convert_text(5.0, 8.0, 14.0, self.page_height_mm), font, true);
+
+                for (i, timecard) in chunk.iter().enumerate() {
+                    let x = LEFT + (i / ROWS) as f64 * col_w;
+                    let y = TOP + (i % ROWS) as f64 * ROW_H;
+                    let label = match timecard.driver.kyuyo_shain_id {
+                        Some(id) => format!("{} {}", id, timecard.driver.name),
+                        None => timecard.driver.name.clone(),
+                    };
+                    let (label, size) = fit_text(&label, col_w, 10.0);
+                    layer.use_text(&label, size, mm(x + 0.5), y_convert_text(y, ROW_H, size, self.page_height_mm), font);
+                }
+            }
+            for (i, _) in chunk.iter().enumerate() {
+                self.links.push(LinkInfo {
+                    page: self.page_count,
+                    x_mm: LEFT + (i / ROWS) as f64 * col_w,
+                    y_mm: TOP + (i % ROWS) as f64 * ROW_H,
+                    w_mm: col_w,
+                    h_mm: ROW_H,
+                    target: LinkTarget::Page { index: (first_driver_page + page_idx * per_page + i) as u32, y_mm: 0.0 },
+                });
+            }
+            if self.render.footer {
+                self.render_footer(page_idx + 1, total_pages, generated_at);
+            }
         }
+        index_pages
     }
 
     /// 集計モード: 右側に集計部分を描画（参考レイアウト準拠）
@@ -991,7 +2053,7 @@ impl TcpdfCompat {
             let headers1 = ["出", "休", "有", "欠", "遅", "早", "特"];
             for (i, header) in headers1.iter().enumerate() {
                 let cx = x + i as f64 * col_w;
-                self.draw_rect(cx, y1, col_w, row_h);
+                self.draw_rect(cx, y1, col_w, row_h, PaintMode::Stroke);
                 let text_x = calc_text_x(cx, col_w, header, 10.0, "C");
                 layer.use_text(*header, 10.0, mm(text_x), y_convert_text(y1, row_h, 10.0, self.page_height_mm), font);
             }
@@ -1016,7 +2078,7 @@ impl TcpdfCompat {
             ];
             for (i, value) in values1.iter().enumerate() {
                 let cx = x + i as f64 * col_w;
-                self.draw_rect(cx, y2, col_w, row_h);
+                self.draw_rect(cx, y2, col_w, row_h, PaintMode::Stroke);
                 let text_x = calc_text_x(cx, col_w, value, 10.0, "C");
                 layer.use_text(value, 10.0, mm(text_x), y_convert_text(y2, row_h, 10.0, self.page_height_mm), font);
             }
@@ -1027,7 +2089,7 @@ impl TcpdfCompat {
             let widths2 = [14.0, 10.0, 10.0, 10.0, 10.0];
             let mut cx = x;
             for (header, w) in headers2.iter().zip(widths2.iter()) {
-                self.draw_rect(cx, y3, *w, row_h);
+                self.draw_rect(cx, y3, *w, row_h, PaintMode::Stroke);
                 let text_x = calc_text_x(cx, *w, header, 10.0, "C");
                 layer.use_text(*header, 10.0, mm(text_x), y_convert_text(y3, row_h, 10.0, self.page_height_mm), font);
                 cx += w;
@@ -1045,7 +2107,7 @@ impl TcpdfCompat {
             ];
             let mut cx = x;
             for (value, w) in values2.iter().zip(widths2.iter()) {
-                self.draw_rect(cx, y4, *w, row_h);
+                self.draw_rect(cx, y4, *w, row_h, PaintMode::Stroke);
                 let text_x = calc_text_x(cx, *w, value, 10.0, "C");
                 layer.use_text(value, 10.0, mm(text_x), y_convert_text(y4, row_h, 10.0, self.page_height_mm), font);
                 cx += w;
@@ -1055,6 +2117,7 @@ impl TcpdfCompat {
 
     /// 縦線を描画（ページ分割用）
     fn draw_vertical_line(&self, x: f64, y1: f64, y2: f64) {
+        self.record_line(x, y1, x, y2);
         if let Some(layer) = &self.current_layer {
             layer.set_outline_thickness(0.2);
             let points = vec![
@@ -1089,169 +2152,1587 @@ impl TcpdfCompat {
         }
     }
 
-    /// PDFをメモリ上で生成してバイト配列を返す（HTTPレスポンス用）
-    pub fn save_to_bytes(self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    /// MediaBoxがないページ用の既定値（pt）
+    fn default_media_box(&self) -> [f64; 4] {
+        [0.0, 0.0, mm_to_pt(self.page_width_mm), mm_to_pt(self.page_height_mm)]
+    }
+
+    /// 最初のAddPageで1ページ目の用紙が変わった場合、そのMediaBox（pt）
+    /// 1ページ目はPdfDocument::newで作成済みのため、保存時にlopdfで書き換える
+    fn resized_first_page(&self) -> Option<[f64; 4]> {
+        let &(width, height) = self.page_sizes.first()?;
+        (self.page_sizes[0] != self.first_page_size).then(|| [0.0, 0.0, mm_to_pt(width), mm_to_pt(height)])
+    }
+
+    /// PDFをメモリ上で生成してバイト配列を返す（HTTPレスポンス・save共通、ファイルは使わない）
+    pub fn save_to_bytes(mut self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let default_media_box = self.default_media_box();
+        let first_page_media_box = self.resized_first_page();
+        self.draw_watermarks()?;
+
         // まずprintpdfでPDFをメモリ上に生成
         let mut buffer = Vec::new();
         {
             self.doc.save(&mut BufWriter::new(&mut buffer))?;
         }
 
-        // リンクがない場合はそのまま返す
-        if self.links.is_empty() {
+        // リンクがなく、決定的出力・文書情報・代替フォントの記録・1ページ目の用紙変更も不要な場合はそのまま返す
+        if self.links.is_empty()
+            && self.outlines.is_empty()
+            && !self.save_options.deterministic
+            && self.metadata.is_empty()
+            && self.render.watermark.is_none()
+            && self.save_options.encryption.is_none()
+            && self.font_fallback.is_none()
+            && first_page_media_box.is_none()
+        {
             return Ok(buffer);
         }
 
         // lopdfでPDFを読み込んでリンクを追加
         let mut doc = Document::load_mem(&buffer)?;
+        if let Some(media_box) = first_page_media_box {
+            set_first_page_media_box(&mut doc, media_box);
+        }
+        add_link_annotations(&mut doc, &self.links, default_media_box);
+        add_outlines(&mut doc, &self.outlines, default_media_box);
+        set_document_info(&mut doc, &self.metadata);
+        if let Some(watermark) = &self.render.watermark {
+            add_watermark_graphics_state(&mut doc, watermark.opacity);
+        }
+        if let Some(fallback) = self.font_fallback {
+            mark_font_fallback(&mut doc, fallback);
+        }
 
-        let page_height_pt = mm_to_pt(self.page_height_mm);
+        if self.save_options.deterministic {
+            let date = self.save_options.fixed_date.or(self.document_month).unwrap_or_default();
+            make_deterministic(&mut doc, date)?;
+        }
 
-        for link in &self.links {
-            let page_idx = (link.page - 1) as usize;
+        // 暗号化は最後（IDと全オブジェクトの内容が確定してから）
+        if let Some(encryption) = &self.save_options.encryption {
+            encryption.encrypt(&mut doc);
+        }
 
-            let x1_pt = mm_to_pt(link.x_mm);
-            let y1_pt = page_height_pt - mm_to_pt(link.y_mm + link.h_mm);
-            let x2_pt = mm_to_pt(link.x_mm + link.w_mm);
-            let y2_pt = page_height_pt - mm_to_pt(link.y_mm);
+        // PDFをメモリ上に保存
+        let mut output = Vec::new();
+        doc.save_to(&mut output)?;
 
-            let action_dict = Dictionary::from_iter(vec![
-                ("S", Object::Name(b"URI".to_vec())),
-                ("URI", Object::String(link.url.as_bytes().to_vec(), StringFormat::Literal)),
-            ]);
+        Ok(output)
+    }
 
-            let annot_dict = Dictionary::from_iter(vec![
-                ("Type", Object::Name(b"Annot".to_vec())),
-                ("Subtype", Object::Name(b"Link".to_vec())),
-                ("Rect", Object::Array(vec![
-                    Object::Real(x1_pt as f32),
-                    Object::Real(y1_pt as f32),
-                    Object::Real(x2_pt as f32),
-                    Object::Real(y2_pt as f32),
-                ])),
-                ("Border", Object::Array(vec![
-                    Object::Integer(0),
-                    Object::Integer(0),
-                    Object::Integer(0),
-                ])),
-                ("A", Object::Dictionary(action_dict)),
-            ]);
+    /// PDFをファイルに保存
+    /// 同じディレクトリの一時ファイルに書いてからrenameする（書きかけのファイルを残さない、同時保存でも衝突しない）
+    pub fn save(self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let link_count = self.links.len();
+        let bytes = self.save_to_bytes()?;
+        write_atomically(Path::new(path), &bytes)?;
 
-            let annot_id = doc.add_object(Object::Dictionary(annot_dict));
-
-            // ページIDを先に取得
-            let page_id = doc.page_iter().nth(page_idx);
-
-            if let Some(page_id) = page_id {
-                if let Ok(page_obj) = doc.get_object_mut(page_id) {
-                    if let Object::Dictionary(ref mut page_dict) = page_obj {
-                        let annots = if let Ok(existing) = page_dict.get(b"Annots") {
-                            if let Object::Array(arr) = existing.clone() {
-                                let mut new_arr = arr;
-                                new_arr.push(Object::Reference(annot_id));
-                                new_arr
-                            } else {
-                                vec![Object::Reference(annot_id)]
-                            }
-                        } else {
-                            vec![Object::Reference(annot_id)]
-                        };
-                        page_dict.set("Annots", Object::Array(annots));
+        debug!("Added {} links to PDF", link_count);
+
+        Ok(())
+    }
+}
+
+/// 一時ファイル（対象と同じディレクトリ、プロセスID＋連番で一意）に書いてからrenameする
+fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("output.pdf");
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// 文書情報をInfo辞書に書き込む（日本語を含むためUTF-16）
+/// printpdfのXMPメタデータには仮のタイトルが残るため、文書情報を設定した場合は削除する
+fn set_document_info(doc: &mut Document, metadata: &PdfMetadata) {
+    if metadata.is_empty() {
+        return;
+    }
+    if let Ok(info_id) = doc.trailer.get(b"Info").and_then(|o| o.as_reference()) {
+        if let Ok(Object::Dictionary(ref mut info)) = doc.get_object_mut(info_id) {
+            for (key, value) in [
+                ("Title", &metadata.title),
+                ("Author", &metadata.author),
+                ("Subject", &metadata.subject),
+                ("Keywords", &metadata.keywords),
+            ] {
+                match value {
+                    Some(text) => info.set(key, pdf_text_string(text)),
+                    None => {
+                        info.remove(key.as_bytes());
                     }
                 }
             }
         }
+    }
+    if let Ok(catalog_id) = doc.trailer.get(b"Root").and_then(|o| o.as_reference()) {
+        if let Ok(Object::Dictionary(ref mut catalog)) = doc.get_object_mut(catalog_id) {
+            catalog.remove(b"Metadata");
+        }
+    }
+}
 
-        // PDFをメモリ上に保存
-        let mut output = Vec::new();
-        doc.save_to(&mut output)?;
+/// 透かしの不透明度（ExtGState）を各ページのResourcesに追加
+fn add_watermark_graphics_state(doc: &mut Document, opacity: f32) {
+    let mut state = Dictionary::new();
+    state.set("Type", Object::Name(b"ExtGState".to_vec()));
+    state.set("ca", Object::Real(opacity));
+    state.set("CA", Object::Real(opacity));
+    let state_id = doc.add_object(Object::Dictionary(state));
+
+    let page_ids: Vec<_> = doc.page_iter().collect();
+    for page_id in page_ids {
+        // Resources・ExtGStateは直接書かれている場合と参照の場合がある
+        let resources_id = match doc.get_dictionary(page_id).and_then(|d| d.get(b"Resources")) {
+            Ok(Object::Reference(id)) => Some(*id),
+            _ => None,
+        };
+        let mut resources = match resources_id {
+            Some(id) => doc.get_dictionary(id).ok().cloned(),
+            None => doc.get_dictionary(page_id).and_then(|d| d.get(b"Resources")).and_then(|o| o.as_dict()).ok().cloned(),
+        }
+        .unwrap_or_default();
+        let mut states = match resources.get(b"ExtGState") {
+            Ok(Object::Reference(id)) => doc.get_dictionary(*id).ok().cloned().unwrap_or_default(),
+            Ok(Object::Dictionary(states)) => states.clone(),
+            _ => Dictionary::new(),
+        };
+        states.set(WATERMARK_GS_NAME, Object::Reference(state_id));
+        resources.set("ExtGState", Object::Dictionary(states));
 
-        Ok(output)
+        match resources_id {
+            Some(id) => {
+                doc.objects.insert(id, Object::Dictionary(resources));
+            }
+            None => {
+                if let Ok(Object::Dictionary(ref mut page)) = doc.get_object_mut(page_id) {
+                    page.set("Resources", Object::Dictionary(resources));
+                }
+            }
+        }
     }
+}
 
-    pub fn save(self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // まずprintpdfでPDFを保存
-        let temp_path = format!("{}.tmp", path);
-        {
-            let file = File::create(&temp_path)?;
-            self.doc.save(&mut BufWriter::new(file))?;
+/// 代替フォントを使ったことをInfo辞書に記録
+fn mark_font_fallback(doc: &mut Document, fallback: &str) {
+    if let Ok(info_id) = doc.trailer.get(b"Info").and_then(|o| o.as_reference()) {
+        if let Ok(Object::Dictionary(ref mut info)) = doc.get_object_mut(info_id) {
+            info.set(FONT_FALLBACK_INFO_KEY, Object::String(fallback.as_bytes().to_vec(), StringFormat::Literal));
         }
+    }
+}
+
+/// 1ページ目のMediaBox（pt）を書き換える
+fn set_first_page_media_box(doc: &mut Document, media_box: [f64; 4]) {
+    let Some(&page_id) = doc.get_pages().get(&1) else {
+        return;
+    };
+    if let Ok(page) = doc.get_dictionary_mut(page_id) {
+        page.set("MediaBox", Object::Array(media_box.iter().map(|&v| Object::Real(v as f32)).collect()));
+    }
+}
 
-        // lopdfでPDFを開いてリンクを追加
-        let mut doc = Document::load(&temp_path)?;
+/// ページのMediaBox（pt）と回転角（0/90/180/270）を取得
+/// どちらも親（Pages）から継承されうるため、Parentをたどって探す
+fn page_geometry(doc: &Document, page_id: lopdf::ObjectId, default_media_box: [f64; 4]) -> ([f64; 4], i64) {
+    let mut media_box = None;
+    let mut rotate = None;
+    let mut current = Some(page_id);
+    // 循環参照に備えて深さを制限
+    for _ in 0..32 {
+        let Some(id) = current else { break };
+        let Ok(dict) = doc.get_dictionary(id) else { break };
+        if media_box.is_none() {
+            if let Ok(Object::Array(arr)) = dict.get(b"MediaBox") {
+                let values: Vec<f64> = arr.iter().filter_map(|o| match o {
+                    Object::Integer(i) => Some(*i as f64),
+                    Object::Real(r) => Some(*r as f64),
+                    _ => None,
+                }).collect();
+                if values.len() == 4 {
+                    media_box = Some([values[0], values[1], values[2], values[3]]);
+                }
+            }
+        }
+        if rotate.is_none() {
+            if let Ok(r) = dict.get(b"Rotate").and_then(|o| o.as_i64()) {
+                rotate = Some(r);
+            }
+        }
+        current = dict.get(b"Parent").and_then(|o| o.as_reference()).ok();
+    }
+    (media_box.unwrap_or(default_media_box), rotate.unwrap_or(0).rem_euclid(360))
+}
 
-        let page_height_pt = mm_to_pt(self.page_height_mm);
+/// リンク位置（表示上の左上原点・mm）をページ座標のRect（pt）に変換
+/// /Rotate のあるページでは、表示上の位置が回転前の座標系のどこに当たるかを計算する
+fn link_rect(link: &LinkInfo, media_box: [f64; 4], rotate: i64) -> [f64; 4] {
+    let [llx, lly, urx, ury] = media_box;
+    // 表示上の座標（u: 右向き, v: 下向き、左上原点）→ 回転前のページ座標
+    let to_page = |u: f64, v: f64| -> (f64, f64) {
+        match rotate {
+            90 => (llx + v, lly + u),
+            180 => (urx - u, lly + v),
+            270 => (urx - v, ury - u),
+            _ => (llx + u, ury - v),
+        }
+    };
+    let (x1, y1) = to_page(mm_to_pt(link.x_mm), mm_to_pt(link.y_mm));
+    let (x2, y2) = to_page(mm_to_pt(link.x_mm + link.w_mm), mm_to_pt(link.y_mm + link.h_mm));
+    [x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)]
+}
 
-        for link in &self.links {
-            // ページインデックス（0-indexed）
-            let page_idx = (link.page - 1) as usize;
+/// Linkコマンドで指定されたリンクをアノテーションとして追加
+/// ページごとにMediaBox・Rotateを読むため、サイズや向きの異なるページが混在してもよい
+fn add_link_annotations(doc: &mut Document, links: &[LinkInfo], default_media_box: [f64; 4]) {
+    let page_ids: Vec<_> = doc.page_iter().collect();
 
-            // TCPDF座標（左上原点）→ PDF座標（左下原点）に変換
-            let x1_pt = mm_to_pt(link.x_mm);
-            let y1_pt = page_height_pt - mm_to_pt(link.y_mm + link.h_mm);  // 下端
-            let x2_pt = mm_to_pt(link.x_mm + link.w_mm);
-            let y2_pt = page_height_pt - mm_to_pt(link.y_mm);  // 上端
+    for link in links {
+        // ページインデックス（0-indexed）
+        let Some(&page_id) = page_ids.get((link.page as usize).wrapping_sub(1)) else {
+            continue;
+        };
+        let (media_box, rotate) = page_geometry(doc, page_id, default_media_box);
+        let rect = link_rect(link, media_box, rotate);
 
-            // URIアクション辞書
-            let action_dict = Dictionary::from_iter(vec![
+        // URIアクション辞書、文書内のページは明示的な移動先（/XYZ）付きのGoToアクション
+        let action_dict = match &link.target {
+            LinkTarget::Uri(url) => Dictionary::from_iter(vec![
                 ("S", Object::Name(b"URI".to_vec())),
-                ("URI", Object::String(link.url.as_bytes().to_vec(), StringFormat::Literal)),
-            ]);
+                ("URI", Object::String(url.as_bytes().to_vec(), StringFormat::Literal)),
+            ]),
+            LinkTarget::Page { index, y_mm } => {
+                let Some(&dest_id) = page_ids.get((*index as usize).wrapping_sub(1)) else {
+                    continue;
+                };
+                let (dest_box, _) = page_geometry(doc, dest_id, default_media_box);
+                Dictionary::from_iter(vec![
+                    ("S", Object::Name(b"GoTo".to_vec())),
+                    ("D", Object::Array(vec![
+                        Object::Reference(dest_id),
+                        Object::Name(b"XYZ".to_vec()),
+                        Object::Null,
+                        Object::Real((dest_box[3] - mm_to_pt(*y_mm)) as f32),
+                        Object::Null,
+                    ])),
+                ])
+            }
+        };
+
+        // リンクアノテーション辞書
+        let annot_dict = Dictionary::from_iter(vec![
+            ("Type", Object::Name(b"Annot".to_vec())),
+            ("Subtype", Object::Name(b"Link".to_vec())),
+            ("Rect", Object::Array(rect.iter().map(|v| Object::Real(*v as f32)).collect())),
+            ("Border", Object::Array(vec![
+                Object::Integer(0),
+                Object::Integer(0),
+                Object::Integer(0),
+            ])),
+            ("A", Object::Dictionary(action_dict)),
+        ]);
+
+        let annot_id = doc.add_object(Object::Dictionary(annot_dict));
+
+        // ページにアノテーションを追加（既存のAnnotsに追記）
+        if let Ok(Object::Dictionary(ref mut page_dict)) = doc.get_object_mut(page_id) {
+            let mut annots = match page_dict.get(b"Annots") {
+                Ok(Object::Array(arr)) => arr.clone(),
+                _ => Vec::new(),
+            };
+            annots.push(Object::Reference(annot_id));
+            page_dict.set("Annots", Object::Array(annots));
+        }
+    }
+}
+
+/// PDFの文字列（UTF-16BE、BOM付き）
+fn pdf_text_string(text: &str) -> Object {
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(text.encode_utf16().flat_map(|u| u.to_be_bytes()));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// しおり（アウトライン）を追加
+/// 全項目がトップレベルで子を持たないため開閉の状態はなく、しおりパネルも自動では開かない（PageModeは変えない）
+fn add_outlines(doc: &mut Document, outlines: &[OutlineEntry], default_media_box: [f64; 4]) {
+    let page_ids: Vec<_> = doc.page_iter().collect();
+    let items: Vec<_> = outlines
+        .iter()
+        .filter_map(|entry| {
+            let page_id = *page_ids.get((entry.page as usize).wrapping_sub(1))?;
+            let (media_box, _) = page_geometry(doc, page_id, default_media_box);
+            Some((entry, page_id, media_box[3] - mm_to_pt(entry.y_mm)))
+        })
+        .collect();
+    if items.is_empty() {
+        return;
+    }
+    let Ok(catalog_id) = doc.trailer.get(b"Root").and_then(|o| o.as_reference()) else {
+        return;
+    };
+
+    let root_id = doc.new_object_id();
+    let item_ids: Vec<_> = items.iter().map(|_| doc.new_object_id()).collect();
+    for (i, (entry, page_id, top)) in items.iter().enumerate() {
+        let mut item = Dictionary::from_iter(vec![
+            ("Title", pdf_text_string(&entry.title)),
+            ("Parent", Object::Reference(root_id)),
+            ("Dest", Object::Array(vec![
+                Object::Reference(*page_id),
+                Object::Name(b"XYZ".to_vec()),
+                Object::Null,
+                Object::Real(*top as f32),
+                Object::Null,
+            ])),
+        ]);
+        if i > 0 {
+            item.set("Prev", Object::Reference(item_ids[i - 1]));
+        }
+        if let Some(next) = item_ids.get(i + 1) {
+            item.set("Next", Object::Reference(*next));
+        }
+        doc.objects.insert(item_ids[i], Object::Dictionary(item));
+    }
+    doc.objects.insert(root_id, Object::Dictionary(Dictionary::from_iter(vec![
+        ("Type", Object::Name(b"Outlines".to_vec())),
+        ("First", Object::Reference(item_ids[0])),
+        ("Last", Object::Reference(*item_ids.last().unwrap())),
+        ("Count", Object::Integer(item_ids.len() as i64)),
+    ])));
+    if let Ok(Object::Dictionary(ref mut catalog)) = doc.get_object_mut(catalog_id) {
+        catalog.set("Outlines", Object::Reference(root_id));
+    }
+}
+
+/// 決定的出力用にPDFを書き換える
+/// 作成日時・更新日時を固定し、ドキュメントIDを内容のハッシュで置き換える
+/// （printpdfは現在時刻とランダムIDを書き込むため）
+fn make_deterministic(doc: &mut Document, date: NaiveDate) -> Result<(), Box<dyn std::error::Error>> {
+    let timestamp = format!("D:{}000000+09'00'", date.format("%Y%m%d"));
+
+    // Info辞書の日時を固定
+    if let Ok(info_id) = doc.trailer.get(b"Info").and_then(|o| o.as_reference()) {
+        if let Ok(Object::Dictionary(ref mut info)) = doc.get_object_mut(info_id) {
+            info.set("CreationDate", Object::String(timestamp.as_bytes().to_vec(), StringFormat::Literal));
+            info.set("ModDate", Object::String(timestamp.as_bytes().to_vec(), StringFormat::Literal));
+        }
+    }
+
+    // XMPメタデータはランダムIDを含むため削除
+    if let Ok(catalog_id) = doc.trailer.get(b"Root").and_then(|o| o.as_reference()) {
+        if let Ok(Object::Dictionary(ref mut catalog)) = doc.get_object_mut(catalog_id) {
+            catalog.remove(b"Metadata");
+        }
+    }
+
+    // printpdfのリンク注釈はHashMap順で出力されるため、ページごとに内容順へ並べ直す
+    let page_ids: Vec<_> = doc.page_iter().collect();
+    for page_id in page_ids {
+        let annot_ids: Vec<_> = match doc.get_dictionary(page_id).and_then(|d| d.get(b"Annots")) {
+            Ok(Object::Array(arr)) => arr.iter().filter_map(|o| o.as_reference().ok()).collect(),
+            _ => continue,
+        };
+        let mut sorted_ids = annot_ids.clone();
+        sorted_ids.sort();
+        let mut annots: Vec<Object> = annot_ids.iter().filter_map(|id| doc.get_object(*id).ok().cloned()).collect();
+        if annots.len() != sorted_ids.len() {
+            continue;
+        }
+        annots.sort_by_cached_key(|o| format!("{:?}", o));
+        for (id, annot) in sorted_ids.iter().zip(annots) {
+            doc.objects.insert(*id, annot);
+        }
+        if let Ok(Object::Dictionary(ref mut page_dict)) = doc.get_object_mut(page_id) {
+            page_dict.set("Annots", Object::Array(sorted_ids.into_iter().map(Object::Reference).collect()));
+        }
+    }
+
+    // IDを除いた内容のMD5からドキュメントIDを生成（DefaultHasherはRustのバージョンで値が変わるため使わない）
+    doc.trailer.remove(b"ID");
+    let mut content = Vec::new();
+    doc.save_to(&mut content)?;
+    let id = format!("{:x}", md5::compute(&content));
+
+    doc.trailer.set("ID", Object::Array(vec![
+        Object::String(id.as_bytes().to_vec(), StringFormat::Literal),
+        Object::String(id.as_bytes().to_vec(), StringFormat::Literal),
+    ]));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timecard_data::{DayRecord, Driver, TimecardSummary};
+    use crate::render_options::RenderOverrides;
+
+    /// PDF内のフォント（Type0）オブジェクト数を数える
+    fn count_embedded_fonts(bytes: &[u8]) -> usize {
+        let doc = Document::load_mem(bytes).unwrap();
+        doc.objects
+            .values()
+            .filter(|obj| match obj {
+                Object::Dictionary(dict) => {
+                    dict.get(b"Type").and_then(|t| t.as_name()).ok() == Some(b"Font".as_slice())
+                        && dict.get(b"Subtype").and_then(|t| t.as_name()).ok() == Some(b"Type0".as_slice())
+                }
+                _ => false,
+            })
+            .count()
+    }
+
+    fn fixture_with_two_families() -> Vec<Element> {
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+            {"seq": 2, "type": "SetFont", "page": 1, "params": {"family": "msgothic", "style": "", "size": 12.0}},
+            {"seq": 3, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 10.0, "w": 50.0, "h": 5.0, "text": "見出し", "border": 1, "align": "C", "fill": false, "ln": 1}},
+            {"seq": 4, "type": "SetFont", "page": 1, "params": {"family": "msmincho", "style": "", "size": 10.0}},
+            {"seq": 5, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 15.0, "w": 50.0, "h": 5.0, "text": "本文", "border": 0, "align": "L", "fill": false, "ln": 1}}
+        ]);
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_multi_cell_fill_draws_rect_before_border() {
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+            {"seq": 2, "type": "setFillColor", "page": 1, "params": {"col1": 200}},
+            {"seq": 3, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 10.0, "w": 50.0, "h": 5.0, "text": "日", "border": 1, "align": "C", "fill": true, "ln": 1}}
+        ]);
+        let elements: Vec<Element> = serde_json::from_value(json).unwrap();
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_elements(&elements).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+
+        let doc = Document::load_mem(&bytes).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let ops: Vec<&str> = content.operations.iter().map(|op| op.operator.as_str()).collect();
+        let position = |name: &str, from: usize| from + ops[from..].iter().position(|op| *op == name).unwrap();
+
+        // グレー(200/255)で塗りつぶし → 文字 → 枠線 の順
+        let rg = position("rg", 0);
+        let gray = content.operations[rg].operands[0].as_float().unwrap();
+        assert!((gray - 200.0 / 255.0).abs() < 0.001);
+        let fill = position("f", position("re", rg));
+        let text = position("Tj", fill);
+        assert_eq!(ops[position("re", text) + 1], "S");
+    }
+
+    /// 1ページ目の矩形（re）の左上（mm、四捨五入）
+    fn rect_origins_mm(bytes: &[u8]) -> Vec<(i64, i64)> {
+        let doc = Document::load_mem(bytes).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let (media_box, _) = page_geometry(&doc, page_id, [0.0; 4]);
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "re")
+            .map(|op| {
+                let v: Vec<f64> = op.operands.iter().map(|o| o.as_float().unwrap() as f64).collect();
+                let top = media_box[3] - (v[1] + v[3]);
+                ((v[0] / mm_to_pt(1.0)).round() as i64, (top / mm_to_pt(1.0)).round() as i64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cells_without_position_follow_cursor() {
+        let cell = |seq: u32, kind: &str, extra: serde_json::Value| {
+            let mut params = serde_json::json!({"w": 40.0, "h": 5.0, "text": "", "border": 1, "align": "L", "fill": false, "ln": 0});
+            params.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            serde_json::json!({"seq": seq, "type": kind, "page": 1, "params": params})
+        };
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+            cell(2, "MultiCell", serde_json::json!({"x": 20.0, "y": 20.0, "ln": 1})),
+            cell(3, "MultiCell", serde_json::json!({"x": null, "y": ""})),
+            cell(4, "Cell", serde_json::json!({})),
+            cell(5, "MultiCell", serde_json::json!({"ln": 2})),
+            cell(6, "MultiCell", serde_json::json!({})),
+            {"seq": 7, "type": "Ln", "page": 1, "params": {"h": ""}},
+            cell(8, "MultiCell", serde_json::json!({})),
+            {"seq": 9, "type": "setAbsX", "page": 1, "params": {"x": 100.0}},
+            {"seq": 10, "type": "setAbsY", "page": 1, "params": {"y": 50.0}},
+            cell(11, "MultiCell", serde_json::json!({})),
+            {"seq": 12, "type": "Ln", "page": 1, "params": {"h": 10.0, "y_before": 50.0}},
+            cell(13, "MultiCell", serde_json::json!({}))
+        ]);
+        let elements: Vec<Element> = serde_json::from_value(json).unwrap();
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_elements(&elements).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+
+        // ln=1 → 左端の次の行、ln=0 → 右隣（Cellは描画しないがカーソルは進む）、ln=2 → 真下
+        assert_eq!(
+            rect_origins_mm(&bytes),
+            [(20, 20), (10, 25), (90, 25), (90, 30), (10, 35), (100, 50), (10, 60)]
+        );
+    }
+
+    #[test]
+    fn test_rect_line_style_text_and_image() {
+        use base64::Engine;
+        let png = crate::pdf_image::tests::png(1, 1, 2, &[(0, vec![255, 0, 0])]);
+        let jpeg_path = std::env::temp_dir().join(format!("timecard_logo_{}.jpg", std::process::id()));
+        std::fs::write(&jpeg_path, crate::pdf_image::tests::jpeg(200, 100)).unwrap();
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+            {"seq": 2, "type": "SetLineWidth", "page": 1, "params": {"width": 1.0}},
+            {"seq": 3, "type": "SetDrawColor", "page": 1, "params": {"col1": 0, "col2": 0, "col3": 255}},
+            {"seq": 4, "type": "Line", "page": 1, "params": {"x1": 10.0, "y1": 30.0, "x2": 287.0, "y2": 30.0}},
+            {"seq": 5, "type": "setFillColor", "page": 1, "params": {"col1": 220}},
+            {"seq": 6, "type": "Rect", "page": 1, "params": {"x": 10.0, "y": 40.0, "w": 50.0, "h": 10.0, "style": "F"}},
+            {"seq": 7, "type": "Rect", "page": 1, "params": {"x": 10.0, "y": 60.0, "w": 50.0, "h": 10.0, "style": "DF"}},
+            {"seq": 8, "type": "Text", "page": 1, "params": {"x": 10.0, "y": 80.0, "text": "会社名"}},
+            {"seq": 9, "type": "Image", "page": 1, "params": {"file": jpeg_path.to_string_lossy(), "x": 240.0, "y": 10.0, "w": 40.0}},
+            {"seq": 10, "type": "Image", "page": 1, "params": {"data": base64::engine::general_purpose::STANDARD.encode(&png), "x": 10.0, "y": 100.0, "w": 5.0, "h": 5.0}},
+            {"seq": 11, "type": "Barcode", "page": 1, "params": {}}
+        ]);
+        let elements: Vec<Element> = serde_json::from_value(json).unwrap();
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_elements(&elements).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+        std::fs::remove_file(&jpeg_path).unwrap();
+
+        let doc = Document::load_mem(&bytes).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let ops: Vec<&str> = content.operations.iter().map(|op| op.operator.as_str()).collect();
+        let operands = |name: &str| -> Vec<Vec<f32>> {
+            content.operations.iter().filter(|op| op.operator == name).map(|op| op.operands.iter().filter_map(|o| o.as_float().ok()).collect()).collect()
+        };
+
+        // 線幅1mm・青の線、塗りつぶしのみ（f）と塗りつぶし＋枠線（B）の矩形
+        assert!(operands("w").iter().any(|w| (w[0] as f64 - mm_to_pt(1.0)).abs() < 0.01));
+        assert!(operands("RG").contains(&vec![0.0, 0.0, 1.0]));
+        assert_eq!(operands("re").len(), 2);
+        assert!(ops.contains(&"f") && ops.contains(&"B"));
+        assert_eq!(page_texts(&bytes), ["会社名"]);
+
+        // 画像2つ（JPEGは幅40mm・縦横比2:1で高さ20mm）
+        assert_eq!(ops.iter().filter(|op| **op == "Do").count(), 2);
+        let scales: Vec<(i64, i64)> = operands("cm").iter().filter(|m| m[1] == 0.0 && m[0] > 1.0).map(|m| ((m[0] as f64 / mm_to_pt(1.0)).round() as i64, (m[3] as f64 / mm_to_pt(1.0)).round() as i64)).collect();
+        assert_eq!(scales, [(40, 20), (5, 5)]);
+    }
+
+    #[test]
+    fn test_cell_border_values() {
+        let border = |value: serde_json::Value| CellBorder::from_value(&value);
+        let all = CellBorder { left: true, top: true, right: true, bottom: true };
+        assert_eq!(border(serde_json::json!(0)), CellBorder::default());
+        assert_eq!(border(serde_json::json!(1)), all);
+        assert_eq!(border(serde_json::json!("1")), all);
+        assert_eq!(border(serde_json::json!("LTRB")), all);
+        assert_eq!(border(serde_json::json!("B")), CellBorder { bottom: true, ..Default::default() });
+        assert_eq!(border(serde_json::json!("lr")), CellBorder { left: true, right: true, ..Default::default() });
+        assert_eq!(border(serde_json::json!("XYZ")), CellBorder::default());
+        assert_eq!(border(serde_json::json!(null)), CellBorder::default());
+    }
 
-            // リンクアノテーション辞書
-            let annot_dict = Dictionary::from_iter(vec![
-                ("Type", Object::Name(b"Annot".to_vec())),
-                ("Subtype", Object::Name(b"Link".to_vec())),
-                ("Rect", Object::Array(vec![
-                    Object::Real(x1_pt as f32),
-                    Object::Real(y1_pt as f32),
-                    Object::Real(x2_pt as f32),
-                    Object::Real(y2_pt as f32),
-                ])),
-                ("Border", Object::Array(vec![
-                    Object::Integer(0),
-                    Object::Integer(0),
-                    Object::Integer(0),
-                ])),
-                ("A", Object::Dictionary(action_dict)),
+    #[test]
+    fn test_multi_cell_partial_border_draws_sides() {
+        let cell = |border: serde_json::Value| {
+            let json = serde_json::json!([
+                {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+                {"seq": 2, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 10.0, "w": 50.0, "h": 5.0, "text": "", "border": border, "align": "L", "fill": false, "ln": 1}}
             ]);
+            let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+            pdf.render_elements(&serde_json::from_value::<Vec<Element>>(json).unwrap()).unwrap();
+            let ops = operators(&pdf.save_to_bytes().unwrap());
+            (ops.iter().filter(|op| *op == "re").count(), ops.iter().filter(|op| *op == "l").count())
+        };
+        // (矩形の数, 線分の数)
+        assert_eq!(cell(serde_json::json!(1)), (1, 0));
+        assert_eq!(cell(serde_json::json!("B")), (0, 1));
+        assert_eq!(cell(serde_json::json!("LR")), (0, 2));
+        assert_eq!(cell(serde_json::json!(0)), (0, 0));
+    }
+
+    /// 各Tjの直前のrg（文字色）が赤のものを数える
+    fn red_text_count(bytes: &[u8]) -> usize {
+        let doc = Document::load_mem(bytes).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let mut red = false;
+        let mut count = 0;
+        for op in &content.operations {
+            match op.operator.as_str() {
+                "rg" => {
+                    let rgb: Vec<f32> = op.operands.iter().map(|o| o.as_float().unwrap()).collect();
+                    red = rgb == [1.0, 0.0, 0.0];
+                }
+                "Tj" if red => count += 1,
+                _ => {}
+            }
+        }
+        count
+    }
+
+    /// 各ページのMediaBox（pt、小数点以下を四捨五入した幅・高さ）
+    fn page_sizes_pt(bytes: &[u8]) -> Vec<(i64, i64)> {
+        let doc = Document::load_mem(bytes).unwrap();
+        doc.get_pages()
+            .values()
+            .map(|&page_id| {
+                let (media_box, _) = page_geometry(&doc, page_id, [0.0; 4]);
+                ((media_box[2] - media_box[0]).round() as i64, (media_box[3] - media_box[1]).round() as i64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_orientation_swaps_page_size() {
+        assert_eq!(page_sizes_pt(&TcpdfCompat::new(297.0, 210.0, "P").save_to_bytes().unwrap()), [(595, 842)]);
+        assert_eq!(page_sizes_pt(&TcpdfCompat::new(210.0, 297.0, "L").save_to_bytes().unwrap()), [(842, 595)]);
+        assert_eq!(page_sizes_pt(&TcpdfCompat::new(297.0, 210.0, "L").save_to_bytes().unwrap()), [(842, 595)]);
+    }
+
+    #[test]
+    fn test_add_page_mixes_landscape_and_portrait() {
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "P", "format": [210.0, 297.0]}},
+            {"seq": 2, "type": "Link", "page": 1, "params": {"x": 10.0, "y": 10.0, "w": 20.0, "h": 5.0, "link": "https://example.com/1"}},
+            {"seq": 3, "type": "AddPage", "page": 2, "params": {"orientation": "L", "format": [210.0, 297.0]}},
+            {"seq": 4, "type": "AddPage", "page": 3, "params": {"orientation": "", "format": []}},
+            {"seq": 5, "type": "AddPage", "page": 4, "params": {"orientation": "P"}}
+        ]);
+        let elements: Vec<Element> = serde_json::from_value(json).unwrap();
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_elements(&elements).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+
+        // 1ページ目は作成時の横向きから縦向きに変わる、空の指定は直前のページと同じ
+        assert_eq!(page_sizes_pt(&bytes), [(595, 842), (842, 595), (842, 595), (595, 842)]);
+        // リンクは縦向きの1ページ目の座標（上から10mm）に付く
+        let doc = Document::load_mem(&bytes).unwrap();
+        let page_id = doc.get_pages()[&1];
+        let annots = doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap().clone();
+        let annot = doc.get_dictionary(annots[0].as_reference().unwrap()).unwrap();
+        let rect: Vec<f64> = annot.get(b"Rect").unwrap().as_array().unwrap().iter().map(|v| v.as_float().unwrap() as f64).collect();
+        assert!((rect[3] - (842.0 - mm_to_pt(10.0))).abs() < 1.0, "{:?}", rect);
+    }
+
+    #[test]
+    fn test_sunday_row_text_is_red() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&fixture_timecards()[..1], &RenderOptions::default()).unwrap();
+        // 日曜日（7,14,21,28日）の日付・曜日・備考（公休）
+        assert_eq!(red_text_count(&pdf.save_to_bytes().unwrap()), 4 * 3);
+
+        // 座標JSONのSetTextColorはその後のMultiCellの文字色になる
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+            {"seq": 2, "type": "SetTextColor", "page": 1, "params": {"col1": 255, "col2": 0, "col3": 0}},
+            {"seq": 3, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 10.0, "w": 20.0, "h": 5.0, "text": "日", "border": 0, "align": "C", "fill": false, "ln": 0}},
+            {"seq": 4, "type": "SetTextColor", "page": 1, "params": {"col1": 0}},
+            {"seq": 5, "type": "MultiCell", "page": 1, "params": {"x": 30.0, "y": 10.0, "w": 20.0, "h": 5.0, "text": "月", "border": 0, "align": "C", "fill": false, "ln": 0}}
+        ]);
+        let elements: Vec<Element> = serde_json::from_value(json).unwrap();
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_elements(&elements).unwrap();
+        assert_eq!(red_text_count(&pdf.save_to_bytes().unwrap()), 1);
+    }
+
+    #[test]
+    fn test_measure_text_uses_font_metrics() {
+        let close = |text: &str, expected: f64| {
+            let width = measure_text(text, 10.0);
+            assert!((width - expected).abs() < 0.01, "{}: {} != {}", text, width, expected);
+        };
+        // MS明朝: 英数字・スペースは0.5em、かな・漢字は1em（10pt = 3.528mm）
+        close("08:00", 8.819);
+        close("山田 太郎", 15.875);
+        close("トランスポーテーションマネージャー", 59.972);
+        // 半角カナはフォントの幅（概算の全角ではない）
+        close("ｶﾀｶﾅ", 7.056);
+        assert_eq!(measure_text("", 10.0), 0.0);
+        // 右揃えは右端からパディング分内側
+        let x = calc_text_x(10.0, 20.0, "08:00", 10.0, "R");
+        assert!((x + measure_text("08:00", 10.0) - 29.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_long_remark_is_shrunk_then_truncated() {
+        let remark = "積置泊休".repeat(5); // 20文字
+        let (text, size) = fit_text(&remark, 12.0, 10.0);
+        assert_eq!(size, MIN_FONT_SIZE);
+        assert!(text.ends_with('…'));
+        assert!(text.chars().count() < 20);
+        assert!(measure_text(&text, size) <= 11.0);
+        // 縮小だけで収まる場合は省略しない
+        let (text, size) = fit_text("積置泊休", 12.0, 10.0);
+        assert_eq!(text, "積置泊休");
+        assert!(size < 10.0 && size > MIN_FONT_SIZE);
+
+        // 座標JSONのMultiCellでも縮小したサイズで出力される
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+            {"seq": 2, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 10.0, "w": 12.0, "h": 5.0, "text": remark, "border": 1, "align": "L", "fill": false, "ln": 1}}
+        ]);
+        let elements: Vec<Element> = serde_json::from_value(json).unwrap();
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_elements(&elements).unwrap();
+        let doc = Document::load_mem(&pdf.save_to_bytes().unwrap()).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let sizes: Vec<f32> = content.operations.iter()
+            .filter(|op| op.operator == "Tf")
+            .map(|op| op.operands[1].as_float().unwrap())
+            .collect();
+        assert_eq!(sizes, [MIN_FONT_SIZE]);
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        // 10pt: 全角3.53mm、半角1.76mm → 19mm に全角5文字・半角10文字
+        assert_eq!(wrap_text("積置泊休・指休出張", 19.0, 10.0), ["積置泊休・", "指休出張"]);
+        // 英単語はスペースで改行し、収まらない単語は途中で切る
+        assert_eq!(wrap_text("go to Transportation", 19.0, 10.0), ["go to", "Transporta", "tion"]);
+        assert_eq!(wrap_text("公休\n出張", 19.0, 10.0), ["公休", "出張"]);
+        assert_eq!(wrap_text("公休", 19.0, 10.0), ["公休"]);
+    }
+
+    #[test]
+    fn test_multi_cell_wraps_within_cell_height() {
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+            {"seq": 2, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 10.0, "w": 20.0, "h": 10.0, "text": "積置泊休・指休出張", "border": 1, "align": "L", "fill": false, "ln": 1}}
+        ]);
+        let elements: Vec<Element> = serde_json::from_value(json).unwrap();
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_elements(&elements).unwrap();
+        let doc = Document::load_mem(&pdf.save_to_bytes().unwrap()).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let operands = |name: &str| -> Vec<Vec<f32>> {
+            content.operations.iter()
+                .filter(|op| op.operator == name)
+                .map(|op| op.operands.iter().filter_map(|o| o.as_float().ok()).collect())
+                .collect()
+        };
+
+        // 2行とも10ptのまま、行の高さ（10pt×1.25 = 4.41mm = 12.5pt）ずつ下に並ぶ
+        let sizes: Vec<f32> = operands("Tf").iter().map(|o| o[0]).collect();
+        assert_eq!(sizes, [10.0, 10.0]);
+        let ys: Vec<f32> = operands("Td").iter().map(|o| o[1]).collect();
+        assert_eq!(ys.len(), 2);
+        assert!((ys[0] - ys[1] - 12.5).abs() < 0.01);
+        // ブロックはセル（10mm）の上下中央
+        let cell_center = mm_to_pt(210.0 - 15.0) as f32;
+        let block_center = (ys[0] + ys[1]) / 2.0 + mm_to_pt(10.0 * 0.352778 * 0.3) as f32;
+        assert!((block_center - cell_center).abs() < 0.5);
+    }
+
+    fn bold_fixture() -> Vec<Element> {
+        let json = serde_json::json!([
+            {"seq": 1, "type": "AddPage", "page": 1, "params": {"orientation": "L", "format": [297.0, 210.0]}},
+            {"seq": 2, "type": "SetFont", "page": 1, "params": {"family": "msmincho", "style": "B", "size": 12.0}},
+            {"seq": 3, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 10.0, "w": 50.0, "h": 5.0, "text": "見出し", "border": 0, "align": "C", "fill": false, "ln": 1}},
+            {"seq": 4, "type": "SetFont", "page": 1, "params": {"family": "msmincho", "style": "", "size": 10.0}},
+            {"seq": 5, "type": "MultiCell", "page": 1, "params": {"x": 10.0, "y": 15.0, "w": 50.0, "h": 5.0, "text": "本文", "border": 0, "align": "L", "fill": false, "ln": 1}}
+        ]);
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn operators(bytes: &[u8]) -> Vec<String> {
+        let doc = Document::load_mem(bytes).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        doc.get_and_decode_page_content(page_id).unwrap().operations.into_iter().map(|op| op.operator).collect()
+    }
+
+    #[test]
+    fn test_bold_style_without_bold_font_is_synthesized() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.bold_font_path = None;
+        pdf.render_elements(&bold_fixture()).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+        let ops = operators(&bytes);
+        // 太字の見出しだけ輪郭線付き（Tr）で描画し、q/Qで元に戻す
+        assert_eq!(ops.iter().filter(|op| *op == "Tr").count(), 1);
+        assert_eq!(ops.iter().filter(|op| *op == "Tj").count(), 2);
+        assert_eq!(count_embedded_fonts(&bytes), 1);
+    }
+
+    #[test]
+    fn test_bold_style_uses_bold_font() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        // 太字フォントの代わりに同じTTFを別ファイルとして登録
+        pdf.bold_font_path = Some(PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fonts/msmincho01.ttf")));
+        pdf.render_elements(&bold_fixture()).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+        assert!(!operators(&bytes).contains(&"Tr".to_string()));
+        assert_eq!(count_embedded_fonts(&bytes), 2);
+    }
+
+    #[test]
+    fn test_two_font_families_are_embedded() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        // ゴシック体の代わりに同じTTFを別ファイルとして登録
+        pdf.register_font("msgothic", concat!(env!("CARGO_MANIFEST_DIR"), "/fonts/msmincho01.ttf"));
+        pdf.render_elements(&fixture_with_two_families()).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+        assert_eq!(count_embedded_fonts(&bytes), 2);
+    }
+
+    #[test]
+    fn test_unknown_family_falls_back_to_default() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_elements(&fixture_with_two_families()).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+        assert_eq!(count_embedded_fonts(&bytes), 1);
+    }
 
-            // アノテーションオブジェクトを追加
-            let annot_id = doc.add_object(Object::Dictionary(annot_dict));
-
-            // ページIDを先に取得
-            let page_id = doc.page_iter().nth(page_idx);
-
-            // ページにアノテーションを追加
-            if let Some(page_id) = page_id {
-                if let Ok(page_obj) = doc.get_object_mut(page_id) {
-                    if let Object::Dictionary(ref mut page_dict) = page_obj {
-                        // 既存のAnnotsを取得または新規作成
-                        let annots = if let Ok(existing) = page_dict.get(b"Annots") {
-                            if let Object::Array(arr) = existing.clone() {
-                                let mut new_arr = arr;
-                                new_arr.push(Object::Reference(annot_id));
-                                new_arr
-                            } else {
-                                vec![Object::Reference(annot_id)]
-                            }
+    /// 固定データのタイムカード（DB不要）
+    fn fixture_timecards() -> Vec<MonthlyTimecard> {
+        let weekdays = ["月", "火", "水", "木", "金", "土", "日"];
+        (0..3)
+            .map(|i| {
+                let days = (1..=31u8)
+                    .map(|d| {
+                        let mut day = DayRecord::new(d, weekdays[(d as usize - 1) % 7]);
+                        if d % 7 != 0 {
+                            day.clock_in.push("08:00".to_string());
+                            day.clock_out.push("17:30".to_string());
+                            day.kosoku_minutes = Some(570);
                         } else {
-                            vec![Object::Reference(annot_id)]
-                        };
-                        page_dict.set("Annots", Object::Array(annots));
+                            day.add_remark("公休");
+                        }
+                        day
+                    })
+                    .collect();
+                let mut tc = MonthlyTimecard {
+                    driver: Driver {
+                        id: 1000 + i,
+                        name: format!("テスト 太郎{}", i),
+                        bumon: None,
+                        category_c: None,
+                        eigyosho_c: None,
+                        kyuyo_shain_id: None,
+                    },
+                    year: 2025,
+                    month: 12,
+                    days,
+                    summary: TimecardSummary::default(),
+                    hire_date: None,
+                    retire_date: None,
+                };
+                tc.calculate_summary();
+                tc
+            })
+            .collect()
+    }
+
+    fn render_deterministic() -> Vec<u8> {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.set_save_options(SaveOptions { deterministic: true, ..Default::default() });
+        pdf.render_timecards(&fixture_timecards(), &RenderOptions::default()).unwrap();
+        pdf.save_to_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_deterministic_output_is_byte_identical() {
+        let first = render_deterministic();
+        let second = render_deterministic();
+        assert!(first == second, "deterministic PDFs differ");
+
+        // 日付は対象月の1日に固定される
+        let doc = Document::load_mem(&first).unwrap();
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        assert_eq!(info.get(b"CreationDate").unwrap().as_str().unwrap(), b"D:20251201000000+09'00'");
+
+        // ドキュメントIDは内容のMD5（32桁の16進）
+        let id = doc.trailer.get(b"ID").unwrap().as_array().unwrap();
+        let id = id[0].as_str().unwrap();
+        assert_eq!(id.len(), 32);
+        assert!(id.iter().all(u8::is_ascii_hexdigit));
+    }
+
+    /// Info辞書の文字列（UTF-16BE）を復号
+    fn info_text(doc: &Document, key: &str) -> Option<String> {
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let bytes = doc.get_dictionary(info_id).unwrap().get(key.as_bytes()).ok()?.as_str().ok()?.to_vec();
+        assert_eq!(&bytes[..2], &[0xFE, 0xFF]);
+        let units: Vec<u16> = bytes[2..].chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+        Some(String::from_utf16(&units).unwrap())
+    }
+
+    #[test]
+    fn test_record_coordinates_round_trip() {
+        let timecards = fixture_timecards();
+        let options = RenderOptions::resolve(&[&RenderOverrides { drivers_per_page: Some(2), ..Default::default() }]).unwrap();
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.record_coordinates();
+        pdf.render_timecards(&timecards, &options).unwrap();
+        let data = pdf.take_coordinates().unwrap();
+
+        // seq は1からの連番、page はAddPageごとに進む
+        assert_eq!(data.total_pages, 2);
+        assert!(data.elements.iter().enumerate().all(|(i, e)| e.seq == i as u32 + 1));
+        assert_eq!(data.elements[0].element_type, "AddPage");
+        let pages: Vec<u32> = data.elements.iter().filter(|e| e.element_type == "AddPage").map(|e| e.page).collect();
+        assert_eq!(pages, [1, 2]);
+        let cell_page = |text: &str| {
+            data.elements.iter().find(|e| e.element_type == "MultiCell" && e.params["text"] == text).map(|e| e.page)
+        };
+        assert_eq!(cell_page("テスト 太郎0"), Some(1));
+        assert_eq!(cell_page("テスト 太郎2"), Some(2));
+        assert!(data.elements.iter().any(|e| e.element_type == "Link" && e.params["link"].as_str().unwrap().contains("driver_id=")));
+        assert!(data.elements.iter().any(|e| e.element_type == "Line"));
+        assert!(data.elements.iter().any(|e| e.element_type == "SetFont" && e.params["style"] == "B"));
+
+        // JSONにしてから読み込み直し、座標JSONモードで描画できる
+        let json = serde_json::to_string(&data).unwrap();
+        let data: CoordinateData = serde_json::from_str(&json).unwrap();
+        let mut replay = TcpdfCompat::new(data.page_width_mm, data.page_height_mm, &data.orientation);
+        replay.render_elements(&data.elements).unwrap();
+        let bytes = replay.save_to_bytes().unwrap();
+        assert_eq!(Document::load_mem(&bytes).unwrap().get_pages().len(), 2);
+        assert!(page_texts(&bytes).contains(&"テスト 太郎0".to_string()));
+    }
+
+    #[test]
+    fn test_document_metadata() {
+        let render = |explicit: Option<PdfMetadata>| {
+            let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+            pdf.set_save_options(SaveOptions { deterministic: true, ..Default::default() });
+            if let Some(metadata) = explicit {
+                pdf.set_metadata(metadata);
+            }
+            let options = RenderOptions {
+                metadata: PdfMetadata {
+                    author: Some("総務部".to_string()),
+                    keywords: Some("勤怠,2025-12".to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            pdf.render_timecards(&fixture_timecards(), &options).unwrap();
+            pdf.save_to_bytes().unwrap()
+        };
+
+        // 既定のタイトルは対象月から、それ以外は RenderOptions から
+        let bytes = render(None);
+        assert!(bytes == render(None), "deterministic PDFs differ");
+        let doc = Document::load_mem(&bytes).unwrap();
+        assert_eq!(info_text(&doc, "Title").as_deref(), Some("タイムカード 2025年12月"));
+        assert_eq!(info_text(&doc, "Author").as_deref(), Some("総務部"));
+        assert_eq!(info_text(&doc, "Keywords").as_deref(), Some("勤怠,2025-12"));
+        assert_eq!(info_text(&doc, "Subject"), None);
+
+        // set_metadata の指定が優先
+        let bytes = render(Some(PdfMetadata { title: Some("12月分".to_string()), author: Some("経理".to_string()), ..Default::default() }));
+        let doc = Document::load_mem(&bytes).unwrap();
+        assert_eq!(info_text(&doc, "Title").as_deref(), Some("12月分"));
+        assert_eq!(info_text(&doc, "Author").as_deref(), Some("経理"));
+        assert_eq!(info_text(&doc, "Keywords").as_deref(), Some("勤怠,2025-12"));
+    }
+
+    #[test]
+    fn test_missing_font_path_falls_back_or_errors() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        // FONT_PATH=存在しないファイル と同じ状態
+        pdf.default_font_path = Some(PathBuf::from("/nonexistent/font.ttf"));
+        let result = pdf.render_timecards(&fixture_timecards(), &RenderOptions::default());
+
+        if !cfg!(feature = "embedded-font") {
+            // 埋め込みフォントがなければパニックせずエラー（HTTPでは500）
+            let error = result.unwrap_err();
+            assert!(error.contains("/nonexistent/font.ttf"), "{}", error);
+            return;
+        }
+        result.unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+        let doc = Document::load_mem(&bytes).unwrap();
+        assert_eq!(doc.get_pages().len(), 1);
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        let marker = info.get(FONT_FALLBACK_INFO_KEY.as_bytes()).unwrap().as_str().unwrap();
+        assert_eq!(marker, b"msmincho (embedded)");
+    }
+
+    #[test]
+    fn test_load_font_file_error() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        let error = pdf.load_font_file(std::path::Path::new("/nonexistent/font.ttf")).unwrap_err();
+        assert!(error.starts_with("フォントを読み込めません: /nonexistent/font.ttf"), "{}", error);
+    }
+
+    /// 1ページ目のTj文字列をToUnicode CMapで文字に戻す（セル単位のテキスト一覧）
+    fn page_texts(bytes: &[u8]) -> Vec<String> {
+        page_texts_on(bytes, 1)
+    }
+
+    /// 指定ページ（1始まり）のTjの文字列
+    fn page_texts_on(bytes: &[u8], page: u32) -> Vec<String> {
+        let doc = Document::load_mem(bytes).unwrap();
+        let mut to_unicode: HashMap<u16, char> = HashMap::new();
+        for obj in doc.objects.values() {
+            let Object::Stream(stream) = obj else { continue };
+            let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+            let text = String::from_utf8_lossy(&content);
+            if !text.contains("beginbfchar") {
+                continue;
+            }
+            for line in text.lines() {
+                let codes: Vec<u32> = line
+                    .split(['<', '>', ' '])
+                    .filter(|s| !s.is_empty())
+                    .filter_map(|s| u32::from_str_radix(s, 16).ok())
+                    .collect();
+                if let [gid, unicode] = codes[..] {
+                    if let Some(c) = char::from_u32(unicode) {
+                        to_unicode.insert(gid as u16, c);
                     }
                 }
             }
         }
 
-        // 最終PDFを保存
-        doc.save(path)?;
+        let page_id = *doc.get_pages().get(&page).unwrap();
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "Tj")
+            .filter_map(|op| op.operands.first()?.as_str().ok())
+            .map(|gids| {
+                gids.chunks(2)
+                    .map(|b| to_unicode.get(&u16::from_be_bytes([b[0], b[1]])).copied().unwrap_or('?'))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_daily_report_mark_is_rendered_with_remarks() {
+        let mut timecards = fixture_timecards();
+        timecards.truncate(1);
+        // 7日は公休、8日は出勤日。どちらも作業日報あり
+        timecards[0].days[6].has_daily_report = true;
+        timecards[0].days[7].has_daily_report = true;
+
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&timecards, &RenderOptions::default()).unwrap();
+        let texts = page_texts(&pdf.save_to_bytes().unwrap());
+
+        // 公休は上書きされず「作」が続く
+        assert!(texts.iter().any(|t| t == "公休作"), "{:?}", texts);
+        assert!(texts.iter().any(|t| t == "作"), "{:?}", texts);
+        // 作業日報のない公休はそのまま
+        assert!(texts.iter().any(|t| t == "公休"), "{:?}", texts);
+    }
 
-        // 一時ファイルを削除
-        std::fs::remove_file(&temp_path)?;
+    #[test]
+    fn test_not_employed_days_are_shaded() {
+        let mut timecards = fixture_timecards();
+        timecards.truncate(1);
+        // 1〜3日は入社前
+        for day in &mut timecards[0].days[..3] {
+            day.add_remark("入社前");
+        }
+        let filled_rows = |options: &RenderOptions| {
+            let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+            pdf.record_coordinates();
+            pdf.render_timecards(&timecards, options).unwrap();
+            let data = pdf.take_coordinates().unwrap();
+            let cells: Vec<_> = data.elements.iter().filter(|e| e.element_type == "MultiCell").collect();
+            // 日付の列（1日のセルと同じx）でグレーのセル
+            let day_x = cells.iter().find(|c| c.params["text"] == "1").map(|c| c.params["x"].clone()).unwrap();
+            cells
+                .iter()
+                .filter(|c| c.params["x"] == day_x && c.params["fill"] == true)
+                .filter_map(|c| c.params["text"].as_str().map(String::from))
+                .collect::<Vec<_>>()
+        };
+        // グレーになるのは在籍していない日だけ、備考には「入社前」
+        assert_eq!(filled_rows(&RenderOptions::default()), ["1", "2", "3"]);
+        assert!(filled_rows(&RenderOptions { shade_not_employed: false, ..Default::default() }).is_empty());
 
-        println!("Added {} links to PDF", self.links.len());
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&timecards, &RenderOptions::default()).unwrap();
+        assert!(page_texts(&pdf.save_to_bytes().unwrap()).iter().any(|t| t == "入社前"));
+    }
 
-        Ok(())
+    #[test]
+    fn test_footer_page_numbers() {
+        let mut timecards = fixture_timecards();
+        timecards.push(timecards[0].clone());
+
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.set_save_options(SaveOptions { deterministic: true, ..Default::default() });
+        pdf.render_timecards(&timecards, &RenderOptions::default()).unwrap();
+        let texts = page_texts(&pdf.save_to_bytes().unwrap());
+        // 4人 → 3人/ページで2ページ
+        assert!(texts.iter().any(|t| t == "ページ 1 / 2"), "{:?}", texts);
+        assert!(texts.iter().any(|t| t == "作成 2025-12-01 00:00  2025年12月分"), "{:?}", texts);
+
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards_shukei(&timecards, &RenderOptions::default()).unwrap();
+        let texts = page_texts(&pdf.save_to_bytes().unwrap());
+        assert!(texts.iter().any(|t| t == "ページ 1 / 4"), "{:?}", texts);
+
+        // --kosoku-source を指定したら出典も表示
+        let options = RenderOptions { kosoku_source_label: Some("拘束時間: TC_DC（PHP）"), ..Default::default() };
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.set_save_options(SaveOptions { deterministic: true, ..Default::default() });
+        pdf.render_timecards(&timecards, &options).unwrap();
+        let texts = page_texts(&pdf.save_to_bytes().unwrap());
+        assert!(texts.iter().any(|t| t == "作成 2025-12-01 00:00  2025年12月分  拘束時間: TC_DC（PHP）"), "{:?}", texts);
+
+        // footer=false ならPHPと同じく何も出さない
+        let options = RenderOptions { footer: false, ..Default::default() };
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&timecards, &options).unwrap();
+        let texts = page_texts(&pdf.save_to_bytes().unwrap());
+        assert!(!texts.iter().any(|t| t.starts_with("ページ") || t.starts_with("作成")), "{:?}", texts);
+    }
+
+    /// 1ページ目の文字と位置（Td、0.01pt単位）を1行ずつ
+    fn page_text_layout(bytes: &[u8]) -> String {
+        let doc = Document::load_mem(bytes).unwrap();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let content = doc.get_and_decode_page_content(page_id).unwrap();
+        let positions: Vec<String> = content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "Td")
+            .map(|op| {
+                let xy: Vec<f32> = op.operands.iter().map(|o| o.as_float().unwrap()).collect();
+                format!("{:.2} {:.2}", xy[0], xy[1])
+            })
+            .collect();
+        let texts = page_texts(bytes);
+        assert_eq!(positions.len(), texts.len());
+        positions.iter().zip(&texts).map(|(p, t)| format!("{} {}\n", p, t)).collect()
+    }
+
+    #[test]
+    fn test_default_layout_matches_golden() {
+        // デフォルトのRenderOptionsはレイアウトを設定可能にする前と同じ位置に同じ文字を出す
+        // 意図してレイアウトを変えた場合は UPDATE_GOLDEN=1 で更新する
+        let golden_path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/golden_timecards_page1.txt");
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.set_save_options(SaveOptions { deterministic: true, ..Default::default() });
+        pdf.render_timecards(&fixture_timecards(), &RenderOptions::default()).unwrap();
+        let layout = page_text_layout(&pdf.save_to_bytes().unwrap());
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(golden_path, &layout).unwrap();
+        }
+        assert!(layout == std::fs::read_to_string(golden_path).unwrap(), "レイアウトが変わりました（{}）", golden_path);
+    }
+
+    #[test]
+    fn test_layout_options() {
+        let options = RenderOptions { drivers_per_page: 2, show_kosoku_column: false, ..Default::default() };
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&fixture_timecards(), &options).unwrap();
+        assert_eq!(pdf.page_count, 2);
+        let texts = page_texts(&pdf.save_to_bytes().unwrap());
+        assert!(texts.iter().any(|t| t == "ページ 1 / 2"), "{:?}", texts);
+        assert!(!texts.iter().any(|t| t == "拘束" || t == "09:30"), "{:?}", texts);
+        assert_eq!(texts.iter().filter(|t| t.starts_with("テスト 太郎")).count(), 4); // ヘッダーと集計に2人分
+    }
+
+    #[test]
+    fn test_wrapped_punch_fits_time_column() {
+        // 時刻列（11mm）に囲み記号付きの時刻が収まる
+        let size = fit_font_size("[08:00]", 11.0, 10.0);
+        assert!(size < 10.0);
+        assert!(measure_text("[08:00]", size) <= 10.0 + 1e-4);
+        // 記号なしならそのまま10pt
+        assert_eq!(fit_font_size("08:00", 11.0, 10.0), 10.0);
+    }
+
+    fn rect_of(doc: &Document, page_id: lopdf::ObjectId) -> Vec<f64> {
+        let page = doc.get_dictionary(page_id).unwrap();
+        let annot_id = page.get(b"Annots").unwrap().as_array().unwrap()[0].as_reference().unwrap();
+        let annot = doc.get_dictionary(annot_id).unwrap();
+        annot.get(b"Rect").unwrap().as_array().unwrap().iter().map(|o| o.as_float().unwrap() as f64).collect()
+    }
+
+    fn assert_rect(actual: &[f64], expected: [f64; 4]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 0.01, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    /// ページごとのリンク先URI（ページ順）
+    fn page_link_uris(bytes: &[u8]) -> Vec<Vec<String>> {
+        let doc = Document::load_mem(bytes).unwrap();
+        let resolve = |obj: &Object| -> Dictionary {
+            match obj {
+                Object::Reference(id) => doc.get_dictionary(*id).unwrap().clone(),
+                Object::Dictionary(d) => d.clone(),
+                _ => panic!("unexpected annotation {:?}", obj),
+            }
+        };
+        doc.get_pages()
+            .values()
+            .map(|page_id| {
+                let page = doc.get_dictionary(*page_id).unwrap();
+                let Ok(annots) = page.get(b"Annots") else { return Vec::new() };
+                let annots = match annots {
+                    Object::Reference(id) => doc.get_object(*id).unwrap().as_array().unwrap().clone(),
+                    other => other.as_array().unwrap().clone(),
+                };
+                annots
+                    .iter()
+                    .map(|a| {
+                        let action = resolve(resolve(a).get(b"A").unwrap());
+                        String::from_utf8_lossy(action.get(b"URI").unwrap().as_str().unwrap()).into_owned()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_driver_links_land_on_their_page() {
+        let mut timecards = fixture_timecards();
+        let mut fourth = timecards[0].clone();
+        fourth.driver.id = 1003;
+        timecards.push(fourth);
+        timecards[1].days[0].has_digitacho = true;
+
+        let options = RenderOptions {
+            link_base: "https://ohishi-dev.ohishi.local".to_string(),
+            digitacho_links: true,
+            ..Default::default()
+        };
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&timecards, &options).unwrap();
+        let url = |id: i32| format!("https://ohishi-dev.ohishi.local/time-card?driver_id={}&month=2025-12", id);
+
+        // 氏名のリンクは描画中のページ番号で LinkInfo として保持する
+        let name_pages: Vec<(u32, String)> = pdf.links.iter().filter_map(|l| match &l.target {
+            LinkTarget::Uri(u) if !u.contains("&day=") => Some((l.page, u.clone())),
+            _ => None,
+        }).collect();
+        assert_eq!(name_pages, vec![(1, url(1000)), (1, url(1001)), (1, url(1002)), (2, url(1003))]);
+
+        let pages = page_link_uris(&pdf.save_to_bytes().unwrap());
+        assert_eq!(pages.len(), 2);
+
+        // 1ページ目に1〜3人目、2ページ目に4人目
+        for id in [1000, 1001, 1002] {
+            assert!(pages[0].contains(&url(id)), "{:?}", pages[0]);
+        }
+        assert!(pages[0].contains(&format!("{}&day=1", url(1001))), "{:?}", pages[0]);
+        assert_eq!(pages[1], vec![url(1003)]);
+    }
+
+    #[test]
+    fn test_zangyo_and_digitacho_links_use_link_info() {
+        let mut timecards = fixture_timecards();
+        timecards.truncate(1);
+        timecards[0].days[0].zangyo = Some(2.5);
+        timecards[0].days[0].zangyo_sources.push(crate::timecard_data::ZangyoSource {
+            table: ZangyoTable::RyohiRows,
+            row_id: "R-42".to_string(),
+            amount: 2.5,
+        });
+        timecards[0].days[1].has_digitacho = true;
+
+        let options = RenderOptions {
+            zangyo_link_template: Some("https://ryohi.example/rows/{id}".to_string()),
+            digitacho_links: true,
+            ..Default::default()
+        };
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&timecards, &options).unwrap();
+        // 保存時にlopdfで追加するリンクとして保持する（printpdfの注釈は使わない）
+        let uris: Vec<&str> = pdf.links.iter().filter_map(|l| match &l.target {
+            LinkTarget::Uri(url) => Some(url.as_str()),
+            LinkTarget::Page { .. } => None,
+        }).collect();
+        assert!(uris.contains(&"https://ryohi.example/rows/R-42"), "{:?}", uris);
+        assert!(uris.iter().any(|u| u.ends_with("&day=2")), "{:?}", uris);
+
+        let pages = page_link_uris(&pdf.save_to_bytes().unwrap());
+        assert_eq!(pages[0].iter().filter(|u| *u == "https://ryohi.example/rows/R-42").count(), 1, "{:?}", pages[0]);
+    }
+
+    /// しおりの（タイトル, ページ番号, 上端のY）を順に
+    fn outline_entries(doc: &Document) -> Vec<(String, u32, f32)> {
+        let pages: HashMap<lopdf::ObjectId, u32> = doc.get_pages().into_iter().map(|(n, id)| (id, n)).collect();
+        let catalog = doc.catalog().unwrap();
+        let root = doc.get_dictionary(catalog.get(b"Outlines").unwrap().as_reference().unwrap()).unwrap();
+        let mut entries = Vec::new();
+        let mut next = root.get(b"First").and_then(|o| o.as_reference()).ok();
+        while let Some(id) = next {
+            let item = doc.get_dictionary(id).unwrap();
+            let title = item.get(b"Title").unwrap().as_str().unwrap();
+            let units: Vec<u16> = title[2..].chunks(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect();
+            let dest = item.get(b"Dest").unwrap().as_array().unwrap();
+            entries.push((
+                String::from_utf16(&units).unwrap(),
+                pages[&dest[0].as_reference().unwrap()],
+                dest[3].as_float().unwrap(),
+            ));
+            next = item.get(b"Next").and_then(|o| o.as_reference()).ok();
+        }
+        assert_eq!(root.get(b"Count").unwrap().as_i64().unwrap(), entries.len() as i64);
+        entries
+    }
+
+    #[test]
+    fn test_outline_per_driver() {
+        let mut timecards = fixture_timecards();
+        timecards.push(timecards[0].clone());
+        timecards[0].driver.kyuyo_shain_id = Some(512);
+
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&timecards, &RenderOptions::default()).unwrap();
+        let dir = std::env::temp_dir().join(format!("timecard-outline-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("outline.pdf");
+        pdf.save(path.to_str().unwrap()).unwrap();
+
+        // ファイルへの保存でも残り、一時ファイルは残らない
+        let doc = Document::load(&path).unwrap();
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, ["outline.pdf"]);
+        let entries = outline_entries(&doc);
+        let top = (mm_to_pt(210.0) - mm_to_pt(5.0)) as f32;
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].0, "512 テスト 太郎0");
+        assert_eq!((entries[1].1, entries[2].1, entries[3].1), (1, 1, 2));
+        assert!((entries[3].2 - top).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_render_months_adds_separator_pages() {
+        let december = fixture_timecards();
+        let january: Vec<MonthlyTimecard> = december.iter().cloned().map(|tc| MonthlyTimecard { year: 2026, month: 1, ..tc }).collect();
+        let months = vec![((2025, 12), december.clone()), ((2025, 11), Vec::new()), ((2026, 1), january)];
+
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_months(&months, &RenderOptions::default(), TcpdfCompat::render_timecards).unwrap();
+        let doc = Document::load_mem(&pdf.save_to_bytes().unwrap()).unwrap();
+        // 区切り・12月（1ページ）・区切り・1月（1ページ）、タイムカードのない月は飛ばす
+        assert_eq!(doc.get_pages().len(), 4);
+        let entries = outline_entries(&doc);
+        assert_eq!((entries[0].0.as_str(), entries[0].1), ("2025年12月分", 1));
+        let january_index = december.len() + 1;
+        assert_eq!((entries[january_index].0.as_str(), entries[january_index].1), ("2026年1月分", 3));
+
+        // 1か月だけなら区切りページなし
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_months(&months[..1], &RenderOptions::default(), TcpdfCompat::render_timecards).unwrap();
+        let doc = Document::load_mem(&pdf.save_to_bytes().unwrap()).unwrap();
+        assert_eq!(doc.get_pages().len(), 1);
+    }
+
+    #[test]
+    fn test_watermark_on_every_page() {
+        let options = RenderOptions {
+            drivers_per_page: 1,
+            watermark: RenderOptions::resolve(&[&RenderOverrides { watermark: Some("控え".to_string()), ..Default::default() }])
+                .unwrap()
+                .watermark,
+            ..Default::default()
+        };
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards(&fixture_timecards(), &options).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+
+        // 途中で追加したページにも描画され、各ページの最後のテキストになる
+        let doc = Document::load_mem(&bytes).unwrap();
+        assert_eq!(doc.get_pages().len(), 3);
+        for (page, page_id) in doc.get_pages() {
+            let texts = page_texts_on(&bytes, page);
+            assert_eq!(texts.last().map(|s| s.as_str()), Some("控え"), "page {}", page);
+            assert_eq!(texts.iter().filter(|t| *t == "控え").count(), 1);
+
+            let ops = doc.get_and_decode_page_content(page_id).unwrap().operations;
+            let gs = ops.iter().find(|op| op.operator == "gs").unwrap();
+            let name = gs.operands[0].as_name().unwrap();
+            let resources = match doc.get_page_resources(page_id) {
+                (Some(resources), _) => resources,
+                (None, ids) => doc.get_dictionary(ids[0]).unwrap(),
+            };
+            let states = resources.get(b"ExtGState").unwrap().as_dict().unwrap();
+            let state = doc.get_dictionary(states.get(name).unwrap().as_reference().unwrap()).unwrap();
+            assert_eq!(state.get(b"ca").unwrap().as_float().unwrap(), 0.15);
+        }
+    }
+
+    #[test]
+    fn test_encrypted_output_requires_password() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.set_save_options(SaveOptions {
+            deterministic: true,
+            encryption: Some(PdfEncryption::new("secret", Some("owner")).unwrap()),
+            ..Default::default()
+        });
+        pdf.render_timecards(&fixture_timecards(), &RenderOptions::default()).unwrap();
+        let bytes = pdf.save_to_bytes().unwrap();
+
+        // パスワードなしでは本文を読めない（暗号化されたままの内容は、偶然演算子として解析できても復号後とは一致しない）
+        let doc = Document::load_mem(&bytes).unwrap();
+        assert!(doc.is_encrypted());
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let operations = |doc: &Document| doc.get_and_decode_page_content(page_id).map(|c| format!("{:?}", c.operations)).ok();
+        let encrypted = operations(&doc);
+        assert!(Document::load_mem(&bytes).unwrap().decrypt("wrong").is_err());
+
+        // パスワードがあれば復号できる
+        let mut doc = Document::load_mem(&bytes).unwrap();
+        doc.decrypt("secret").unwrap();
+        assert!(operations(&doc).is_some());
+        assert_ne!(encrypted, operations(&doc));
+        doc.trailer.remove(b"Encrypt");
+        let mut decrypted = Vec::new();
+        doc.save_to(&mut decrypted).unwrap();
+        assert!(page_texts(&decrypted).iter().any(|t| t == "テスト 太郎0"));
+    }
+
+    #[test]
+    fn test_concurrent_save() {
+        let dir = std::env::temp_dir().join(format!("timecard-concurrent-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 別スレッドで同時に生成（同じファイル名と別のファイル名）
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let path = dir.join(if i % 2 == 0 { "same.pdf".to_string() } else { format!("{}.pdf", i) });
+                std::thread::spawn(move || {
+                    let mut timecards = fixture_timecards();
+                    timecards.truncate(i + 1);
+                    let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+                    pdf.set_save_options(SaveOptions { deterministic: true, ..Default::default() });
+                    pdf.render_timecards(&timecards, &RenderOptions::default()).unwrap();
+                    pdf.save(path.to_str().unwrap()).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+        files.sort();
+        assert_eq!(files, ["1.pdf", "3.pdf", "same.pdf"]);
+        for name in &files {
+            let doc = Document::load(dir.join(name)).unwrap();
+            assert_eq!(doc.get_pages().len(), 1);
+        }
+        let names = |name: &str| page_texts(&std::fs::read(dir.join(name)).unwrap()).into_iter().filter(|t| t.starts_with("テスト")).collect::<HashSet<_>>().len();
+        assert_eq!(names("1.pdf"), 2);
+        assert_eq!(names("3.pdf"), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shukei_remark_symbols_in_day_cell() {
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards_shukei(&fixture_timecards()[..1], &RenderOptions::default()).unwrap();
+        let texts = page_texts(&pdf.save_to_bytes().unwrap());
+        // 記号は日付のセルに続けて表示し、記号だけの行は作らない
+        assert!(texts.iter().any(|t| t == "7公"), "{:?}", texts);
+        assert!(texts.iter().any(|t| t == "8"), "{:?}", texts);
+        assert!(!texts.iter().any(|t| t == "公"), "{:?}", texts);
+        assert!(texts.iter().any(|t| t == "凡例: 公=公休"), "{:?}", texts);
+    }
+
+    #[test]
+    fn test_shukei_index_links_to_driver_pages() {
+        let mut timecards = fixture_timecards();
+        timecards.push(timecards[0].clone());
+        let options = RenderOptions { shukei_index: true, ..Default::default() };
+        let mut pdf = TcpdfCompat::new(297.0, 210.0, "L");
+        pdf.render_timecards_shukei(&timecards, &options).unwrap();
+        // 各ページの氏名のリンクはそのページ（目次の後ろ）に載る
+        let name_pages: Vec<u32> = pdf.links.iter().filter(|l| matches!(l.target, LinkTarget::Uri(_))).map(|l| l.page).collect();
+        assert_eq!(name_pages, vec![2, 3, 4, 5]);
+        let bytes = pdf.save_to_bytes().unwrap();
+        let texts = page_texts(&bytes);
+        assert!(texts.iter().any(|t| t == "目次 2025年12月"), "{:?}", texts);
+        assert!(texts.iter().any(|t| t == "ページ 1 / 5"), "{:?}", texts);
+
+        // 目次の各行は明示的な移動先（/XYZ）付きのGoToで2〜5ページ目へ
+        let doc = Document::load_mem(&bytes).unwrap();
+        let pages: HashMap<lopdf::ObjectId, u32> = doc.get_pages().into_iter().map(|(n, id)| (id, n)).collect();
+        let index_page = doc.get_dictionary(*doc.get_pages().get(&1).unwrap()).unwrap();
+        let mut targets: Vec<u32> = index_page
+            .get(b"Annots")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| doc.get_dictionary(a.as_reference().unwrap()).unwrap())
+            .filter_map(|annot| {
+                let action = annot.get(b"A").unwrap().as_dict().unwrap();
+                (action.get(b"S").unwrap().as_name().unwrap() == b"GoTo").then(|| {
+                    let dest = action.get(b"D").unwrap().as_array().unwrap();
+                    assert_eq!(dest[1].as_name().unwrap(), b"XYZ");
+                    pages[&dest[0].as_reference().unwrap()]
+                })
+            })
+            .collect();
+        targets.sort();
+        assert_eq!(targets, vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_link_rect_on_rotated_and_mixed_size_pages() {
+        use lopdf::dictionary;
+
+        // 1ページ目: 縦置きA4を /Rotate 90 で横向き表示、2ページ目: A3（継承したMediaBox）で回転なし
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let page1 = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), Object::Real(595.28), Object::Real(841.89)],
+            "Rotate" => 90,
+        });
+        let page2 = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+        });
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page1.into(), page2.into()],
+            "Count" => 2,
+            "MediaBox" => vec![0.into(), 0.into(), Object::Real(841.89), Object::Real(1190.55)],
+        }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+
+        let link = |page| LinkInfo { page, x_mm: 10.0, y_mm: 20.0, w_mm: 30.0, h_mm: 5.0, target: LinkTarget::Uri("/x".to_string()) };
+        add_link_annotations(&mut doc, &[link(1), link(2)], [0.0, 0.0, 841.89, 595.28]);
+
+        let (u1, v1) = (mm_to_pt(10.0), mm_to_pt(20.0));
+        let (u2, v2) = (mm_to_pt(40.0), mm_to_pt(25.0));
+        // 回転90: 表示上の左上は回転前の左下、右方向は上、下方向は右
+        assert_rect(&rect_of(&doc, page1), [v1, u1, v2, u2]);
+        // A3ページは自分の高さ（1190.55pt）から変換
+        assert_rect(&rect_of(&doc, page2), [u1, 1190.55 - v2, u2, 1190.55 - v1]);
+
+        // 180度・270度
+        let media_box = [0.0, 0.0, 595.28, 841.89];
+        assert_rect(&link_rect(&link(1), media_box, 180), [595.28 - u2, v1, 595.28 - u1, v2]);
+        assert_rect(&link_rect(&link(1), media_box, 270), [595.28 - v2, 841.89 - u2, 595.28 - v1, 841.89 - u1]);
     }
 }
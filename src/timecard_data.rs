@@ -1,38 +1,197 @@
+use chrono::{Duration, NaiveTime};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::holiday_mapping::{HolidayMapping, SummaryCategory};
+use crate::shift_rules::ShiftRules;
+
 /// ドライバー（従業員）情報
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct Driver {
+    /// ドライバーID（drivers.id）
     pub id: i32,
+    /// 氏名
     pub name: String,
     // ソート用フィールド（給与番号順）
-    pub bumon: Option<i32>,       // 部門
-    pub category_c: Option<i32>,  // 給与区分
-    pub eigyosho_c: Option<i32>,  // 営業所コード
-    pub kyuyo_shain_id: Option<i32>, // 給与社員ID
+    /// 部門
+    pub bumon: Option<i32>,
+    /// 給与区分
+    pub category_c: Option<i32>,
+    /// 営業所コード
+    pub eigyosho_c: Option<i32>,
+    /// 給与社員ID
+    pub kyuyo_shain_id: Option<i32>,
+}
+
+/// 残業の元データのテーブル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZangyoTable {
+    /// ryohi_rows（旅費）
+    RyohiRows,
+    /// time_card_zangyo
+    TimeCardZangyo,
+}
+
+impl ZangyoTable {
+    /// テーブル名
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RyohiRows => "ryohi_rows",
+            Self::TimeCardZangyo => "time_card_zangyo",
+        }
+    }
+}
+
+/// 残業の出所（どの行から何時間来たか）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZangyoSource {
+    /// 元のテーブル
+    pub table: ZangyoTable,
+    /// 元の行のID
+    pub row_id: String,
+    /// 残業時間
+    pub amount: f64,
+}
+
+/// 打刻の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PunchKind {
+    /// 始業（time_card_dstate state=30）
+    In,
+    /// 終業（time_card_dstate state=31）
+    Out,
+}
+
+/// 1回分の打刻（時刻順に並べてから出社/退社のペアを組む）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Punch {
+    /// 時刻
+    pub time: NaiveTime,
+    /// 種類（time_card_injectの手動打刻はNone、前後の打刻から判定）
+    pub kind: Option<PunchKind>,
+    /// 翌日の打刻を前日の終業として振り替えたもの（その日の打刻より後に並べる）
+    pub overnight: bool,
+}
+
+/// 夜勤用の社員番号（time_card_yakin）の打刻をまとめた日の備考（PHPと同じ表示）
+pub const YAKIN_REMARK: &str = "※夜勤";
+
+/// PDFに表示する出社/退社のペア数（出社1/退社1、出社2/退社2、3ペア目以降は「+N」）
+pub const PRINTED_PUNCH_PAIRS: usize = 2;
+
+/// TC_DC版とデジタコ版の拘束時間の差を不一致とみなす閾値（分）
+const DEFAULT_KOSOKU_MISMATCH_MINUTES: i32 = 30;
+
+/// 環境変数 KOSOKU_MISMATCH_MINUTES（デフォルト30分）
+pub fn kosoku_mismatch_minutes_from_env() -> i32 {
+    std::env::var("KOSOKU_MISMATCH_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_KOSOKU_MISMATCH_MINUTES)
+}
+
+/// 二度押しとみなす打刻の間隔の既定値（分）
+const DEFAULT_PUNCH_DEDUP_MINUTES: i64 = 2;
+
+/// 環境変数 PUNCH_DEDUP_MINUTES（デフォルト2分、0でまとめない）
+pub(crate) fn punch_dedup_window_from_env() -> Duration {
+    let minutes = std::env::var("PUNCH_DEDUP_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|m| *m >= 0)
+        .unwrap_or(DEFAULT_PUNCH_DEDUP_MINUTES);
+    Duration::minutes(minutes)
+}
+
+/// 環境変数 OVERNIGHT_CUTOFF（HH:MM、未設定なら日跨ぎの振り替えをしない）
+pub(crate) fn overnight_cutoff_from_env() -> Option<NaiveTime> {
+    std::env::var("OVERNIGHT_CUTOFF")
+        .ok()
+        .and_then(|v| NaiveTime::parse_from_str(v.trim(), "%H:%M").ok())
+}
+
+/// 日跨ぎ勤務（20:00出社→翌05:30退社など）の終業を出社日に振り替える
+///
+/// 前日に退社のない出社がある場合、その日の最初の打刻が cutoff より前の終業（または種類なし）なら前日の退社にする
+/// days は1日から順に並べたもの。末尾に翌月1日を置けば月末日の日跨ぎも振り替えられる
+pub(crate) fn attribute_overnight_punches(days: &mut [DayRecord], cutoff: NaiveTime) {
+    for i in 1..days.len() {
+        if !days[i - 1].has_open_clock_in() {
+            continue;
+        }
+        let first = days[i]
+            .punches
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| !p.overnight)
+            .min_by_key(|(_, p)| p.time)
+            .map(|(index, p)| (index, p.clone()));
+        if let Some((index, punch)) = first {
+            if punch.time < cutoff && punch.kind != Some(PunchKind::In) {
+                days[i].punches.remove(index);
+                days[i - 1].punches.push(Punch { overnight: true, ..punch });
+            }
+        }
+    }
 }
 
 /// 1日分の勤怠記録
-#[derive(Debug, Clone)]
+/// JSON出力のフィールド名と分析用CSV（export::EXPORT_COLUMNS）の列名はBI連携で使うため変更しないこと（DAY_RECORD_FIELDS のテストで検出）
+/// JSONから読み込む場合（render-fixture）は省略したフィールドを空にする
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DayRecord {
-    pub day: u8,                    // 日（1-31）
-    pub weekday: String,            // 曜日（日,月,火,水,木,金,土）
-    pub clock_in: Vec<String>,      // 出勤時刻（最大2回）
-    pub clock_out: Vec<String>,     // 退勤時刻（最大2回）
-    pub remarks: String,            // 備考（公休、有休等）
-    pub detail_st: String,          // 出張マーク（「出」）
-    pub is_sunday: bool,            // 日曜日フラグ
-    pub is_holiday: bool,           // 祝日・非法定休日フラグ
-    pub kosoku_minutes: Option<i32>, // 拘束時間（分）- 表示用（TC_DC + デジタコ合算）
-    pub kosoku_tcdc: Option<i32>,   // TC_DC版拘束時間（分）- INSERT用
-    pub kosoku_digitacho: Option<i32>, // デジタコ版拘束時間（分）- INSERT用
-    pub zangyo: Option<f64>,        // 残業時間（旅費から取得）
-    pub is_kachiku: bool,           // 家畜車フラグ（「畜」マーク）
-    pub is_trailer: bool,           // トレーラーフラグ（「引」マーク）
-    pub has_digitacho: bool,        // デジタコデータありフラグ（リンク表示用）
-    pub has_daily_report: bool,     // 作業日報フラグ（「作」マーク）
-    pub tsuika_count: i32,          // 追加作業件数
+    /// 日（1-31）
+    pub day: u8,
+    /// 曜日（日,月,火,水,木,金,土）
+    pub weekday: String,
+    /// 出勤時刻（全ペア、PDFは2回まで表示）
+    pub clock_in: Vec<String>,
+    /// 退勤時刻（全ペア、PDFは2回まで表示）
+    pub clock_out: Vec<String>,
+    /// 打刻（pair_punchesでclock_in/clock_outに振り分け）
+    #[serde(skip)]
+    pub punches: Vec<Punch>,
+    /// 監査用の警告（二度押しをまとめた件数など、JSON APIのみ）
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+    /// 備考（公休、有休等、同じ日に複数あれば全て。add_remarkで優先順に並べる）
+    pub remarks: Vec<String>,
+    /// 出張マーク（「出」）
+    pub detail_st: String,
+    /// 日曜日フラグ
+    pub is_sunday: bool,
+    /// 祝日・非法定休日フラグ
+    pub is_holiday: bool,
+    /// 国民の祝日の名称（「祝」表示・祝日出勤用、JSON出力には含めない）
+    #[serde(skip_serializing)]
+    pub shukujitsu: Option<String>,
+    /// 拘束時間（分）- 表示用（TC_DC + デジタコ合算）
+    pub kosoku_minutes: Option<i32>,
+    /// TC_DC版拘束時間（分）- INSERT用
+    pub kosoku_tcdc: Option<i32>,
+    /// デジタコ版拘束時間（分）- INSERT用
+    pub kosoku_digitacho: Option<i32>,
+    /// 残業時間（旅費から取得）
+    pub zangyo: Option<f64>,
+    /// 残業の元データ（ryohi_rows / time_card_zangyo の行）
+    pub zangyo_sources: Vec<ZangyoSource>,
+    /// 家畜車フラグ（「畜」マーク）
+    pub is_kachiku: bool,
+    /// トレーラーフラグ（「引」マーク）
+    pub is_trailer: bool,
+    /// デジタコデータありフラグ（リンク表示用）
+    pub has_digitacho: bool,
+    /// 作業日報フラグ（「作」マーク）
+    pub has_daily_report: bool,
+    /// 追加作業件数
+    pub tsuika_count: i32,
 }
 
 impl DayRecord {
+    /// 日・曜日だけの空の行（日曜日なら is_sunday）
     pub fn new(day: u8, weekday: &str) -> Self {
         let is_sunday = weekday == "日";
         Self {
@@ -40,14 +199,18 @@ impl DayRecord {
             weekday: weekday.to_string(),
             clock_in: Vec::new(),
             clock_out: Vec::new(),
-            remarks: String::new(),
+            punches: Vec::new(),
+            warnings: Vec::new(),
+            remarks: Vec::new(),
             detail_st: String::new(),
             is_sunday,
             is_holiday: false,
+            shukujitsu: None,
             kosoku_minutes: None,
             kosoku_tcdc: None,
             kosoku_digitacho: None,
             zangyo: None,
+            zangyo_sources: Vec::new(),
             is_kachiku: false,
             is_trailer: false,
             has_digitacho: false,
@@ -68,6 +231,207 @@ impl DayRecord {
         }
     }
 
+    /// TC_DC版とデジタコ版の拘束時間が両方あり、差が閾値（分）を超えているか
+    pub fn kosoku_mismatch(&self, threshold_minutes: i32) -> bool {
+        match (self.kosoku_tcdc, self.kosoku_digitacho) {
+            (Some(tcdc), Some(digitacho)) => (tcdc - digitacho).abs() > threshold_minutes,
+            _ => false,
+        }
+    }
+
+    /// 短時間の二度押しをまとめる（時刻順で連続する同じ種類の打刻の間隔が window 以内なら1つにする）
+    ///
+    /// - 始業は最初、終業は最後の打刻を残す
+    /// - 種類のない手動打刻は隣の打刻と同じ種類として扱う
+    /// - まとめた件数は warnings に記録する
+    pub fn dedup_punches(&mut self, window: Duration) {
+        if window <= Duration::zero() {
+            return;
+        }
+        let mut punches = std::mem::take(&mut self.punches);
+        punches.sort_by_key(|p| (p.overnight, p.time));
+
+        let mut kept: Vec<Punch> = Vec::with_capacity(punches.len());
+        let mut previous: Option<NaiveTime> = None;
+        let mut collapsed = 0;
+        for punch in punches {
+            let duplicate = match (kept.last(), previous) {
+                (Some(last), Some(previous)) => {
+                    last.overnight == punch.overnight
+                        && punch.time - previous <= window
+                        && (last.kind == punch.kind || last.kind.is_none() || punch.kind.is_none())
+                }
+                _ => false,
+            };
+            previous = Some(punch.time);
+            if !duplicate {
+                kept.push(punch);
+                continue;
+            }
+            let last = kept.last_mut().unwrap();
+            last.kind = last.kind.or(punch.kind);
+            if last.kind == Some(PunchKind::Out) {
+                last.time = punch.time;
+            }
+            collapsed += 1;
+        }
+        self.punches = kept;
+        if collapsed > 0 {
+            self.warnings.push(format!("二度押しの打刻{}件をまとめました", collapsed));
+        }
+    }
+
+    /// 打刻を時刻順に並べ、出社→退社のペアを組み直して clock_in / clock_out に設定
+    ///
+    /// - 退社1より前の出社だけが出社1になり、退社1より後の出社は出社2になる
+    /// - 種類のない手動打刻は、出社済みで退社がまだなら退社、それ以外は出社として扱う
+    /// - 同じペアで2回目の出社は無視する（最初の打刻を採用）
+    /// - 翌日から振り替えた終業はその日の打刻の後に並べる
+    /// - 3ペア目以降も捨てずに保持する（PDFでは extra_punch_pairs の件数を「+N」で表示）
+    pub fn pair_punches(&mut self) {
+        let pairs = self.punch_pairs();
+
+        // 空のペアは "" で埋め、末尾の空は詰める（従来の clock_in / clock_out と同じ形）
+        let fmt = |t: Option<NaiveTime>| t.map(|t| t.format("%H:%M").to_string()).unwrap_or_default();
+        let mut clock_in: Vec<String> = pairs.iter().map(|p| fmt(p.0)).collect();
+        let mut clock_out: Vec<String> = pairs.iter().map(|p| fmt(p.1)).collect();
+        while clock_in.last().is_some_and(|s| s.is_empty()) {
+            clock_in.pop();
+        }
+        while clock_out.last().is_some_and(|s| s.is_empty()) {
+            clock_out.pop();
+        }
+        self.clock_in = clock_in;
+        self.clock_out = clock_out;
+    }
+
+    /// 打刻から出社/退社のペアを組む（ペア数の上限なし）
+    fn punch_pairs(&self) -> Vec<(Option<NaiveTime>, Option<NaiveTime>)> {
+        let mut punches = self.punches.clone();
+        punches.sort_by_key(|p| (p.overnight, p.time));
+
+        let mut pairs: Vec<(Option<NaiveTime>, Option<NaiveTime>)> = vec![(None, None)];
+        for punch in &punches {
+            let (current_in, current_out) = *pairs.last().unwrap();
+            let kind = punch.kind.unwrap_or(if current_in.is_some() && current_out.is_none() {
+                PunchKind::Out
+            } else {
+                PunchKind::In
+            });
+            match kind {
+                PunchKind::In => {
+                    if current_out.is_some() {
+                        // 退社後の出社 → 次のペア
+                        pairs.push((Some(punch.time), None));
+                    } else if current_in.is_none() {
+                        pairs.last_mut().unwrap().0 = Some(punch.time);
+                    }
+                }
+                PunchKind::Out => {
+                    if current_out.is_some() {
+                        // 退社が続いた場合は出社なしの次のペア
+                        pairs.push((None, Some(punch.time)));
+                    } else {
+                        pairs.last_mut().unwrap().1 = Some(punch.time);
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    /// PDFに表示しきれない出社/退社のペア数（3ペア目以降）
+    pub fn extra_punch_pairs(&self) -> usize {
+        self.clock_in.len().max(self.clock_out.len()).saturating_sub(PRINTED_PUNCH_PAIRS)
+    }
+
+    /// 最初の出社と最後の退社（遅刻・早退の判定用）
+    /// 翌日から振り替えた終業で終わる日は日跨ぎ勤務なので退社はNone
+    pub fn first_in_last_out(&self) -> (Option<NaiveTime>, Option<NaiveTime>) {
+        let pairs = self.punch_pairs();
+        let first_in = pairs.iter().find_map(|p| p.0);
+        let last_out = if self.punches.iter().any(|p| p.overnight) {
+            None
+        } else {
+            pairs.iter().rev().find_map(|p| p.1)
+        };
+        (first_in, last_out)
+    }
+
+    /// 曜日の表示文字列（国民の祝日は「祝」を付ける）
+    pub fn weekday_label(&self) -> String {
+        if self.shukujitsu.is_some() {
+            format!("{}祝", self.weekday)
+        } else {
+            self.weekday.clone()
+        }
+    }
+
+    /// 国民の祝日に打刻があるか（祝日出勤）
+    pub fn is_shukujitsu_work(&self) -> bool {
+        self.shukujitsu.is_some() && (!self.clock_in.is_empty() || !self.clock_out.is_empty())
+    }
+
+    /// 退社のない出社で終わっているか（日跨ぎ勤務の判定用）
+    pub fn has_open_clock_in(&self) -> bool {
+        matches!(self.punch_pairs().last(), Some((Some(_), None)))
+    }
+
+    /// 残業を加算し、元データを記録（同じ日の値は加算）
+    pub fn add_zangyo(&mut self, source: ZangyoSource) {
+        self.zangyo = Some(self.zangyo.unwrap_or(0.0) + source.amount);
+        self.zangyo_sources.push(source);
+    }
+
+    /// 備考を追加（空・重複は無視し、休暇種別 > 出張 > その他 の順に並べ直す）
+    pub fn add_remark(&mut self, remark: &str) {
+        let remark = remark.trim();
+        if remark.is_empty() || self.remarks.iter().any(|r| r == remark) {
+            return;
+        }
+        self.remarks.push(remark.to_string());
+        self.remarks.sort_by_key(|r| LeaveType::from_remarks(r).map_or(u8::MAX, |t| t.priority()));
+    }
+
+    /// 在籍していない日か（備考が入社前・退職後）
+    pub fn is_not_employed(&self) -> bool {
+        self.has_remark(|t| matches!(t, LeaveType::NyushaMae | LeaveType::TaishokuGo))
+    }
+
+    /// 備考の種別（優先順）
+    pub fn remark_types(&self) -> Vec<LeaveType> {
+        self.remarks.iter().filter_map(|r| LeaveType::from_remarks(r)).collect()
+    }
+
+    /// 備考のいずれかが条件を満たすか
+    pub fn has_remark(&self, f: impl Fn(&LeaveType) -> bool) -> bool {
+        self.remark_types().iter().any(f)
+    }
+
+    /// 備考と出張マークの種別（優先順、集計PDFの記号用）
+    pub fn leave_types(&self) -> Vec<LeaveType> {
+        let mut types = self.remark_types();
+        if self.detail_st.contains('出') && !types.contains(&LeaveType::Shutcho) {
+            types.push(LeaveType::Shutcho);
+        }
+        types.sort_by_key(|t| t.priority());
+        types
+    }
+
+    /// 備考欄の表示（備考と出張マークを優先順に separator で連結）
+    pub fn remarks_text(&self, separator: &str) -> String {
+        let mut items: Vec<(u8, &str)> = self
+            .remarks
+            .iter()
+            .map(|r| (LeaveType::from_remarks(r).map_or(u8::MAX, |t| t.priority()), r.as_str()))
+            .collect();
+        if !self.detail_st.is_empty() {
+            items.push((LeaveType::Shutcho.priority(), self.detail_st.as_str()));
+        }
+        items.sort_by_key(|(priority, _)| *priority);
+        items.iter().map(|(_, text)| *text).collect::<Vec<_>>().join(separator)
+    }
+
     /// 残業時間を文字列で取得（整数の場合は整数表示）
     pub fn zangyo_str(&self) -> String {
         match self.zangyo {
@@ -105,32 +469,356 @@ impl DayRecord {
     }
 }
 
+/// 休暇・備考の種別（time_card_holidaysの備考文字列から判定）
+/// 集計PDFの記号に使用（日数の集計は holiday_mapping の対応表で行う）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LeaveType {
+    /// 公休
+    Koukyu,
+    /// 泊休
+    Hakukyu,
+    /// 積置泊休
+    TsumiokiHakukyu,
+    /// 指休
+    Shikyu,
+    /// 有休
+    Yukyu,
+    /// 欠勤
+    Kekkin,
+    /// 遅刻
+    Chikoku,
+    /// 早退
+    Soutai,
+    /// 特休
+    Tokukyu,
+    /// 前休（半休）
+    Zenkyu,
+    /// 後休（半休）
+    Kokyu,
+    /// 前休作（半休）
+    ZenkyuSaku,
+    /// 後休作（半休）
+    KokyuSaku,
+    /// 仮乗
+    Karinori,
+    /// 入社前
+    NyushaMae,
+    /// 退職後
+    TaishokuGo,
+    /// 出張（detail_stの「出」）
+    Shutcho,
+    /// 未定義の備考
+    Other(String),
+}
+
+impl LeaveType {
+    /// 備考文字列から種別を判定（空文字はNone）
+    pub fn from_remarks(remarks: &str) -> Option<Self> {
+        let t = match remarks.trim() {
+            "" => return None,
+            "公休" => Self::Koukyu,
+            "泊休" => Self::Hakukyu,
+            "積置泊休" => Self::TsumiokiHakukyu,
+            "指休" => Self::Shikyu,
+            "有休" => Self::Yukyu,
+            "欠勤" => Self::Kekkin,
+            "遅刻" => Self::Chikoku,
+            "早退" => Self::Soutai,
+            "特休" => Self::Tokukyu,
+            "前休" => Self::Zenkyu,
+            "後休" => Self::Kokyu,
+            "前休作" => Self::ZenkyuSaku,
+            "後休作" => Self::KokyuSaku,
+            "仮乗" => Self::Karinori,
+            "入社前" => Self::NyushaMae,
+            "退職後" => Self::TaishokuGo,
+            "出張" | "出" => Self::Shutcho,
+            other => Self::Other(other.to_string()),
+        };
+        Some(t)
+    }
+
+    /// 表示名（凡例用）
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Koukyu => "公休",
+            Self::Hakukyu => "泊休",
+            Self::TsumiokiHakukyu => "積置泊休",
+            Self::Shikyu => "指休",
+            Self::Yukyu => "有休",
+            Self::Kekkin => "欠勤",
+            Self::Chikoku => "遅刻",
+            Self::Soutai => "早退",
+            Self::Tokukyu => "特休",
+            Self::Zenkyu => "前休",
+            Self::Kokyu => "後休",
+            Self::ZenkyuSaku => "前休作",
+            Self::KokyuSaku => "後休作",
+            Self::Karinori => "仮乗",
+            Self::NyushaMae => "入社前",
+            Self::TaishokuGo => "退職後",
+            Self::Shutcho => "出張",
+            Self::Other(text) => text,
+        }
+    }
+
+    /// デフォルトの記号（1文字）
+    pub fn default_symbol(&self) -> char {
+        match self {
+            Self::Koukyu => '公',
+            Self::Hakukyu => '泊',
+            Self::TsumiokiHakukyu => '積',
+            Self::Shikyu => '指',
+            Self::Yukyu => '有',
+            Self::Kekkin => '欠',
+            Self::Chikoku => '遅',
+            Self::Soutai => '早',
+            Self::Tokukyu => '特',
+            Self::Zenkyu | Self::ZenkyuSaku => '前',
+            Self::Kokyu | Self::KokyuSaku => '後',
+            Self::Karinori => '仮',
+            Self::NyushaMae => '入',
+            Self::TaishokuGo => '退',
+            Self::Shutcho => '出',
+            Self::Other(_) => '※',
+        }
+    }
+
+    /// 同じ日に複数ある場合の並び順（休暇種別 > 出張 > その他）
+    pub fn priority(&self) -> u8 {
+        match self {
+            Self::Shutcho => 1,
+            Self::Other(_) => 2,
+            _ => 0,
+        }
+    }
+
+    /// 集計PDFで休暇としてグレー表示する種別
+    pub fn is_day_off(&self) -> bool {
+        matches!(self, Self::Koukyu | Self::Hakukyu | Self::Yukyu | Self::Tokukyu | Self::Kekkin)
+    }
+}
+
+/// 凡例の1項目（記号と、その記号で表示した備考）
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegendEntry {
+    /// 記号
+    pub symbol: char,
+    /// その記号で表示した備考
+    pub labels: Vec<String>,
+}
+
+/// 備考→記号の対応表（集計PDFの日別欄用）
+/// RenderOptions の remark_symbols で上書き可能（例: "積置泊休=積,指休=指"）
+#[derive(Debug, Clone, Default)]
+pub struct RemarkSymbols {
+    overrides: HashMap<LeaveType, char>,
+}
+
+impl RemarkSymbols {
+    /// "備考=記号,備考=記号" 形式を解析（不正な項目があればNone）
+    pub fn try_parse(spec: &str) -> Option<Self> {
+        let mut overrides = HashMap::new();
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (remarks, symbol) = item.split_once('=')?;
+            let leave = LeaveType::from_remarks(remarks)?;
+            let c = symbol.trim().chars().next()?;
+            overrides.insert(leave, c);
+        }
+        Some(Self { overrides })
+    }
+
+    /// 種別の記号（未定義の備考は常に「※」）
+    pub fn symbol(&self, leave: &LeaveType) -> char {
+        if let LeaveType::Other(_) = leave {
+            return '※';
+        }
+        self.overrides.get(leave).copied().unwrap_or_else(|| leave.default_symbol())
+    }
+
+    /// 日別の記号文字列（備考 + 出張）
+    pub fn day_symbols(&self, day: &DayRecord) -> String {
+        day.leave_types().iter().map(|t| self.symbol(t)).collect()
+    }
+
+    /// 使われている記号だけの凡例（出現順）
+    pub fn legend(&self, days: &[DayRecord]) -> Vec<LegendEntry> {
+        let mut entries: Vec<LegendEntry> = Vec::new();
+        for leave in days.iter().flat_map(|d| d.leave_types()) {
+            let symbol = self.symbol(&leave);
+            let label = leave.label().to_string();
+            match entries.iter_mut().find(|e| e.symbol == symbol) {
+                Some(entry) => {
+                    if !entry.labels.contains(&label) {
+                        entry.labels.push(label);
+                    }
+                }
+                None => entries.push(LegendEntry { symbol, labels: vec![label] }),
+            }
+        }
+        entries
+    }
+}
+
+/// 出退勤時刻の囲み記号（PHPと同じくデジタコがある日は [08:00]、ない日は <08:00>）
+/// RenderOptions の punch_symbols で変更可能
+///   - "off": 囲まない（集計PDFの記号は既定のまま）
+///   - "[]<>": デジタコあり・なしの左右記号を4文字で指定
+#[derive(Debug, Clone, PartialEq)]
+pub struct PunchSymbols {
+    /// 囲むか（"off" なら false）
+    pub enabled: bool,
+    /// デジタコがある日（または仮乗）の記号
+    pub digitacho: (char, char),
+    /// タイムカードの打刻のみの日の記号
+    pub reader: (char, char),
+}
+
+impl Default for PunchSymbols {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            digitacho: ('[', ']'),
+            reader: ('<', '>'),
+        }
+    }
+}
+
+impl PunchSymbols {
+    /// "off" または4文字の記号指定を解析（不正な値はNone）
+    pub fn try_parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        if matches!(spec.to_lowercase().as_str(), "off" | "0" | "false") {
+            return Some(Self { enabled: false, ..Self::default() });
+        }
+        let chars: Vec<char> = spec.chars().filter(|c| !c.is_whitespace() && *c != ',').collect();
+        match chars.as_slice() {
+            [a, b, c, d] => Some(Self { enabled: true, digitacho: (*a, *b), reader: (*c, *d) }),
+            _ => None,
+        }
+    }
+
+    /// その日の記号（デジタコデータまたは備考が「仮乗」なら digitacho）
+    pub fn pair(&self, day: &DayRecord) -> (char, char) {
+        if day.has_digitacho || day.has_remark(|t| *t == LeaveType::Karinori) {
+            self.digitacho
+        } else {
+            self.reader
+        }
+    }
+
+    /// 時刻を囲む（空の時刻・無効時はそのまま）
+    pub fn wrap(&self, day: &DayRecord, time: &str) -> String {
+        if !self.enabled || time.is_empty() {
+            return time.to_string();
+        }
+        let (left, right) = self.pair(day);
+        format!("{}{}{}", left, time, right)
+    }
+
+    /// 出勤1・退勤1・出勤2・退勤2 の表示文字列
+    pub fn punches(&self, day: &DayRecord) -> [String; 4] {
+        let get = |list: &Vec<String>, i: usize| list.get(i).map(|s| self.wrap(day, s)).unwrap_or_default();
+        [get(&day.clock_in, 0), get(&day.clock_out, 0), get(&day.clock_in, 1), get(&day.clock_out, 1)]
+    }
+}
+
 /// 月別タイムカードデータ
-#[derive(Debug, Clone)]
+/// JSON APIでは日別・合計の拘束時間に "HH:MM" 文字列を付ける（DayRecord自体のスキーマは変えない）
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonthlyTimecard {
+    /// ドライバー
     pub driver: Driver,
+    /// 対象年
     pub year: i32,
+    /// 対象月
     pub month: u32,
+    /// 日別の行（1日〜月末）
+    #[serde(serialize_with = "serialize_days")]
     pub days: Vec<DayRecord>,
+    /// 月の集計
+    #[serde(serialize_with = "serialize_summary", default)]
     pub summary: TimecardSummary,
+    /// 入社日・退職日（YYYY-MM-DD、対象月に掛かる最後の在籍期間。月内の再入社の空きは日別の備考「退職後」で分かる）
+    #[serde(default)]
+    pub hire_date: Option<String>,
+    /// 退職日（YYYY-MM-DD）
+    #[serde(default)]
+    pub retire_date: Option<String>,
+}
+
+/// タイムカード一覧の対象年月（ログのspan用、先頭のタイムカードから。空なら None）
+pub fn target_month(timecards: &[MonthlyTimecard]) -> (Option<i32>, Option<u32>) {
+    (timecards.first().map(|tc| tc.year), timecards.first().map(|tc| tc.month))
+}
+
+/// JSON API用の1日分（kosoku_hhmm、TC_DC/デジタコの不一致フラグ、警告を追加）
+#[derive(Serialize)]
+struct DayRecordJson<'a> {
+    #[serde(flatten)]
+    day: &'a DayRecord,
+    kosoku_hhmm: String,
+    kosoku_mismatch: bool,
+    warnings: &'a [String],
+}
+
+fn serialize_days<S: Serializer>(days: &[DayRecord], serializer: S) -> Result<S::Ok, S::Error> {
+    let threshold = kosoku_mismatch_minutes_from_env();
+    serializer.collect_seq(days.iter().map(|day| DayRecordJson {
+        day,
+        kosoku_hhmm: day.kosoku_str(),
+        kosoku_mismatch: day.kosoku_mismatch(threshold),
+        warnings: &day.warnings,
+    }))
+}
+
+/// JSON API用の集計（total_kosoku_hhmm を追加）
+#[derive(Serialize)]
+struct TimecardSummaryJson<'a> {
+    #[serde(flatten)]
+    summary: &'a TimecardSummary,
+    total_kosoku_hhmm: String,
+}
+
+fn serialize_summary<S: Serializer>(summary: &TimecardSummary, serializer: S) -> Result<S::Ok, S::Error> {
+    TimecardSummaryJson { summary, total_kosoku_hhmm: summary.total_kosoku_str() }.serialize(serializer)
 }
 
 /// 集計データ
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TimecardSummary {
-    pub shukkin: f64,      // 出勤日数（半休対応のためf64）
-    pub kyuka: i32,        // 公休日数
-    pub yukyu: f64,        // 有休日数（半休対応のためf64）
-    pub kekkin: i32,       // 欠勤日数
-    pub chikoku: i32,      // 遅刻日数
-    pub soutai: i32,       // 早退日数
-    pub tokukyu: i32,      // 特休日数
-    pub total_zangyo: f64, // 残業合計
-    pub kyushutsu: f64,    // 休出日数（半休対応のためf64）
-    pub total_kosoku: i32, // 拘束時間合計（分）
-    pub trailer: i32,      // トレーラー手当日数
-    pub kachiku: i32,      // 家畜車手当日数
-    pub tsuika: i32,       // 追加作業
+    /// 出勤日数（半休対応のためf64）
+    pub shukkin: f64,
+    /// 公休日数
+    pub kyuka: i32,
+    /// 有休日数（半休対応のためf64）
+    pub yukyu: f64,
+    /// 欠勤日数
+    pub kekkin: i32,
+    /// 遅刻日数
+    pub chikoku: i32,
+    /// 早退日数
+    pub soutai: i32,
+    /// 特休日数
+    pub tokukyu: i32,
+    /// 残業合計
+    pub total_zangyo: f64,
+    /// 休出日数（半休対応のためf64）
+    pub kyushutsu: f64,
+    /// 拘束時間合計（分）
+    pub total_kosoku: i32,
+    /// トレーラー手当日数
+    pub trailer: i32,
+    /// 家畜車手当日数
+    pub kachiku: i32,
+    /// 追加作業
+    pub tsuika: i32,
+    /// 祝日出勤日数（打刻のある国民の祝日）
+    pub shukujitsu_work: i32,
+    /// 集計できなかった備考などの警告
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 impl TimecardSummary {
@@ -147,14 +835,55 @@ impl TimecardSummary {
 }
 
 impl MonthlyTimecard {
+    /// 「YYYY年MM月」
     pub fn year_month_str(&self) -> String {
         format!("{}年{:02}月", self.year, self.month)
     }
 
+    /// 夜勤用の社員番号（time_card_yakin）のタイムカードを日ごとにまとめる（集計は呼び出し側で計算し直す）
+    ///
+    /// - 同じ日に両方の打刻があれば両方のペアを残す（本人のペアの後に夜勤のペア）
+    /// - 夜勤の打刻がある日は備考に「※夜勤」を付け、夜勤側の備考も加える（夜勤側の入社前・退職後は除く）
+    /// - 表示用の拘束時間は合算する（INSERT用の kosoku_tcdc / kosoku_digitacho はドライバーごとのまま）
+    pub fn merge_yakin(&mut self, yakin: &MonthlyTimecard) {
+        for (day, other) in self.days.iter_mut().zip(&yakin.days) {
+            if !other.clock_in.is_empty() || !other.clock_out.is_empty() {
+                let pairs = day.clock_in.len().max(day.clock_out.len());
+                let other_pairs = other.clock_in.len().max(other.clock_out.len());
+                for (mine, theirs) in [(&mut day.clock_in, &other.clock_in), (&mut day.clock_out, &other.clock_out)] {
+                    mine.resize(pairs, String::new());
+                    mine.extend(theirs.iter().cloned().chain(std::iter::repeat(String::new())).take(other_pairs));
+                    while mine.last().is_some_and(|s| s.is_empty()) {
+                        mine.pop();
+                    }
+                }
+                day.add_remark(YAKIN_REMARK);
+            }
+            for remark in &other.remarks {
+                if !matches!(LeaveType::from_remarks(remark), Some(LeaveType::NyushaMae | LeaveType::TaishokuGo)) {
+                    day.add_remark(remark);
+                }
+            }
+            if let Some(minutes) = other.kosoku_minutes {
+                day.kosoku_minutes = Some(day.kosoku_minutes.unwrap_or(0) + minutes);
+            }
+        }
+    }
+
+    /// 国民の祝日（日 → 名称）と非法定休日の日に休日フラグを付け、祝日出勤日数を数え直す
+    pub fn mark_holidays(&mut self, national: &HashMap<u32, String>, non_legal: &HashSet<u32>) {
+        for day in &mut self.days {
+            let d = day.day as u32;
+            day.shukujitsu = national.get(&d).cloned();
+            day.is_holiday = day.shukujitsu.is_some() || non_legal.contains(&d);
+        }
+        self.summary.shukujitsu_work = self.days.iter().filter(|d| d.is_shukujitsu_work()).count() as i32;
+    }
+
     /// 日別データから集計を計算（基礎日数なしの基本集計）
     /// 休出計算は別途calculate_summary_with_kiso()を使用
     pub fn calculate_summary(&mut self) {
-        self.calculate_summary_with_kiso(0, 0, 0);
+        self.calculate_summary_with_kiso(0, 0);
     }
 
     /// 日別データから集計を計算（基礎日数ベースで休出計算）
@@ -162,15 +891,28 @@ impl MonthlyTimecard {
     ///
     /// # Arguments
     /// * `kiso_date` - 基礎日数（kyuyo_kiso_dateテーブルから）
-    /// * `before_hire_count` - 入社前日数
-    /// * `after_retire_count` - 退職後日数
-    pub fn calculate_summary_with_kiso(&mut self, kiso_date: i32, before_hire_count: i32, after_retire_count: i32) {
+    /// * `not_employed_days` - 在籍していない日数（入社前・退職後・月内の退職から再入社まで）
+    pub fn calculate_summary_with_kiso(&mut self, kiso_date: i32, not_employed_days: i32) {
+        self.calculate_summary_with_rules(kiso_date, not_employed_days, HolidayMapping::global(), ShiftRules::global());
+    }
+
+    /// 備考の対応表と始業・終業を指定して集計（calculate_summary_with_kisoの本体）
+    /// - 対応表にない備考は集計せず summary.warnings に記録する
+    /// - 遅刻・早退は備考に加えて打刻からも判定する（公休・有休・出張の日は除く、1日1回）
+    pub fn calculate_summary_with_rules(
+        &mut self,
+        kiso_date: i32,
+        not_employed_days: i32,
+        mapping: &HolidayMapping,
+        shift_rules: &ShiftRules,
+    ) {
+        let shift = shift_rules.times_for(self.driver.category_c);
         // 既存の手当データを保持
         let existing_kachiku = self.summary.kachiku;
         let existing_trailer = self.summary.trailer;
-        let existing_tsuika = self.summary.tsuika;
 
         let mut summary = TimecardSummary::default();
+        let mut unknown_remarks: BTreeMap<String, i32> = BTreeMap::new();
 
         // 日別データから各種カウントを集計（ShukkinboRowのmakeDisplayData相当）
         for day in &self.days {
@@ -184,20 +926,47 @@ impl MonthlyTimecard {
                 summary.total_zangyo += zangyo;
             }
 
-            // 備考から休暇種別をカウント（PHPの_makeTimeCardDisplayArray switch文と同じ）
+            // 備考の種別ごとにカウント（PHPの_makeTimeCardDisplayArray switch文と同じ）
             // TimeCardController.php:2922-2954
-            match day.remarks.as_str() {
-                "公休" | "泊休" | "積置泊休" | "指休" => summary.kyuka += 1,
-                "有休" => summary.yukyu += 1.0,
-                "欠勤" => summary.kekkin += 1,
-                "遅刻" => summary.chikoku += 1,
-                "早退" => summary.soutai += 1,
-                "特休" => summary.tokukyu += 1,
-                "前休" | "後休" | "前休作" | "後休作" => {
+            // 同じ日に複数の備考があれば全てを数える（公休扱いは1日1回）
+            let mut is_kyuka = false;
+            let mut is_yukyu = false;
+            let mut is_chikoku = false;
+            let mut is_soutai = false;
+            for remark in day.remarks.iter().filter(|r| !r.trim().is_empty()) {
+                let Some(rule) = mapping.rule(remark) else {
+                    *unknown_remarks.entry(remark.trim().to_string()).or_insert(0) += 1;
+                    continue;
+                };
+                match rule.category {
+                    SummaryCategory::Kyuka => is_kyuka = true,
                     // 半休は0.5日（PHPでは前休作/後休作も0.5）
-                    summary.yukyu += 0.5;
+                    SummaryCategory::Yukyu => {
+                        is_yukyu = true;
+                        summary.yukyu += rule.weight;
+                    }
+                    SummaryCategory::Kekkin => summary.kekkin += 1,
+                    SummaryCategory::Chikoku => is_chikoku = true,
+                    SummaryCategory::Soutai => is_soutai = true,
+                    SummaryCategory::Tokukyu => summary.tokukyu += 1,
+                    SummaryCategory::Ignore => {}
                 }
-                _ => {}
+            }
+            if is_kyuka {
+                summary.kyuka += 1;
+            }
+
+            // 打刻から遅刻・早退を判定（PHPと同じく最初の出社・最後の退社で判定）
+            if !is_kyuka && !is_yukyu && !day.leave_types().contains(&LeaveType::Shutcho) {
+                let (first_in, last_out) = day.first_in_last_out();
+                is_chikoku |= first_in.is_some_and(|t| shift_rules.is_late(shift, t));
+                is_soutai |= last_out.is_some_and(|t| shift_rules.is_early(shift, t));
+            }
+            if is_chikoku {
+                summary.chikoku += 1;
+            }
+            if is_soutai {
+                summary.soutai += 1;
             }
 
             // 日別の手当フラグからカウント
@@ -207,6 +976,13 @@ impl MonthlyTimecard {
             if day.is_trailer {
                 summary.trailer += 1;
             }
+
+            // 追加作業（月間の件数は日別の合計）
+            summary.tsuika += day.tsuika_count;
+
+            if day.is_shukujitsu_work() {
+                summary.shukujitsu_work += 1;
+            }
         }
 
         // 既存の手当データがあれば使用（日別データから再計算されなかった場合のフォールバック）
@@ -216,12 +992,10 @@ impl MonthlyTimecard {
         if summary.trailer == 0 && existing_trailer > 0 {
             summary.trailer = existing_trailer;
         }
-        // 追加作業は既存値を保持（日別データにはない）
-        summary.tsuika = existing_tsuika;
 
         // PHPの計算式に従って出勤日数と休出日数を計算
-        // kyujitsu_shukkin = 月の日数 - 公休 - 基礎日数 - 欠勤 - 入社前 - 退職後
-        // shukkin = 月の日数 - 公休 - 有休 - 休出 - 欠勤 - 特休 - 入社前 - 退職後
+        // kyujitsu_shukkin = 月の日数 - 公休 - 基礎日数 - 欠勤 - 在籍していない日数
+        // shukkin = 月の日数 - 公休 - 有休 - 休出 - 欠勤 - 特休 - 在籍していない日数
         let days_in_month = self.days.len() as f64;
 
         // 休出日数計算
@@ -229,8 +1003,7 @@ impl MonthlyTimecard {
             - summary.kyuka as f64
             - kiso_date as f64
             - summary.kekkin as f64
-            - before_hire_count as f64
-            - after_retire_count as f64;
+            - not_employed_days as f64;
         summary.kyushutsu = if kyushutsu > 0.0 { kyushutsu } else { 0.0 };
 
         // 出勤日数計算
@@ -240,10 +1013,510 @@ impl MonthlyTimecard {
             - summary.kyushutsu
             - summary.kekkin as f64
             - summary.tokukyu as f64
-            - before_hire_count as f64
-            - after_retire_count as f64;
+            - not_employed_days as f64;
         summary.shukkin = if shukkin > 0.0 { shukkin } else { 0.0 };
 
+        summary.warnings = unknown_remarks
+            .into_iter()
+            .map(|(remark, days)| format!("未定義の備考「{}」({}日)は集計していません", remark, days))
+            .collect();
+
         self.summary = summary;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_punch_symbols_for_mixed_month() {
+        let mut days: Vec<DayRecord> = (1..=3).map(|d| DayRecord::new(d, "月")).collect();
+        for d in days.iter_mut() {
+            d.clock_in.push("08:00".to_string());
+            d.clock_out.push("17:30".to_string());
+        }
+        // 1日: デジタコあり、2日: 打刻のみ、3日: 仮乗（デジタコ扱い）
+        days[0].has_digitacho = true;
+        days[2].add_remark("仮乗");
+
+        let symbols = PunchSymbols::default();
+        assert_eq!(symbols.punches(&days[0]), ["[08:00]", "[17:30]", "", ""].map(String::from));
+        assert_eq!(symbols.punches(&days[1]), ["<08:00>", "<17:30>", "", ""].map(String::from));
+        assert_eq!(symbols.punches(&days[2])[0], "[08:00]");
+
+        let off = PunchSymbols::try_parse("off").unwrap();
+        assert_eq!(off.punches(&days[0])[0], "08:00");
+        let custom = PunchSymbols::try_parse("(),{}").unwrap();
+        assert_eq!(custom.punches(&days[1])[1], "{17:30}");
+    }
+
+    fn punch(time: &str, kind: Option<PunchKind>) -> Punch {
+        let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            .unwrap();
+        Punch { time, kind, overnight: false }
+    }
+
+    fn paired(punches: Vec<Punch>) -> (Vec<String>, Vec<String>) {
+        let mut day = DayRecord::new(1, "月");
+        day.punches = punches;
+        day.pair_punches();
+        (day.clock_in, day.clock_out)
+    }
+
+    #[test]
+    fn test_pair_punches_orders_by_time() {
+        use PunchKind::*;
+        // 取得順がばらばらでも時刻順にペアを組む
+        let (ins, outs) = paired(vec![
+            punch("17:00", Some(Out)),
+            punch("13:00", Some(In)),
+            punch("08:00", Some(In)),
+            punch("12:00", Some(Out)),
+        ]);
+        assert_eq!(ins, vec!["08:00", "13:00"]);
+        assert_eq!(outs, vec!["12:00", "17:00"]);
+
+        // 退社1の後の出社は出社2（出社1は空）
+        let (ins, outs) = paired(vec![punch("12:00", Some(Out)), punch("13:00", Some(In))]);
+        assert_eq!(ins, vec!["", "13:00"]);
+        assert_eq!(outs, vec!["12:00"]);
+
+        // 手動打刻（種類なし）も同じロジックで振り分け
+        let (ins, outs) = paired(vec![
+            punch("08:00", Some(In)),
+            punch("12:00", Some(Out)),
+            punch("13:00", None),
+            punch("18:00", None),
+        ]);
+        assert_eq!(ins, vec!["08:00", "13:00"]);
+        assert_eq!(outs, vec!["12:00", "18:00"]);
+    }
+
+    #[test]
+    fn test_pair_punches_invariant() {
+        use PunchKind::*;
+        let times = ["06:00", "09:00", "12:00", "15:00", "18:00"];
+        // 5打刻の出社/退社の全組み合わせで不変条件を確認
+        for mask in 0..(1u32 << times.len()) {
+            let punches: Vec<Punch> = times
+                .iter()
+                .enumerate()
+                .map(|(i, t)| punch(t, Some(if mask & (1 << i) != 0 { In } else { Out })))
+                .collect();
+            let (ins, outs) = paired(punches);
+            let t = |v: &Vec<String>, i: usize| v.get(i).filter(|s| !s.is_empty()).cloned();
+            for i in 0..ins.len().max(outs.len()) {
+                if let (Some(clock_in), Some(clock_out)) = (t(&ins, i), t(&outs, i)) {
+                    assert!(clock_in < clock_out, "mask={:b} {:?} {:?}", mask, ins, outs);
+                }
+                if let (Some(clock_out), Some(next_in)) = (t(&outs, i), t(&ins, i + 1)) {
+                    assert!(clock_out < next_in, "mask={:b} {:?} {:?}", mask, ins, outs);
+                }
+            }
+            // 打刻は捨てない（5打刻なら最大5ペア）
+            assert!(ins.len() <= times.len() && outs.len() <= times.len());
+        }
+    }
+
+    #[test]
+    fn test_dedup_double_punches() {
+        use PunchKind::*;
+        let window = Duration::minutes(2);
+        let dedup = |punches: Vec<Punch>| {
+            let mut day = DayRecord::new(1, "月");
+            day.punches = punches;
+            day.dedup_punches(window);
+            day.pair_punches();
+            (day.clock_in, day.clock_out, day.warnings)
+        };
+
+        // 08:00:10 / 08:00:40 の始業は最初を残す、終業は最後を残す
+        let (ins, outs, warnings) = dedup(vec![
+            punch("08:00:40", Some(In)),
+            punch("08:00:10", Some(In)),
+            punch("17:00:00", Some(Out)),
+            punch("17:01:30", Some(Out)),
+        ]);
+        assert_eq!(ins, vec!["08:00"]);
+        assert_eq!(outs, vec!["17:01"]);
+        assert_eq!(warnings, vec!["二度押しの打刻2件をまとめました"]);
+
+        // 07:59 / 08:02 は3分離れているのでまとめない（出社2が残る）
+        let (ins, _, warnings) = dedup(vec![punch("07:59", Some(In)), punch("08:02", Some(In)), punch("17:00", Some(Out))]);
+        assert_eq!(ins, vec!["07:59"]);
+        assert!(warnings.is_empty());
+
+        // 手動打刻（time_card_inject）の二度押しもまとめる
+        let (ins, outs, warnings) = dedup(vec![punch("08:00", Some(In)), punch("08:01", None), punch("17:00", None)]);
+        assert_eq!(ins, vec!["08:00"]);
+        assert_eq!(outs, vec!["17:00"]);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_third_punch_pair_is_kept() {
+        use PunchKind::*;
+        let mut day = DayRecord::new(1, "月");
+        day.punches = ["05:00", "09:00", "11:00", "14:00", "17:00", "21:00"]
+            .iter()
+            .enumerate()
+            .map(|(i, t)| punch(t, Some(if i % 2 == 0 { In } else { Out })))
+            .collect();
+        day.pair_punches();
+        assert_eq!(day.clock_in, vec!["05:00", "11:00", "17:00"]);
+        assert_eq!(day.clock_out, vec!["09:00", "14:00", "21:00"]);
+        assert_eq!(day.extra_punch_pairs(), 1);
+        // JSON（BI連携）にも全ペアが出る
+        let value = serde_json::to_value(&day).unwrap();
+        assert_eq!(value["clock_in"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_overnight_clock_out_moves_to_start_day() {
+        use PunchKind::*;
+        let cutoff = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        // 1日 20:00出社 → 2日 05:30退社、2日は 20:00出社 → 3日 05:00退社（手動打刻）、3日は通常勤務
+        let mut days: Vec<DayRecord> = (1..=4).map(|d| DayRecord::new(d, "月")).collect();
+        days[0].punches = vec![punch("20:00", Some(In))];
+        days[1].punches = vec![punch("05:30", Some(Out)), punch("20:00", Some(In))];
+        days[2].punches = vec![punch("05:00", None), punch("08:00", Some(In)), punch("17:00", Some(Out))];
+        // 4日: 前日に未退社の出社がないので振り替えない
+        days[3].punches = vec![punch("06:00", Some(Out))];
+        attribute_overnight_punches(&mut days, cutoff);
+        for day in days.iter_mut() {
+            day.pair_punches();
+        }
+        assert_eq!((days[0].clock_in.clone(), days[0].clock_out.clone()), (vec!["20:00".to_string()], vec!["05:30".to_string()]));
+        assert_eq!((days[1].clock_in.clone(), days[1].clock_out.clone()), (vec!["20:00".to_string()], vec!["05:00".to_string()]));
+        assert_eq!(days[2].clock_in, vec!["08:00"]);
+        assert_eq!(days[2].clock_out, vec!["17:00"]);
+        assert_eq!(days[3].clock_out, vec!["06:00"]);
+
+        // cutoff以降の終業は振り替えない
+        let mut days: Vec<DayRecord> = (1..=2).map(|d| DayRecord::new(d, "月")).collect();
+        days[0].punches = vec![punch("20:00", Some(In))];
+        days[1].punches = vec![punch("09:30", Some(Out))];
+        attribute_overnight_punches(&mut days, cutoff);
+        assert!(days[0].has_open_clock_in());
+        assert_eq!(days[1].punches.len(), 1);
+    }
+
+    /// DayRecordのシリアライズ結果のフィールド名（BI連携のスキーマ）
+    /// 変更する場合はBI側と調整したうえでこの一覧も更新すること
+    const DAY_RECORD_FIELDS: &[&str] = &[
+        "day", "weekday", "clock_in", "clock_out", "remarks", "detail_st",
+        "is_sunday", "is_holiday", "kosoku_minutes", "kosoku_tcdc", "kosoku_digitacho",
+        "zangyo", "zangyo_sources", "is_kachiku", "is_trailer", "has_digitacho",
+        "has_daily_report", "tsuika_count",
+    ];
+
+    #[test]
+    fn test_day_record_schema_is_stable() {
+        let mut day = DayRecord::new(1, "月");
+        day.add_zangyo(ZangyoSource { table: ZangyoTable::RyohiRows, row_id: "12".to_string(), amount: 1.5 });
+        day.has_digitacho = true;
+        day.kosoku_tcdc = Some(600);
+        day.kosoku_digitacho = Some(540);
+        day.warnings.push("二度押しの打刻1件をまとめました".to_string());
+        let value = serde_json::to_value(&day).unwrap();
+        let fields: Vec<&str> = value.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+        let mut expected = DAY_RECORD_FIELDS.to_vec();
+        expected.sort();
+        let mut actual = fields.clone();
+        actual.sort();
+        assert_eq!(actual, expected);
+        assert_eq!(value["zangyo_sources"][0]["table"], "ryohi_rows");
+
+        // 分析用CSV（export_rows）にもDayRecordのフィールドと警告を同じ名前の列で出す
+        let tc = MonthlyTimecard {
+            driver: Driver { id: 1071, name: "テスト".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 12,
+            days: vec![day],
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        };
+        let rows = tc.export_rows();
+        let columns = crate::export::EXPORT_COLUMNS;
+        assert_eq!(rows[0].len(), columns.len());
+        for field in DAY_RECORD_FIELDS.iter().filter(|f| !matches!(**f, "day" | "weekday" | "clock_in" | "clock_out")) {
+            assert!(columns.contains(field), "CSVに列がない: {}", field);
+        }
+        let row: HashMap<&str, &str> = columns.iter().copied().zip(rows[0].iter().map(String::as_str)).collect();
+        assert_eq!(row["date"], "2025-12-01");
+        assert_eq!(row["zangyo_sources"], "ryohi_rows#12");
+        assert_eq!(row["has_digitacho"], "1");
+        assert_eq!(row["is_kachiku"], "0");
+        assert_eq!(row["kosoku_tcdc"], "600");
+        assert_eq!(row["kosoku_digitacho"], "540");
+        assert_eq!(row["tsuika_count"], "0");
+        assert_eq!(row["kosoku_mismatch"], if kosoku_mismatch_minutes_from_env() < 60 { "1" } else { "0" });
+        assert_eq!(row["warnings"], "二度押しの打刻1件をまとめました");
+    }
+
+    #[test]
+    fn test_kosoku_mismatch_threshold() {
+        let mut day = DayRecord::new(1, "月");
+        day.kosoku_tcdc = Some(600);
+        assert!(!day.kosoku_mismatch(30));
+        day.kosoku_digitacho = Some(570);
+        assert!(!day.kosoku_mismatch(30));
+        day.kosoku_digitacho = Some(569);
+        assert!(day.kosoku_mismatch(30));
+        assert!(!day.kosoku_mismatch(31));
+    }
+
+    #[test]
+    fn test_tsuika_summary_is_sum_of_days() {
+        let mut days: Vec<DayRecord> = (1..=31).map(|d| DayRecord::new(d, "月")).collect();
+        days[1].tsuika_count = 2;
+        days[1].zangyo = Some(1.5);
+        days[30].tsuika_count = 3;
+        let mut tc = MonthlyTimecard {
+            driver: Driver { id: 1071, name: "テスト".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 12,
+            days,
+            summary: TimecardSummary { tsuika: 99, ..Default::default() },
+            hire_date: None,
+            retire_date: None,
+        };
+        tc.calculate_summary();
+        assert_eq!(tc.summary.tsuika, 5);
+        assert_eq!(tc.days[1].zangyo_with_tsuika_str(), "1.5〇〇");
+        assert_eq!(tc.days[30].zangyo_with_tsuika_str(), "〇3");
+    }
+
+    #[test]
+    fn test_summary_with_custom_mapping_and_unknown_remarks() {
+        let mut days: Vec<DayRecord> = (1..=30).map(|d| DayRecord::new(d, "月")).collect();
+        days[0].add_remark("公休");
+        days[0].add_remark("泊休"); // 公休扱いは1日1回
+        days[1].add_remark("前休作");
+        days[2].add_remark("半特休");
+        days[3].add_remark("半特休");
+        days[4].add_remark("謎休");
+        let mut tc = MonthlyTimecard {
+            driver: Driver { id: 1071, name: "テスト".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 11,
+            days,
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        };
+
+        // デフォルトの対応表では半特休・謎休は数えず警告
+        tc.calculate_summary_with_rules(0, 0, &HolidayMapping::default(), &ShiftRules::default());
+        assert_eq!(tc.summary.kyuka, 1);
+        assert_eq!(tc.summary.yukyu, 0.5);
+        assert_eq!(tc.summary.tokukyu, 0);
+        assert_eq!(tc.summary.warnings, [
+            "未定義の備考「半特休」(2日)は集計していません",
+            "未定義の備考「謎休」(1日)は集計していません",
+        ]);
+
+        let mapping = HolidayMapping::from_json(r#"{"半特休": {"category": "tokukyu"}}"#).unwrap();
+        tc.calculate_summary_with_rules(27, 0, &mapping, &ShiftRules::default());
+        assert_eq!(tc.summary.tokukyu, 2);
+        // 30日 - 公休1 - 有休0.5 - 休出2 - 特休2
+        assert_eq!(tc.summary.kyushutsu, 2.0);
+        assert_eq!(tc.summary.shukkin, 24.5);
+        assert_eq!(tc.summary.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_mark_holidays() {
+        let mut days: Vec<DayRecord> = (1..=4).map(|d| DayRecord::new(d, "月")).collect();
+        days[0].clock_in.push("08:00".to_string());
+        days[2].clock_in.push("08:00".to_string());
+        let mut tc = MonthlyTimecard {
+            driver: Driver { id: 1071, name: "テスト".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 11,
+            days,
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        };
+        let national = HashMap::from([(1, "文化の日".to_string()), (2, "休日".to_string())]);
+        tc.mark_holidays(&national, &HashSet::from([3]));
+        assert_eq!(tc.days[0].weekday_label(), "月祝");
+        assert!(tc.days[1].is_holiday);
+        // 非法定休日は休日フラグのみ（「祝」・祝日出勤にはしない）
+        assert!(tc.days[2].is_holiday && tc.days[2].shukujitsu.is_none());
+        assert!(!tc.days[3].is_holiday);
+        assert_eq!(tc.summary.shukujitsu_work, 1);
+        tc.calculate_summary();
+        assert_eq!(tc.summary.shukujitsu_work, 1);
+        // JSON出力のスキーマは変えない
+        assert!(serde_json::to_value(&tc.days[0]).unwrap().get("shukujitsu").is_none());
+    }
+
+    #[test]
+    fn test_chikoku_soutai_from_punches() {
+        let mut days: Vec<DayRecord> = (1..=7).map(|d| DayRecord::new(d, "月")).collect();
+        let punches = |day: &mut DayRecord, list: &[(&str, PunchKind)]| {
+            day.punches = list.iter().map(|(t, k)| punch(t, Some(*k))).collect();
+        };
+        punches(&mut days[0], &[("08:05", PunchKind::In), ("17:00", PunchKind::Out)]); // 猶予内
+        punches(&mut days[1], &[("08:06", PunchKind::In), ("16:30", PunchKind::Out)]); // 遅刻・早退
+        punches(&mut days[2], &[("09:00", PunchKind::In)]); // 出社のみ → 遅刻だけ
+        punches(&mut days[3], &[("15:00", PunchKind::Out)]); // 退社のみ → 早退だけ
+        punches(&mut days[4], &[("10:00", PunchKind::In), ("12:00", PunchKind::Out)]);
+        days[4].add_remark("前休"); // 有休の日は判定しない
+        punches(&mut days[5], &[("13:00", PunchKind::In), ("15:00", PunchKind::Out)]);
+        days[5].detail_st = "出".to_string(); // 出張の日も判定しない
+        punches(&mut days[6], &[("08:30", PunchKind::In)]);
+        days[6].add_remark("遅刻"); // 備考と打刻の両方でも1日
+        let mut tc = MonthlyTimecard {
+            driver: Driver { id: 1071, name: "テスト".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 12,
+            days,
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        };
+        let rules = ShiftRules { grace_minutes: 5, ..Default::default() };
+        tc.calculate_summary_with_rules(0, 0, &HolidayMapping::default(), &rules);
+        assert_eq!(tc.summary.chikoku, 3);
+        assert_eq!(tc.summary.soutai, 2);
+        assert_eq!(crate::db::AllowanceData::from_timecard(&tc).chikoku_count, 3);
+
+        // 日跨ぎ勤務（翌日の終業を振り替えた日）は早退にしない
+        tc.days[1].punches[1] = Punch { overnight: true, ..punch("02:00", Some(PunchKind::Out)) };
+        tc.calculate_summary_with_rules(0, 0, &HolidayMapping::default(), &rules);
+        assert_eq!(tc.summary.soutai, 1);
+    }
+
+    #[test]
+    fn test_monthly_timecard_from_json() {
+        // render-fixture のサンプル（省略したフィールドは空）
+        let fixtures: Vec<MonthlyTimecard> = serde_json::from_str(include_str!("../fixtures/timecards_sample.json")).unwrap();
+        assert_eq!(fixtures.len(), 2);
+        assert_eq!(fixtures[0].days.len(), 31);
+        assert_eq!(fixtures[0].days[2].clock_in.len(), 3);
+        assert_eq!(fixtures[1].driver.kyuyo_shain_id, None);
+        assert!(fixtures[1].days[0].remarks.is_empty());
+
+        // /api/timecards の出力（kosoku_hhmm などの追加フィールド付き）も読み込める
+        let json = serde_json::to_string(&fixtures[0]).unwrap();
+        let round_trip: MonthlyTimecard = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&round_trip).unwrap(), json);
+    }
+
+    #[test]
+    fn test_monthly_timecard_json() {
+        let mut day = DayRecord::new(1, "月");
+        day.kosoku_minutes = Some(615);
+        day.kosoku_tcdc = Some(600);
+        day.kosoku_digitacho = Some(15);
+        let mut tc = MonthlyTimecard {
+            driver: Driver { id: 1071, name: "テスト".to_string(), bumon: Some(2), category_c: None, eigyosho_c: None, kyuyo_shain_id: Some(55) },
+            year: 2025,
+            month: 12,
+            days: vec![day, DayRecord::new(2, "火")],
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        };
+        tc.calculate_summary();
+        let value = serde_json::to_value(&tc).unwrap();
+        assert_eq!(value["driver"]["id"], 1071);
+        assert_eq!(value["driver"]["kyuyo_shain_id"], 55);
+        assert_eq!(value["days"][0]["kosoku_minutes"], 615);
+        assert_eq!(value["days"][0]["kosoku_hhmm"], "10:15");
+        assert_eq!(value["days"][1]["kosoku_hhmm"], "");
+        assert_eq!(value["days"][0]["kosoku_tcdc"], 600);
+        assert_eq!(value["days"][0]["kosoku_digitacho"], 15);
+        assert_eq!(value["days"][0]["kosoku_mismatch"], true);
+        assert_eq!(value["days"][1]["kosoku_mismatch"], false);
+        assert_eq!(value["summary"]["total_kosoku"], 615);
+        assert_eq!(value["summary"]["total_kosoku_hhmm"], "10:15");
+        // DayRecordの他のフィールドもそのまま出る
+        assert_eq!(value["days"][0].as_object().unwrap().len(), DAY_RECORD_FIELDS.len() + 3);
+    }
+
+    fn day_with(day: u8, remarks: &str, detail_st: &str) -> DayRecord {
+        let mut d = DayRecord::new(day, "月");
+        d.add_remark(remarks);
+        d.detail_st = detail_st.to_string();
+        d
+    }
+
+    #[test]
+    fn test_legend_lists_only_used_symbols() {
+        let days = vec![
+            day_with(1, "公休", ""),
+            day_with(2, "", "出"),
+            day_with(3, "積置泊休", ""),
+            day_with(4, "公休", ""),
+            day_with(5, "前休", ""),
+            day_with(6, "前休作", ""),
+            day_with(7, "", ""),
+        ];
+        let symbols = RemarkSymbols::default();
+        let legend = symbols.legend(&days);
+        let expected = vec![
+            LegendEntry { symbol: '公', labels: vec!["公休".to_string()] },
+            LegendEntry { symbol: '出', labels: vec!["出張".to_string()] },
+            LegendEntry { symbol: '積', labels: vec!["積置泊休".to_string()] },
+            LegendEntry { symbol: '前', labels: vec!["前休".to_string(), "前休作".to_string()] },
+        ];
+        assert_eq!(legend, expected);
+        assert_eq!(symbols.day_symbols(&days[2]), "積");
+        assert_eq!(symbols.day_symbols(&days[6]), "");
+    }
+
+    #[test]
+    fn test_multiple_remarks_per_day() {
+        // 有休+家畜: どちらも残り、休暇種別が先
+        let mut day = DayRecord::new(1, "月");
+        day.add_remark("家畜");
+        day.add_remark("有休");
+        day.add_remark("有休");
+        assert_eq!(day.remarks, vec!["有休", "家畜"]);
+        assert_eq!(day.remarks_text("・"), "有休・家畜");
+
+        // 出+遅刻: 遅刻（休暇種別）> 出
+        let mut late = DayRecord::new(2, "火");
+        late.detail_st = "出".to_string();
+        late.add_remark("遅刻");
+        assert_eq!(late.remarks_text("・"), "遅刻・出");
+        assert_eq!(late.leave_types(), vec![LeaveType::Chikoku, LeaveType::Shutcho]);
+
+        // 集計は後から書いた備考で上書きされず、両方数える
+        let mut tc = MonthlyTimecard {
+            driver: Driver { id: 1, name: "test".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 12,
+            days: vec![day, late],
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        };
+        tc.calculate_summary_with_kiso(0, 0);
+        assert_eq!(tc.summary.yukyu, 1.0);
+        assert_eq!(tc.summary.chikoku, 1);
+
+        // JSONは全件の配列
+        let value = serde_json::to_value(&tc.days[0]).unwrap();
+        assert_eq!(value["remarks"], serde_json::json!(["有休", "家畜"]));
+    }
+
+    #[test]
+    fn test_unknown_remarks_use_kome_with_full_text() {
+        let days = vec![day_with(1, "研修", "出"), day_with(2, "慶弔", "")];
+        let symbols = RemarkSymbols::try_parse("研修=研,公休=休").unwrap();
+        // 出張が未定義の備考より先（休暇種別 > 出張 > その他）
+        assert_eq!(symbols.day_symbols(&days[0]), "出※");
+        let legend = symbols.legend(&days);
+        assert_eq!(legend[1], LegendEntry { symbol: '※', labels: vec!["研修".to_string(), "慶弔".to_string()] });
+        // 上書きした記号
+        assert_eq!(symbols.day_symbols(&day_with(3, "公休", "")), "休");
+    }
+}
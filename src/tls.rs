@@ -0,0 +1,223 @@
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection};
+use std::env;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// サーバーモードのTLS証明書・秘密鍵（PEM）のパス
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsFiles {
+    /// 環境変数 TLS_CERT / TLS_KEY（両方なければ平文のHTTP、片方だけはエラー）
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |name| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        match (var("TLS_CERT"), var("TLS_KEY")) {
+            (Some(cert), Some(key)) => Ok(Some(Self { cert: cert.into(), key: key.into() })),
+            (None, None) => Ok(None),
+            _ => Err("TLS_CERT と TLS_KEY は両方指定してください".to_string()),
+        }
+    }
+
+    /// 証明書・秘密鍵を読み込んでTLSの設定を作る（読めない・対応しない形式ならエラー）
+    pub fn load(&self) -> Result<Arc<ServerConfig>, String> {
+        let certs = read_certs(&self.cert)?;
+        let key = PrivateKeyDer::from_pem_file(&self.key)
+            .map_err(|e| format!("TLS秘密鍵を読み込めません: {}: {}", self.key.display(), e))?;
+        let mut config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("TLSの設定を作れません: {}", e))?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| format!("TLS証明書と秘密鍵が対応していません: {}: {}", self.cert.display(), e))?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+/// PEMの証明書チェーン（1つもなければエラー）
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let error = |e: &dyn std::fmt::Display| format!("TLS証明書を読み込めません: {}: {}", path.display(), e);
+    let certs = CertificateDer::pem_file_iter(path)
+        .map_err(|e| error(&e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error(&e))?;
+    if certs.is_empty() {
+        return Err(error(&"証明書がありません"));
+    }
+    Ok(certs)
+}
+
+/// TLSの接続（rustls の平文側を tokio の AsyncRead / AsyncWrite として使う）
+/// ハンドシェイクは最初の読み書きの中で進む
+pub struct TlsStream<IO> {
+    io: IO,
+    conn: ServerConnection,
+    /// 相手がTCPを閉じた
+    eof: bool,
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin> TlsStream<IO> {
+    pub fn new(io: IO, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let conn = ServerConnection::new(config).map_err(io::Error::other)?;
+        Ok(Self { io, conn, eof: false })
+    }
+
+    /// TCPから読んでTLSレコードを処理する（Ok(0) はTCPの終わり）
+    fn read_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let n = match self.conn.read_tls(&mut SyncIo { io: &mut self.io, cx }) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+            result => result?,
+        };
+        if let Err(e) = self.conn.process_new_packets() {
+            // 相手にアラートを送れるなら送ってから閉じる
+            let _ = self.write_io(cx);
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e)));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    /// 送信待ちのTLSレコードをTCPに書く
+    fn write_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        match self.conn.write_tls(&mut SyncIo { io: &mut self.io, cx }) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+
+    /// 送信待ちをすべて書く
+    fn flush_tls(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.conn.wants_write() {
+            match self.write_io(cx) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin> AsyncRead for TlsStream<IO> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            match this.conn.reader().read(buf.initialize_unfilled()) {
+                Ok(n) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                // close_notify なしでTCPが閉じた
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && this.eof => return Poll::Ready(Ok(())),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+            // ハンドシェイクの応答などを先に送る（書けない間も読みは進める）
+            if let Poll::Ready(Err(e)) = this.flush_tls(cx) {
+                return Poll::Ready(Err(e));
+            }
+            if this.eof {
+                return Poll::Ready(Ok(()));
+            }
+            match this.read_io(cx) {
+                Poll::Ready(Ok(0)) => this.eof = true,
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin> AsyncWrite for TlsStream<IO> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = this.conn.writer().write(buf)?;
+        match this.flush_tls(cx) {
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            // 平文の送信バッファがいっぱいで1バイトも受け取れなかった
+            Poll::Pending if n == 0 && !buf.is_empty() => Poll::Pending,
+            _ => Poll::Ready(Ok(n)),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.conn.writer().flush()?;
+        match this.flush_tls(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.io).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.conn.send_close_notify();
+        match this.flush_tls(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.io).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+/// rustls の同期の read_tls / write_tls に渡すアダプター（待つ時は WouldBlock）
+struct SyncIo<'a, 'b, IO> {
+    io: &'a mut IO,
+    cx: &'a mut Context<'b>,
+}
+
+impl<IO: AsyncRead + Unpin> Read for SyncIo<'_, '_, IO> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut buf = ReadBuf::new(buf);
+        match Pin::new(&mut *self.io).poll_read(self.cx, &mut buf) {
+            Poll::Ready(Ok(())) => Ok(buf.filled().len()),
+            Poll::Ready(Err(e)) => Err(e),
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> Write for SyncIo<'_, '_, IO> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match Pin::new(&mut *self.io).poll_write(self.cx, buf) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match Pin::new(&mut *self.io).poll_flush(self.cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_reports_unreadable_files() {
+        let files = TlsFiles { cert: "fixtures/tls/localhost.crt".into(), key: "fixtures/tls/localhost.key".into() };
+        assert!(files.load().is_ok());
+
+        let missing = TlsFiles { cert: "fixtures/tls/missing.crt".into(), ..files.clone() };
+        let err = missing.load().unwrap_err();
+        assert!(err.starts_with("TLS証明書を読み込めません: fixtures/tls/missing.crt"), "{}", err);
+        // 証明書のないファイル
+        let empty = TlsFiles { cert: "fixtures/tls/localhost.key".into(), ..files.clone() };
+        assert!(empty.load().unwrap_err().contains("証明書がありません"));
+        let no_key = TlsFiles { key: "fixtures/tls/localhost.crt".into(), ..files };
+        assert!(no_key.load().unwrap_err().starts_with("TLS秘密鍵を読み込めません"));
+    }
+}
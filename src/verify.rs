@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+
+use crate::timecard_data::Driver;
+
+/// verifyモードの対象ドライバーの選び方
+#[derive(Debug, Clone, PartialEq)]
+pub enum DriverSelection {
+    /// アクティブドライバー全員
+    All,
+    /// 給与区分ごとに層別した N 人のサンプル（同じseedなら同じ結果）
+    Sample { size: usize, seed: u64 },
+    /// ファイルで指定したドライバー（前回のサンプルで差異のあったドライバーの再実行用）
+    Ids(Vec<i32>),
+}
+
+impl DriverSelection {
+    /// サンプル実行か（レポートに明記する）
+    pub fn is_partial(&self) -> bool {
+        !matches!(self, Self::All)
+    }
+}
+
+/// ドライバーIDの一覧ファイルを読み込み（1行1ID、カンマ区切り可、#以降はコメント）
+pub(crate) fn read_driver_ids(path: &str) -> Result<Vec<i32>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{} を読み込めません: {}", path, e))?;
+    let mut ids = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        for item in line.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let id = item
+                .parse::<i32>()
+                .map_err(|_| format!("{}:{} ドライバーIDが不正です: {}", path, line_no + 1, item))?;
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// 対象ドライバーを選ぶ（元の並び順を保つ）
+pub fn select_drivers(drivers: &[Driver], selection: &DriverSelection) -> Vec<Driver> {
+    match selection {
+        DriverSelection::All => drivers.to_vec(),
+        DriverSelection::Ids(ids) => {
+            let ids: HashSet<i32> = ids.iter().copied().collect();
+            drivers.iter().filter(|d| ids.contains(&d.id)).cloned().collect()
+        }
+        DriverSelection::Sample { size, seed } => {
+            let chosen = sample_driver_ids(drivers, *size, *seed);
+            drivers.iter().filter(|d| chosen.contains(&d.id)).cloned().collect()
+        }
+    }
+}
+
+/// 給与区分（category_c）ごとに層別してサンプリング
+/// 各区分から最低1人（Nが区分数以上の場合）、残りは区分の人数比で配分する
+fn sample_driver_ids(drivers: &[Driver], size: usize, seed: u64) -> HashSet<i32> {
+    // 区分ごとにID順で並べてからseedで並べ替える（取得順に依存しない）
+    let mut strata: BTreeMap<Option<i32>, Vec<i32>> = BTreeMap::new();
+    for d in drivers {
+        strata.entry(d.category_c).or_default().push(d.id);
+    }
+    let mut strata: Vec<(Option<i32>, Vec<i32>)> = strata.into_iter().collect();
+    for (category, ids) in strata.iter_mut() {
+        ids.sort_unstable();
+        ids.dedup();
+        let mut rng = SplitMix64::new(seed ^ (category.unwrap_or(-1) as i64 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        // Fisher-Yates
+        for i in (1..ids.len()).rev() {
+            let j = (rng.next() % (i as u64 + 1)) as usize;
+            ids.swap(i, j);
+        }
+    }
+
+    let sizes: Vec<usize> = strata.iter().map(|(_, ids)| ids.len()).collect();
+    let allocation = allocate(&sizes, size);
+    strata
+        .iter()
+        .zip(allocation)
+        .flat_map(|((_, ids), n)| ids.iter().take(n).copied().collect::<Vec<_>>())
+        .collect()
+}
+
+/// 各層への配分（抽出率が最も低い層に1人ずつ足していく。同率なら人数の多い層を優先）
+fn allocate(sizes: &[usize], size: usize) -> Vec<usize> {
+    let mut alloc = vec![0usize; sizes.len()];
+    let total: usize = sizes.iter().sum();
+    for _ in 0..size.min(total) {
+        let next = (0..sizes.len())
+            .filter(|&i| alloc[i] < sizes[i])
+            .min_by(|&a, &b| {
+                // alloc[a]/sizes[a] と alloc[b]/sizes[b] を比較
+                (alloc[a] * sizes[b])
+                    .cmp(&(alloc[b] * sizes[a]))
+                    .then(sizes[b].cmp(&sizes[a]))
+                    .then(a.cmp(&b))
+            });
+        match next {
+            Some(i) => alloc[i] += 1,
+            None => break,
+        }
+    }
+    alloc
+}
+
+/// 決定的な疑似乱数（seed再現用、外部クレート不要）
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drivers() -> Vec<Driver> {
+        // 運転手（category_c=2）40人、事務（category_c=3）8人、区分なし2人
+        (0..50)
+            .map(|i| Driver {
+                id: 1000 + i,
+                name: format!("driver{}", i),
+                bumon: None,
+                category_c: match i {
+                    0..=39 => Some(2),
+                    40..=47 => Some(3),
+                    _ => None,
+                },
+                eigyosho_c: Some(1),
+                kyuyo_shain_id: Some(i),
+            })
+            .collect()
+    }
+
+    fn ids(list: &[Driver]) -> Vec<i32> {
+        list.iter().map(|d| d.id).collect()
+    }
+
+    #[test]
+    fn test_sample_is_deterministic_for_seed() {
+        let all = drivers();
+        let a = select_drivers(&all, &DriverSelection::Sample { size: 10, seed: 42 });
+        let b = select_drivers(&all, &DriverSelection::Sample { size: 10, seed: 42 });
+        assert_eq!(ids(&a), ids(&b));
+        assert_eq!(a.len(), 10);
+
+        // 取得順が変わっても同じドライバーが選ばれる
+        let mut reversed = all.clone();
+        reversed.reverse();
+        let mut c = ids(&select_drivers(&reversed, &DriverSelection::Sample { size: 10, seed: 42 }));
+        c.sort();
+        assert_eq!(c, ids(&a));
+
+        // seedが違えば別のサンプル
+        let d = select_drivers(&all, &DriverSelection::Sample { size: 10, seed: 7 });
+        assert_ne!(ids(&a), ids(&d));
+    }
+
+    #[test]
+    fn test_sample_covers_every_category() {
+        let all = drivers();
+        let sample = select_drivers(&all, &DriverSelection::Sample { size: 5, seed: 1 });
+        let categories: HashSet<Option<i32>> = sample.iter().map(|d| d.category_c).collect();
+        assert_eq!(categories, HashSet::from([Some(2), Some(3), None]));
+        // 人数比で配分（40:8:2 に5人 → 3:1:1）
+        assert_eq!(allocate(&[40, 8, 2], 5), vec![3, 1, 1]);
+        // 全員より多い指定は全員
+        assert_eq!(select_drivers(&all, &DriverSelection::Sample { size: 100, seed: 1 }).len(), 50);
+    }
+
+    #[test]
+    fn test_read_driver_ids() {
+        let path = std::env::temp_dir().join(format!("verify_ids_{}.txt", std::process::id()));
+        fs::write(&path, "# failures\n1071\n1080, 1090\n\n1071 # dup\n").unwrap();
+        let result = read_driver_ids(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap(), vec![1071, 1080, 1090]);
+
+        let selected = select_drivers(&drivers(), &DriverSelection::Ids(vec![1003, 9999]));
+        assert_eq!(ids(&selected), vec![1003]);
+    }
+}
@@ -0,0 +1,186 @@
+use serde::Serialize;
+use std::env;
+
+use crate::export::csv_field;
+use crate::timecard_data::{MonthlyTimecard, ZangyoSource};
+
+/// 残業異常レポートの判定ルール設定
+///   - ZANGYO_REPORT_MAX_HOURS: 1日の残業の上限（時間、デフォルト8、0で判定しない）
+///   - ZANGYO_REPORT_MULTI_SOURCE: 旅費と残業テーブルの両方から来た日を報告するか（デフォルトtrue）
+#[derive(Debug, Clone)]
+pub struct ZangyoReportConfig {
+    pub max_hours_per_day: Option<f64>,
+    pub flag_multiple_sources: bool,
+}
+
+impl Default for ZangyoReportConfig {
+    fn default() -> Self {
+        Self {
+            max_hours_per_day: Some(8.0),
+            flag_multiple_sources: true,
+        }
+    }
+}
+
+impl ZangyoReportConfig {
+    /// 環境変数から設定を読み込み
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let max_hours_per_day = match env::var("ZANGYO_REPORT_MAX_HOURS").ok().and_then(|v| v.parse::<f64>().ok()) {
+            Some(h) if h <= 0.0 => None,
+            Some(h) => Some(h),
+            None => default.max_hours_per_day,
+        };
+        let flag_multiple_sources = env::var("ZANGYO_REPORT_MULTI_SOURCE")
+            .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "off"))
+            .unwrap_or(default.flag_multiple_sources);
+        Self { max_hours_per_day, flag_multiple_sources }
+    }
+}
+
+/// 違反したルール
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZangyoRule {
+    /// ryohi_rows と time_card_zangyo の両方に残業がある
+    MultipleSources,
+    /// 1日の残業が上限を超えている
+    ExceedsDailyLimit,
+}
+
+impl ZangyoRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MultipleSources => "multiple_sources",
+            Self::ExceedsDailyLimit => "exceeds_daily_limit",
+        }
+    }
+}
+
+/// 残業の異常（ドライバー・日単位）
+#[derive(Debug, Clone, Serialize)]
+pub struct ZangyoAnomaly {
+    pub driver_id: i32,
+    pub driver_name: String,
+    pub date: String,
+    pub sources: Vec<ZangyoSource>,
+    pub total: f64,
+    pub rules: Vec<ZangyoRule>,
+}
+
+/// 日別の残業元データから異常を抽出（ドライバー順・日付順）
+pub fn find_anomalies(timecards: &[MonthlyTimecard], config: &ZangyoReportConfig) -> Vec<ZangyoAnomaly> {
+    let mut anomalies = Vec::new();
+    for tc in timecards {
+        for day in &tc.days {
+            if day.zangyo_sources.is_empty() {
+                continue;
+            }
+            let total: f64 = day.zangyo_sources.iter().map(|s| s.amount).sum();
+
+            let mut rules = Vec::new();
+            let first_table = day.zangyo_sources[0].table;
+            if config.flag_multiple_sources && day.zangyo_sources.iter().any(|s| s.table != first_table) {
+                rules.push(ZangyoRule::MultipleSources);
+            }
+            if config.max_hours_per_day.is_some_and(|max| total > max) {
+                rules.push(ZangyoRule::ExceedsDailyLimit);
+            }
+            if rules.is_empty() {
+                continue;
+            }
+
+            anomalies.push(ZangyoAnomaly {
+                driver_id: tc.driver.id,
+                driver_name: tc.driver.name.clone(),
+                date: format!("{}-{:02}-{:02}", tc.year, tc.month, day.day),
+                sources: day.zangyo_sources.clone(),
+                total,
+                rules,
+            });
+        }
+    }
+    anomalies
+}
+
+/// 異常レポートをCSVに変換
+/// sources は「テーブル#行ID=時間」をセミコロン区切り、rules もセミコロン区切り
+pub fn to_csv(anomalies: &[ZangyoAnomaly]) -> String {
+    let mut out = String::from("driver_id,driver_name,date,sources,total,rules\n");
+    for a in anomalies {
+        let sources = a.sources
+            .iter()
+            .map(|s| format!("{}#{}={}", s.table.as_str(), s.row_id, s.amount))
+            .collect::<Vec<_>>()
+            .join(";");
+        let rules = a.rules.iter().map(|r| r.as_str()).collect::<Vec<_>>().join(";");
+        let fields = [
+            a.driver_id.to_string(),
+            a.driver_name.clone(),
+            a.date.clone(),
+            sources,
+            a.total.to_string(),
+            rules,
+        ];
+        out.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timecard_data::{DayRecord, Driver, TimecardSummary, ZangyoTable};
+
+    fn source(table: ZangyoTable, row_id: &str, amount: f64) -> ZangyoSource {
+        ZangyoSource { table, row_id: row_id.to_string(), amount }
+    }
+
+    /// 重複・上限超過を含むテストデータ
+    fn seeded_timecard() -> MonthlyTimecard {
+        let mut days: Vec<DayRecord> = (1..=3).map(|d| DayRecord::new(d, "月")).collect();
+        // 1日: 旅費と残業テーブルの両方
+        days[0].add_zangyo(source(ZangyoTable::RyohiRows, "101", 2.0));
+        days[0].add_zangyo(source(ZangyoTable::TimeCardZangyo, "501", 1.5));
+        // 2日: 旅費のみだが上限超過
+        days[1].add_zangyo(source(ZangyoTable::RyohiRows, "102", 6.0));
+        days[1].add_zangyo(source(ZangyoTable::RyohiRows, "103", 3.0));
+        // 3日: 正常
+        days[2].add_zangyo(source(ZangyoTable::TimeCardZangyo, "502", 2.0));
+        MonthlyTimecard {
+            driver: Driver { id: 1071, name: "山田, 太郎".to_string(), bumon: None, category_c: None, eigyosho_c: None, kyuyo_shain_id: None },
+            year: 2025,
+            month: 12,
+            days,
+            summary: TimecardSummary::default(),
+            hire_date: None,
+            retire_date: None,
+        }
+    }
+
+    #[test]
+    fn test_find_anomalies_with_overlapping_rows() {
+        let anomalies = find_anomalies(&[seeded_timecard()], &ZangyoReportConfig::default());
+        assert_eq!(anomalies.len(), 2);
+        assert_eq!(anomalies[0].date, "2025-12-01");
+        assert_eq!(anomalies[0].rules, vec![ZangyoRule::MultipleSources]);
+        assert_eq!(anomalies[0].total, 3.5);
+        assert_eq!(anomalies[1].date, "2025-12-02");
+        assert_eq!(anomalies[1].rules, vec![ZangyoRule::ExceedsDailyLimit]);
+
+        // ルールを無効にすると報告されない
+        let config = ZangyoReportConfig { max_hours_per_day: None, flag_multiple_sources: false };
+        assert!(find_anomalies(&[seeded_timecard()], &config).is_empty());
+    }
+
+    #[test]
+    fn test_csv_output() {
+        let anomalies = find_anomalies(&[seeded_timecard()], &ZangyoReportConfig::default());
+        let csv = to_csv(&anomalies);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "driver_id,driver_name,date,sources,total,rules");
+        assert_eq!(lines[1], "1071,\"山田, 太郎\",2025-12-01,ryohi_rows#101=2;time_card_zangyo#501=1.5,3.5,multiple_sources");
+        assert_eq!(lines.len(), 3);
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{self, Write};
+
+/// ファイル名がUTF-8であることを示すフラグ（汎用ビット11）
+const FLAG_UTF8: u16 = 0x0800;
+
+/// 圧縮方式: Deflate
+const METHOD_DEFLATE: u16 = 8;
+
+/// 展開に必要なバージョン（2.0、Deflate）
+const VERSION: u16 = 20;
+
+/// ZIPに書き込んだファイル1件（中央ディレクトリ用）
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// ファイル名とバイト列の一覧からZIPアーカイブを作成（Deflate、ZIP64は非対応）
+/// modified は全ファイル共通の更新日時（MS-DOS形式のため1980年以降・2秒単位）
+pub fn write_zip(entries: &[(String, Vec<u8>)], modified: NaiveDateTime) -> io::Result<Vec<u8>> {
+    let (time, date) = dos_datetime(modified);
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "ZIPのサイズが4GBを超えます");
+    let mut out = Vec::new();
+    let mut central = Vec::with_capacity(entries.len());
+
+    for (name, data) in entries {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let entry = CentralEntry {
+            name: name.clone(),
+            crc: crc32fast::hash(data),
+            compressed_size: u32::try_from(compressed.len()).map_err(|_| too_large())?,
+            size: u32::try_from(data.len()).map_err(|_| too_large())?,
+            offset: u32::try_from(out.len()).map_err(|_| too_large())?,
+        };
+
+        // ローカルファイルヘッダー
+        put_u32(&mut out, 0x0403_4b50);
+        put_u16(&mut out, VERSION);
+        put_u16(&mut out, FLAG_UTF8);
+        put_u16(&mut out, METHOD_DEFLATE);
+        put_u16(&mut out, time);
+        put_u16(&mut out, date);
+        put_u32(&mut out, entry.crc);
+        put_u32(&mut out, entry.compressed_size);
+        put_u32(&mut out, entry.size);
+        put_u16(&mut out, name.len() as u16);
+        put_u16(&mut out, 0); // 拡張フィールドなし
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&compressed);
+        central.push(entry);
+    }
+
+    // 中央ディレクトリ
+    let central_offset = u32::try_from(out.len()).map_err(|_| too_large())?;
+    for entry in &central {
+        put_u32(&mut out, 0x0201_4b50);
+        put_u16(&mut out, VERSION); // 作成したバージョン
+        put_u16(&mut out, VERSION);
+        put_u16(&mut out, FLAG_UTF8);
+        put_u16(&mut out, METHOD_DEFLATE);
+        put_u16(&mut out, time);
+        put_u16(&mut out, date);
+        put_u32(&mut out, entry.crc);
+        put_u32(&mut out, entry.compressed_size);
+        put_u32(&mut out, entry.size);
+        put_u16(&mut out, entry.name.len() as u16);
+        put_u16(&mut out, 0); // 拡張フィールド
+        put_u16(&mut out, 0); // コメント
+        put_u16(&mut out, 0); // ディスク番号
+        put_u16(&mut out, 0); // 内部属性
+        put_u32(&mut out, 0); // 外部属性
+        put_u32(&mut out, entry.offset);
+        out.extend_from_slice(entry.name.as_bytes());
+    }
+    let central_size = u32::try_from(out.len()).map_err(|_| too_large())? - central_offset;
+    let count = u16::try_from(central.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "ZIPのファイル数が多すぎます"))?;
+
+    // 終端レコード
+    put_u32(&mut out, 0x0605_4b50);
+    put_u16(&mut out, 0);
+    put_u16(&mut out, 0);
+    put_u16(&mut out, count);
+    put_u16(&mut out, count);
+    put_u32(&mut out, central_size);
+    put_u32(&mut out, central_offset);
+    put_u16(&mut out, 0);
+    Ok(out)
+}
+
+/// MS-DOS形式の時刻・日付（1980年より前は1980-01-01 00:00）
+fn dos_datetime(dt: NaiveDateTime) -> (u16, u16) {
+    if dt.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = (dt.hour() << 11) | (dt.minute() << 5) | (dt.second() / 2);
+    let date = (((dt.year() - 1980) as u32) << 9) | (dt.month() << 5) | dt.day();
+    (time as u16, date as u16)
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    fn u16_at(bytes: &[u8], pos: usize) -> usize {
+        u16::from_le_bytes([bytes[pos], bytes[pos + 1]]) as usize
+    }
+
+    fn u32_at(bytes: &[u8], pos: usize) -> usize {
+        u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize
+    }
+
+    /// 中央ディレクトリをたどって（名前, 中身）を取り出す
+    fn read_zip(bytes: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(bytes, end), 0x0605_4b50);
+        let mut pos = u32_at(bytes, end + 16);
+        (0..u16_at(bytes, end + 10))
+            .map(|_| {
+                assert_eq!(u32_at(bytes, pos), 0x0201_4b50);
+                let crc = u32_at(bytes, pos + 16) as u32;
+                let compressed_size = u32_at(bytes, pos + 20);
+                let name_len = u16_at(bytes, pos + 28);
+                let offset = u32_at(bytes, pos + 42);
+                let name = String::from_utf8(bytes[pos + 46..pos + 46 + name_len].to_vec()).unwrap();
+                pos += 46 + name_len;
+
+                let data_start = offset + 30 + u16_at(bytes, offset + 26);
+                let mut data = Vec::new();
+                DeflateDecoder::new(&bytes[data_start..data_start + compressed_size]).read_to_end(&mut data).unwrap();
+                assert_eq!(crc32fast::hash(&data), crc);
+                (name, data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let entries = vec![
+            ("timecard_2025_12_1_テスト.pdf".to_string(), b"%PDF-1.3 first".repeat(100)),
+            ("empty.pdf".to_string(), Vec::new()),
+        ];
+        let modified = NaiveDateTime::parse_from_str("2025-12-01 09:30:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let zip = write_zip(&entries, modified).unwrap();
+        assert_eq!(read_zip(&zip), entries);
+        assert_eq!(dos_datetime(modified), ((9 << 11) | (30 << 5), (45 << 9) | (12 << 5) | 1));
+    }
+}